@@ -0,0 +1,402 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    annotation::Region,
+    cell::{BinaryOperator, CellValue, Direction, IfDir, Operator, TernaryOperator},
+    dialect::Extension,
+    grid::Grid,
+};
+
+type Position = (usize, usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        }
+    }
+}
+
+/// Issue found at a given cell.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub position: Position,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Result of statically following every possible control-flow path from the origin.
+#[derive(Clone, Debug, Default)]
+pub struct Analysis {
+    reachable: HashSet<Position>,
+    /// Reachable cells executed as instructions rather than pushed in string mode
+    executed: HashSet<Position>,
+    /// Reachable cells pushed in string mode rather than executed, the closing `"` excluded
+    quoted: HashSet<Position>,
+    /// Whether reachable cells are crossed going left or right, and going up or down
+    axes: HashMap<Position, (bool, bool)>,
+    successors: HashMap<Position, HashSet<Position>>,
+    predecessors: HashMap<Position, HashSet<Position>>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// State of the instruction pointer as seen by the analyzer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct Ip {
+    pub position: Position,
+    pub direction: Direction,
+    pub string_mode: bool,
+}
+
+impl Ip {
+    /// Where and how every program starts.
+    pub const START: Ip = Ip {
+        position: (0, 0),
+        direction: Direction::Right,
+        string_mode: false,
+    };
+}
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Down,
+    Direction::Left,
+    Direction::Right,
+];
+
+/// Explores all paths the instruction pointer can take, assuming the grid is never modified.
+/// Instructions of the enabled `extensions` are diagnosed as such rather than as unknown ones.
+pub fn analyze(grid: &Grid, extensions: &[Extension]) -> Analysis {
+    analyze_with_data(grid, &[], extensions)
+}
+
+/// Same as [analyze], for a program whose data tables are declared. Writing to them is expected
+/// rather than self-modifying, while executing them is reported.
+pub fn analyze_with_data(grid: &Grid, data: &[Region], extensions: &[Extension]) -> Analysis {
+    let mut analysis = Analysis::default();
+
+    let (width, height) = grid.size();
+    if width == 0 || height == 0 {
+        return analysis;
+    }
+
+    let mut visited = HashSet::new();
+    let mut queue = vec![Ip::START];
+
+    while let Some(ip) = queue.pop() {
+        if !visited.insert(ip) {
+            continue;
+        }
+
+        analysis.reachable.insert(ip.position);
+        let axes = analysis.axes.entry(ip.position).or_default();
+        match ip.direction {
+            Direction::Left | Direction::Right => axes.0 = true,
+            Direction::Up | Direction::Down => axes.1 = true,
+            Direction::Random => (),
+        }
+
+        for next in successors(grid, ip) {
+            analysis.link(ip.position, next.position);
+            queue.push(next);
+        }
+    }
+
+    analysis.diagnose(grid, &visited, data, extensions);
+
+    analysis
+}
+
+/// Possible states of the instruction pointer after executing the cell under it.
+pub(crate) fn successors(grid: &Grid, ip: Ip) -> Vec<Ip> {
+    let value = grid.get(ip.position.0, ip.position.1).value;
+    let moved = |direction: Direction, string_mode: bool| Ip {
+        position: grid.neighbour(ip.position, direction),
+        direction,
+        string_mode,
+    };
+
+    if ip.string_mode {
+        return vec![moved(ip.direction, !matches!(value, CellValue::StringMode))];
+    }
+
+    match value {
+        CellValue::End => vec![],
+        CellValue::StringMode => vec![moved(ip.direction, true)],
+        CellValue::Bridge => {
+            let skipped = grid.neighbour(ip.position, ip.direction);
+            vec![Ip {
+                position: grid.neighbour(skipped, ip.direction),
+                ..ip
+            }]
+        }
+        CellValue::Dir(Direction::Random) => DIRECTIONS
+            .iter()
+            .map(|direction| moved(*direction, false))
+            .collect(),
+        CellValue::Dir(direction) => vec![moved(direction, false)],
+        CellValue::If(IfDir::Horizontal) => vec![
+            moved(Direction::Left, false),
+            moved(Direction::Right, false),
+        ],
+        CellValue::If(IfDir::Vertical) => {
+            vec![moved(Direction::Up, false), moved(Direction::Down, false)]
+        }
+        _ => vec![moved(ip.direction, false)],
+    }
+}
+
+impl Analysis {
+    fn link(&mut self, from: Position, to: Position) {
+        self.successors.entry(from).or_default().insert(to);
+        self.predecessors.entry(to).or_default().insert(from);
+    }
+
+    fn diagnose(
+        &mut self,
+        grid: &Grid,
+        visited: &HashSet<Ip>,
+        data: &[Region],
+        extensions: &[Extension],
+    ) {
+        let mut terminates = false;
+        let mut executed = HashSet::new();
+        let mut puts = Vec::new();
+        let assert = extensions.contains(&Extension::Assert);
+
+        for ip in visited.iter().filter(|ip| !ip.string_mode) {
+            if !executed.insert(ip.position) {
+                continue;
+            }
+
+            let (x, y) = ip.position;
+            match grid.get(x, y).value {
+                CellValue::End => terminates = true,
+                CellValue::Char('A') if assert && self.follows_zero_push(grid, ip.position) => {
+                    self.diagnostics.push(Diagnostic {
+                        position: ip.position,
+                        severity: Severity::Warning,
+                        message: "Assertion always fails".to_owned(),
+                    })
+                }
+                CellValue::Char('A') if assert => (),
+                CellValue::Char(c) => self.diagnostics.push(Diagnostic {
+                    position: ip.position,
+                    severity: Severity::Warning,
+                    message: format!("Unknown instruction `{c}` is executed as a no-op"),
+                }),
+                CellValue::Op(Operator::Ternary(TernaryOperator::Put)) => puts.push(ip.position),
+                CellValue::Op(Operator::Binary(
+                    BinaryOperator::Divide | BinaryOperator::Modulo,
+                )) if self.follows_zero_push(grid, ip.position) => {
+                    self.diagnostics.push(Diagnostic {
+                        position: ip.position,
+                        severity: Severity::Warning,
+                        message: "Division by zero pushes 0".to_owned(),
+                    })
+                }
+                _ => (),
+            }
+        }
+
+        self.executed = executed;
+        self.quoted = visited
+            .iter()
+            .filter(|ip| ip.string_mode)
+            .map(|ip| ip.position)
+            .filter(|&(x, y)| !matches!(grid.get(x, y).value, CellValue::StringMode))
+            .collect();
+
+        for position in puts {
+            let target = self.constant_target(grid, position);
+            if !target.is_some_and(|target| data.iter().any(|region| region.contains(target))) {
+                self.diagnostics.push(Diagnostic {
+                    position,
+                    severity: Severity::Info,
+                    message: "Self-modifying code, analysis may be inaccurate".to_owned(),
+                })
+            }
+        }
+
+        for region in data {
+            let entered = self
+                .executed
+                .iter()
+                .filter(|position| region.contains(**position))
+                .min_by_key(|(x, y)| (*y, *x));
+            if let Some(position) = entered {
+                self.diagnostics.push(Diagnostic {
+                    position: *position,
+                    severity: Severity::Warning,
+                    message: "Control flow runs into a data region".to_owned(),
+                });
+            }
+        }
+
+        if !terminates {
+            self.diagnostics.push(Diagnostic {
+                position: (0, 0),
+                severity: Severity::Error,
+                message: "No `@` is reachable, the program can never end".to_owned(),
+            });
+        }
+
+        self.diagnostics
+            .sort_by_key(|diagnostic| (diagnostic.position.1, diagnostic.position.0));
+    }
+
+    /// Whether every path to this cell comes straight from a `0`.
+    fn follows_zero_push(&self, grid: &Grid, position: Position) -> bool {
+        self.predecessors(position).is_some_and(|predecessors| {
+            !predecessors.is_empty()
+                && predecessors
+                    .iter()
+                    .all(|(x, y)| matches!(grid.get(*x, *y).value, CellValue::Number(0)))
+        })
+    }
+
+    /// Cell a `g` or `p` always accesses, when its coordinates are pushed by the two digits
+    /// leading straight to it.
+    fn constant_target(&self, grid: &Grid, position: Position) -> Option<Position> {
+        let only = |position: Position| {
+            let predecessors = self.predecessors(position)?;
+            let predecessor = *predecessors.iter().next()?;
+            (predecessors.len() == 1 && self.is_executed(predecessor)).then_some(predecessor)
+        };
+        let digit = |(x, y): Position| match grid.get(x, y).value {
+            CellValue::Number(n) => Some(n as usize),
+            _ => None,
+        };
+
+        let y = only(position)?;
+        let x = only(y)?;
+        Some((digit(x)?, digit(y)?))
+    }
+
+    /// Whether the instruction pointer can ever reach the cell.
+    pub fn is_reachable(&self, position: Position) -> bool {
+        self.reachable.contains(&position)
+    }
+
+    /// Whether the cell can ever be executed as an instruction.
+    pub fn is_executed(&self, position: Position) -> bool {
+        self.executed.contains(&position)
+    }
+
+    /// Whether the cell can be pushed as text in string mode.
+    pub fn is_quoted(&self, position: Position) -> bool {
+        self.quoted.contains(&position)
+    }
+
+    /// Whether the instruction pointer can cross the cell going left or right, and going up or
+    /// down.
+    pub fn axes(&self, position: Position) -> (bool, bool) {
+        self.axes.get(&position).copied().unwrap_or_default()
+    }
+
+    /// Cells that can be pushed as text in string mode.
+    pub fn quoted(&self) -> &HashSet<Position> {
+        &self.quoted
+    }
+
+    /// Cells the instruction pointer can move to from this one.
+    pub fn successors(&self, position: Position) -> Option<&HashSet<Position>> {
+        self.successors.get(&position)
+    }
+
+    /// Cells the instruction pointer can come from to reach this one.
+    pub fn predecessors(&self, position: Position) -> Option<&HashSet<Position>> {
+        self.predecessors.get(&position)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn flow() {
+        let grid = crate::grid!["v", ">1_@", "  x"];
+        let analysis = analyze(&grid, &[]);
+
+        assert!(analysis.is_reachable((3, 1)));
+        assert!(!analysis.is_reachable((2, 2)));
+        assert_eq!(
+            analysis.successors((2, 1)),
+            Some(&HashSet::from([(1, 1), (3, 1)]))
+        );
+        assert!(analysis.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn diagnostics() {
+        let grid = Grid::from("\"x\"x10/>".to_owned());
+        let analysis = analyze(&grid, &[]);
+
+        assert_eq!(
+            analysis
+                .diagnostics
+                .iter()
+                .map(|diagnostic| (diagnostic.position, diagnostic.severity))
+                .collect::<Vec<_>>(),
+            vec![
+                ((0, 0), Severity::Error),
+                ((3, 0), Severity::Warning),
+                ((6, 0), Severity::Warning),
+            ]
+        );
+        assert_eq!(analysis.quoted(), &HashSet::from([(1, 0)]));
+    }
+
+    #[test]
+    fn assert() {
+        let grid = Grid::from("1A0A@".to_owned());
+        let diagnostics = |extensions: &[Extension]| {
+            analyze(&grid, extensions)
+                .diagnostics
+                .into_iter()
+                .map(|diagnostic| (diagnostic.position, diagnostic.severity))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            diagnostics(&[]),
+            vec![((1, 0), Severity::Warning), ((3, 0), Severity::Warning)]
+        );
+        assert_eq!(
+            diagnostics(&[Extension::Assert]),
+            vec![((3, 0), Severity::Warning)]
+        );
+    }
+
+    #[test]
+    fn data() {
+        let grid = crate::grid!["v  ..", ">:30p@"];
+        let diagnostics = |data: &[Region]| {
+            analyze_with_data(&grid, data, &[])
+                .diagnostics
+                .into_iter()
+                .map(|diagnostic| (diagnostic.position, diagnostic.severity))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(diagnostics(&[]), vec![((4, 1), Severity::Info)]);
+        assert_eq!(diagnostics(&[Region::new((3, 0), (4, 0))]), vec![]);
+        assert_eq!(
+            diagnostics(&[Region::new((1, 0), (2, 0)), Region::new((3, 1), (3, 1))]),
+            vec![((3, 1), Severity::Warning), ((4, 1), Severity::Info)]
+        );
+    }
+}
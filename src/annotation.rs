@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+
+/// Rectangle of cells, both corners included.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Region {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+}
+
+impl Region {
+    /// Region spanning two corners given in any order.
+    pub fn new((x1, y1): (usize, usize), (x2, y2): (usize, usize)) -> Self {
+        Self {
+            from: (x1.min(x2), y1.min(y2)),
+            to: (x1.max(x2), y1.max(y2)),
+        }
+    }
+
+    pub fn cell(position: (usize, usize)) -> Self {
+        Self::new(position, position)
+    }
+
+    pub fn contains(&self, (x, y): (usize, usize)) -> bool {
+        (self.from.0..=self.to.0).contains(&x) && (self.from.1..=self.to.1).contains(&y)
+    }
+
+    /// Width and height of the region, in cells.
+    pub fn size(&self) -> (usize, usize) {
+        (self.to.0 - self.from.0 + 1, self.to.1 - self.from.1 + 1)
+    }
+
+    /// Number of cells in the region.
+    pub fn area(&self) -> usize {
+        let (width, height) = self.size();
+        width * height
+    }
+}
+
+/// Measurement from a marked cell to another, telling the coordinates `g` and `p` take and how
+/// far apart cells are without counting them by hand.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Measure {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+}
+
+impl Measure {
+    /// Steps from the marked cell to the other one, positive going right and down.
+    pub fn offset(&self) -> (isize, isize) {
+        (
+            self.to.0 as isize - self.from.0 as isize,
+            self.to.1 as isize - self.from.1 as isize,
+        )
+    }
+
+    /// Manhattan distance between the two cells, the number of moves from one to the other.
+    pub fn distance(&self) -> usize {
+        let (dx, dy) = self.offset();
+        dx.unsigned_abs() + dy.unsigned_abs()
+    }
+}
+
+impl std::fmt::Display for Measure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ((x1, y1), (x2, y2)) = (self.from, self.to);
+        let (width, height) = Region::new(self.from, self.to).size();
+        let (dx, dy) = self.offset();
+        write!(
+            f,
+            "({x1}, {y1}) to ({x2}, {y2}): {width}x{height} cells, dx {dx:+}, dy {dy:+}, {} apart",
+            self.distance()
+        )
+    }
+}
+
+/// Note attached to cells, as Befunge has no comments of its own.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub region: Region,
+    pub text: String,
+}
+
+/// Named region, such as a data table, drawn outlined or folded away.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Label {
+    pub region: Region,
+    pub name: String,
+    /// Whether the region is collapsed to its name
+    #[serde(default)]
+    pub folded: bool,
+    /// Whether the region only holds data, so that instructions there are most likely mistakes
+    #[serde(default)]
+    pub data: bool,
+}
+
+/// Sets the text of the annotation of a region, or removes it without text.
+pub fn annotate(annotations: &mut Vec<Annotation>, region: Region, text: Option<String>) {
+    let existing = annotations
+        .iter()
+        .position(|annotation| annotation.region == region);
+
+    match (existing, text) {
+        (Some(index), Some(text)) => annotations[index].text = text,
+        (Some(index), None) => {
+            annotations.remove(index);
+        }
+        (None, Some(text)) => annotations.push(Annotation { region, text }),
+        (None, None) => (),
+    }
+}
+
+/// Innermost annotation covering a cell.
+pub fn at(annotations: &[Annotation], position: (usize, usize)) -> Option<&Annotation> {
+    annotations
+        .iter()
+        .filter(|annotation| annotation.region.contains(position))
+        .min_by_key(|annotation| annotation.region.area())
+}
+
+/// Adds a label or replaces that of the same region, an empty name removing it.
+pub fn label(labels: &mut Vec<Label>, label: Label) {
+    labels.retain(|existing| existing.region != label.region);
+    if !label.name.is_empty() {
+        labels.push(label);
+    }
+}
+
+/// Innermost label covering a cell.
+pub fn label_at(labels: &[Label], position: (usize, usize)) -> Option<&Label> {
+    labels
+        .iter()
+        .filter(|label| label.region.contains(position))
+        .min_by_key(|label| label.region.area())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn annotate() {
+        let mut annotations = Vec::new();
+        let block = Region::new((4, 2), (0, 0));
+        super::annotate(&mut annotations, block, Some("input loop".to_owned()));
+        super::annotate(
+            &mut annotations,
+            Region::cell((1, 1)),
+            Some("counter".to_owned()),
+        );
+
+        assert_eq!(at(&annotations, (1, 1)).unwrap().text, "counter");
+        assert_eq!(at(&annotations, (4, 0)).unwrap().text, "input loop");
+        assert_eq!(at(&annotations, (5, 0)), None);
+
+        super::annotate(&mut annotations, block, Some("read digits".to_owned()));
+        assert_eq!(at(&annotations, (0, 2)).unwrap().text, "read digits");
+
+        super::annotate(&mut annotations, Region::cell((1, 1)), None);
+        assert_eq!(at(&annotations, (1, 1)).unwrap().text, "read digits");
+        assert_eq!(annotations.len(), 1);
+    }
+
+    #[test]
+    fn measure() {
+        let measure = Measure {
+            from: (6, 4),
+            to: (1, 5),
+        };
+        assert_eq!(measure.offset(), (-5, 1));
+        assert_eq!(measure.distance(), 6);
+        assert_eq!(
+            measure.to_string(),
+            "(6, 4) to (1, 5): 6x2 cells, dx -5, dy +1, 6 apart"
+        );
+    }
+}
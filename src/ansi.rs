@@ -0,0 +1,258 @@
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans, Text},
+};
+
+/// Terminal screen program output is drawn on, interpreting the ANSI escape sequences that move
+/// the cursor, erase and set colours. Other sequences are dropped.
+#[derive(Default, Debug)]
+pub(crate) struct Screen {
+    lines: Vec<Vec<(char, Style)>>,
+    cursor: (usize, usize),
+    style: Style,
+}
+
+impl Screen {
+    pub fn parse(output: &str) -> Self {
+        let mut screen = Screen::default();
+
+        let mut chars = output.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\x1b' => {
+                    // Other sequences are two characters long, e.g. charset selection, and have
+                    // no visible effect
+                    if chars.next() != Some('[') {
+                        continue;
+                    }
+
+                    let mut parameters = String::new();
+                    for c in chars.by_ref() {
+                        if ('\x40'..='\x7e').contains(&c) {
+                            screen.control(c, &parameters);
+                            break;
+                        }
+                        parameters.push(c);
+                    }
+                }
+                '\n' => screen.cursor = (0, screen.cursor.1 + 1),
+                '\r' => screen.cursor.0 = 0,
+                '\x08' => screen.cursor.0 = screen.cursor.0.saturating_sub(1),
+                '\t' => screen.cursor.0 = (screen.cursor.0 / 8 + 1) * 8,
+                c if c.is_control() => (),
+                c => screen.put(c),
+            }
+        }
+
+        screen
+    }
+
+    fn put(&mut self, c: char) {
+        let (x, y) = self.cursor;
+        if self.lines.len() <= y {
+            self.lines.resize_with(y + 1, Vec::new);
+        }
+
+        let line = &mut self.lines[y];
+        if line.len() <= x {
+            line.resize(x + 1, (' ', Style::default()));
+        }
+        line[x] = (c, self.style);
+
+        self.cursor.0 += 1;
+    }
+
+    /// Applies a Control Sequence Introducer sequence.
+    fn control(&mut self, command: char, parameters: &str) {
+        // Private sequences, e.g. hiding the cursor
+        if parameters.starts_with('?') {
+            return;
+        }
+
+        let numbers = parameters
+            .split(';')
+            .map(|n| n.parse::<usize>().ok())
+            .collect::<Vec<_>>();
+        // Like terminals do, 0 counts as 1 where a count is expected
+        let n = |index: usize, default: usize| {
+            numbers
+                .get(index)
+                .copied()
+                .flatten()
+                .unwrap_or(default)
+                .max(default.min(1))
+        };
+
+        let (x, y) = &mut self.cursor;
+        match command {
+            'A' => *y = y.saturating_sub(n(0, 1)),
+            'B' => *y += n(0, 1),
+            'C' => *x += n(0, 1),
+            'D' => *x = x.saturating_sub(n(0, 1)),
+            'E' => (*x, *y) = (0, *y + n(0, 1)),
+            'F' => (*x, *y) = (0, y.saturating_sub(n(0, 1))),
+            'G' => *x = n(0, 1) - 1,
+            'H' | 'f' => (*y, *x) = (n(0, 1) - 1, n(1, 1) - 1),
+            'J' => self.erase_display(n(0, 0)),
+            'K' => self.erase_line(n(0, 0)),
+            'm' => self.select_graphic_rendition(&numbers),
+            _ => (),
+        }
+    }
+
+    fn erase_display(&mut self, mode: usize) {
+        let (x, y) = self.cursor;
+        match mode {
+            0 => {
+                self.lines.truncate(y + 1);
+                self.erase_line(0);
+            }
+            1 => {
+                for line in self.lines.iter_mut().take(y) {
+                    line.clear();
+                }
+                self.erase_line(1);
+            }
+            _ => self.lines.clear(),
+        }
+        self.cursor = (x, y);
+    }
+
+    fn erase_line(&mut self, mode: usize) {
+        let (x, y) = self.cursor;
+        let Some(line) = self.lines.get_mut(y) else {
+            return;
+        };
+
+        match mode {
+            0 => line.truncate(x),
+            1 => line
+                .iter_mut()
+                .take(x + 1)
+                .for_each(|cell| *cell = (' ', Style::default())),
+            _ => line.clear(),
+        }
+    }
+
+    fn select_graphic_rendition(&mut self, numbers: &[Option<usize>]) {
+        let mut numbers = numbers.iter().map(|n| n.unwrap_or_default());
+        while let Some(n) = numbers.next() {
+            self.style = match n {
+                0 => Style::default(),
+                1 => self.style.add_modifier(Modifier::BOLD),
+                2 => self.style.add_modifier(Modifier::DIM),
+                3 => self.style.add_modifier(Modifier::ITALIC),
+                4 => self.style.add_modifier(Modifier::UNDERLINED),
+                5 => self.style.add_modifier(Modifier::SLOW_BLINK),
+                7 => self.style.add_modifier(Modifier::REVERSED),
+                22 => self.style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+                23 => self.style.remove_modifier(Modifier::ITALIC),
+                24 => self.style.remove_modifier(Modifier::UNDERLINED),
+                25 => self.style.remove_modifier(Modifier::SLOW_BLINK),
+                27 => self.style.remove_modifier(Modifier::REVERSED),
+                30..=37 => self.style.fg(palette(n - 30)),
+                38 => match extended(&mut numbers) {
+                    Some(color) => self.style.fg(color),
+                    None => return,
+                },
+                39 => Style {
+                    fg: None,
+                    ..self.style
+                },
+                40..=47 => self.style.bg(palette(n - 40)),
+                48 => match extended(&mut numbers) {
+                    Some(color) => self.style.bg(color),
+                    None => return,
+                },
+                49 => Style {
+                    bg: None,
+                    ..self.style
+                },
+                90..=97 => self.style.fg(palette(n - 90 + 8)),
+                100..=107 => self.style.bg(palette(n - 100 + 8)),
+                _ => self.style,
+            };
+        }
+    }
+
+    pub fn into_text(self) -> Text<'static> {
+        let lines = self
+            .lines
+            .into_iter()
+            .map(|line| {
+                let mut spans: Vec<(String, Style)> = Vec::new();
+                for (c, style) in line {
+                    match spans.last_mut() {
+                        Some((content, last)) if *last == style => content.push(c),
+                        _ => spans.push((c.to_string(), style)),
+                    }
+                }
+
+                Spans::from(
+                    spans
+                        .into_iter()
+                        .map(|(content, style)| Span::styled(content, style))
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        Text::from(lines)
+    }
+}
+
+/// One of the 16 standard colours, bright ones starting at 8.
+fn palette(index: usize) -> Color {
+    match index {
+        0 => Color::Black,
+        1 => Color::Red,
+        2 => Color::Green,
+        3 => Color::Yellow,
+        4 => Color::Blue,
+        5 => Color::Magenta,
+        6 => Color::Cyan,
+        7 => Color::Gray,
+        8 => Color::DarkGray,
+        9 => Color::LightRed,
+        10 => Color::LightGreen,
+        11 => Color::LightYellow,
+        12 => Color::LightBlue,
+        13 => Color::LightMagenta,
+        14 => Color::LightCyan,
+        _ => Color::White,
+    }
+}
+
+/// Colour of a `38` or `48` rendition, either `5;INDEX` or `2;R;G;B`.
+fn extended(numbers: &mut impl Iterator<Item = usize>) -> Option<Color> {
+    let mut component = || numbers.next().map(|n| n.min(255) as u8);
+    match component()? {
+        5 => component().map(Color::Indexed),
+        2 => Some(Color::Rgb(component()?, component()?, component()?)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rows(screen: &Screen) -> Vec<String> {
+        screen
+            .lines
+            .iter()
+            .map(|line| line.iter().map(|(c, _)| c).collect())
+            .collect()
+    }
+
+    #[test]
+    fn parse() {
+        let screen = Screen::parse("hello\nworld\x1b[1;2Ha\x1b[2;4H\x1b[K\x1b[31mx\x1b[0m!");
+        assert_eq!(rows(&screen), vec!["hallo", "worx!"]);
+        assert_eq!(screen.lines[1][3].1, Style::default().fg(Color::Red));
+        assert_eq!(screen.lines[1][4].1, Style::default());
+
+        let screen = Screen::parse("junk\x1b[2J\x1b[Hok");
+        assert_eq!(rows(&screen), vec!["ok"]);
+    }
+}
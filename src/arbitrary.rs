@@ -0,0 +1,116 @@
+use std::fmt::Debug;
+
+use crate::{
+    cell::CellValue,
+    grid::Grid,
+    sources::{Entropy, Xorshift},
+};
+
+/// Characters cells are drawn from, spaces coming up more often as they do in programs.
+const CHARACTERS: &str = "     0123456789+-*/%!`:\\$.,&~gp><^v?_|#\"@xy";
+
+/// Instructions of [Program], which neither move the instruction pointer nor wait for input.
+const STRAIGHT: &str = " 0123456789+-*/%!`:\\$.,";
+
+/// Value generated from a seeded [Xorshift], for property tests.
+///
+/// Property testing frameworks can generate these from a seed they generate themselves, e.g.
+/// `any::<u64>().prop_map(|seed| Grid::arbitrary(&mut Xorshift::new(seed)))` with proptest.
+pub trait Arbitrary: Sized {
+    fn arbitrary(rng: &mut Xorshift) -> Self;
+}
+
+/// Number in `0..bound`.
+fn below(rng: &mut Xorshift, bound: usize) -> usize {
+    (rng.next_u64() % bound as u64) as usize
+}
+
+fn pick(rng: &mut Xorshift, characters: &str) -> char {
+    let characters = characters.chars().collect::<Vec<_>>();
+    characters[below(rng, characters.len())]
+}
+
+impl Arbitrary for CellValue {
+    fn arbitrary(rng: &mut Xorshift) -> Self {
+        CellValue::from(pick(rng, CHARACTERS))
+    }
+}
+
+/// Grid of up to 8 by 8 cells.
+impl Arbitrary for Grid {
+    fn arbitrary(rng: &mut Xorshift) -> Self {
+        let (width, height) = (1 + below(rng, 8), 1 + below(rng, 8));
+        let lines = (0..height)
+            .map(|_| {
+                (0..width)
+                    .map(|_| pick(rng, CHARACTERS))
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        Grid::from(lines.join("\n"))
+    }
+}
+
+/// Row of up to 16 instructions ending with `@`, none of which changes the direction of the
+/// instruction pointer, jumps, reads input or reads and writes cells. It ends after a tick per
+/// cell.
+#[derive(Clone, Debug)]
+pub struct Program(pub Grid);
+
+impl Arbitrary for Program {
+    fn arbitrary(rng: &mut Xorshift) -> Self {
+        let length = below(rng, 16);
+        let mut row = (0..length).map(|_| pick(rng, STRAIGHT)).collect::<String>();
+        row.push('@');
+        Program(Grid::from(row))
+    }
+}
+
+/// Checks `property` on `cases` generated values, panicking with the seed of the first one it
+/// doesn't hold for.
+pub fn check<T: Arbitrary + Debug>(cases: u64, mut property: impl FnMut(&T) -> bool) {
+    for seed in 0..cases {
+        let value = T::arbitrary(&mut Xorshift::new(seed));
+        assert!(
+            property(&value),
+            "Property doesn't hold for seed {seed}: {value:?}"
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interpreter::{Interpreter, Status};
+
+    #[test]
+    fn round_trips() {
+        check(500, |value: &CellValue| {
+            CellValue::from(char::from(*value)) == *value
+        });
+        // Lines of program files end with a newline, the last one included
+        check(500, |grid: &Grid| {
+            let lines = grid.lines();
+            let text = lines
+                .iter()
+                .map(|line| format!("{line}\n"))
+                .collect::<String>();
+            Grid::from(text).lines() == lines
+        });
+    }
+
+    #[test]
+    fn programs() {
+        check(500, |Program(grid): &Program| {
+            let (width, _) = grid.size();
+            let run = |fast_forward| {
+                let mut interpreter = Interpreter::new(grid.clone());
+                interpreter.set_fast_forward(fast_forward);
+                while interpreter.step().unwrap() == Status::Running {}
+                (interpreter.ticks(), interpreter.take_output())
+            };
+            let (ticks, output) = run(false);
+            ticks == width as u64 && run(true) == (ticks, output)
+        });
+    }
+}
@@ -0,0 +1,44 @@
+use puccinia::{assembler, sidecar::Sidecar};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Could not read `{0}`: {1}")]
+    Read(String, std::io::Error),
+    #[error("Could not write `{0}`: {1}")]
+    Write(String, std::io::Error),
+    #[error("In `{0}`: {1}")]
+    Assemble(String, assembler::Error),
+}
+
+#[derive(clap::Args)]
+pub(crate) struct Options {
+    /// Funge assembly file location
+    input: String,
+
+    /// Where to write the program, defaults to stdout. Its sidecar then maps its cells back to
+    /// the lines they come from, for the TUI to show them
+    #[arg(short, long, value_name = "PATH")]
+    output: Option<String>,
+}
+
+/// Lays out a funge assembly file as a Befunge-93 program, keeping its source map in the sidecar
+/// of the program when written to a file.
+pub(crate) fn run(options: Options) -> anyhow::Result<()> {
+    let source = std::fs::read_to_string(&options.input)
+        .map_err(|err| Error::Read(options.input.clone(), err))?;
+    let mut assembly =
+        assembler::assemble(&source).map_err(|err| Error::Assemble(options.input.clone(), err))?;
+    assembly.source_map.file = options.input;
+
+    match &options.output {
+        Some(path) => {
+            std::fs::write(path, &assembly.program)
+                .map_err(|err| Error::Write(path.clone(), err))?;
+            let mut sidecar = Sidecar::load(path)?;
+            sidecar.source_map = Some(assembly.source_map);
+            sidecar.save(path)?;
+        }
+        None => print!("{}", assembly.program),
+    }
+    Ok(())
+}
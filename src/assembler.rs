@@ -0,0 +1,436 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::Range,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{builder::GridBuilder, synthesis, toml};
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Line {0}: unknown mnemonic `{1}`")]
+    Unknown(usize, String),
+    #[error("Line {0}: `{1}` expects {2}")]
+    Operand(usize, String, &'static str),
+    #[error("Line {0}: label `{1}` is already defined")]
+    Duplicate(usize, String),
+    #[error("Line {0}: undefined label `{1}`")]
+    Undefined(usize, String),
+}
+
+pub type Result<T> = anyhow::Result<T, Error>;
+
+/// Mnemonics of the instructions taking a single cell.
+pub const MNEMONICS: [(&str, char); 17] = [
+    ("add", '+'),
+    ("sub", '-'),
+    ("mul", '*'),
+    ("div", '/'),
+    ("mod", '%'),
+    ("not", '!'),
+    ("gt", '`'),
+    ("dup", ':'),
+    ("swap", '\\'),
+    ("pop", '$'),
+    ("write", ','),
+    ("writen", '.'),
+    ("read", '~'),
+    ("readn", '&'),
+    ("get", 'g'),
+    ("put", 'p'),
+    ("halt", '@'),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Condition {
+    Always,
+    /// Pops a value, jumping if it is zero
+    Zero,
+    /// Pops a value, jumping if it isn't zero
+    NonZero,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    /// Instruction taking a single cell, from `MNEMONICS`
+    Cell(char),
+    Push(i32),
+    /// Pushes the characters of a text in order
+    PushText(String),
+    /// Prints a text, leaving the stack as it found it
+    Print(String),
+    Jump(Condition, String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Item {
+    Label(String),
+    Instruction(Instruction),
+}
+
+/// Program laid out by the assembler, along with where each of its cells comes from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Assembly {
+    pub program: String,
+    pub source_map: SourceMap,
+}
+
+/// Cells of an assembled program traced back to the lines of funge assembly they come from.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceMap {
+    /// Funge assembly file
+    pub file: String,
+    pub spans: Vec<Span>,
+    /// Cell each label stands for
+    pub labels: BTreeMap<String, (usize, usize)>,
+}
+
+/// Cells of a row laid out from a single line.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Span {
+    pub y: usize,
+    pub columns: Range<usize>,
+    /// Line number, from 1
+    pub line: usize,
+    /// Instruction on that line
+    pub text: String,
+}
+
+impl SourceMap {
+    pub fn at(&self, (x, y): (usize, usize)) -> Option<&Span> {
+        self.spans
+            .iter()
+            .find(|span| span.y == y && span.columns.contains(&x))
+    }
+}
+
+/// Reads funge assembly: one instruction per line, each optionally preceded by a `label:`, with
+/// comments starting at a `;`. Items come with the number of their line.
+pub fn parse(source: &str) -> Result<Vec<(usize, Item)>> {
+    let mut items = Vec::new();
+    let mut defined = HashSet::new();
+    let mut jumps = Vec::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let number = index + 1;
+        let (label, line) = split_label(line);
+
+        if let Some(label) = label {
+            if !defined.insert(label.to_owned()) {
+                return Err(Error::Duplicate(number, label.to_owned()));
+            }
+            items.push((number, Item::Label(label.to_owned())));
+        }
+        if line.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, operand) = match line.split_once(char::is_whitespace) {
+            Some((mnemonic, operand)) => (mnemonic, operand.trim()),
+            None => (line, ""),
+        };
+        let expect = |what: &'static str| Error::Operand(number, mnemonic.to_owned(), what);
+
+        let instruction = match mnemonic {
+            "push" => match toml::unquote(operand) {
+                Some(text) => Instruction::PushText(text),
+                None => Instruction::Push(
+                    number_operand(operand)
+                        .ok_or_else(|| expect("a number, a character or a quoted text"))?,
+                ),
+            },
+            "print" => {
+                Instruction::Print(toml::unquote(operand).ok_or_else(|| expect("a quoted text"))?)
+            }
+            "jmp" | "jz" | "jnz" => {
+                if !is_label(operand) {
+                    return Err(expect("a label"));
+                }
+                jumps.push((number, operand.to_owned()));
+                let condition = match mnemonic {
+                    "jz" => Condition::Zero,
+                    "jnz" => Condition::NonZero,
+                    _ => Condition::Always,
+                };
+                Instruction::Jump(condition, operand.to_owned())
+            }
+            mnemonic => {
+                let (_, cell) = MNEMONICS
+                    .iter()
+                    .find(|(name, _)| *name == mnemonic)
+                    .ok_or_else(|| Error::Unknown(number, mnemonic.to_owned()))?;
+                if !operand.is_empty() {
+                    return Err(expect("no operand"));
+                }
+                Instruction::Cell(*cell)
+            }
+        };
+        items.push((number, Item::Instruction(instruction)));
+    }
+
+    if let Some((number, label)) = jumps
+        .into_iter()
+        .find(|(_, label)| !defined.contains(label))
+    {
+        return Err(Error::Undefined(number, label));
+    }
+    Ok(items)
+}
+
+/// Assembles funge assembly into a Befunge-93 program, leaving the file of its source map empty.
+///
+/// Instructions are laid out from left to right on the first row, each jump target getting a `>`
+/// to land on. Jumps leave it downwards into a lane, a row below the code where they travel to
+/// their target before going back up. Lanes are shared by jumps whose spans don't overlap.
+pub fn assemble(source: &str) -> Result<Assembly> {
+    let items = parse(source)?;
+    let lines = source.lines().collect::<Vec<_>>();
+
+    let targets = items
+        .iter()
+        .filter_map(|(_, item)| match item {
+            Item::Instruction(Instruction::Jump(_, label)) => Some(label.as_str()),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+
+    let mut code = String::new();
+    let mut labels = HashMap::new();
+    // Columns jumps go down from, with their target and line
+    let mut jumps = Vec::new();
+    let mut spans = Vec::new();
+    let span = |y: usize, columns: Range<usize>, line: usize| Span {
+        y,
+        columns,
+        line,
+        text: split_label(lines[line - 1]).1.to_owned(),
+    };
+
+    for (line, item) in &items {
+        let column = code.chars().count();
+        match item {
+            Item::Label(label) => {
+                labels.insert(label.as_str(), column);
+                if targets.contains(label.as_str()) {
+                    code.push('>');
+                }
+            }
+            Item::Instruction(instruction) => {
+                let snippet = match instruction {
+                    Instruction::Cell(cell) => cell.to_string(),
+                    Instruction::Push(value) => synthesis::number(*value, None),
+                    Instruction::PushText(text) => synthesis::text(text),
+                    Instruction::Print(text) => synthesis::print(text),
+                    Instruction::Jump(condition, label) => {
+                        // Conditional jumps skip the `v` going right, and reach it from the `_`
+                        // when the value isn't zero
+                        let snippet = match condition {
+                            Condition::Always => "v",
+                            Condition::Zero => "!#v_",
+                            Condition::NonZero => "#v_",
+                        };
+                        let offset = snippet.find('v').expect("jumps go down a `v`");
+                        jumps.push((column + offset, label.as_str(), *line));
+                        snippet.to_owned()
+                    }
+                };
+                code.push_str(&snippet);
+                spans.push(span(0, column..code.chars().count(), *line));
+            }
+        }
+    }
+
+    let width = code.chars().count();
+    let mut builder = GridBuilder::new().row(&code);
+    // Rightmost column taken in each lane
+    let mut lanes = Vec::<usize>::new();
+
+    let mut jumps = jumps
+        .into_iter()
+        .map(|(from, label, line)| (from, labels[label], line))
+        .collect::<Vec<_>>();
+    jumps.sort_by_key(|(from, to, _)| *from.min(to));
+    for (from, to, line) in jumps {
+        let (left, right) = (from.min(to), from.max(to));
+        let lane = match lanes.iter().position(|taken| *taken < left) {
+            Some(lane) => lane,
+            None => {
+                lanes.push(0);
+                lanes.len() - 1
+            }
+        };
+        lanes[lane] = right;
+
+        builder = builder
+            .at(from, lane + 1, if to > from { ">" } else { "<" })
+            .at(to, lane + 1, "^");
+        spans.push(span(lane + 1, left..right + 1, line));
+    }
+
+    let program = builder.text();
+    // Labels at the very end stand for no cell
+    let labels = labels
+        .into_iter()
+        .filter(|(_, column)| *column < width)
+        .map(|(label, column)| (label.to_owned(), (column, 0)))
+        .collect();
+
+    Ok(Assembly {
+        program,
+        source_map: SourceMap {
+            file: String::new(),
+            spans,
+            labels,
+        },
+    })
+}
+
+/// Splits the label off a line, comment excluded.
+fn split_label(line: &str) -> (Option<&str>, &str) {
+    let line = strip_comment(line).trim();
+    match line.split_once(':').filter(|(label, _)| is_label(label)) {
+        Some((label, rest)) => (Some(label), rest.trim()),
+        None => (None, line),
+    }
+}
+
+fn is_label(text: &str) -> bool {
+    !text.is_empty()
+        && !text.starts_with(|c: char| c.is_ascii_digit())
+        && text.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Reads a decimal number or a quoted character such as `'a'`.
+fn number_operand(text: &str) -> Option<i32> {
+    if let Some(inner) = text
+        .strip_prefix('\'')
+        .and_then(|text| text.strip_suffix('\''))
+    {
+        let mut chars = inner.chars();
+        return match (chars.next(), chars.next()) {
+            (Some(c), None) => i32::try_from(u32::from(c)).ok(),
+            _ => None,
+        };
+    }
+    text.parse().ok()
+}
+
+/// Cuts a line at a `;` starting a word outside of any text.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut previous = ' ';
+    for (index, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted && previous.is_whitespace() => return &line[..index],
+            _ => (),
+        }
+        previous = c;
+    }
+    line
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        grid::Grid,
+        interpreter::{Interpreter, Status},
+    };
+
+    fn run(source: &str, input: &str) -> String {
+        let mut interpreter =
+            Interpreter::new(Grid::from(super::assemble(source).unwrap().program));
+        interpreter.feed_input(input);
+        for _ in 0..100_000 {
+            if interpreter.step().unwrap() != Status::Running {
+                break;
+            }
+        }
+        interpreter.take_output()
+    }
+
+    #[test]
+    fn layout() {
+        let countdown = "
+                push 5
+            loop:
+                dup
+                writen      ; print the counter
+                push 1
+                sub
+                dup
+                jnz loop
+                pop
+                print \"done\\n\"
+                halt
+        ";
+        assert_eq!(run(countdown, ""), "5 4 3 2 1 done\n");
+        let source_map = super::assemble(countdown).unwrap().source_map;
+        assert_eq!(source_map.labels["loop"], (1, 0));
+        let dup = source_map.at((2, 0)).unwrap();
+        assert_eq!((dup.line, dup.text.as_str()), (4, "dup"));
+        assert_eq!(source_map.at((1, 0)), None);
+
+        let echo = "
+            next: read
+                dup
+                push '.'
+                sub
+                jz end
+                write
+                jmp next
+            end: halt
+        ";
+        assert_eq!(run(echo, "abc.def"), "abc");
+
+        let nested = "
+                push 3
+            outer:
+                push 0      ; end of the text
+                push \"ba\"
+            inner:
+                dup
+                jz inner_end
+                write
+                jmp inner
+            inner_end:
+                pop
+                push 1
+                sub
+                dup
+                jnz outer
+                halt
+        ";
+        assert_eq!(run(nested, ""), "ababab");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse("jmp nowhere"),
+            Err(Error::Undefined(1, "nowhere".to_owned()))
+        );
+        assert_eq!(
+            parse("a:\na: halt"),
+            Err(Error::Duplicate(2, "a".to_owned()))
+        );
+        assert_eq!(parse("\nfoo"), Err(Error::Unknown(2, "foo".to_owned())));
+        assert_eq!(
+            parse("push x"),
+            Err(Error::Operand(
+                1,
+                "push".to_owned(),
+                "a number, a character or a quoted text"
+            ))
+        );
+        assert_eq!(
+            parse("push ';' ; semicolon"),
+            Ok(vec![(1, Item::Instruction(Instruction::Push(59)))])
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    io::Read,
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+use rayon::prelude::*;
+
+use puccinia::{
+    debugger::Debugger,
+    dialect::{Dialect, Extension},
+    interpreter::{Flush, Interpreter, Schedule},
+    script::{Player, Script},
+    statistics::Statistics,
+};
+
+use crate::{
+    headless::{self, Error, Geometry, Observers, System},
+    settings::Semantics,
+};
+
+type Result<T> = anyhow::Result<T>;
+
+/// Options shared by every program of a batch.
+pub(crate) struct Settings {
+    pub jobs: Option<usize>,
+    pub history: usize,
+    pub flush: Flush,
+    pub dialect: Option<Dialect>,
+    pub extensions: Vec<Extension>,
+    /// Dialect and extensions of the settings, for programs leaving them out
+    pub config: Semantics,
+    pub schedule: Option<Schedule>,
+    /// Whether input is written to the output as it is read
+    pub echo: bool,
+    pub geometry: Geometry,
+    pub system: System,
+    pub stats: bool,
+    /// Input fed to every program instead of stdin
+    pub script: Option<Script>,
+}
+
+/// How a single program of a batch went.
+struct Outcome {
+    output: Vec<u8>,
+    ticks: u64,
+    elapsed: Duration,
+    statistics: Statistics,
+    /// Code the program quit with through `q`
+    exit_code: Option<i32>,
+    result: Result<()>,
+}
+
+/// Runs programs concurrently, each of them being fed the whole of stdin as input, unless it is
+/// a bundle with input of its own.
+/// Outputs are printed in order once every program ended, followed by a report on stderr.
+pub(crate) fn run(inputs: &[String], settings: Settings) -> Result<()> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(settings.jobs.unwrap_or_default())
+        .build()?;
+
+    let stdin = OnceLock::new();
+    let outcomes = pool.install(|| {
+        inputs
+            .par_iter()
+            .map(|input| execute(input, &settings, &stdin))
+            .collect::<Vec<_>>()
+    });
+
+    for (input, outcome) in inputs.iter().zip(&outcomes) {
+        println!("==> {input} <==");
+        print!("{}", String::from_utf8_lossy(&outcome.output));
+        if !outcome.output.is_empty() && !outcome.output.ends_with(b"\n") {
+            println!();
+        }
+    }
+
+    let mut failures = 0;
+    let mut statistics = Statistics::default();
+    for (input, outcome) in inputs.iter().zip(&outcomes) {
+        // Programs quitting with a non-zero code signal they failed
+        match (&outcome.result, outcome.exit_code.unwrap_or_default()) {
+            (Ok(()), 0) => eprintln!(
+                "{input}: terminated after {} ticks in {:.2?}",
+                outcome.ticks, outcome.elapsed
+            ),
+            (Ok(()), code) => {
+                failures += 1;
+                eprintln!(
+                    "{input}: exited with code {code} after {} ticks",
+                    outcome.ticks
+                );
+            }
+            (Err(err), _) => {
+                failures += 1;
+                eprintln!("{input}: failed after {} ticks: {err}", outcome.ticks);
+            }
+        }
+        statistics.merge(&outcome.statistics);
+    }
+
+    if settings.stats {
+        eprint!("{statistics}");
+    }
+
+    if failures > 0 {
+        return Err(Error::Failures(failures, inputs.len()).into());
+    }
+
+    eprintln!("{} programs terminated", inputs.len());
+    Ok(())
+}
+
+fn execute(input: &str, settings: &Settings, stdin: &OnceLock<String>) -> Outcome {
+    let start = Instant::now();
+
+    let (grid, directives, canned) = match headless::load(input, settings.geometry) {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            return Outcome {
+                output: Vec::new(),
+                ticks: 0,
+                elapsed: start.elapsed(),
+                statistics: Statistics::default(),
+                exit_code: None,
+                result: Err(err.into()),
+            }
+        }
+    };
+
+    let mut interpreter = Interpreter::new(grid);
+    interpreter.set_fast_forward(true);
+    interpreter.set_echo(settings.echo);
+    interpreter.set_flush(settings.flush);
+    let semantics =
+        Semantics::new(settings.dialect, &settings.extensions).or(&settings.config, &directives);
+    headless::configure(
+        &mut interpreter,
+        &directives,
+        semantics.dialect,
+        &semantics.extensions,
+        settings.schedule.as_ref(),
+    );
+    settings.system.apply(&mut interpreter, input);
+
+    let mut debugger = Debugger::new(interpreter).with_history(settings.history);
+
+    let mut output = Vec::new();
+    let mut fed = false;
+    let player = settings.script.as_ref().map(Player::new);
+    let result = headless::execute(
+        &mut debugger,
+        &mut output,
+        player,
+        Observers::<std::io::Sink>::default(),
+        |_| {
+            if let Some(canned) = &canned {
+                return Ok((!std::mem::replace(&mut fed, true)).then(|| canned.clone()));
+            }
+
+            // Only read once the first program asks for input, so that stdin is not waited on when
+            // no program needs it
+            let stdin = stdin.get_or_init(|| {
+                let mut content = String::new();
+                let _ = std::io::stdin().read_to_string(&mut content);
+                content
+            });
+            Ok((!std::mem::replace(&mut fed, true)).then(|| stdin.clone()))
+        },
+    )
+    .and_then(|()| headless::check_output(&directives, &output));
+
+    Outcome {
+        output,
+        ticks: debugger.interpreter().ticks(),
+        elapsed: start.elapsed(),
+        statistics: debugger.statistics().clone(),
+        exit_code: debugger.interpreter().exit_code(),
+        result,
+    }
+}
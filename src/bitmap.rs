@@ -0,0 +1,118 @@
+/// Image as the brightness of its pixels, row by row, 0 being black.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bitmap {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Bitmap {
+    /// Reads pixels of `channels` bytes each, grey, grey and alpha, RGB or RGBA. Transparent
+    /// pixels are laid over white.
+    pub fn from_pixels(width: usize, height: usize, channels: usize, data: &[u8]) -> Self {
+        let pixels = data
+            .chunks_exact(channels)
+            .take(width * height)
+            .map(|pixel| {
+                let (colour, alpha) = match pixel {
+                    [grey] => (*grey as u32, 255),
+                    [grey, alpha] => (*grey as u32, *alpha as u32),
+                    [r, g, b] => (luma(*r, *g, *b), 255),
+                    [r, g, b, alpha, ..] => (luma(*r, *g, *b), *alpha as u32),
+                    [] => (255, 255),
+                };
+                ((colour * alpha + 255 * (255 - alpha)) / 255) as u8
+            })
+            .collect();
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    fn get(&self, x: usize, y: usize) -> u8 {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Scales the image down to `width` pixels across, keeping its proportions and averaging the
+    /// pixels merged together. Images narrower than that are left as they are.
+    pub fn shrink(&self, width: usize) -> Self {
+        if width == 0 || width >= self.width {
+            return self.clone();
+        }
+        let height = (self.height * width).div_ceil(self.width).max(1);
+
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let rows = y * self.height / height..(y + 1) * self.height / height;
+            for x in 0..width {
+                let columns = x * self.width / width..(x + 1) * self.width / width;
+                let area = rows.len() * columns.len();
+                let sum = rows
+                    .clone()
+                    .flat_map(|y| columns.clone().map(move |x| (x, y)))
+                    .map(|(x, y)| self.get(x, y) as usize)
+                    .sum::<usize>();
+                pixels.push((sum / area) as u8);
+            }
+        }
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Block of cells, one per pixel: `ink` for pixels darker than `threshold` and `paper` for
+    /// the others, each line ending with a newline.
+    pub fn to_text(&self, threshold: u8, ink: char, paper: char) -> String {
+        let mut text = String::with_capacity((self.width + 1) * self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                text.push(if self.get(x, y) < threshold {
+                    ink
+                } else {
+                    paper
+                });
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Same image with dark and light swapped.
+    pub fn inverted(mut self) -> Self {
+        for pixel in &mut self.pixels {
+            *pixel = 255 - *pixel;
+        }
+        self
+    }
+}
+
+/// Brightness of a colour as perceived, after ITU-R BT.601.
+fn luma(r: u8, g: u8, b: u8) -> u32 {
+    (299 * r as u32 + 587 * g as u32 + 114 * b as u32) / 1000
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn to_text() {
+        // Black, white, red and a transparent pixel
+        let data = [0, 0, 0, 255, 255, 255, 255, 255, 255, 0, 0, 255, 0, 0, 0, 0];
+        let bitmap = Bitmap::from_pixels(2, 2, 4, &data);
+        assert_eq!(bitmap.pixels, vec![0, 255, 76, 255]);
+        assert_eq!(bitmap.to_text(128, '#', ' '), "# \n# \n");
+        assert_eq!(bitmap.to_text(50, '#', '.'), "#.\n..\n");
+        assert_eq!(bitmap.clone().inverted().to_text(128, '#', ' '), " #\n #\n");
+
+        let wide = Bitmap::from_pixels(4, 2, 1, &[0, 0, 255, 255, 0, 255, 255, 255]);
+        let shrunk = wide.shrink(2);
+        assert_eq!((shrunk.width, shrunk.height), (2, 1));
+        assert_eq!(shrunk.pixels, vec![63, 255]);
+    }
+}
@@ -0,0 +1,185 @@
+use std::fmt::Write;
+
+use crate::{annotation::Region, assembler};
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Unmatched `{2}` at line {0}, column {1}")]
+    Unmatched(usize, usize, char),
+}
+
+pub type Result<T> = anyhow::Result<T, Error>;
+
+/// Befunge-93 program simulating a Brainfuck one, along with the row holding its tape.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Transpiled {
+    pub program: String,
+    pub tape: Region,
+}
+
+/// Brainfuck instruction, repeated ones merged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Move(isize),
+    Add(i32),
+    Clear,
+    Write,
+    Read,
+    /// Start of a loop, numbered
+    Open(usize),
+    Close(usize),
+}
+
+/// Merges runs of Brainfuck instructions and numbers loops, ignoring any other character.
+fn parse(source: &str) -> Result<Vec<Op>> {
+    let mut ops = Vec::new();
+    let mut open = Vec::new();
+    let mut loops = 0;
+
+    for (y, line) in source.lines().enumerate() {
+        for (x, c) in line.chars().enumerate() {
+            let op = match c {
+                '>' => Op::Move(1),
+                '<' => Op::Move(-1),
+                '+' => Op::Add(1),
+                '-' => Op::Add(-1),
+                '.' => Op::Write,
+                ',' => Op::Read,
+                '[' => {
+                    open.push((loops, y + 1, x + 1));
+                    loops += 1;
+                    Op::Open(loops - 1)
+                }
+                ']' => match open.pop() {
+                    Some((number, ..)) => Op::Close(number),
+                    None => return Err(Error::Unmatched(y + 1, x + 1, ']')),
+                },
+                _ => continue,
+            };
+
+            match (ops.last_mut(), op) {
+                (Some(Op::Move(offset)), Op::Move(more)) => *offset += more,
+                (Some(Op::Add(amount)), Op::Add(more)) => *amount += more,
+                _ => ops.push(op),
+            }
+            // `[-]` and `[+]` clear the current cell
+            if let [.., Op::Open(_), Op::Add(1 | -1), Op::Close(_)] = ops[..] {
+                ops.truncate(ops.len() - 3);
+                ops.push(Op::Clear);
+            }
+        }
+    }
+
+    match open.pop() {
+        Some((_, y, x)) => Err(Error::Unmatched(y, x, '[')),
+        None => Ok(ops),
+    }
+}
+
+/// Funge assembly of a Brainfuck program keeping its tape of `tape` cells on row `row`. The
+/// stack only ever holds the position of the tape head.
+fn assembly(ops: &[Op], tape: usize, row: usize) -> String {
+    // Cells of the tape are only the grid's once zeroed
+    let mut asm = format!(
+        "    push {tape}\n\
+         clear:\n    push 1\n    sub\n    dup\n    push 0\n    swap\n    push {row}\n    put\n    \
+         dup\n    jnz clear\n"
+    );
+    let load = format!("    dup\n    push {row}\n    get\n");
+
+    for op in ops {
+        match *op {
+            Op::Move(0) => Ok(()),
+            Op::Add(amount) if amount.rem_euclid(256) == 0 => Ok(()),
+            Op::Move(offset) if offset > 0 => write!(asm, "    push {offset}\n    add\n"),
+            Op::Move(offset) => write!(asm, "    push {}\n    sub\n", -offset),
+            Op::Add(amount) => write!(
+                asm,
+                "    dup\n{load}    push {}\n    add\n    push 256\n    mod\n    swap\n    \
+                 push {row}\n    put\n",
+                amount.rem_euclid(256)
+            ),
+            Op::Clear => write!(
+                asm,
+                "    dup\n    push 0\n    swap\n    push {row}\n    put\n"
+            ),
+            Op::Write => writeln!(asm, "{load}    write"),
+            Op::Read => write!(
+                asm,
+                "    dup\n    read\n    swap\n    push {row}\n    put\n"
+            ),
+            Op::Open(number) => write!(asm, "{load}    jz end{number}\nloop{number}:\n"),
+            Op::Close(number) => write!(asm, "{load}    jnz loop{number}\nend{number}:\n"),
+        }
+        .expect("writing to a string can't fail");
+    }
+
+    asm + "    halt\n"
+}
+
+/// Transpiles a Brainfuck program to Befunge-93, its tape of `tape` cells being kept in a row
+/// below the code, which its cells are read from and written to with `g` and `p`.
+///
+/// Cells wrap around at 256 and reading input writes the character read, as is.
+pub fn transpile(source: &str, tape: usize) -> Result<Transpiled> {
+    let ops = parse(source)?;
+    let assemble = |row: usize| {
+        assembler::assemble(&assembly(&ops, tape, row))
+            .expect("generated funge assembly is valid")
+            .program
+    };
+
+    // Each loop takes at most two lanes, and the clearing loop one
+    let loops = ops.iter().filter(|op| matches!(op, Op::Open(_))).count();
+    let mut row = 2 * loops + 2;
+    let mut program = assemble(row);
+    // A smaller row number is a shorter push, which mustn't take more lanes
+    let rows = program.lines().count();
+    let tighter = assemble(rows);
+    if tighter.lines().count() <= rows {
+        (row, program) = (rows, tighter);
+    }
+
+    let padding = row - program.lines().count();
+    program += &"\n".repeat(padding);
+    program += &"0".repeat(tape);
+    program.push('\n');
+
+    Ok(Transpiled {
+        program,
+        tape: Region::new((0, row), (tape.saturating_sub(1), row)),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        grid::Grid,
+        interpreter::{Interpreter, Status},
+    };
+
+    fn run(source: &str, input: &str) -> String {
+        let transpiled = super::transpile(source, 30).unwrap();
+        let mut interpreter = Interpreter::new(Grid::from(transpiled.program));
+        interpreter.feed_input(input);
+        for _ in 0..1_000_000 {
+            if interpreter.step().unwrap() != Status::Running {
+                break;
+            }
+        }
+        interpreter.take_output()
+    }
+
+    #[test]
+    fn transpile() {
+        assert_eq!(run("++++++++[>++++++++<-]>+.+.", ""), "AB");
+        assert_eq!(run(",[.,]", "echo\0"), "echo");
+        assert_eq!(run("-.>+[-]+[>+<-]>.", ""), "\u{ff}\u{1}");
+        assert_eq!(
+            super::transpile("+[\n[-]", 30),
+            Err(Error::Unmatched(1, 2, '['))
+        );
+        assert_eq!(super::transpile("]", 30), Err(Error::Unmatched(1, 1, ']')));
+    }
+}
@@ -0,0 +1,111 @@
+use crate::grid::Grid;
+
+/// Program laid out piece by piece, rows growing as text is written past their end, e.g.
+/// `GridBuilder::new().row("v  <").at(10, 2, ">:#,_@").build()`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GridBuilder {
+    rows: Vec<Vec<char>>,
+    /// Size the grid is grown to when built, whatever it holds
+    size: (usize, usize),
+}
+
+impl GridBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds rows below the others, one per line of `text`.
+    pub fn row(mut self, text: &str) -> Self {
+        let y = self.rows.len();
+        if text.is_empty() {
+            self.rows.push(Vec::new());
+            return self;
+        }
+        self.at(0, y, text)
+    }
+
+    /// Writes `text` from `(x, y)` to the right, its next lines starting at `x` on the rows
+    /// below. Spaces are written too, replacing what was there.
+    pub fn at(mut self, x: usize, y: usize, text: &str) -> Self {
+        for (dy, line) in text.lines().enumerate() {
+            for (dx, c) in line.chars().enumerate() {
+                self.set(x + dx, y + dy, c);
+            }
+        }
+        self
+    }
+
+    /// Writes `text` from `(x, y)` downwards, as read by an instruction pointer going down.
+    pub fn down(mut self, x: usize, y: usize, text: &str) -> Self {
+        for (dy, c) in text.chars().enumerate() {
+            self.set(x, y + dy, c);
+        }
+        self
+    }
+
+    /// Makes the grid at least `width` by `height` once built.
+    pub fn size(mut self, width: usize, height: usize) -> Self {
+        self.size = (width, height);
+        self
+    }
+
+    fn set(&mut self, x: usize, y: usize, c: char) {
+        if self.rows.len() <= y {
+            self.rows.resize_with(y + 1, Vec::new);
+        }
+        let row = &mut self.rows[y];
+        if row.len() <= x {
+            row.resize(x + 1, ' ');
+        }
+        row[x] = c;
+    }
+
+    /// Program file holding the rows, without trailing spaces.
+    pub fn text(&self) -> String {
+        self.rows
+            .iter()
+            .map(|row| row.iter().collect::<String>().trim_end().to_owned() + "\n")
+            .collect()
+    }
+
+    pub fn build(&self) -> Grid {
+        let mut grid = Grid::from(self.text());
+        let (width, height) = self.size;
+        if width > 0 && height > 0 {
+            grid.grow_to(width - 1, height - 1);
+        }
+        grid
+    }
+}
+
+/// Builds a [Grid](crate::grid::Grid) from its rows, followed by pieces of text to write at
+/// given positions: `grid!["v", ">1.v"; (3, 2) => "@"]`.
+#[macro_export]
+macro_rules! grid {
+    ($($row:expr),* $(,)? $(; $(($x:expr, $y:expr) => $text:expr),* $(,)?)?) => {
+        $crate::builder::GridBuilder::new()
+            $(.row($row))*
+            $($(.at($x, $y, $text))*)?
+            .build()
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn overlap() {
+        let builder = GridBuilder::new()
+            .row("12345")
+            .at(1, 0, " x")
+            .at(2, 2, "ab\ncd")
+            .down(0, 1, "vv")
+            .size(8, 5);
+        assert_eq!(builder.text(), "1 x45\nv\nv ab\n  cd\n");
+        assert_eq!(builder.build().size(), (8, 5));
+
+        let grid = crate::grid!["v", ">1.v"; (3, 2) => "@"];
+        assert_eq!(grid.lines(), vec!["v", ">1.v", "   @"]);
+    }
+}
@@ -0,0 +1,149 @@
+use std::{fs::File, io::Read, path::Path};
+
+use crate::{
+    directives::{self, Directives},
+    grid::Grid,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("Bundle has no `{PROGRAM}`")]
+    MissingProgram,
+    #[error(transparent)]
+    Directives(#[from] directives::Error),
+}
+
+pub type Result<T> = anyhow::Result<T, Error>;
+
+/// File extension of bundles.
+pub const EXTENSION: &str = "mstpkg";
+
+const PROGRAM: &str = "program.bf";
+/// Directives in the `key: value` form, taking precedence over the program's own
+const DIRECTIVES: &str = "directives";
+const INPUT: &str = "input.txt";
+const OUTPUT: &str = "output.txt";
+
+/// Program packed with everything needed to reproduce a run of it, stored as a tar archive.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Bundle {
+    pub program: String,
+    /// Expected output included
+    pub directives: Directives,
+    /// Fed to the program as a whole when it starts
+    pub input: Option<String>,
+}
+
+/// Whether a path designates a bundle rather than a plain program.
+pub fn is_bundle(path: impl AsRef<Path>) -> bool {
+    path.as_ref()
+        .extension()
+        .is_some_and(|extension| extension == EXTENSION)
+}
+
+impl Bundle {
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_reader(File::open(path)?)
+    }
+
+    pub fn from_reader(reader: impl Read) -> Result<Self> {
+        let mut archive = tar::Archive::new(reader);
+
+        let mut program = None;
+        let mut bundle = Bundle::default();
+        let mut output = None;
+
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let name = entry.path()?.to_string_lossy().into_owned();
+
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+
+            match name.as_str() {
+                PROGRAM => program = Some(content),
+                DIRECTIVES => bundle.directives = Directives::parse(&content, false)?,
+                INPUT => bundle.input = Some(content),
+                OUTPUT => output = Some(content),
+                // Left for newer versions
+                _ => (),
+            }
+        }
+
+        bundle.program = program.ok_or(Error::MissingProgram)?;
+        bundle.directives.output = output.or(bundle.directives.output);
+
+        Ok(bundle)
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut builder = tar::Builder::new(File::create(path)?);
+
+        let mut append = |name: &str, content: &str| {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, content.as_bytes())
+        };
+
+        append(PROGRAM, &self.program)?;
+        append(
+            DIRECTIVES,
+            &Directives {
+                output: None,
+                ..self.directives.clone()
+            }
+            .to_text(),
+        )?;
+        if let Some(input) = &self.input {
+            append(INPUT, input)?;
+        }
+        if let Some(output) = &self.directives.output {
+            append(OUTPUT, output)?;
+        }
+
+        builder.into_inner()?;
+        Ok(())
+    }
+
+    /// Parses the program, the bundle's directives overriding the program's own.
+    pub fn load(&self) -> Result<(Grid, Directives)> {
+        let (grid, directives) = directives::parse_program(self.program.as_bytes())?;
+        Ok((grid, directives.merge(self.directives.clone())))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let bundle = Bundle {
+            program: "#!mst seed=3\n&.@\n".to_owned(),
+            directives: Directives {
+                seed: Some(4),
+                output: Some("12 \n".to_owned()),
+                ..Default::default()
+            },
+            input: Some("12".to_owned()),
+        };
+
+        let path =
+            std::env::temp_dir().join(format!("puccinia-{}.{EXTENSION}", std::process::id()));
+        bundle.write(&path).unwrap();
+        let read = Bundle::read(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let read = read.unwrap();
+        assert_eq!(read, bundle);
+        assert!(is_bundle(&path));
+
+        let (grid, directives) = read.load().unwrap();
+        assert_eq!(grid.size(), (3, 1));
+        assert_eq!(directives.seed, Some(4));
+    }
+}
@@ -1,17 +1,58 @@
 use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
 
 /// Represents a single cell of the grid.
-#[derive(Clone, Debug, Copy)]
+#[derive(Clone, Debug, Copy, Serialize, Deserialize)]
 pub struct Cell {
     /// The content of the cell
     pub value: CellValue,
-    /// Heat represents how long ago the cell was last "visited" by a cursor.
-    pub heat: i8,
+    /// Tick at which the cell was last "visited" by a cursor.
+    pub last_visit: Option<u64>,
+    /// Where the content of the cell comes from
+    #[serde(default)]
+    pub origin: Origin,
+}
+
+/// Where the content of a cell comes from, telling apart what the program was loaded with from
+/// what was changed since.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Origin {
+    /// The program file, or nothing for cells past it
+    #[default]
+    Loaded,
+    /// Typed or pasted in an editor, or by a collaborator
+    Edited,
+    /// Written by `p` at this tick of the current run
+    Written(u64),
+}
+
+impl std::fmt::Display for Origin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Origin::Loaded => write!(f, "as loaded"),
+            Origin::Edited => write!(f, "edited"),
+            Origin::Written(tick) => write!(f, "written by `p` at tick {tick}"),
+        }
+    }
+}
+
+impl Cell {
+    /// Heat represents how long ago the cell was last visited, from `i8::MAX` when visited on the
+    /// previous tick down to 0.
+    pub fn heat(&self, now: u64) -> i8 {
+        self.last_visit.map_or(0, |tick| {
+            (i8::MAX as u64).saturating_sub(now.saturating_sub(tick + 1)) as i8
+        })
+    }
 }
 
 impl From<CellValue> for Cell {
     fn from(value: CellValue) -> Self {
-        Cell { value, heat: 0 }
+        Cell {
+            value,
+            last_visit: None,
+            origin: Origin::Loaded,
+        }
     }
 }
 
@@ -22,7 +63,8 @@ impl From<char> for Cell {
 }
 
 #[cfg_attr(test, derive(Hash, PartialEq, Eq))]
-#[derive(Clone, Debug, Copy)]
+#[derive(Clone, Debug, Copy, Serialize, Deserialize)]
+#[serde(from = "char", into = "char")]
 pub enum CellValue {
     Empty,
     Op(Operator),
@@ -42,12 +84,14 @@ impl From<char> for CellValue {
             '\"' => CellValue::StringMode,
             '#' => CellValue::Bridge,
             '@' => CellValue::End,
-            v @ '0'..='9' => CellValue::Number(u32::from(v)),
+            v @ '0'..='9' => CellValue::Number(v.to_digit(10).unwrap()),
             c => {
                 if let Ok(op) = Operator::try_from(c) {
                     CellValue::Op(op)
                 } else if let Ok(dir) = Direction::try_from(c) {
                     CellValue::Dir(dir)
+                } else if let Ok(dir) = IfDir::try_from(c) {
+                    CellValue::If(dir)
                 } else {
                     CellValue::Char(c)
                 }
@@ -56,6 +100,57 @@ impl From<char> for CellValue {
     }
 }
 
+impl CellValue {
+    /// Short description of the instruction's behaviour.
+    pub fn documentation(&self) -> &'static str {
+        match self {
+            CellValue::Empty => "No-op",
+            CellValue::Op(Operator::Nullary(NullaryOperator::Integer)) => {
+                "Read an integer from input and push it"
+            }
+            CellValue::Op(Operator::Nullary(NullaryOperator::Ascii)) => {
+                "Read a character from input and push its value"
+            }
+            CellValue::Op(Operator::Unary(op)) => match op {
+                UnaryOperator::Negate => "Pop a value, push 1 if it is zero, 0 otherwise",
+                UnaryOperator::Duplicate => "Duplicate the top of the stack",
+                UnaryOperator::Pop => "Pop a value and discard it",
+                UnaryOperator::WriteNumber => "Pop a value and output it as an integer",
+                UnaryOperator::WriteASCII => "Pop a value and output it as a character",
+            },
+            CellValue::Op(Operator::Binary(op)) => match op {
+                BinaryOperator::Greater => "Pop b then a, push 1 if a > b, 0 otherwise",
+                BinaryOperator::Add => "Pop b then a, push a + b",
+                BinaryOperator::Subtract => "Pop b then a, push a - b",
+                BinaryOperator::Multiply => "Pop b then a, push a * b",
+                BinaryOperator::Divide => "Pop b then a, push a / b",
+                BinaryOperator::Modulo => "Pop b then a, push a % b",
+                BinaryOperator::Swap => "Swap the two values on top of the stack",
+                BinaryOperator::Get => "Pop y then x, push the value of the cell at (x, y)",
+            },
+            CellValue::Op(Operator::Ternary(TernaryOperator::Put)) => {
+                "Pop y, x then v, store v in the cell at (x, y)"
+            }
+            CellValue::Dir(Direction::Up) => "Move up",
+            CellValue::Dir(Direction::Down) => "Move down",
+            CellValue::Dir(Direction::Left) => "Move left",
+            CellValue::Dir(Direction::Right) => "Move right",
+            CellValue::Dir(Direction::Random) => "Move in a random direction",
+            CellValue::If(IfDir::Horizontal) => "Pop a value, move right if zero, left otherwise",
+            CellValue::If(IfDir::Vertical) => "Pop a value, move down if zero, up otherwise",
+            CellValue::StringMode => "Toggle string mode, pushing each traversed character",
+            CellValue::Bridge => "Skip the next cell",
+            CellValue::End => "End the program",
+            CellValue::Number(_) => "Push this digit",
+            CellValue::Char('A') => {
+                "Pop a value, end the program with a failed assertion if zero, with the `assert` \
+                 extension"
+            }
+            CellValue::Char(_) => "Unknown instruction, treated as a no-op",
+        }
+    }
+}
+
 impl From<CellValue> for char {
     fn from(value: CellValue) -> Self {
         match value {
@@ -66,7 +161,7 @@ impl From<CellValue> for char {
             CellValue::StringMode => '"',
             CellValue::Bridge => '#',
             CellValue::End => '@',
-            CellValue::Number(num) => num.to_string().chars().next().unwrap(),
+            CellValue::Number(num) => char::from_digit(num, 10).unwrap(),
             CellValue::Char(c) => c,
         }
     }
@@ -176,8 +271,7 @@ char_mapping! {
         Random = '?';
 }
 
-#[cfg_attr(test, derive(Hash, Eq))]
-#[derive(Default, PartialEq, Clone, Debug, Copy)]
+#[derive(Default, PartialEq, Eq, Hash, Clone, Debug, Copy, Serialize, Deserialize)]
 pub enum Direction {
     Up,
     Down,
@@ -187,6 +281,31 @@ pub enum Direction {
     Random,
 }
 
+impl Direction {
+    /// Direction pointing the other way, [Direction::Random] staying as it is.
+    pub fn reversed(self) -> Self {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+            Direction::Random => Direction::Random,
+        }
+    }
+
+    /// Whether going this way from `from` to `to` went past an edge of the grid and came back
+    /// in from the opposite one.
+    pub fn wraps(self, from: (usize, usize), to: (usize, usize)) -> bool {
+        match self {
+            Direction::Up => to.1 > from.1,
+            Direction::Down => to.1 < from.1,
+            Direction::Left => to.0 > from.0,
+            Direction::Right => to.0 < from.0,
+            Direction::Random => false,
+        }
+    }
+}
+
 #[cfg_attr(test, derive(Hash, PartialEq, Eq))]
 #[derive(Clone, Debug, Copy)]
 pub enum NullaryOperator {
@@ -278,4 +397,15 @@ mod test {
             assert_eq!(*expected, got, "Failed to serialize {cell_value:?}: {got}",);
         }
     }
+
+    #[test]
+    fn deserialize() {
+        for c in "&~!:$.,`+-*/%\\gp^v<>?_|\"#@ 0123456789c".chars() {
+            let got = char::from(CellValue::from(c));
+            assert_eq!(c, got, "Failed to round-trip `{c}`");
+        }
+
+        assert_eq!(CellValue::from('7'), CellValue::Number(7));
+        assert_eq!(CellValue::from('_'), CellValue::If(IfDir::Horizontal));
+    }
 }
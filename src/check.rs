@@ -0,0 +1,77 @@
+use puccinia::{
+    analyzer::Severity,
+    dialect::{self, Dialect},
+    directives::{self, Tabs},
+    report::{Entry, Report},
+};
+
+use crate::headless;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Could not load `{0}`: {1}")]
+    Load(String, directives::Error),
+    #[error("Program is invalid in {1}, {0} error(s) found")]
+    Incompatible(usize, Dialect),
+}
+
+#[derive(clap::Args)]
+pub(crate) struct Options {
+    /// Program file location
+    input: String,
+
+    /// Dialect the program is meant to be run as
+    #[arg(long, value_name = "DIALECT", default_value_t)]
+    target: Dialect,
+
+    /// Also write the diagnostics to this file for editors and CI, as JSON or as SARIF if it
+    /// ends with `.sarif`
+    #[arg(long, value_name = "PATH")]
+    diagnostics: Option<String>,
+}
+
+/// Reports every construct of a program that would behave differently in the target dialect.
+pub(crate) fn run(options: Options) -> anyhow::Result<()> {
+    let (grid, _, _, source) = directives::open_with(&options.input, Tabs::default())
+        .map_err(|err| Error::Load(options.input.clone(), err))?;
+
+    let diagnostics = dialect::check(&grid, options.target);
+    for diagnostic in &diagnostics {
+        let (x, y) = diagnostic.position;
+        println!(
+            "{}:({x}, {y}): {}: {}",
+            options.input,
+            diagnostic.severity.name(),
+            diagnostic.message
+        );
+    }
+
+    if let Some(path) = &options.diagnostics {
+        let report = Report {
+            diagnostics: diagnostics
+                .iter()
+                .map(|diagnostic| {
+                    Entry::new(
+                        &options.input,
+                        diagnostic.severity,
+                        "dialect",
+                        &diagnostic.message,
+                    )
+                    .at(diagnostic.position, &source)
+                })
+                .collect(),
+            ..Default::default()
+        };
+        headless::write_report(path, &report)?;
+    }
+
+    let errors = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .count();
+    if errors > 0 {
+        return Err(Error::Incompatible(errors, options.target).into());
+    }
+
+    Ok(())
+}
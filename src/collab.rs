@@ -0,0 +1,391 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, Receiver, Sender},
+};
+
+use serde::{Deserialize, Serialize};
+
+use puccinia::{cell::CellValue, grid::Grid};
+
+type Result<T> = anyhow::Result<T>;
+
+/// Identifier of an instance taking part in a session, the host being 0.
+pub type Site = u32;
+
+const HOST: Site = 0;
+
+/// Lamport timestamp of an edit, the latest edit of a cell winning on every site whatever the
+/// order edits are received in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Stamp {
+    clock: u64,
+    site: Site,
+}
+
+/// Message exchanged between sites, one JSON document per line. Joining sites only talk to the
+/// host, which relays their messages to the others.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum Op {
+    /// Sent by the host to a joining site
+    Welcome {
+        site: Site,
+        grid: Box<Grid>,
+        stamps: Vec<((usize, usize), Stamp)>,
+        cursors: Vec<(Site, (usize, usize))>,
+    },
+    Set {
+        position: (usize, usize),
+        value: char,
+        stamp: Stamp,
+    },
+    Cursor {
+        site: Site,
+        position: (usize, usize),
+    },
+    Leave {
+        site: Site,
+    },
+}
+
+/// What happened on the network, as seen by the logic thread.
+#[derive(Debug)]
+enum Event {
+    Joined(Site, Sender<Op>),
+    Received(Site, Op),
+    /// A message that couldn't be read, and why
+    Malformed(Site, String),
+    Left(Site),
+}
+
+/// Change to the grid or cursors to show, resulting from remote edits.
+pub(crate) enum Update {
+    /// The whole grid was replaced
+    Load,
+    Cell((usize, usize)),
+    Cursors,
+    /// A site sent something that couldn't be read
+    Malformed(Site, String),
+}
+
+/// Shared grid being edited by several instances.
+#[derive(Debug)]
+pub(crate) struct Session {
+    site: Site,
+    /// Local edits made while joining, only shared once the host gave this site its identifier
+    pending: Option<Vec<((usize, usize), char)>>,
+    clock: u64,
+    /// Stamp of the last edit of each edited cell
+    stamps: HashMap<(usize, usize), Stamp>,
+    cursor: (usize, usize),
+    /// Cursors of the other sites
+    cursors: BTreeMap<Site, (usize, usize)>,
+    /// Host of the session for joining sites, every joined site for the host
+    peers: BTreeMap<Site, Sender<Op>>,
+    events: Receiver<Event>,
+}
+
+impl Session {
+    /// Waits for other instances to join on `address`.
+    pub fn host(address: &str) -> Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let (sender, events) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            for (site, stream) in (HOST + 1..).zip(listener.incoming()) {
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                if let Ok(outgoing) = connect(stream, site, sender.clone()) {
+                    if sender.send(Event::Joined(site, outgoing)).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Self::new(HOST, events))
+    }
+
+    /// Joins the session hosted on `address`, the grid being received once joined.
+    pub fn join(address: &str) -> Result<Self> {
+        let stream = TcpStream::connect(address)?;
+        let (sender, events) = mpsc::channel();
+
+        let outgoing = connect(stream, HOST, sender)?;
+
+        let mut session = Self::new(HOST, events);
+        session.pending = Some(Vec::new());
+        session.peers.insert(HOST, outgoing);
+        Ok(session)
+    }
+
+    fn new(site: Site, events: Receiver<Event>) -> Self {
+        Self {
+            site,
+            pending: None,
+            clock: 0,
+            stamps: HashMap::new(),
+            cursor: (0, 0),
+            cursors: BTreeMap::new(),
+            peers: BTreeMap::new(),
+            events,
+        }
+    }
+
+    pub fn cursors(&self) -> Vec<(Site, (usize, usize))> {
+        self.cursors
+            .iter()
+            .map(|(site, cursor)| (*site, *cursor))
+            .collect()
+    }
+
+    /// Shares a local edit of the grid, once joined.
+    pub fn set(&mut self, position: (usize, usize), value: char) {
+        if let Some(pending) = self.pending.as_mut() {
+            pending.push((position, value));
+            return;
+        }
+
+        self.clock += 1;
+        let stamp = Stamp {
+            clock: self.clock,
+            site: self.site,
+        };
+        self.stamps.insert(position, stamp);
+        self.broadcast(
+            None,
+            Op::Set {
+                position,
+                value,
+                stamp,
+            },
+        );
+    }
+
+    /// Shares the local cursor position.
+    pub fn move_cursor(&mut self, position: (usize, usize)) {
+        self.cursor = position;
+        if self.pending.is_some() {
+            return;
+        }
+        let site = self.site;
+        self.broadcast(None, Op::Cursor { site, position });
+    }
+
+    /// Applies what other sites did to `grid`.
+    pub fn receive(&mut self, grid: &mut Grid) -> Vec<Update> {
+        let mut updates = Vec::new();
+
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                Event::Joined(site, outgoing) => {
+                    let mut cursors = self.cursors();
+                    cursors.retain(|(other, _)| *other != site);
+                    cursors.push((self.site, self.cursor));
+                    let welcome = Op::Welcome {
+                        site,
+                        grid: Box::new(grid.clone()),
+                        stamps: self.stamps.iter().map(|(p, s)| (*p, *s)).collect(),
+                        cursors,
+                    };
+                    if outgoing.send(welcome).is_ok() {
+                        self.peers.insert(site, outgoing);
+                    }
+                }
+                Event::Received(from, op) => {
+                    if let Some(update) = self.apply(grid, &op) {
+                        updates.push(update);
+                    }
+                    // Joined sites only hear from the host, which passes messages on
+                    if self.hosting() && !matches!(op, Op::Welcome { .. }) {
+                        self.broadcast(Some(from), op);
+                    }
+                }
+                Event::Malformed(site, err) => updates.push(Update::Malformed(site, err)),
+                Event::Left(site) => {
+                    self.peers.remove(&site);
+                    if self.hosting() {
+                        self.cursors.remove(&site);
+                        self.broadcast(None, Op::Leave { site });
+                    } else {
+                        // The session ended with the host
+                        self.cursors.clear();
+                    }
+                    updates.push(Update::Cursors);
+                }
+            }
+        }
+
+        updates
+    }
+
+    fn apply(&mut self, grid: &mut Grid, op: &Op) -> Option<Update> {
+        match op {
+            Op::Welcome {
+                site,
+                grid: shared,
+                stamps,
+                cursors,
+            } => {
+                self.site = *site;
+                self.stamps = stamps.iter().copied().collect();
+                self.clock = self.stamps.values().map(|s| s.clock).max().unwrap_or(0);
+                self.cursors = cursors.iter().copied().collect();
+                grid.load(shared);
+
+                // What was edited while joining is made again on the shared grid
+                for ((x, y), value) in self.pending.take().unwrap_or_default() {
+                    grid.grow_to(x, y);
+                    grid.edit(x, y, CellValue::from(value));
+                    self.set((x, y), value);
+                }
+                self.move_cursor(self.cursor);
+                Some(Update::Load)
+            }
+            Op::Set {
+                position,
+                value,
+                stamp,
+            } => {
+                self.clock = self.clock.max(stamp.clock);
+                if self.stamps.get(position).is_some_and(|last| last >= stamp) {
+                    return None;
+                }
+                self.stamps.insert(*position, *stamp);
+
+                let (x, y) = *position;
+                let (width, height) = grid.size();
+                grid.grow_to(x, y);
+                grid.edit(x, y, CellValue::from(*value));
+
+                Some(if x < width && y < height {
+                    Update::Cell(*position)
+                } else {
+                    Update::Load
+                })
+            }
+            Op::Cursor { site, position } => {
+                self.cursors.insert(*site, *position);
+                Some(Update::Cursors)
+            }
+            Op::Leave { site } => {
+                self.cursors.remove(site);
+                Some(Update::Cursors)
+            }
+        }
+    }
+
+    /// Whether this site hosts the session, rather than joining it.
+    fn hosting(&self) -> bool {
+        self.site == HOST && self.pending.is_none()
+    }
+
+    fn broadcast(&mut self, except: Option<Site>, op: Op) {
+        self.peers
+            .retain(|site, peer| Some(*site) == except || peer.send(op.clone()).is_ok());
+    }
+}
+
+/// Spawns the threads exchanging messages with `peer` over `stream`, returning where to send
+/// messages to it.
+fn connect(stream: TcpStream, peer: Site, events: Sender<Event>) -> Result<Sender<Op>> {
+    let reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    std::thread::spawn(move || {
+        for line in reader.lines().map_while(std::io::Result::ok) {
+            match serde_json::from_str(&line) {
+                Ok(op) => {
+                    if events.send(Event::Received(peer, op)).is_err() {
+                        return;
+                    }
+                }
+                Err(err) => {
+                    if events
+                        .send(Event::Malformed(peer, err.to_string()))
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+            }
+        }
+        let _ = events.send(Event::Left(peer));
+    });
+
+    let (outgoing, receiver) = mpsc::channel::<Op>();
+    std::thread::spawn(move || {
+        for op in receiver {
+            let sent = serde_json::to_string(&op)
+                .map_err(std::io::Error::from)
+                .and_then(|line| writeln!(writer, "{line}"));
+            if sent.is_err() {
+                break;
+            }
+        }
+        let _ = writer.shutdown(std::net::Shutdown::Both);
+    });
+
+    Ok(outgoing)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn concurrent_edits() {
+        let mut sessions = [1, 2].map(|site| Session::new(site, mpsc::channel().1));
+        let mut grids = [Grid::new(2, 1), Grid::new(2, 1)];
+
+        // Both sites edit the same cell before hearing of the other's edit
+        for ((session, grid), value) in sessions.iter_mut().zip(&mut grids).zip(['a', 'b']) {
+            grid.set(0, 0, CellValue::from(value));
+            session.set((0, 0), value);
+        }
+        let edits = [(1, 'a'), (2, 'b')].map(|(site, value)| Op::Set {
+            position: (0, 0),
+            value,
+            stamp: Stamp { clock: 1, site },
+        });
+
+        sessions[0].apply(&mut grids[0], &edits[1]);
+        sessions[1].apply(&mut grids[1], &edits[0]);
+        for grid in &grids {
+            assert_eq!(char::from(grid.get(0, 0).value), 'b');
+        }
+    }
+    #[test]
+    fn edits_while_joining() {
+        let mut session = Session::new(HOST, mpsc::channel().1);
+        session.pending = Some(Vec::new());
+        let (host, received) = mpsc::channel();
+        session.peers.insert(HOST, host);
+        let mut grid = Grid::new(2, 1);
+
+        // Nothing is sent under the host's identifier before being welcomed
+        grid.set(1, 0, CellValue::from('x'));
+        session.set((1, 0), 'x');
+        assert!(received.try_recv().is_err());
+
+        let welcome = Op::Welcome {
+            site: 3,
+            grid: Box::new(Grid::new(3, 1)),
+            stamps: vec![],
+            cursors: vec![],
+        };
+        session.apply(&mut grid, &welcome);
+        assert_eq!(grid.size(), (3, 1));
+        assert_eq!(char::from(grid.get(1, 0).value), 'x');
+        assert!(matches!(
+            received.try_recv(),
+            Ok(Op::Set {
+                position: (1, 0),
+                value: 'x',
+                stamp: Stamp { site: 3, .. },
+            })
+        ));
+    }
+}
@@ -0,0 +1,668 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dialect::{Dialect, Extension},
+    toml::{self, Document, Table},
+};
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error(transparent)]
+    Toml(#[from] toml::Error),
+    #[error("Unknown setting `{0}`")]
+    Unknown(String),
+    #[error("Invalid `{0}`: {1}")]
+    Invalid(String, String),
+    #[error("Unknown key binding `{0}`, expected one of {1}")]
+    Action(String, String),
+    #[error("`{0}` is bound to both `{1}` and `{2}`")]
+    Conflict(char, String, String),
+    #[error("Unknown layout `{0}`, expected one of {1}")]
+    Layout(String, String),
+}
+
+pub type Result<T> = anyhow::Result<T, Error>;
+
+/// Configuration file of a project, looked for in the current directory and its parents.
+pub const PROJECT: &str = ".mst.toml";
+
+/// Keys of the TUI's normal mode that can be rebound, by the name of what they do.
+pub const ACTIONS: [(&str, char); 27] = [
+    ("left", 'h'),
+    ("down", 'j'),
+    ("up", 'k'),
+    ("right", 'l'),
+    ("insert", 'i'),
+    ("command", ':'),
+    ("run", 'r'),
+    ("breakpoint", 'b'),
+    ("select", 'v'),
+    ("measure", 'M'),
+    ("annotate", 'a'),
+    ("label", 'L'),
+    ("fold", 'z'),
+    ("data", 'D'),
+    ("table", 'T'),
+    ("numeric", 'N'),
+    ("changes", 'C'),
+    ("trail", 'H'),
+    ("yank", 'y'),
+    ("paste", 'p'),
+    ("register", '"'),
+    ("mark", 'm'),
+    ("jump", '\''),
+    ("buffers", 'B'),
+    ("routines", 'I'),
+    ("panels", 'W'),
+    ("quit", 'q'),
+];
+
+/// Colours the TUI draws markers over the grid with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Theme {
+    #[default]
+    Default,
+    /// Markers telling apart through brightness and text attributes rather than hue alone
+    HighContrast,
+}
+
+impl FromStr for Theme {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name {
+            "default" => Ok(Theme::Default),
+            "high-contrast" => Ok(Theme::HighContrast),
+            _ => Err(Error::Invalid("theme".to_owned(), name.to_owned())),
+        }
+    }
+}
+
+impl std::fmt::Display for Theme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Theme::Default => "default",
+            Theme::HighContrast => "high-contrast",
+        })
+    }
+}
+
+/// Panel of the TUI shown next to the grid during runs, under the status of the run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Panel {
+    /// Stack depth over time, or the narration with `--accessible`
+    Timeline,
+    /// Watched expressions along with their values, taking no room without any
+    Watches,
+    /// Stack of the instruction pointer, or of each of them in concurrent runs
+    Stack,
+    /// Writes to the grid, input read, references to a cell or the backtrace
+    Trace,
+    /// Instruction mix
+    Stats,
+    Output,
+    /// Whole grid shrunk to fit, with the instruction pointer on it
+    Minimap,
+}
+
+impl Panel {
+    pub const ALL: [Panel; 7] = [
+        Panel::Timeline,
+        Panel::Watches,
+        Panel::Stack,
+        Panel::Trace,
+        Panel::Stats,
+        Panel::Output,
+        Panel::Minimap,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Panel::Timeline => "timeline",
+            Panel::Watches => "watches",
+            Panel::Stack => "stack",
+            Panel::Trace => "trace",
+            Panel::Stats => "stats",
+            Panel::Output => "output",
+            Panel::Minimap => "minimap",
+        }
+    }
+
+    /// Height of the panel when opened.
+    pub fn size(self) -> Size {
+        match self {
+            Panel::Timeline => Size::Lines(5),
+            Panel::Watches => Size::Fit,
+            Panel::Minimap => Size::Lines(10),
+            _ => Size::Rest,
+        }
+    }
+}
+
+/// Height of a panel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Size {
+    Lines(u16),
+    Percent(u16),
+    /// Lines the content of the panel takes, for watches and the stack
+    Fit,
+    /// Share of what the other panels leave
+    Rest,
+}
+
+impl Size {
+    /// Grows or shrinks by a line or 5%, sizes depending on the rest of the layout or on the
+    /// content of the panel getting a fixed one first.
+    pub fn resized(self, grow: bool) -> Self {
+        let step = |value: u16, by: u16, range: std::ops::RangeInclusive<u16>| {
+            let value = match grow {
+                true => value.saturating_add(by),
+                false => value.saturating_sub(by),
+            };
+            value.clamp(*range.start(), *range.end())
+        };
+        match self {
+            Size::Lines(lines) => Size::Lines(step(lines, 1, 3..=100)),
+            Size::Percent(percent) => Size::Percent(step(percent, 5, 5..=100)),
+            Size::Fit => Size::Lines(5),
+            Size::Rest => Size::Percent(25),
+        }
+    }
+}
+
+impl std::fmt::Display for Size {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Size::Lines(lines) => write!(f, "{lines} lines"),
+            Size::Percent(percent) => write!(f, "{percent}%"),
+            Size::Fit => f.write_str("fit"),
+            Size::Rest => f.write_str("rest"),
+        }
+    }
+}
+
+/// Panels shown during runs and how much room they get, written as
+///
+/// ```toml
+/// [layouts.wide]
+/// width = 50
+/// panels = ["timeline:5", "stack:30%", "trace", "output"]
+/// ```
+///
+/// where panels are listed from top to bottom along with their height in lines, as a percentage
+/// or `fit` for the lines they hold, those without one sharing what is left.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Layout {
+    /// Columns taken by the panels
+    pub width: u16,
+    pub panels: Vec<(Panel, Size)>,
+}
+
+impl Layout {
+    /// Layouts known without being configured, any of them being replaced by a configured one of
+    /// the same name.
+    pub const PRESETS: [&str; 3] = ["default", "debug", "minimal"];
+
+    pub fn preset(name: &str) -> Option<Self> {
+        let (width, panels): (u16, &[_]) = match name {
+            "default" => (
+                30,
+                &[
+                    (Panel::Timeline, Size::Lines(5)),
+                    (Panel::Watches, Size::Fit),
+                    (Panel::Stack, Size::Percent(25)),
+                    (Panel::Trace, Size::Percent(20)),
+                    (Panel::Stats, Size::Percent(25)),
+                    (Panel::Output, Size::Rest),
+                ],
+            ),
+            "debug" => (
+                40,
+                &[
+                    (Panel::Timeline, Size::Lines(5)),
+                    (Panel::Watches, Size::Fit),
+                    (Panel::Stack, Size::Percent(30)),
+                    (Panel::Trace, Size::Percent(30)),
+                    (Panel::Minimap, Size::Lines(10)),
+                    (Panel::Output, Size::Rest),
+                ],
+            ),
+            "minimal" => (
+                30,
+                &[
+                    (Panel::Stack, Size::Percent(30)),
+                    (Panel::Output, Size::Rest),
+                ],
+            ),
+            _ => return None,
+        };
+        Some(Self {
+            width,
+            panels: panels.to_vec(),
+        })
+    }
+
+    /// Opens a panel at the bottom, or closes it.
+    pub fn toggle(&mut self, panel: Panel) {
+        match self.panels.iter().position(|(open, _)| *open == panel) {
+            Some(index) => {
+                self.panels.remove(index);
+            }
+            None => self.panels.push((panel, panel.size())),
+        }
+    }
+
+    /// Moves the panel at `index` one place down or up, returning where it ends up.
+    pub fn shift(&mut self, index: usize, down: bool) -> usize {
+        let other = match down {
+            true => index + 1,
+            false => index.wrapping_sub(1),
+        };
+        if index < self.panels.len() && other < self.panels.len() {
+            self.panels.swap(index, other);
+            return other;
+        }
+        index
+    }
+
+    /// Widens or narrows the panels by two columns.
+    pub fn widen(&mut self, wider: bool) {
+        self.width = match wider {
+            true => self.width.saturating_add(2).min(120),
+            false => self.width.saturating_sub(2).max(20),
+        };
+    }
+
+    /// Configured layout of that name, or the preset.
+    pub fn find(name: &str, layouts: &BTreeMap<String, Layout>) -> Result<Self> {
+        layouts
+            .get(name)
+            .cloned()
+            .or_else(|| Layout::preset(name))
+            .ok_or_else(|| Error::Layout(name.to_owned(), Layout::names(layouts).join(", ")))
+    }
+
+    /// Names of the presets and of the configured layouts, quoted.
+    pub fn names(layouts: &BTreeMap<String, Layout>) -> Vec<String> {
+        let configured = layouts.keys().map(String::as_str);
+        let mut names = Layout::PRESETS
+            .into_iter()
+            .chain(configured)
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        names.into_iter().map(|name| format!("`{name}`")).collect()
+    }
+
+    fn read(name: &str, table: &Table) -> Result<Self> {
+        let mut layout = Layout::default();
+        for (key, value) in &table.entries {
+            let invalid = || Error::Invalid(format!("layouts.{name}.{key}"), value.to_string());
+            match key.as_str() {
+                "width" => {
+                    layout.width = value
+                        .as_integer()
+                        .and_then(|width| u16::try_from(width).ok())
+                        .ok_or_else(invalid)?
+                }
+                "panels" => {
+                    layout.panels = value
+                        .as_array()
+                        .ok_or_else(invalid)?
+                        .iter()
+                        .map(|panel| panel.as_str().and_then(Layout::panel))
+                        .collect::<Option<_>>()
+                        .ok_or_else(invalid)?
+                }
+                _ => return Err(Error::Unknown(format!("layouts.{name}.{key}"))),
+            }
+        }
+        Ok(layout)
+    }
+
+    /// Reads a panel written as `NAME`, `NAME:LINES` or `NAME:PERCENT%`.
+    fn panel(text: &str) -> Option<(Panel, Size)> {
+        let (name, size) = match text.split_once(':') {
+            Some((name, size)) => (name, Some(size)),
+            None => (text, None),
+        };
+        let panel = Panel::ALL.into_iter().find(|panel| panel.name() == name)?;
+        let size = match size {
+            None => Size::Rest,
+            Some("fit") => Size::Fit,
+            Some(size) => match size.strip_suffix('%') {
+                Some(percent) => Size::Percent(percent.parse().ok().filter(|p| *p <= 100)?),
+                None => Size::Lines(size.parse().ok()?),
+            },
+        };
+        Some((panel, size))
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout::preset("default").expect("the default layout is a preset")
+    }
+}
+
+/// Keys rebound from their defaults in [ACTIONS].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Keymap {
+    bindings: BTreeMap<String, char>,
+}
+
+impl Keymap {
+    /// Default key of the action `pressed` triggers, `None` if it triggers none since what it
+    /// did by default was rebound to another key.
+    pub fn resolve(&self, pressed: char) -> Option<char> {
+        let action = |name: &str| ACTIONS.iter().find(|(action, _)| *action == name);
+        if let Some((name, _)) = self.bindings.iter().find(|(_, key)| **key == pressed) {
+            return action(name).map(|(_, default)| *default);
+        }
+        match ACTIONS
+            .iter()
+            .find(|(name, default)| *default == pressed && self.bindings.contains_key(*name))
+        {
+            Some(_) => None,
+            None => Some(pressed),
+        }
+    }
+
+    fn read(table: &Table) -> Result<Self> {
+        let mut keymap = Keymap::default();
+        for (name, value) in &table.entries {
+            if !ACTIONS.iter().any(|(action, _)| action == name) {
+                let names = ACTIONS.map(|(action, _)| format!("`{action}`"));
+                return Err(Error::Action(name.clone(), names.join(", ")));
+            }
+            let key = match value.as_str().map(|key| key.chars().collect::<Vec<_>>()) {
+                Some(chars) if chars.len() == 1 => chars[0],
+                _ => return Err(Error::Invalid(format!("keys.{name}"), value.to_string())),
+            };
+            keymap.bindings.insert(name.clone(), key);
+        }
+        keymap.check()?;
+        Ok(keymap)
+    }
+
+    /// Makes sure no key triggers two actions, counting those left to their default key.
+    fn check(&self) -> Result<()> {
+        let mut actions = BTreeMap::new();
+        for (name, default) in ACTIONS {
+            let key = self.bindings.get(name).copied().unwrap_or(default);
+            if let Some(other) = actions.insert(key, name) {
+                return Err(Error::Conflict(key, other.to_owned(), name.to_owned()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Settings of the user and of projects, standing in for the command line options of the same
+/// name when those are left out. Both are written in the same format:
+///
+/// ```toml
+/// dialect = "befunge97"
+/// extensions = ["multi-digit"]
+/// max-ticks = 1000000
+/// theme = "high-contrast"
+/// ansi = true
+/// trail = true
+/// trail-length = 20
+///
+/// # Files fed to programs as input, relative to the configuration file
+/// [inputs]
+/// "golf/fizzbuzz.bf" = "golf/fizzbuzz.in"
+///
+/// [keys]
+/// run = "R"
+///
+/// # Panels shown during runs at startup, among the presets and those of `[layouts]`
+/// layout = "debug"
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Config {
+    pub dialect: Option<Dialect>,
+    pub extensions: Vec<Extension>,
+    /// Instructions after which programs are considered to run forever
+    pub max_ticks: Option<u64>,
+    pub theme: Option<Theme>,
+    pub ansi: Option<bool>,
+    pub accessible: Option<bool>,
+    /// Whether instruction pointers leave a trail in the TUI, and how long
+    pub trail: Option<bool>,
+    pub trail_length: Option<usize>,
+    /// Input file of programs
+    pub inputs: BTreeMap<PathBuf, PathBuf>,
+    pub keys: Keymap,
+    /// Name of the layout used at startup
+    pub layout: Option<String>,
+    /// Layouts switched between with `:layout`, see [Layout]
+    pub layouts: BTreeMap<String, Layout>,
+}
+
+impl Config {
+    pub fn parse(text: &str) -> Result<Self> {
+        let document = Document::parse(text)?;
+        let mut config = Config::default();
+
+        for table in &document.tables {
+            match table.path.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+                [] => config.read(table)?,
+                ["inputs"] => {
+                    for (program, value) in &table.entries {
+                        let input = value.as_str().ok_or_else(|| {
+                            Error::Invalid(format!("inputs.{program}"), value.to_string())
+                        })?;
+                        config.inputs.insert(program.into(), input.into());
+                    }
+                }
+                ["keys"] => config.keys = Keymap::read(table)?,
+                ["layouts", name] => {
+                    let layout = Layout::read(name, table)?;
+                    config.layouts.insert(name.to_owned(), layout);
+                }
+                _ => return Err(Error::Unknown(table.path.join("."))),
+            }
+        }
+
+        Ok(config)
+    }
+
+    fn read(&mut self, table: &Table) -> Result<()> {
+        for (key, value) in &table.entries {
+            let invalid = || Error::Invalid(key.clone(), value.to_string());
+            let string = || value.as_str().ok_or_else(invalid);
+            let boolean = || value.as_bool().ok_or_else(invalid);
+            match key.as_str() {
+                "dialect" => self.dialect = Some(string()?.parse().map_err(|_| invalid())?),
+                "extensions" => {
+                    self.extensions = value
+                        .as_array()
+                        .ok_or_else(invalid)?
+                        .iter()
+                        .map(|name| name.as_str().and_then(|name| name.parse().ok()))
+                        .collect::<Option<_>>()
+                        .ok_or_else(invalid)?
+                }
+                "max-ticks" => {
+                    self.max_ticks = Some(
+                        value
+                            .as_integer()
+                            .and_then(|ticks| u64::try_from(ticks).ok())
+                            .ok_or_else(invalid)?,
+                    )
+                }
+                "theme" => self.theme = Some(string()?.parse()?),
+                "ansi" => self.ansi = Some(boolean()?),
+                "accessible" => self.accessible = Some(boolean()?),
+                "trail" => self.trail = Some(boolean()?),
+                "layout" => self.layout = Some(string()?.to_owned()),
+                "trail-length" => {
+                    self.trail_length = Some(
+                        value
+                            .as_integer()
+                            .and_then(|length| usize::try_from(length).ok())
+                            .ok_or_else(invalid)?,
+                    )
+                }
+                _ => return Err(Error::Unknown(key.clone())),
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolves the paths of the configuration against the directory of its file.
+    pub fn rooted(mut self, root: &Path) -> Self {
+        self.inputs = self
+            .inputs
+            .into_iter()
+            .map(|(program, input)| (root.join(program), root.join(input)))
+            .collect();
+        self
+    }
+
+    /// Settings of `other` taking precedence over these, key bindings and inputs being merged
+    /// one by one.
+    pub fn merge(self, other: Config) -> Result<Self> {
+        let mut keys = self.keys;
+        keys.bindings.extend(other.keys.bindings);
+        keys.check()?;
+
+        let mut inputs = self.inputs;
+        inputs.extend(other.inputs);
+
+        let mut layouts = self.layouts;
+        layouts.extend(other.layouts);
+
+        Ok(Self {
+            dialect: other.dialect.or(self.dialect),
+            extensions: match other.extensions.is_empty() {
+                true => self.extensions,
+                false => other.extensions,
+            },
+            max_ticks: other.max_ticks.or(self.max_ticks),
+            theme: other.theme.or(self.theme),
+            ansi: other.ansi.or(self.ansi),
+            accessible: other.accessible.or(self.accessible),
+            trail: other.trail.or(self.trail),
+            trail_length: other.trail_length.or(self.trail_length),
+            inputs,
+            keys,
+            layout: other.layout.or(self.layout),
+            layouts,
+        })
+    }
+
+    /// Input file of a program, if one is configured for it.
+    pub fn input(&self, program: &Path) -> Option<&Path> {
+        let program = program.canonicalize().ok()?;
+        self.inputs
+            .iter()
+            .find(|(path, _)| path.canonicalize().is_ok_and(|path| path == program))
+            .map(|(_, input)| input.as_path())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn merge() {
+        let user = Config::parse("theme = \"high-contrast\"\nmax-ticks = 500\n[keys]\nrun = \"R\"")
+            .unwrap();
+        let project = Config::parse(
+            "dialect = \"96\"\nmax-ticks = 1000\ntrail-length = 20\n\n[inputs]\n\"a.bf\" = \"a.in\"\n\n[keys]\nquit = \
+             \"Q\"",
+        )
+        .unwrap()
+        .rooted(Path::new("golf"));
+        let config = user.merge(project).unwrap();
+
+        assert_eq!(config.dialect, Some(Dialect::Befunge96));
+        assert_eq!(config.max_ticks, Some(1000));
+        assert_eq!(config.theme, Some(Theme::HighContrast));
+        assert_eq!(config.trail_length, Some(20));
+        assert_eq!(
+            config.inputs.get(Path::new("golf/a.bf")),
+            Some(&PathBuf::from("golf/a.in"))
+        );
+        assert_eq!(config.keys.resolve('R'), Some('r'));
+        assert_eq!(config.keys.resolve('r'), None);
+        assert_eq!(config.keys.resolve('Q'), Some('q'));
+        assert_eq!(config.keys.resolve('x'), Some('x'));
+
+        assert_eq!(
+            Config::parse("[keys]\nrun = \"b\""),
+            Err(Error::Conflict(
+                'b',
+                "run".to_owned(),
+                "breakpoint".to_owned()
+            ))
+        );
+        assert_eq!(
+            Config::parse("dialect = 93"),
+            Err(Error::Invalid("dialect".to_owned(), "93".to_owned()))
+        );
+        assert!(matches!(
+            Config::parse("speed = 3"),
+            Err(Error::Unknown(key)) if key == "speed"
+        ));
+    }
+
+    #[test]
+    fn layouts() {
+        let config = Config::parse(
+            "layout = \"wide\"\n[layouts.wide]\nwidth = 50\npanels = [\"stack:30%\", \
+             \"minimap:8\", \"output\"]",
+        )
+        .unwrap();
+        assert_eq!(config.layout.as_deref(), Some("wide"));
+        assert_eq!(
+            Layout::find("wide", &config.layouts),
+            Ok(Layout {
+                width: 50,
+                panels: vec![
+                    (Panel::Stack, Size::Percent(30)),
+                    (Panel::Minimap, Size::Lines(8)),
+                    (Panel::Output, Size::Rest),
+                ],
+            })
+        );
+        assert_eq!(
+            Layout::find("minimal", &config.layouts),
+            Ok(Layout::preset("minimal").unwrap())
+        );
+        assert_eq!(
+            Layout::find("tall", &config.layouts),
+            Err(Error::Layout(
+                "tall".to_owned(),
+                "`debug`, `default`, `minimal`, `wide`".to_owned()
+            ))
+        );
+        assert!(Config::parse("[layouts.wide]\npanels = [\"stack:120%\"]").is_err());
+
+        // Rearranged at runtime
+        let mut layout = Layout::find("wide", &config.layouts).unwrap();
+        layout.toggle(Panel::Stack);
+        layout.toggle(Panel::Watches);
+        assert_eq!(layout.shift(1, true), 2);
+        assert_eq!(layout.shift(2, true), 2);
+        layout.panels[0].1 = layout.panels[0].1.resized(false);
+        assert_eq!(
+            layout.panels,
+            [
+                (Panel::Minimap, Size::Lines(7)),
+                (Panel::Watches, Size::Fit),
+                (Panel::Output, Size::Rest),
+            ]
+        );
+    }
+}
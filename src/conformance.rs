@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+
+use puccinia::{
+    dialect::Dialect,
+    directives::Directives,
+    grid::Grid,
+    interpreter::Interpreter,
+    suite::{self, Tally},
+};
+
+use crate::{
+    difftest,
+    headless::{self, Ending, Geometry},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0} of {1} checks failed")]
+    Failures(usize, usize),
+}
+
+#[derive(clap::Args)]
+pub(crate) struct Options {
+    /// Semantics to run the suite with
+    #[arg(long, value_name = "DIALECT", default_value_t)]
+    dialect: Dialect,
+
+    /// Mycology-style test programs, or directories searched for `.bf` and `.b93` files, run
+    /// on top of the bundled suite and scored by the `GOOD`, `BAD` and `UNDEF` lines they print
+    #[arg(long, value_name = "PATH")]
+    suite: Vec<String>,
+
+    /// Instructions after which a program is considered to run forever
+    #[arg(long, value_name = "N", default_value_t = 10_000_000)]
+    max_ticks: u64,
+}
+
+/// Runs the bundled conformance suite and the given test programs, summarising how many checks
+/// pass in each section.
+pub(crate) fn run(options: Options) -> anyhow::Result<()> {
+    let mut sections = BTreeMap::<&str, (usize, usize)>::new();
+    let mut failures = Vec::new();
+    for case in suite::CASES
+        .iter()
+        .filter(|case| case.applies(options.dialect))
+    {
+        let mut interpreter = Interpreter::new(Grid::from(case.program.to_owned()));
+        headless::configure(
+            &mut interpreter,
+            &Directives::default(),
+            Some(options.dialect),
+            &[],
+            None,
+        );
+        let ending = headless::run_to_end(&mut interpreter, case.input, options.max_ticks);
+        let output = interpreter.take_output();
+
+        let (passed, total) = sections.entry(case.section).or_default();
+        *total += 1;
+        if ending == Ending::Ended && output == case.output {
+            *passed += 1;
+        } else {
+            failures.push(format!(
+                "{}: {}: expected {:?}, got {output:?} and {}",
+                case.section,
+                case.name,
+                case.output,
+                ending.describe()
+            ));
+        }
+    }
+
+    println!("Bundled suite, as {}", options.dialect);
+    for (section, (passed, total)) in &sections {
+        println!("  {section:<12} {passed:>3}/{total}");
+    }
+    for failure in &failures {
+        println!("  FAIL {failure}");
+    }
+
+    let (mut failed, mut checks) = (
+        failures.len(),
+        sections.values().map(|(_, total)| total).sum::<usize>(),
+    );
+    for program in difftest::corpus(&options.suite)? {
+        let (grid, directives, canned) = headless::load(&program, Geometry::default())?;
+        let mut interpreter = Interpreter::new(grid);
+        headless::configure(
+            &mut interpreter,
+            &directives,
+            Some(options.dialect),
+            &[],
+            None,
+        );
+        let ending = headless::run_to_end(
+            &mut interpreter,
+            &canned.unwrap_or_default(),
+            options.max_ticks,
+        );
+        let tally = Tally::of(&interpreter.take_output());
+
+        println!(
+            "{program}: {} good, {} bad, {} undefined, {}",
+            tally.good,
+            tally.bad,
+            tally.undefined,
+            ending.describe()
+        );
+        checks += tally.good + tally.bad;
+        failed += tally.bad;
+        // Stopping early leaves later checks unrun
+        if ending != Ending::Ended {
+            checks += 1;
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        return Err(Error::Failures(failed, checks).into());
+    }
+    Ok(())
+}
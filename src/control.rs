@@ -0,0 +1,130 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    sync::mpsc::{self, Sender},
+};
+
+use serde::{Deserialize, Serialize};
+
+use puccinia::interpreter::Status;
+
+type Result<T> = anyhow::Result<T>;
+
+/// Command sent by an external process, one JSON document per line such as
+/// `{"command": "poke", "x": 0, "y": 0, "value": "@"}`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Command {
+    /// Start a new run, paused on the first instruction
+    Start,
+    /// Let the run progress on its own, starting one if needed
+    Resume,
+    Pause,
+    Step,
+    /// End the current run
+    Stop,
+    /// Feed data to the program's input
+    Input {
+        data: String,
+    },
+    /// Set a cell of the running program, or of the grid when not running
+    Poke {
+        x: usize,
+        y: usize,
+        value: char,
+    },
+    /// Only reply with the current state
+    State,
+}
+
+/// State of the instance after a command.
+#[derive(Debug, Default, Serialize)]
+pub struct Snapshot {
+    pub active: bool,
+    pub running: bool,
+    pub position: (usize, usize),
+    pub stack: Vec<i32>,
+    pub ticks: u64,
+    pub status: Status,
+    /// Rows of the grid, trailing spaces trimmed
+    pub grid: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reply {
+    State(Snapshot),
+    Error(String),
+}
+
+/// Command along with where to send its reply.
+pub(crate) struct Request {
+    pub command: Command,
+    pub reply: Sender<Reply>,
+}
+
+/// Accepts control connections on a TCP address, or a Unix socket for `unix:PATH` addresses,
+/// forwarding their commands to `requests`.
+pub(crate) fn listen(address: &str, requests: Sender<Request>) -> Result<()> {
+    #[cfg(unix)]
+    if let Some(path) = address.strip_prefix("unix:") {
+        // A socket left over by a previous instance would fail binding
+        let _ = std::fs::remove_file(path);
+        let listener = std::os::unix::net::UnixListener::bind(path)?;
+        std::thread::spawn(move || {
+            for stream in listener.incoming().map_while(std::io::Result::ok) {
+                if let Ok(reader) = stream.try_clone() {
+                    spawn_session(BufReader::new(reader), stream, requests.clone());
+                }
+            }
+        });
+        return Ok(());
+    }
+
+    let listener = TcpListener::bind(address)?;
+    std::thread::spawn(move || {
+        for stream in listener.incoming().map_while(std::io::Result::ok) {
+            if let Ok(reader) = stream.try_clone() {
+                spawn_session(BufReader::new(reader), stream, requests.clone());
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Answers the commands of a single connection until it closes or the instance exits.
+fn spawn_session(
+    reader: impl BufRead + Send + 'static,
+    mut writer: impl Write + Send + 'static,
+    requests: Sender<Request>,
+) {
+    std::thread::spawn(move || {
+        for line in reader.lines().map_while(std::io::Result::ok) {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let reply = match serde_json::from_str(&line) {
+                Ok(command) => {
+                    let (reply, receiver) = mpsc::channel();
+                    if requests.send(Request { command, reply }).is_err() {
+                        break;
+                    }
+                    match receiver.recv() {
+                        Ok(reply) => reply,
+                        Err(_) => break,
+                    }
+                }
+                Err(err) => Reply::Error(format!("Malformed command: {err}")),
+            };
+
+            let sent = serde_json::to_string(&reply)
+                .map_err(std::io::Error::from)
+                .and_then(|line| writeln!(writer, "{line}"));
+            if sent.is_err() {
+                break;
+            }
+        }
+    });
+}
@@ -0,0 +1,728 @@
+use std::{
+    collections::HashSet,
+    io::{BufReader, Write},
+    sync::mpsc::{self, TryRecvError},
+};
+
+use serde_json::{json, Value};
+
+use puccinia::{
+    debugger::{Debugger, Stop},
+    directives,
+    interpreter::Interpreter,
+    sidecar::Sidecar,
+    watch::Watch,
+};
+
+use crate::protocol::{read_message, write_message, Result};
+
+/// Instructions executed between two checks for incoming requests while running.
+const SLICE: usize = 10_000;
+
+/// Radius of the neighbourhood shown as variables around the instruction pointer.
+const NEIGHBOURHOOD: i32 = 2;
+
+const THREAD_ID: i64 = 1;
+
+const STACK_REFERENCE: i64 = 1;
+const NEIGHBOURHOOD_REFERENCE: i64 = 2;
+const STATE_REFERENCE: i64 = 3;
+const BACKTRACE_REFERENCE: i64 = 4;
+const WATCHES_REFERENCE: i64 = 5;
+
+/// Serves the Debug Adapter Protocol over stdio until the client disconnects.
+pub(crate) fn run() -> Result<()> {
+    let (sender, receiver) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(std::io::stdin());
+        while let Ok(Some(message)) = read_message(&mut reader) {
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut server = Server::new(std::io::stdout());
+
+    loop {
+        let message = if server.running {
+            match receiver.try_recv() {
+                Ok(message) => Some(message),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => break,
+            }
+        } else {
+            match receiver.recv() {
+                Ok(message) => Some(message),
+                Err(_) => break,
+            }
+        };
+
+        if let Some(message) = message {
+            if !server.handle(message)? {
+                break;
+            }
+        }
+
+        if server.running {
+            server.run_slice()?;
+        }
+    }
+
+    Ok(())
+}
+
+struct Server<W: Write> {
+    writer: W,
+    seq: i64,
+
+    lines_start_at_1: bool,
+    columns_start_at_1: bool,
+
+    program: Option<String>,
+    debugger: Option<Debugger>,
+    breakpoints: HashSet<(usize, usize)>,
+    /// Expressions added from the client's watch panel or kept next to the program
+    watches: Vec<Watch>,
+    /// Breakpoints and watches kept next to the program, for later sessions
+    sidecar: Option<Sidecar>,
+
+    configured: bool,
+    stop_on_entry: bool,
+    running: bool,
+}
+
+impl<W: Write> Server<W> {
+    fn new(writer: W) -> Self {
+        Self {
+            writer,
+            seq: 0,
+            lines_start_at_1: true,
+            columns_start_at_1: true,
+            program: None,
+            debugger: None,
+            breakpoints: HashSet::new(),
+            watches: Vec::new(),
+            sidecar: None,
+            configured: false,
+            stop_on_entry: false,
+            running: false,
+        }
+    }
+
+    /// Handles a single request, returns false once the session is over.
+    fn handle(&mut self, message: Value) -> Result<bool> {
+        if message["type"] != "request" {
+            return Ok(true);
+        }
+
+        let command = message["command"].as_str().unwrap_or_default().to_owned();
+        let arguments = &message["arguments"];
+
+        let body = match command.as_str() {
+            "initialize" => {
+                self.lines_start_at_1 = arguments["linesStartAt1"].as_bool().unwrap_or(true);
+                self.columns_start_at_1 = arguments["columnsStartAt1"].as_bool().unwrap_or(true);
+
+                self.respond(&message, Ok(self.capabilities()))?;
+                self.event("initialized", json!({}))?;
+                return Ok(true);
+            }
+            "launch" => {
+                let body = self.launch(arguments);
+                self.respond(&message, body)?;
+                self.persist()?;
+                self.start()?;
+                return Ok(true);
+            }
+            "setBreakpoints" => {
+                let body = self.set_breakpoints(arguments);
+                self.persist()?;
+                Ok(body)
+            }
+            "setExceptionBreakpoints" => Ok(json!({ "breakpoints": [] })),
+            "configurationDone" => {
+                self.configured = true;
+                self.respond(&message, Ok(json!({})))?;
+                self.start()?;
+                return Ok(true);
+            }
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "IP" }] })),
+            "stackTrace" => self.stack_trace(),
+            "scopes" => Ok(self.scopes()),
+            "variables" => self.variables(arguments),
+            "continue" => {
+                self.running = true;
+                Ok(json!({ "allThreadsContinued": true }))
+            }
+            "next" | "stepIn" | "stepOut" => {
+                self.respond(&message, Ok(json!({})))?;
+                self.step()?;
+                return Ok(true);
+            }
+            "pause" => {
+                self.respond(&message, Ok(json!({})))?;
+                self.running = false;
+                self.stopped("pause", None)?;
+                return Ok(true);
+            }
+            "evaluate" => {
+                let body = self.evaluate(arguments);
+                self.persist()?;
+                body
+            }
+            "disconnect" | "terminate" => {
+                self.respond(&message, Ok(json!({})))?;
+                return Ok(false);
+            }
+            other => Err(format!("Unsupported request `{other}`")),
+        };
+
+        self.respond(&message, body)?;
+
+        Ok(true)
+    }
+
+    fn capabilities(&self) -> Value {
+        json!({
+            "supportsConfigurationDoneRequest": true,
+            "supportsTerminateRequest": true,
+            "supportsEvaluateForHovers": false,
+        })
+    }
+
+    fn launch(&mut self, arguments: &Value) -> std::result::Result<Value, String> {
+        let program = arguments["program"]
+            .as_str()
+            .ok_or("Missing `program` launch argument")?;
+        let (mut grid, directives) = directives::open(program)
+            .map_err(|err| format!("Could not load `{program}`: {err}"))?;
+        directives.sizing.unwrap_or_default().fit(&mut grid);
+        directives.lines.unwrap_or_default().apply(&mut grid);
+
+        // Breakpoints kept from earlier sessions come on top of those set by the client
+        self.sidecar = match Sidecar::load(program) {
+            Ok(sidecar) => Some(sidecar),
+            Err(err) => {
+                self.report(&format!("Could not read the sidecar file: {err}"))
+                    .map_err(|err| err.to_string())?;
+                None
+            }
+        };
+        if let Some(sidecar) = &self.sidecar {
+            self.breakpoints.extend(sidecar.breakpoints.iter().copied());
+            for expression in sidecar.watches.clone() {
+                if let Err(err) = self.watch(&expression) {
+                    self.report(&format!("Invalid watch in the sidecar file: {err}"))
+                        .map_err(|err| err.to_string())?;
+                }
+            }
+        }
+
+        let mut interpreter = Interpreter::new(grid);
+        directives.apply(&mut interpreter);
+        let mut debugger = Debugger::new(interpreter);
+        debugger.set_breakpoints(self.breakpoints.iter().copied());
+        if let Some(input) = arguments["input"].as_str() {
+            debugger.feed_input(input);
+        }
+
+        self.program = Some(program.to_owned());
+        self.debugger = Some(debugger);
+        self.stop_on_entry = arguments["stopOnEntry"].as_bool().unwrap_or(false);
+
+        Ok(json!({}))
+    }
+
+    /// Starts execution once the program is both launched and configured.
+    fn start(&mut self) -> Result<()> {
+        if !self.configured || self.debugger.is_none() {
+            return Ok(());
+        }
+
+        if self.stop_on_entry {
+            self.stopped("entry", None)
+        } else {
+            self.running = true;
+            Ok(())
+        }
+    }
+
+    fn set_breakpoints(&mut self, arguments: &Value) -> Value {
+        let requested = arguments["breakpoints"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+
+        self.breakpoints = requested
+            .iter()
+            .filter_map(|breakpoint| {
+                let line = breakpoint["line"].as_i64()?;
+                let column = breakpoint["column"]
+                    .as_i64()
+                    .unwrap_or(self.columns_start_at_1 as i64);
+                Some(self.grid_position(column, line))
+            })
+            .collect();
+
+        if let Some(debugger) = self.debugger.as_mut() {
+            debugger.set_breakpoints(self.breakpoints.iter().copied());
+        }
+
+        let size = self
+            .debugger
+            .as_ref()
+            .map(|d| d.interpreter().grid().size());
+        let breakpoints = requested
+            .iter()
+            .map(|breakpoint| {
+                let line = breakpoint["line"].as_i64().unwrap_or_default();
+                let column = breakpoint["column"]
+                    .as_i64()
+                    .unwrap_or(self.columns_start_at_1 as i64);
+                let (x, y) = self.grid_position(column, line);
+                let verified = size.is_none_or(|(width, height)| x < width && y < height);
+                json!({ "verified": verified, "line": line, "column": column })
+            })
+            .collect::<Vec<_>>();
+
+        json!({ "breakpoints": breakpoints })
+    }
+
+    fn stack_trace(&self) -> std::result::Result<Value, String> {
+        let debugger = self.debugger.as_ref().ok_or("No program launched")?;
+        let interpreter = debugger.interpreter();
+        let (x, y) = interpreter.position();
+        let (column, line) = self.client_position(x, y);
+        let instruction = char::from(interpreter.grid().get(x, y).value);
+
+        let mut frames = vec![json!({
+            "id": 0,
+            "name": format!("`{instruction}` at ({x}, {y})"),
+            "line": line,
+            "column": column,
+            "source": { "path": self.program },
+        })];
+
+        frames.extend(
+            interpreter
+                .stack()
+                .iter()
+                .rev()
+                .enumerate()
+                .map(|(index, value)| {
+                    json!({
+                        "id": index + 1,
+                        "name": format!("stack[{index}] = {}", describe(*value)),
+                        "line": 0,
+                        "column": 0,
+                        "presentationHint": "label",
+                    })
+                }),
+        );
+
+        Ok(json!({ "stackFrames": frames, "totalFrames": frames.len() }))
+    }
+
+    /// Keeps the breakpoints and watches next to the program, once it is launched.
+    fn persist(&mut self) -> Result<()> {
+        let (Some(program), Some(sidecar)) = (&self.program, self.sidecar.as_mut()) else {
+            return Ok(());
+        };
+
+        let breakpoints = self.breakpoints.iter().copied().collect();
+        let watches = self.watches.iter().map(ToString::to_string).collect();
+        if sidecar.breakpoints == breakpoints && sidecar.watches == watches {
+            return Ok(());
+        }
+        sidecar.breakpoints = breakpoints;
+        sidecar.watches = watches;
+        if let Err(err) = sidecar.save(program) {
+            self.report(&format!("Could not save the sidecar file: {err}"))?;
+        }
+        Ok(())
+    }
+
+    /// Adds a watch unless there already is one for the same expression.
+    fn watch(&mut self, expression: &str) -> std::result::Result<&Watch, String> {
+        let watch = expression
+            .parse::<Watch>()
+            .map_err(|err| format!("Invalid watch `{expression}`: {err}"))?;
+        let index = match self.watches.iter().position(|watched| watched.same(&watch)) {
+            Some(index) => index,
+            None => {
+                self.watches.push(watch);
+                self.watches.len() - 1
+            }
+        };
+        Ok(&self.watches[index])
+    }
+
+    fn scopes(&self) -> Value {
+        json!({
+            "scopes": [
+                { "name": "Stack", "variablesReference": STACK_REFERENCE, "expensive": false },
+                { "name": "Cells near IP", "variablesReference": NEIGHBOURHOOD_REFERENCE, "expensive": false },
+                { "name": "State", "variablesReference": STATE_REFERENCE, "expensive": false },
+                { "name": "Backtrace", "variablesReference": BACKTRACE_REFERENCE, "expensive": false },
+                { "name": "Watches", "variablesReference": WATCHES_REFERENCE, "expensive": false },
+            ]
+        })
+    }
+
+    fn variables(&self, arguments: &Value) -> std::result::Result<Value, String> {
+        let debugger = self.debugger.as_ref().ok_or("No program launched")?;
+        let interpreter = debugger.interpreter();
+
+        let variable = |name: String, value: String| json!({ "name": name, "value": value, "variablesReference": 0 });
+
+        let variables = match arguments["variablesReference"].as_i64() {
+            Some(STACK_REFERENCE) => interpreter
+                .stack()
+                .iter()
+                .rev()
+                .enumerate()
+                .map(|(index, value)| variable(format!("[{index}]"), describe(*value)))
+                .collect(),
+            Some(NEIGHBOURHOOD_REFERENCE) => {
+                let (width, height) = interpreter.grid().size();
+                let (x, y) = interpreter.position();
+                let mut cells = vec![];
+
+                for dy in -NEIGHBOURHOOD..=NEIGHBOURHOOD {
+                    for dx in -NEIGHBOURHOOD..=NEIGHBOURHOOD {
+                        let (cx, cy) = (x as i32 + dx, y as i32 + dy);
+                        if (0..width as i32).contains(&cx) && (0..height as i32).contains(&cy) {
+                            let value = interpreter.grid().get(cx as usize, cy as usize).value;
+                            cells.push(variable(
+                                format!("({cx}, {cy})"),
+                                describe(char::from(value) as i32),
+                            ));
+                        }
+                    }
+                }
+
+                cells
+            }
+            Some(STATE_REFERENCE) => vec![
+                variable(
+                    "position".to_owned(),
+                    format!("{:?}", interpreter.position()),
+                ),
+                variable(
+                    "direction".to_owned(),
+                    format!("{:?}", interpreter.direction()),
+                ),
+                variable(
+                    "string mode".to_owned(),
+                    interpreter.string_mode().to_string(),
+                ),
+                variable("ticks".to_owned(), interpreter.ticks().to_string()),
+            ],
+            Some(BACKTRACE_REFERENCE) => debugger
+                .history()
+                .iter()
+                .rev()
+                .enumerate()
+                .map(|(depth, entry)| variable(format!("#{depth}"), entry.to_string()))
+                .collect(),
+            Some(WATCHES_REFERENCE) => self
+                .watches
+                .iter()
+                .map(|watch| variable(watch.to_string(), value(watch, interpreter)))
+                .collect(),
+            _ => return Err("Unknown variables reference".to_owned()),
+        };
+
+        Ok(json!({ "variables": variables }))
+    }
+
+    /// Expressions of the client's watch panel are watches, kept for later sessions, while those
+    /// typed in the debug console are fed to the program as input.
+    fn evaluate(&mut self, arguments: &Value) -> std::result::Result<Value, String> {
+        let expression = arguments["expression"].as_str().unwrap_or_default();
+        if arguments["context"] == "watch" {
+            let watch = self.watch(expression)?.clone();
+            let result = match &self.debugger {
+                Some(debugger) => value(&watch, debugger.interpreter()),
+                None => "?".to_owned(),
+            };
+            return Ok(json!({ "result": result, "variablesReference": 0 }));
+        }
+
+        let debugger = self.debugger.as_mut().ok_or("No program launched")?;
+
+        debugger.feed_input(&format!("{expression}\n"));
+
+        Ok(json!({ "result": format!("fed {:?} as input", expression), "variablesReference": 0 }))
+    }
+
+    fn step(&mut self) -> Result<()> {
+        let Some(debugger) = self.debugger.as_mut() else {
+            return Ok(());
+        };
+
+        let stop = debugger.step().map_err(|err| err.to_string());
+        self.flush_output()?;
+
+        match stop {
+            Ok(Stop::Terminated) => self.terminated(),
+            Ok(Stop::WaitingForInput) => self.stopped("pause", Some("Waiting for input")),
+            Ok(_) => self.stopped("step", None),
+            Err(err) => self.failed(err),
+        }
+    }
+
+    fn run_slice(&mut self) -> Result<()> {
+        let Some(debugger) = self.debugger.as_mut() else {
+            self.running = false;
+            return Ok(());
+        };
+
+        let stop = debugger.resume(SLICE).map_err(|err| err.to_string());
+        self.flush_output()?;
+
+        match stop {
+            Ok(None) => Ok(()),
+            Ok(Some(stop)) => {
+                self.running = false;
+                match stop {
+                    Stop::Terminated => self.terminated(),
+                    Stop::WaitingForInput => self.stopped("pause", Some("Waiting for input")),
+                    Stop::Breakpoint(_) => self.stopped("breakpoint", None),
+                    Stop::Step | Stop::Reached | Stop::Spawned(_) | Stop::Exited(_) => {
+                        self.stopped("step", None)
+                    }
+                }
+            }
+            Err(err) => {
+                self.running = false;
+                self.failed(err)
+            }
+        }
+    }
+
+    fn flush_output(&mut self) -> Result<()> {
+        let output = self
+            .debugger
+            .as_mut()
+            .map(|debugger| debugger.interpreter_mut().take_output())
+            .unwrap_or_default();
+
+        if output.is_empty() {
+            return Ok(());
+        }
+
+        self.event("output", json!({ "category": "stdout", "output": output }))
+    }
+
+    fn stopped(&mut self, reason: &str, description: Option<&str>) -> Result<()> {
+        self.event(
+            "stopped",
+            json!({
+                "reason": reason,
+                "description": description,
+                "threadId": THREAD_ID,
+                "allThreadsStopped": true,
+            }),
+        )
+    }
+
+    fn failed(&mut self, err: String) -> Result<()> {
+        self.report(&err)?;
+        self.stopped("exception", Some(&err))
+    }
+
+    fn report(&mut self, err: &str) -> Result<()> {
+        self.event(
+            "output",
+            json!({ "category": "stderr", "output": format!("{err}\n") }),
+        )
+    }
+
+    fn terminated(&mut self) -> Result<()> {
+        let code = self
+            .debugger
+            .as_ref()
+            .and_then(|debugger| debugger.interpreter().exit_code())
+            .unwrap_or_default();
+        self.event("exited", json!({ "exitCode": code }))?;
+        self.event("terminated", json!({}))
+    }
+
+    fn respond(&mut self, request: &Value, body: std::result::Result<Value, String>) -> Result<()> {
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": body.is_ok(),
+        });
+
+        match body {
+            Ok(body) => response["body"] = body,
+            Err(message) => response["message"] = Value::String(message),
+        }
+
+        self.send(response)
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> Result<()> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+
+    fn send(&mut self, mut message: Value) -> Result<()> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        write_message(&mut self.writer, &message)
+    }
+
+    /// Converts client coordinates to grid coordinates.
+    fn grid_position(&self, column: i64, line: i64) -> (usize, usize) {
+        let x = column - self.columns_start_at_1 as i64;
+        let y = line - self.lines_start_at_1 as i64;
+        (x.max(0) as usize, y.max(0) as usize)
+    }
+
+    /// Converts grid coordinates to client coordinates.
+    fn client_position(&self, x: usize, y: usize) -> (i64, i64) {
+        (
+            x as i64 + self.columns_start_at_1 as i64,
+            y as i64 + self.lines_start_at_1 as i64,
+        )
+    }
+}
+
+/// Value of a watch, `?` when it can't be computed.
+fn value(watch: &Watch, interpreter: &Interpreter) -> String {
+    watch
+        .evaluate(interpreter)
+        .map_or_else(|| "?".to_owned(), |value| value.to_string())
+}
+
+/// Formats a stack or cell value along with its character representation when printable.
+fn describe(value: i32) -> String {
+    match u32::try_from(value).ok().and_then(char::from_u32) {
+        Some(c) if !c.is_control() => format!("{value} ({c:?})"),
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Handles a request as [run] does, returning the responses and events sent back.
+    fn request(server: &mut Server<Vec<u8>>, command: &str, arguments: Value) -> Vec<Value> {
+        let seq = server.seq;
+        let message = json!({
+            "seq": seq,
+            "type": "request",
+            "command": command,
+            "arguments": arguments,
+        });
+        assert!(server.handle(message).unwrap());
+        while server.running {
+            server.run_slice().unwrap();
+        }
+
+        let written = std::mem::take(&mut server.writer);
+        let mut reader = &written[..];
+        std::iter::from_fn(|| read_message(&mut reader).unwrap()).collect()
+    }
+
+    /// Name of each response or event, checking that every request succeeded.
+    fn names(messages: &[Value]) -> Vec<&str> {
+        messages
+            .iter()
+            .map(|message| match message["type"].as_str() {
+                Some("response") => {
+                    assert_eq!(message["success"], true, "{message}");
+                    message["command"].as_str().unwrap()
+                }
+                _ => message["event"].as_str().unwrap(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn session() {
+        let path = std::env::temp_dir().join(format!("mst-dap-{}.bf", std::process::id()));
+        std::fs::write(&path, "v\n>12+.@").unwrap();
+        let sidecar = Sidecar {
+            watches: vec!["stack[0] * 10".to_owned()],
+            ..Default::default()
+        };
+        sidecar.save(&path).unwrap();
+        let mut server = Server::new(Vec::new());
+
+        let messages = request(&mut server, "initialize", json!({}));
+        assert_eq!(names(&messages), ["initialize", "initialized"]);
+
+        // Clients count lines and columns from 1, the `+` being at (3, 1)
+        let breakpoints = json!({ "breakpoints": [{ "line": 2, "column": 4 }] });
+        let messages = request(&mut server, "setBreakpoints", breakpoints);
+        assert_eq!(
+            messages[0]["body"]["breakpoints"],
+            json!([{ "verified": true, "line": 2, "column": 4 }])
+        );
+        assert_eq!(server.breakpoints, HashSet::from([(3, 1)]));
+
+        let launch = json!({ "program": path.to_str(), "stopOnEntry": true });
+        let messages = request(&mut server, "launch", launch);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(names(&messages), ["launch"]);
+        let messages = request(&mut server, "configurationDone", json!({}));
+        assert_eq!(names(&messages), ["configurationDone", "stopped"]);
+        assert_eq!(messages[1]["body"]["reason"], "entry");
+
+        let messages = request(&mut server, "continue", json!({ "threadId": THREAD_ID }));
+        assert_eq!(names(&messages), ["continue", "stopped"]);
+        assert_eq!(messages[1]["body"]["reason"], "breakpoint");
+
+        let messages = request(&mut server, "stackTrace", json!({ "threadId": THREAD_ID }));
+        let frames = &messages[0]["body"]["stackFrames"];
+        assert_eq!(frames[0]["name"], "`+` at (3, 1)");
+        assert_eq!(
+            (&frames[0]["line"], &frames[0]["column"]),
+            (&json!(2), &json!(4))
+        );
+        assert_eq!(frames[1]["name"], "stack[0] = 2");
+        assert_eq!(frames[2]["name"], "stack[1] = 1");
+
+        let stack = json!({ "variablesReference": STACK_REFERENCE });
+        let messages = request(&mut server, "variables", stack);
+        let values = messages[0]["body"]["variables"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|variable| (variable["name"].clone(), variable["value"].clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            [(json!("[0]"), json!("2")), (json!("[1]"), json!("1"))]
+        );
+
+        // Watches of the client add to those kept next to the program
+        let watch = json!({ "expression": "stack[1]", "context": "watch" });
+        let messages = request(&mut server, "evaluate", watch);
+        assert_eq!(messages[0]["body"]["result"], "1");
+        let watches = json!({ "variablesReference": WATCHES_REFERENCE });
+        let messages = request(&mut server, "variables", watches);
+        assert_eq!(messages[0]["body"]["variables"][0]["value"], "20");
+        let sidecar = Sidecar::load(&path).unwrap();
+        std::fs::remove_file(puccinia::sidecar::path(&path)).unwrap();
+        assert_eq!(sidecar.breakpoints, [(3, 1)].into());
+        assert_eq!(sidecar.watches, ["stack[0] * 10", "stack[1]"]);
+
+        // Every kind of step executes a single instruction
+        let messages = request(&mut server, "next", json!({ "threadId": THREAD_ID }));
+        assert_eq!(names(&messages), ["next", "stopped"]);
+        assert_eq!(messages[1]["body"]["reason"], "step");
+        let messages = request(&mut server, "stepIn", json!({ "threadId": THREAD_ID }));
+        assert_eq!(names(&messages), ["stepIn", "output", "stopped"]);
+        assert_eq!(messages[1]["body"]["output"], "3 ");
+        let messages = request(&mut server, "stepOut", json!({ "threadId": THREAD_ID }));
+        assert_eq!(names(&messages), ["stepOut", "exited", "terminated"]);
+    }
+}
@@ -0,0 +1,973 @@
+use std::collections::{HashSet, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cell::{CellValue, Direction},
+    heatmap::Heatmap,
+    interpreter::{Interpreter, Result, Status},
+    loops::{self, LoopProfile},
+    statistics::Statistics,
+    timeline::Timeline,
+};
+
+/// Reason for which execution was handed back to the user.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Stop {
+    /// A single step was executed
+    Step,
+    /// The instruction pointer reached a breakpoint
+    Breakpoint((usize, usize)),
+    /// The program is waiting for input to be fed
+    WaitingForInput,
+    /// The program reached an `@`
+    Terminated,
+    /// The target given to [Debugger::run_to] was reached
+    Reached,
+    /// An instruction pointer with this id was started by `t`, see [Debugger::set_ip_events]
+    Spawned(u32),
+    /// The instruction pointer with this id reached an `@` while others were left
+    Exited(u32),
+}
+
+/// Where to hand execution back when running over uninteresting parts of a program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Target {
+    /// The instruction pointer reaches a cell
+    Cell((usize, usize)),
+    /// The instruction pointer leaves the cycle it is in, nested cycles included
+    LoopExit,
+    /// String mode ends, that of the next string if not in one
+    StringEnd,
+}
+
+/// Progress towards a [Target].
+#[derive(Clone, Debug)]
+enum Pending {
+    Cell((usize, usize)),
+    /// Cycle being stepped over, only known once the instruction pointer came back to `start`
+    Loop {
+        start: loops::State,
+        cycle: HashSet<loops::State>,
+        closed: bool,
+    },
+    StringEnd {
+        entered: bool,
+    },
+}
+
+/// Default number of executed instructions remembered for backtraces.
+pub const DEFAULT_HISTORY: usize = 32;
+
+/// Instruction executed at some point of the run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub tick: u64,
+    pub position: (usize, usize),
+    pub instruction: char,
+    /// Instruction pointer that executed the instruction
+    #[serde(default)]
+    pub ip: u32,
+    /// Cell read by `g` or written by `p`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cell: Option<(usize, usize)>,
+}
+
+/// How an instruction of the history touched a cell, see [Debugger::references].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Access {
+    /// The cell was executed
+    Executed,
+    /// The cell was read by `g`
+    Read,
+    /// The cell was written to by `p`
+    Written,
+}
+
+impl Access {
+    pub fn name(self) -> &'static str {
+        match self {
+            Access::Executed => "executed",
+            Access::Read => "read",
+            Access::Written => "written",
+        }
+    }
+}
+
+impl std::fmt::Display for HistoryEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (x, y) = self.position;
+        write!(f, "tick {:<8} ({x}, {y}) `{}`", self.tick, self.instruction)?;
+        match self.ip {
+            0 => Ok(()),
+            ip => write!(f, " IP {ip}"),
+        }
+    }
+}
+
+/// Ticks between two snapshots at the start of a run, doubling whenever there are too many.
+pub const SNAPSHOT_INTERVAL: u64 = 1024;
+
+/// Number of grid writes remembered in the journal.
+pub const JOURNAL_LENGTH: usize = 10_000;
+
+/// Cell written to by a `p` instruction, as remembered in the journal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub tick: u64,
+    /// Instruction pointer that executed the `p`
+    pub ip: u32,
+    pub position: (usize, usize),
+    pub old: char,
+    pub new: char,
+}
+
+impl std::fmt::Display for JournalEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (x, y) = self.position;
+        write!(
+            f,
+            "tick {:<8} ({x}, {y}) `{}` -> `{}`",
+            self.tick, self.old, self.new
+        )?;
+        match self.ip {
+            0 => Ok(()),
+            ip => write!(f, " IP {ip}"),
+        }
+    }
+}
+
+/// Number of wraps around the edges of the grid remembered.
+pub const WRAPS_LENGTH: usize = 4;
+
+/// Move of an instruction pointer past an edge of the grid, coming back in from the opposite one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Wrap {
+    pub tick: u64,
+    /// Cell the instruction pointer left from
+    pub from: (usize, usize),
+    /// Cell it came back in at
+    pub to: (usize, usize),
+    pub direction: Direction,
+}
+
+/// Path an instruction pointer is bound to follow, as found by running a copy of the
+/// interpreter ahead. Anything random or read from input may make it go another way.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Prediction {
+    /// Cells it executes next, in order
+    pub path: Vec<(usize, usize)>,
+    pub wraps: Vec<Wrap>,
+}
+
+/// Number of snapshots past which every other one is dropped.
+const MAX_SNAPSHOTS: usize = 64;
+
+/// State of a run at some point, travelling in time replays from the closest one.
+/// Grids share their unchanged chunks, so each snapshot costs memory proportional to the cells
+/// written to since the previous one.
+#[derive(Clone, Debug)]
+struct Snapshot {
+    interpreter: Interpreter,
+    /// Characters of input consumed by then
+    consumed: usize,
+    statistics: Statistics,
+}
+
+/// Writes to the grid, each within [BURST_TICKS] ticks of the previous one, making a burst worth
+/// a checkpoint.
+pub const BURST_WRITES: usize = 16;
+
+/// Ticks past which a write isn't part of the same burst as the previous one.
+pub const BURST_TICKS: u64 = 64;
+
+/// Number of checkpoints past which the oldest one is dropped.
+const MAX_CHECKPOINTS: usize = 32;
+
+/// Point right before a burst of writes to the grid, kept whatever the snapshot interval so the
+/// run can be brought back to before the program rewrote itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Tick of the first write of the burst
+    pub tick: u64,
+    /// Writes in the burst so far
+    pub writes: usize,
+}
+
+impl std::fmt::Display for Checkpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "tick {:<8} before {} writes", self.tick, self.writes)
+    }
+}
+
+/// Writes made in a row, along with the state right before the first one.
+#[derive(Clone, Debug)]
+struct Burst {
+    snapshot: Snapshot,
+    writes: usize,
+    /// Tick of the latest write
+    last: u64,
+}
+
+/// Wraps an [Interpreter] with breakpoint handling.
+#[derive(Clone, Debug)]
+pub struct Debugger {
+    interpreter: Interpreter,
+    /// Snapshots in tick order, the first one being the start of the run
+    snapshots: Vec<Snapshot>,
+    snapshot_interval: u64,
+    /// Checkpoints in tick order, with the snapshot they bring the run back to
+    checkpoints: Vec<(Checkpoint, Snapshot)>,
+    burst: Option<Burst>,
+    /// Writes and ticks making a burst, see [BURST_WRITES] and [BURST_TICKS]
+    bursts: (usize, u64),
+    /// Everything fed to the program so far
+    input: String,
+    breakpoints: HashSet<(usize, usize)>,
+    history: VecDeque<HistoryEntry>,
+    history_capacity: usize,
+    /// Latest writes to the grid, oldest first
+    journal: VecDeque<JournalEntry>,
+    timeline: Timeline,
+    statistics: Statistics,
+    loops: Option<LoopProfile>,
+    heatmap: Option<Heatmap>,
+    /// Cells pushed as text in string mode so far
+    strings: HashSet<(usize, usize)>,
+    /// Latest wraps around the edges of the grid, oldest first
+    wraps: VecDeque<Wrap>,
+    target: Option<Pending>,
+    /// Instruction pointer alone stopping at breakpoints, in concurrent runs
+    ip_filter: Option<u32>,
+    /// Whether runs stop when an instruction pointer starts, and when one ends
+    ip_events: (bool, bool),
+}
+
+impl Debugger {
+    pub fn new(interpreter: Interpreter) -> Self {
+        let mut timeline = Timeline::default();
+        timeline.record(interpreter.ticks(), interpreter.stack());
+
+        Self {
+            snapshots: vec![Snapshot {
+                interpreter: interpreter.clone(),
+                consumed: 0,
+                statistics: Statistics::default(),
+            }],
+            snapshot_interval: SNAPSHOT_INTERVAL,
+            checkpoints: Vec::new(),
+            burst: None,
+            bursts: (BURST_WRITES, BURST_TICKS),
+            interpreter,
+            input: String::new(),
+            breakpoints: HashSet::new(),
+            history: VecDeque::with_capacity(DEFAULT_HISTORY),
+            history_capacity: DEFAULT_HISTORY,
+            journal: VecDeque::new(),
+            timeline,
+            statistics: Statistics::default(),
+            loops: None,
+            heatmap: None,
+            strings: HashSet::new(),
+            wraps: VecDeque::new(),
+            target: None,
+            ip_filter: None,
+            ip_events: (false, false),
+        }
+    }
+
+    /// Sets how many executed instructions are remembered, forgetting the oldest ones if needed.
+    pub fn with_history(mut self, capacity: usize) -> Self {
+        self.history_capacity = capacity;
+        while self.history.len() > capacity {
+            self.history.pop_front();
+        }
+        self
+    }
+
+    /// Aggregates executed instructions into loops, which costs a hash lookup per instruction.
+    pub fn with_loop_profile(mut self) -> Self {
+        self.loops = Some(LoopProfile::default());
+        self
+    }
+
+    /// Counts executions of each cell.
+    pub fn with_heatmap(mut self) -> Self {
+        self.heatmap = Some(Heatmap::default());
+        self
+    }
+
+    /// Sets how many writes, each within `ticks` ticks of the previous one, make a checkpoint.
+    /// No checkpoints are made with 0 writes.
+    pub fn with_bursts(mut self, writes: usize, ticks: u64) -> Self {
+        self.bursts = (writes, ticks);
+        self
+    }
+
+    /// Executes a single instruction.
+    pub fn step(&mut self) -> Result<Stop> {
+        let position = self.interpreter.position();
+        let ips = self.interpreter.ips().count();
+        let value = self
+            .interpreter
+            .grid()
+            .try_get(position.0, position.1)
+            .map_or(CellValue::Empty, |cell| cell.value);
+        let mut entry = HistoryEntry {
+            tick: self.interpreter.ticks(),
+            position,
+            instruction: char::from(value),
+            ip: self.interpreter.ip().id,
+            cell: None,
+        };
+
+        let string_mode = self.interpreter.string_mode();
+        let direction = self.interpreter.direction();
+        if string_mode && entry.instruction != '"' {
+            self.strings.insert(position);
+        }
+
+        // The state before a write is only known to be worth keeping once the burst is long
+        // enough, so it is set aside whenever a new burst may start
+        let (writes, ticks) = self.bursts;
+        let quiet = self
+            .burst
+            .as_ref()
+            .is_none_or(|burst| entry.tick - burst.last > ticks);
+        if writes > 0 && entry.instruction == 'p' && !string_mode && quiet && !self.profiled() {
+            self.burst = Some(Burst {
+                snapshot: self.capture(),
+                writes: 0,
+                last: entry.tick,
+            });
+        }
+
+        let status = self.interpreter.step();
+
+        // Empty cells jumped over when fast-forwarding
+        let skipped = self.interpreter.ticks().saturating_sub(entry.tick + 1);
+        self.statistics.record_skipped(skipped);
+
+        let written = self.interpreter.written();
+        entry.cell = self
+            .interpreter
+            .read()
+            .or(written.map(|write| write.position));
+
+        // Instructions waiting for input will be executed again once it arrives
+        if !matches!(status, Ok(Status::WaitingForInput)) {
+            self.statistics.record(value, string_mode);
+            self.statistics.record_turn(entry.ip);
+
+            if let Some(loops) = self.loops.as_mut() {
+                loops.record(entry.tick, (position, direction));
+            }
+
+            if let Some(heatmap) = self.heatmap.as_mut() {
+                heatmap.record(entry.tick, position);
+            }
+
+            let moved = self.interpreter.ips().find(|ip| ip.id == entry.ip);
+            if let Some(ip) = moved.filter(|ip| ip.direction.wraps(position, ip.position)) {
+                if self.wraps.len() == WRAPS_LENGTH {
+                    self.wraps.pop_front();
+                }
+                self.wraps.push_back(Wrap {
+                    tick: entry.tick,
+                    from: position,
+                    to: ip.position,
+                    direction: ip.direction,
+                });
+            }
+
+            if self.history_capacity > 0 {
+                if self.history.len() == self.history_capacity {
+                    self.history.pop_front();
+                }
+                self.history.push_back(entry);
+            }
+        }
+
+        if let Some(write) = written {
+            self.statistics.record_write();
+            if self.journal.len() == JOURNAL_LENGTH {
+                self.journal.pop_front();
+            }
+            self.journal.push_back(JournalEntry {
+                tick: entry.tick,
+                ip: entry.ip,
+                position: write.position,
+                old: write.old,
+                new: write.new,
+            });
+            self.record_burst(entry.tick);
+        }
+
+        if matches!(status, Ok(Status::Running | Status::Terminated)) {
+            self.statistics.record_depth(self.interpreter.stack().len());
+            self.timeline
+                .record(self.interpreter.ticks(), self.interpreter.stack());
+        }
+
+        if matches!(status, Ok(Status::Running))
+            && self
+                .interpreter
+                .ticks()
+                .is_multiple_of(self.snapshot_interval)
+        {
+            self.snapshot();
+        }
+
+        let (spawn, exit) = self.ip_events;
+        let now = self.interpreter.ips().count();
+        Ok(match status? {
+            Status::Running if spawn && now > ips => {
+                let id = self.interpreter.ips().map(|ip| ip.id).max();
+                Stop::Spawned(id.unwrap_or_default())
+            }
+            Status::Running if exit && now < ips => Stop::Exited(entry.ip),
+            Status::Running => Stop::Step,
+            Status::WaitingForInput => Stop::WaitingForInput,
+            Status::Terminated => Stop::Terminated,
+        })
+    }
+
+    /// Runs at most `budget` instructions, stopping early on breakpoints, input requests,
+    /// termination and the target set by [Debugger::run_to].
+    /// Returns `None` if the budget ran out first.
+    pub fn resume(&mut self, budget: usize) -> Result<Option<Stop>> {
+        for _ in 0..budget {
+            match self.step() {
+                Ok(Stop::Step) => (),
+                Ok(Stop::WaitingForInput) => return Ok(Some(Stop::WaitingForInput)),
+                stop => {
+                    self.target = None;
+                    return stop.map(Some);
+                }
+            }
+
+            let position = self.interpreter.position();
+            let filtered = self
+                .ip_filter
+                .is_some_and(|id| id != self.interpreter.ip().id);
+            if self.breakpoints.contains(&position) && !filtered {
+                self.target = None;
+                return Ok(Some(Stop::Breakpoint(position)));
+            }
+
+            if self.reached() {
+                self.target = None;
+                return Ok(Some(Stop::Reached));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Makes [Debugger::resume] stop once `target` is reached, or forgets about the previous
+    /// target for `None`. Targets are also forgotten on breakpoints and when the run ends.
+    pub fn run_to(&mut self, target: Option<Target>) {
+        self.target = target.map(|target| match target {
+            Target::Cell(position) => Pending::Cell(position),
+            Target::LoopExit => {
+                let start = (self.interpreter.position(), self.interpreter.direction());
+                Pending::Loop {
+                    start,
+                    cycle: HashSet::from([start]),
+                    closed: false,
+                }
+            }
+            Target::StringEnd => Pending::StringEnd {
+                entered: self.interpreter.string_mode(),
+            },
+        });
+    }
+
+    /// Whether the instruction pointer is where the target wants it.
+    fn reached(&mut self) -> bool {
+        let interpreter = &self.interpreter;
+        match self.target.as_mut() {
+            None => false,
+            Some(Pending::Cell(position)) => interpreter.position() == *position,
+            Some(Pending::Loop {
+                start,
+                cycle,
+                closed,
+            }) => {
+                let state = (interpreter.position(), interpreter.direction());
+                if *closed {
+                    !cycle.contains(&state)
+                } else {
+                    // The first iteration tells which states are part of the cycle
+                    *closed = state == *start;
+                    cycle.insert(state);
+                    false
+                }
+            }
+            Some(Pending::StringEnd { entered }) => {
+                let string_mode = interpreter.string_mode();
+                *entered |= string_mode;
+                *entered && !string_mode
+            }
+        }
+    }
+
+    /// Brings the program back (or forward) to the state it was in after `tick` instructions.
+    ///
+    /// Going back restores the closest snapshot or checkpoint and replays from there with the input fed since,
+    /// so edits made to the grid from outside the program are lost. Breakpoints are ignored during
+    /// the replay, which stops early if the program terminates or waits for input.
+    pub fn travel_to(&mut self, tick: u64) -> Result<Stop> {
+        self.target = None;
+        if tick < self.interpreter.ticks() {
+            self.snapshots
+                .retain(|snapshot| snapshot.interpreter.ticks() <= tick);
+            self.checkpoints
+                .retain(|(_, snapshot)| snapshot.interpreter.ticks() <= tick);
+            self.burst = None;
+            let latest = self
+                .snapshots
+                .last()
+                .expect("the start of the run is always kept");
+            let snapshot = match self.checkpoints.last() {
+                Some((_, checkpoint))
+                    if checkpoint.interpreter.ticks() > latest.interpreter.ticks() =>
+                {
+                    checkpoint
+                }
+                _ => latest,
+            }
+            .clone();
+
+            self.interpreter = snapshot.interpreter;
+            self.interpreter.clear_input();
+            self.interpreter.feed_input(
+                &self
+                    .input
+                    .chars()
+                    .skip(snapshot.consumed)
+                    .collect::<String>(),
+            );
+            self.statistics = snapshot.statistics;
+            self.history.clear();
+            let from = self.interpreter.ticks();
+            self.journal.retain(|entry| entry.tick < from);
+            self.wraps.retain(|wrap| wrap.tick < from);
+
+            // Runs using profiles only have the start of the run as snapshot
+            if let Some(loops) = self.loops.as_mut() {
+                *loops = LoopProfile::default();
+            }
+            if let Some(heatmap) = self.heatmap.as_mut() {
+                *heatmap = Heatmap::default();
+            }
+        }
+
+        let mut stop = Stop::Step;
+        while self.interpreter.ticks() < tick {
+            stop = self.step()?;
+            if !matches!(stop, Stop::Step | Stop::Spawned(_) | Stop::Exited(_)) {
+                break;
+            }
+        }
+
+        Ok(stop)
+    }
+
+    fn snapshot(&mut self) {
+        // Profiles can't be restored, runs using them are always replayed from the start
+        if self.profiled() {
+            return;
+        }
+
+        let snapshot = self.capture();
+        self.snapshots.push(snapshot);
+
+        if self.snapshots.len() > MAX_SNAPSHOTS {
+            self.snapshot_interval *= 2;
+            let interval = self.snapshot_interval;
+            self.snapshots
+                .retain(|snapshot| snapshot.interpreter.ticks().is_multiple_of(interval));
+        }
+    }
+
+    fn capture(&self) -> Snapshot {
+        Snapshot {
+            interpreter: self.interpreter.clone(),
+            consumed: self.input.chars().count() - self.interpreter.pending_input(),
+            statistics: self.statistics.clone(),
+        }
+    }
+
+    fn profiled(&self) -> bool {
+        self.loops.is_some() || self.heatmap.is_some()
+    }
+
+    fn record_burst(&mut self, tick: u64) {
+        let Some(burst) = self.burst.as_mut() else {
+            return;
+        };
+        burst.writes += 1;
+        burst.last = tick;
+        if burst.writes < self.bursts.0 {
+            return;
+        }
+
+        let start = burst.snapshot.interpreter.ticks();
+        match self.checkpoints.iter_mut().find(|(c, _)| c.tick == start) {
+            Some((checkpoint, _)) => checkpoint.writes = burst.writes,
+            None => {
+                let checkpoint = Checkpoint {
+                    tick: start,
+                    writes: burst.writes,
+                };
+                self.checkpoints.push((checkpoint, burst.snapshot.clone()));
+                if self.checkpoints.len() > MAX_CHECKPOINTS {
+                    self.checkpoints.remove(0);
+                }
+            }
+        }
+    }
+
+    /// Points right before the bursts of writes to the grid made so far, oldest first. Runs
+    /// using profiles have none.
+    pub fn checkpoints(&self) -> impl Iterator<Item = &Checkpoint> + '_ {
+        self.checkpoints.iter().map(|(checkpoint, _)| checkpoint)
+    }
+
+    /// Feeds data to the program's input, remembering it for replays.
+    pub fn feed_input(&mut self, input: &str) {
+        self.input.push_str(input);
+        self.interpreter.feed_input(input);
+    }
+
+    /// Stack depth over the course of the run.
+    pub fn timeline(&self) -> &Timeline {
+        &self.timeline
+    }
+
+    /// Instruction mix executed so far.
+    pub fn statistics(&self) -> &Statistics {
+        &self.statistics
+    }
+
+    /// Loops executed so far, if profiling them was enabled.
+    pub fn loop_profile(&self) -> Option<&LoopProfile> {
+        self.loops.as_ref()
+    }
+
+    /// Per-cell execution counts so far, if enabled.
+    pub fn heatmap(&self) -> Option<&Heatmap> {
+        self.heatmap.as_ref()
+    }
+
+    /// Cells the run pushed as text in string mode so far, whatever they hold now.
+    pub fn strings(&self) -> &HashSet<(usize, usize)> {
+        &self.strings
+    }
+
+    /// Latest moves of instruction pointers past an edge of the grid, oldest first.
+    pub fn wraps(&self) -> &VecDeque<Wrap> {
+        &self.wraps
+    }
+
+    /// Path the current instruction pointer follows over the next `ticks` ticks, stopping early
+    /// when it ends or waits for input. The run itself is left untouched.
+    pub fn predict(&self, ticks: u64) -> Prediction {
+        let mut interpreter = self.interpreter.clone();
+        let id = interpreter.ip().id;
+        let mut prediction = Prediction::default();
+        let find = |interpreter: &Interpreter| {
+            let ip = interpreter.ips().find(|ip| ip.id == id)?;
+            Some((ip.position, ip.direction))
+        };
+
+        let end = interpreter.ticks() + ticks;
+        while interpreter.ticks() < end {
+            let Some((from, _)) = find(&interpreter) else {
+                break;
+            };
+            let tick = interpreter.ticks();
+            if !matches!(interpreter.step(), Ok(Status::Running)) {
+                break;
+            }
+            let Some((to, direction)) = find(&interpreter) else {
+                break;
+            };
+            if to != from {
+                prediction.path.push(to);
+            }
+            if direction.wraps(from, to) {
+                prediction.wraps.push(Wrap {
+                    tick,
+                    from,
+                    to,
+                    direction,
+                });
+            }
+        }
+
+        prediction
+    }
+
+    /// Toggles breakpoint at position, returns whether it is now set.
+    pub fn toggle_breakpoint(&mut self, position: (usize, usize)) -> bool {
+        if self.breakpoints.remove(&position) {
+            false
+        } else {
+            self.breakpoints.insert(position)
+        }
+    }
+
+    pub fn set_breakpoints(&mut self, breakpoints: impl IntoIterator<Item = (usize, usize)>) {
+        self.breakpoints = breakpoints.into_iter().collect();
+    }
+
+    pub fn breakpoints(&self) -> &HashSet<(usize, usize)> {
+        &self.breakpoints
+    }
+
+    /// Makes breakpoints only stop the run when reached by the instruction pointer with this id,
+    /// or by any of them for `None`.
+    pub fn set_ip_filter(&mut self, id: Option<u32>) {
+        self.ip_filter = id;
+    }
+
+    pub fn ip_filter(&self) -> Option<u32> {
+        self.ip_filter
+    }
+
+    /// Makes runs stop with [Stop::Spawned] when `t` starts an instruction pointer, and with
+    /// [Stop::Exited] when one ends while others are left.
+    pub fn set_ip_events(&mut self, spawn: bool, exit: bool) {
+        self.ip_events = (spawn, exit);
+    }
+
+    pub fn ip_events(&self) -> (bool, bool) {
+        self.ip_events
+    }
+
+    /// Everything fed to the program so far, consumed or not.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// Input consumed by the program so far, as it was fed.
+    pub fn consumed_input(&self) -> String {
+        let consumed = self.input.chars().count() - self.interpreter.pending_input();
+        self.input.chars().take(consumed).collect()
+    }
+
+    /// Most recent writes to the grid made by the program, oldest first.
+    pub fn journal(&self) -> &VecDeque<JournalEntry> {
+        &self.journal
+    }
+
+    /// Instructions of the history that executed, read or wrote a cell, oldest first. An
+    /// instruction reading or writing the cell it is in is listed twice.
+    pub fn references(
+        &self,
+        cell: (usize, usize),
+    ) -> impl Iterator<Item = (Access, &HistoryEntry)> + '_ {
+        self.history.iter().flat_map(move |entry| {
+            let executed = (entry.position == cell).then_some((Access::Executed, entry));
+            let access = match entry.instruction {
+                'p' => Access::Written,
+                _ => Access::Read,
+            };
+            let accessed = (entry.cell == Some(cell)).then_some((access, entry));
+            executed.into_iter().chain(accessed)
+        })
+    }
+
+    /// Most recently executed instructions, oldest first.
+    pub fn history(&self) -> &VecDeque<HistoryEntry> {
+        &self.history
+    }
+
+    pub fn interpreter(&self) -> &Interpreter {
+        &self.interpreter
+    }
+
+    pub fn interpreter_mut(&mut self) -> &mut Interpreter {
+        &mut self.interpreter
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::grid::Grid;
+
+    #[test]
+    fn travel() {
+        // Reads a number then randomly prints it, prints it plus one, reads another or ends
+        let program = "&?1+.@\n .\n @".to_owned();
+        let mut debugger = Debugger::new(Interpreter::new(Grid::from(program)));
+        debugger.feed_input("41 7 ");
+
+        assert_eq!(debugger.resume(100).unwrap(), Some(Stop::Terminated));
+        let output = debugger.interpreter_mut().take_output();
+        let ticks = debugger.interpreter().ticks();
+
+        assert_eq!(debugger.travel_to(1).unwrap(), Stop::Step);
+        assert_eq!(debugger.consumed_input(), "41");
+        assert_eq!(debugger.interpreter().ticks(), 1);
+        assert_eq!(debugger.interpreter().stack(), &[41]);
+        assert_eq!(debugger.timeline().samples().last().unwrap().tick, 1);
+
+        assert_eq!(debugger.travel_to(ticks).unwrap(), Stop::Terminated);
+        assert_eq!(debugger.interpreter_mut().take_output(), output);
+    }
+
+    #[test]
+    fn journal() {
+        let mut debugger = Debugger::new(Interpreter::new(Grid::from("\"A\"00p@".to_owned())));
+        assert_eq!(debugger.resume(100).unwrap(), Some(Stop::Terminated));
+
+        let write = JournalEntry {
+            tick: 5,
+            ip: 0,
+            position: (0, 0),
+            old: '"',
+            new: 'A',
+        };
+        assert_eq!(debugger.journal(), &[write]);
+        assert_eq!(write.to_string(), "tick 5        (0, 0) `\"` -> `A`");
+
+        // Writes are forgotten when going back past them, and made again when replayed
+        debugger.travel_to(3).unwrap();
+        assert!(debugger.journal().is_empty());
+        debugger.travel_to(6).unwrap();
+        assert_eq!(debugger.journal(), &[write]);
+    }
+
+    #[test]
+    fn checkpoints() {
+        let program = "\"X\"01p\"X\"11p\"X\"21p\"X\"31p\"X\"41p@\n     ".to_owned();
+        let mut debugger = Debugger::new(Interpreter::new(Grid::from(program))).with_bursts(4, 8);
+        assert_eq!(debugger.resume(100).unwrap(), Some(Stop::Terminated));
+
+        let checkpoint = Checkpoint { tick: 5, writes: 5 };
+        assert_eq!(debugger.checkpoints().collect::<Vec<_>>(), [&checkpoint]);
+        assert_eq!(checkpoint.to_string(), "tick 5        before 5 writes");
+
+        debugger.travel_to(checkpoint.tick).unwrap();
+        assert_eq!(debugger.interpreter().stack(), &[88, 0, 1]);
+        assert_eq!(
+            debugger.interpreter().grid().get(0, 1).value,
+            CellValue::Empty
+        );
+        assert!(debugger.checkpoints().next().is_some());
+    }
+
+    #[test]
+    fn wraps() {
+        let mut debugger = Debugger::new(Interpreter::new(Grid::from("<@.1".to_owned())));
+        let wrap = Wrap {
+            tick: 0,
+            from: (0, 0),
+            to: (3, 0),
+            direction: Direction::Left,
+        };
+
+        let prediction = debugger.predict(10);
+        assert_eq!(prediction.path, vec![(3, 0), (2, 0), (1, 0)]);
+        assert_eq!(prediction.wraps, vec![wrap]);
+        assert_eq!(debugger.interpreter().ticks(), 0);
+
+        assert_eq!(debugger.resume(100).unwrap(), Some(Stop::Terminated));
+        assert_eq!(debugger.wraps(), &[wrap]);
+    }
+
+    #[test]
+    fn references() {
+        // Copies the `@` at (1, 1) over the empty cell at (2, 1), then runs into it
+        let program = "11g21pv\n @    <".to_owned();
+        let mut debugger = Debugger::new(Interpreter::new(Grid::from(program)));
+        assert_eq!(debugger.resume(100).unwrap(), Some(Stop::Terminated));
+
+        let references = |cell| {
+            debugger
+                .references(cell)
+                .map(|(access, entry)| (access, entry.tick))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(references((1, 1)), [(Access::Read, 2)]);
+        assert_eq!(
+            references((2, 1)),
+            [(Access::Written, 5), (Access::Executed, 11)]
+        );
+    }
+
+    #[test]
+    fn snapshots() {
+        // Counts forever, storing the counter in the cell at (0, 2)
+        let program = ">1+:02p v\n^       <\n ".to_owned();
+        let run = |ticks| {
+            let mut debugger = Debugger::new(Interpreter::new(Grid::from(program.clone())));
+            debugger.resume(ticks).unwrap();
+            debugger
+        };
+
+        let mut debugger = run(10 * SNAPSHOT_INTERVAL as usize);
+        assert!(debugger.snapshots.len() > 1);
+
+        let expected = run(5_000);
+        debugger.travel_to(5_000).unwrap();
+
+        assert_eq!(debugger.interpreter().ticks(), 5_000);
+        assert_eq!(
+            debugger.interpreter().stack(),
+            expected.interpreter().stack()
+        );
+        assert_eq!(
+            debugger.interpreter().position(),
+            expected.interpreter().position()
+        );
+        assert_eq!(
+            char::from(debugger.interpreter().grid().get(0, 2).value),
+            char::from(expected.interpreter().grid().get(0, 2).value)
+        );
+    }
+
+    #[test]
+    fn run_to() {
+        // Counts down from 3 in a loop, then prints a string
+        let program = "3>:#v_\"kd\",,@\n ^-1<".to_owned();
+        let mut debugger = Debugger::new(Interpreter::new(Grid::from(program)));
+        debugger.resume(3).unwrap();
+
+        debugger.run_to(Some(Target::LoopExit));
+        assert_eq!(debugger.resume(1000).unwrap(), Some(Stop::Reached));
+        assert_eq!(debugger.interpreter().position(), (6, 0));
+        assert_eq!(debugger.interpreter().stack(), &[0]);
+
+        debugger.run_to(Some(Target::StringEnd));
+        assert_eq!(debugger.resume(1000).unwrap(), Some(Stop::Reached));
+        assert_eq!(debugger.interpreter().position(), (10, 0));
+
+        debugger.run_to(Some(Target::Cell((12, 0))));
+        assert_eq!(debugger.resume(1000).unwrap(), Some(Stop::Reached));
+        assert_eq!(debugger.interpreter_mut().take_output(), "dk");
+    }
+
+    #[test]
+    fn ips() {
+        // Both instruction pointers go through the `.` at (1, 0), the child one first
+        let mut interpreter = Interpreter::new(Grid::from("1.t2.@".to_owned()));
+        interpreter.enable(crate::dialect::Extension::Concurrent);
+        let mut debugger = Debugger::new(interpreter);
+        debugger.set_ip_events(true, true);
+        debugger.set_breakpoints([(4, 0)]);
+        debugger.set_ip_filter(Some(1));
+
+        assert_eq!(debugger.resume(100).unwrap(), Some(Stop::Spawned(1)));
+        assert_eq!(debugger.resume(100).unwrap(), Some(Stop::Exited(1)));
+        assert_eq!(debugger.resume(100).unwrap(), Some(Stop::Terminated));
+
+        let ips = debugger.history().iter().map(|entry| entry.ip);
+        assert_eq!(ips.collect::<Vec<_>>(), [0, 0, 0, 1, 0, 1, 0, 1, 0]);
+    }
+}
@@ -0,0 +1,22 @@
+use puccinia::{decompiler, directives};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Could not load `{0}`: {1}")]
+    Load(String, directives::Error),
+}
+
+#[derive(clap::Args)]
+pub(crate) struct Options {
+    /// Program file location
+    input: String,
+}
+
+/// Prints the pseudocode of a program.
+pub(crate) fn run(options: Options) -> anyhow::Result<()> {
+    let (grid, _) =
+        directives::open(&options.input).map_err(|err| Error::Load(options.input.clone(), err))?;
+
+    print!("{}", decompiler::decompile(&grid));
+    Ok(())
+}
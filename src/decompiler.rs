@@ -0,0 +1,467 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+use crate::{
+    analyzer::{self, Ip},
+    cell::{
+        BinaryOperator, CellValue, Direction, NullaryOperator, Operator, TernaryOperator,
+        UnaryOperator,
+    },
+    grid::Grid,
+};
+
+/// Value on the symbolic stack of a block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Value {
+    Number(i64),
+    Char(char),
+    /// Value on the stack when the block starts, or temporary holding a result
+    Name(String),
+    /// Result of an operation, parenthesized when nested
+    Expression(String),
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Number(number) => write!(f, "{number}"),
+            Value::Char(c) if !c.is_control() && *c != '\'' && *c != '\\' => write!(f, "'{c}'"),
+            Value::Char(c) => write!(f, "{}", *c as u32),
+            Value::Name(name) => f.write_str(name),
+            // `{:#}` leaves out the parentheses of a whole expression
+            Value::Expression(expression) if f.alternate() => f.write_str(expression),
+            Value::Expression(expression) => write!(f, "({expression})"),
+        }
+    }
+}
+
+impl Value {
+    fn constant(&self) -> Option<i64> {
+        match self {
+            Value::Number(number) => Some(*number),
+            Value::Char(c) => Some(*c as i64),
+            _ => None,
+        }
+    }
+}
+
+/// How a block hands over control.
+enum Exit {
+    End,
+    /// Goes on with a single state
+    Next(usize),
+    /// `_` or `|`, going to the first state on a non-zero value
+    Branch(Value, usize, usize),
+    Random(Vec<usize>),
+}
+
+/// Straight run of instructions translated to statements, along with the values it leaves on
+/// the stack.
+struct Block {
+    statements: Vec<String>,
+    stack: Vec<Value>,
+    /// Values popped from the stack the block started with, named `a0`, `a1`... from the top
+    popped: usize,
+}
+
+impl Block {
+    fn pop(&mut self) -> Value {
+        self.stack.pop().unwrap_or_else(|| {
+            self.popped += 1;
+            Value::Name(format!("a{}", self.popped - 1))
+        })
+    }
+
+    /// Binds a value to a temporary, so that it is computed once and in order.
+    fn bind(&mut self, value: String, temporaries: &mut usize) -> Value {
+        let name = format!("t{temporaries}");
+        *temporaries += 1;
+        self.statements.push(format!("{name} = {value}"));
+        Value::Name(name)
+    }
+
+    fn binary(&mut self, operator: BinaryOperator, temporaries: &mut usize) {
+        let (b, a) = (self.pop(), self.pop());
+        let folded = a
+            .constant()
+            .zip(b.constant())
+            .and_then(|(a, b)| match operator {
+                BinaryOperator::Add => Some(a + b),
+                BinaryOperator::Subtract => Some(a - b),
+                BinaryOperator::Multiply => Some(a * b),
+                BinaryOperator::Divide => a.checked_div(b),
+                BinaryOperator::Modulo => a.checked_rem(b),
+                BinaryOperator::Greater => Some(i64::from(a > b)),
+                BinaryOperator::Swap | BinaryOperator::Get => None,
+            });
+        if let Some(folded) = folded {
+            self.stack.push(Value::Number(folded));
+            return;
+        }
+
+        let value = match operator {
+            BinaryOperator::Add => Value::Expression(format!("{a} + {b}")),
+            BinaryOperator::Subtract => Value::Expression(format!("{a} - {b}")),
+            BinaryOperator::Multiply => Value::Expression(format!("{a} * {b}")),
+            BinaryOperator::Divide => Value::Expression(format!("{a} / {b}")),
+            BinaryOperator::Modulo => Value::Expression(format!("{a} % {b}")),
+            BinaryOperator::Greater => Value::Expression(format!("{a} > {b}")),
+            BinaryOperator::Swap => {
+                self.stack.push(b);
+                a
+            }
+            BinaryOperator::Get => self.bind(format!("cell({a}, {b})"), temporaries),
+        };
+        self.stack.push(value);
+    }
+
+    /// Runs an instruction on the symbolic stack.
+    fn execute(&mut self, ip: Ip, value: CellValue, temporaries: &mut usize) {
+        if ip.string_mode {
+            if !matches!(value, CellValue::StringMode) {
+                self.stack.push(Value::Char(char::from(value)));
+            }
+            return;
+        }
+
+        match value {
+            CellValue::Number(number) => self.stack.push(Value::Number(i64::from(number))),
+            CellValue::Op(Operator::Nullary(operator)) => {
+                let read = match operator {
+                    NullaryOperator::Integer => "read_number()",
+                    NullaryOperator::Ascii => "read_char()",
+                };
+                let value = self.bind(read.to_owned(), temporaries);
+                self.stack.push(value);
+            }
+            CellValue::Op(Operator::Unary(operator)) => {
+                let value = self.pop();
+                match operator {
+                    UnaryOperator::Negate => self.stack.push(match value.constant() {
+                        Some(constant) => Value::Number(i64::from(constant == 0)),
+                        None => Value::Expression(format!("{value} == 0")),
+                    }),
+                    UnaryOperator::Duplicate => {
+                        let value = match value {
+                            Value::Expression(expression) => self.bind(expression, temporaries),
+                            value => value,
+                        };
+                        self.stack.push(value.clone());
+                        self.stack.push(value);
+                    }
+                    UnaryOperator::Pop => (),
+                    UnaryOperator::WriteNumber => {
+                        self.statements.push(format!("print_number({value:#})"))
+                    }
+                    UnaryOperator::WriteASCII => {
+                        self.statements.push(format!("print_char({value:#})"))
+                    }
+                }
+            }
+            CellValue::Op(Operator::Binary(operator)) => self.binary(operator, temporaries),
+            CellValue::Op(Operator::Ternary(TernaryOperator::Put)) => {
+                let (y, x, value) = (self.pop(), self.pop(), self.pop());
+                self.statements.push(format!("cell({x}, {y}) = {value:#}"));
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Control-flow graph over instruction pointer states, assuming the grid is never modified.
+struct Graph {
+    states: Vec<Ip>,
+    successors: Vec<Vec<usize>>,
+    predecessors: Vec<usize>,
+}
+
+impl Graph {
+    fn new(grid: &Grid) -> Self {
+        let mut graph = Graph {
+            states: vec![Ip::START],
+            successors: Vec::new(),
+            predecessors: vec![0],
+        };
+        let mut indices = HashMap::from([(Ip::START, 0)]);
+
+        let mut next = 0;
+        while next < graph.states.len() {
+            let successors = analyzer::successors(grid, graph.states[next])
+                .into_iter()
+                .map(|ip| {
+                    let index = *indices.entry(ip).or_insert_with(|| {
+                        graph.states.push(ip);
+                        graph.predecessors.push(0);
+                        graph.states.len() - 1
+                    });
+                    graph.predecessors[index] += 1;
+                    index
+                })
+                .collect();
+            graph.successors.push(successors);
+            next += 1;
+        }
+
+        graph
+    }
+
+    /// States starting a block: the first one, those reached from several places, and those a
+    /// branch leads to.
+    fn leaders(&self) -> HashSet<usize> {
+        let mut leaders = HashSet::from([0]);
+        for (state, successors) in self.successors.iter().enumerate() {
+            if self.predecessors[state] != 1 {
+                leaders.insert(state);
+            }
+            if successors.len() > 1 {
+                leaders.extend(successors);
+            }
+        }
+        leaders
+    }
+}
+
+fn direction(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Up => "up",
+        Direction::Down => "down",
+        Direction::Left => "left",
+        Direction::Right => "right",
+        Direction::Random => "randomly",
+    }
+}
+
+/// Pseudocode of a program, as labelled blocks of statements ending with jumps. Values the
+/// block finds on the stack are named `a0`, `a1`... from the top, and each block is commented
+/// with its stack effect. Self-modifying programs are described as they are loaded.
+pub fn decompile(grid: &Grid) -> String {
+    let (width, height) = grid.size();
+    if width == 0 || height == 0 {
+        return String::new();
+    }
+
+    let graph = Graph::new(grid);
+    let leaders = graph.leaders();
+    let mut temporaries = 0;
+
+    // Translates every block, following states until the next leader or branch
+    let mut blocks = HashMap::new();
+    for &leader in &leaders {
+        let mut block = Block {
+            statements: Vec::new(),
+            stack: Vec::new(),
+            popped: 0,
+        };
+        let mut state = leader;
+        let exit = loop {
+            let ip = graph.states[state];
+            let value = grid.get(ip.position.0, ip.position.1).value;
+            let successors = &graph.successors[state];
+
+            match (value, successors.as_slice()) {
+                (_, []) => break Exit::End,
+                (CellValue::If(_), [nonzero, zero]) if !ip.string_mode => {
+                    break Exit::Branch(block.pop(), *nonzero, *zero)
+                }
+                (_, [next]) => {
+                    block.execute(ip, value, &mut temporaries);
+                    if leaders.contains(next) {
+                        break Exit::Next(*next);
+                    }
+                    state = *next;
+                }
+                (_, successors) => break Exit::Random(successors.to_vec()),
+            }
+        };
+        blocks.insert(leader, (block, exit));
+    }
+
+    // Lays blocks out in depth-first order, so that most jumps fall through, and finds the
+    // loops closed by jumps back to a block being visited
+    let mut order = Vec::new();
+    let mut loops = HashMap::<usize, Vec<usize>>::new();
+    let mut visited = HashSet::new();
+    let mut path = Vec::new();
+    let mut stack = vec![(0, false)];
+    while let Some((leader, done)) = stack.pop() {
+        if done {
+            path.retain(|state| *state != leader);
+            continue;
+        }
+        if !visited.insert(leader) {
+            continue;
+        }
+        order.push(leader);
+        path.push(leader);
+        stack.push((leader, true));
+
+        let targets = match &blocks[&leader].1 {
+            Exit::End => vec![],
+            Exit::Next(next) => vec![*next],
+            Exit::Branch(_, nonzero, zero) => vec![*nonzero, *zero],
+            Exit::Random(targets) => targets.clone(),
+        };
+        for target in targets.into_iter().rev() {
+            if path.contains(&target) {
+                loops.entry(target).or_default().push(leader);
+            }
+            stack.push((target, false));
+        }
+    }
+    let labels = order
+        .iter()
+        .enumerate()
+        .map(|(index, leader)| (*leader, format!("L{index}")))
+        .collect::<HashMap<_, _>>();
+
+    let mut listing = String::new();
+    let executed = graph
+        .states
+        .iter()
+        .filter(|ip| !ip.string_mode)
+        .map(|ip| ip.position)
+        .collect::<HashSet<_>>();
+    let mut puts = executed
+        .into_iter()
+        .filter(|(x, y)| {
+            matches!(
+                grid.get(*x, *y).value,
+                CellValue::Op(Operator::Ternary(TernaryOperator::Put))
+            )
+        })
+        .collect::<Vec<_>>();
+    puts.sort_by_key(|(x, y)| (*y, *x));
+    for (x, y) in puts {
+        let _ = writeln!(
+            listing,
+            "// `p` at ({x}, {y}) may rewrite the program, which is described as loaded"
+        );
+    }
+
+    for (index, leader) in order.iter().enumerate() {
+        let (block, exit) = &blocks[leader];
+        let ip = graph.states[*leader];
+        let (x, y) = ip.position;
+        let _ = writeln!(
+            listing,
+            "{}:  // ({x}, {y}) going {}{}",
+            labels[leader],
+            direction(ip.direction),
+            if ip.string_mode {
+                " in string mode"
+            } else {
+                ""
+            }
+        );
+        if let Some(sources) = loops.get(leader) {
+            let sources = sources
+                .iter()
+                .map(|source| labels[source].as_str())
+                .collect::<Vec<_>>();
+            let _ = writeln!(listing, "    // loop, repeated from {}", sources.join(", "));
+        }
+
+        let inputs = (0..block.popped)
+            .rev()
+            .map(|index| format!("a{index}"))
+            .collect::<Vec<_>>();
+        let outputs = block
+            .stack
+            .iter()
+            .map(|value| format!("{value:#}"))
+            .collect::<Vec<_>>();
+        let _ = writeln!(
+            listing,
+            "    // ( {}-- {})",
+            inputs
+                .iter()
+                .map(|input| format!("{input} "))
+                .collect::<String>(),
+            outputs
+                .iter()
+                .map(|output| format!("{output} "))
+                .collect::<String>()
+        );
+
+        for statement in &block.statements {
+            let _ = writeln!(listing, "    {statement}");
+        }
+        if !outputs.is_empty() {
+            let _ = writeln!(listing, "    push {}", outputs.join(", "));
+        }
+
+        let next = order.get(index + 1);
+        let goto = |target: &usize| match Some(target) == next {
+            true => None,
+            false => Some(format!("    goto {}", labels[target])),
+        };
+        match exit {
+            Exit::End => {
+                let _ = writeln!(listing, "    end");
+            }
+            Exit::Next(target) => {
+                if let Some(goto) = goto(target) {
+                    let _ = writeln!(listing, "{goto}");
+                }
+            }
+            Exit::Branch(condition, nonzero, zero) => {
+                let _ = writeln!(
+                    listing,
+                    "    if {condition:#} != 0 goto {}",
+                    labels[nonzero]
+                );
+                if let Some(goto) = goto(zero) {
+                    let _ = writeln!(listing, "{goto}");
+                }
+            }
+            Exit::Random(targets) => {
+                let targets = targets
+                    .iter()
+                    .map(|target| labels[target].as_str())
+                    .collect::<Vec<_>>();
+                let _ = writeln!(listing, "    goto any of {}", targets.join(", "));
+            }
+        }
+    }
+
+    listing
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decompile() {
+        let grid = Grid::from("0\"ih\">:#,_@".to_owned());
+        let listing = super::decompile(&grid);
+        assert_eq!(
+            listing,
+            "\
+L0:  // (0, 0) going right
+    // ( -- 0 'i' 'h' )
+    push 0, 'i', 'h'
+L1:  // (6, 0) going right
+    // loop, repeated from L2
+    // ( a0 -- a0 )
+    push a0
+    if a0 != 0 goto L2
+    goto L3
+L2:  // (8, 0) going left
+    // ( a0 -- )
+    print_char(a0)
+    goto L1
+L3:  // (10, 0) going right
+    // ( -- )
+    end
+"
+        );
+
+        let listing = super::decompile(&Grid::from("&:*.925*3p@".to_owned()));
+        assert!(listing.starts_with("// `p` at (9, 0) may rewrite the program"));
+        assert!(listing.contains("    t0 = read_number()\n    print_number(t0 * t0)\n"));
+        assert!(listing.contains("    cell(10, 3) = 9\n"));
+    }
+}
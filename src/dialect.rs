@@ -0,0 +1,275 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    analyzer::{self, Diagnostic, Severity},
+    cell::{CellValue, Operator, UnaryOperator},
+    grid::Grid,
+};
+
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Unknown dialect `{0}`, expected `befunge93`, `befunge96` or `befunge97`")]
+    Unknown(String),
+    #[error(
+        "Unknown extension `{0}`, expected `multi-digit`, `concurrent`, `system-info`, `quit` or \
+         `assert`"
+    )]
+    UnknownExtension(String),
+}
+
+/// Befunge revision whose semantics the interpreter follows.
+///
+/// The intermediate 96 and 97 revisions were never finalised, their quirks are those of the
+/// interpreters that shipped with them and that historical programs rely on.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Dialect {
+    #[default]
+    Befunge93,
+    /// Wraps around a fixed 80×25 page and `!` pushes -1 for true
+    Befunge96,
+    /// Wraps around a fixed 80×25 page
+    Befunge97,
+}
+
+/// Size of the fixed page of the 96 and 97 revisions.
+pub const PAGE: (usize, usize) = (80, 25);
+
+impl Dialect {
+    pub const ALL: [Dialect; 3] = [Dialect::Befunge93, Dialect::Befunge96, Dialect::Befunge97];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dialect::Befunge93 => "befunge93",
+            Dialect::Befunge96 => "befunge96",
+            Dialect::Befunge97 => "befunge97",
+        }
+    }
+
+    /// Fixed area the instruction pointer wraps around, rather than the program's bounds.
+    pub fn page(&self) -> Option<(usize, usize)> {
+        match self {
+            Dialect::Befunge93 => None,
+            Dialect::Befunge96 | Dialect::Befunge97 => Some(PAGE),
+        }
+    }
+
+    /// Value pushed by `!` when popping 0.
+    pub fn truth(&self) -> i32 {
+        match self {
+            Dialect::Befunge96 => -1,
+            Dialect::Befunge93 | Dialect::Befunge97 => 1,
+        }
+    }
+}
+
+impl FromStr for Dialect {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        // The `befunge` prefix is optional
+        let version = name.strip_prefix("befunge").unwrap_or(name);
+        Dialect::ALL
+            .into_iter()
+            .find(|dialect| dialect.name().strip_prefix("befunge") == Some(version))
+            .ok_or_else(|| Error::Unknown(name.to_owned()))
+    }
+}
+
+impl std::fmt::Display for Dialect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Opt-in departure from the semantics of every dialect, none being enabled by default.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Extension {
+    /// A run of digits in the direction of travel pushes a single multi-digit number
+    MultiDigit,
+    /// `t` starts a new instruction pointer going the other way, with a copy of the stack, and
+    /// instruction pointers take turns executing an instruction each
+    Concurrent,
+    /// `y` pushes the Funge-98 system information, the command line arguments and environment
+    /// variables given to the program included
+    SystemInfo,
+    /// `q` ends the program, every instruction pointer included, with the exit code it pops
+    Quit,
+    /// `A` pops a value and ends the program with a failed assertion if it is zero, for tests
+    /// to check what they expect along the way
+    Assert,
+}
+
+impl Extension {
+    pub const ALL: [Extension; 5] = [
+        Extension::MultiDigit,
+        Extension::Concurrent,
+        Extension::SystemInfo,
+        Extension::Quit,
+        Extension::Assert,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Extension::MultiDigit => "multi-digit",
+            Extension::Concurrent => "concurrent",
+            Extension::SystemInfo => "system-info",
+            Extension::Quit => "quit",
+            Extension::Assert => "assert",
+        }
+    }
+}
+
+impl FromStr for Extension {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Extension::ALL
+            .into_iter()
+            .find(|extension| extension.name() == name)
+            .ok_or_else(|| Error::UnknownExtension(name.to_owned()))
+    }
+}
+
+impl std::fmt::Display for Extension {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// What a Funge-98 instruction does, for instructions Befunge-93 doesn't have.
+pub(crate) fn funge98(instruction: char) -> Option<&'static str> {
+    Some(match instruction {
+        'a'..='f' => "pushes a hexadecimal digit",
+        '\'' => "pushes the next cell",
+        's' => "stores into the next cell",
+        ';' => "jumps over code up to the next `;`",
+        '[' => "turns left",
+        ']' => "turns right",
+        'r' => "reverses direction",
+        'x' => "sets the direction from the stack",
+        'j' => "jumps forward",
+        'k' => "repeats the next instruction",
+        'n' => "clears the stack",
+        'q' => "quits with an exit code",
+        't' => "starts a new instruction pointer",
+        'w' => "compares and turns",
+        'y' => "pushes system information",
+        'z' => "does nothing",
+        '{' => "begins a block",
+        '}' => "ends a block",
+        'u' => "transfers between stacks",
+        '(' | ')' => "loads or unloads a fingerprint",
+        '=' => "executes a system command",
+        'i' => "reads a file",
+        'o' => "writes a file",
+        _ => return None,
+    })
+}
+
+/// Constructs of a program that would behave differently, or be invalid, when run as `target`.
+pub fn check(grid: &Grid, target: Dialect) -> Vec<Diagnostic> {
+    let analysis = analyzer::analyze(grid, &[]);
+    let mut diagnostics = Vec::new();
+
+    let (width, height) = grid.size();
+    let (page_width, page_height) = PAGE;
+    for y in 0..height {
+        let columns = if y < page_height { page_width } else { 0 };
+        for x in columns..width {
+            if !matches!(grid.get(x, y).value, CellValue::Empty) {
+                diagnostics.push(Diagnostic {
+                    position: (x, y),
+                    severity: Severity::Error,
+                    message: format!(
+                        "Outside of the {page_width}×{page_height} page, {target} interpreters may \
+                         not load it"
+                    ),
+                });
+            }
+        }
+    }
+
+    for y in 0..height.min(page_height) {
+        for x in 0..width.min(page_width) {
+            if !analysis.is_executed((x, y)) {
+                continue;
+            }
+
+            let diagnostic = |severity, message| Diagnostic {
+                position: (x, y),
+                severity,
+                message,
+            };
+
+            match grid.get(x, y).value {
+                CellValue::Char(c) => diagnostics.push(match funge98(c) {
+                    Some(meaning) => diagnostic(
+                        Severity::Error,
+                        format!("`{c}` {meaning} in Funge-98 but is not a {target} instruction"),
+                    ),
+                    None => diagnostic(
+                        Severity::Warning,
+                        format!("`{c}` is not a {target} instruction"),
+                    ),
+                }),
+                CellValue::Op(Operator::Unary(UnaryOperator::Negate)) if target.truth() != 1 => {
+                    diagnostics.push(diagnostic(
+                        Severity::Warning,
+                        format!("`!` pushes {} for true in {target}", target.truth()),
+                    ))
+                }
+                _ => (),
+            }
+
+            let wraps = analysis.successors((x, y)).is_some_and(|successors| {
+                successors
+                    .iter()
+                    .any(|(sx, sy)| sx.abs_diff(x) > 2 || sy.abs_diff(y) > 2)
+            });
+            if wraps && target.page().is_some_and(|page| page != (width, height)) {
+                diagnostics.push(diagnostic(
+                    Severity::Warning,
+                    format!(
+                        "Wraps around the program's bounds, but around the \
+                         {page_width}×{page_height} page in {target}"
+                    ),
+                ));
+            }
+        }
+    }
+
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.position.1, diagnostic.position.0));
+    diagnostics
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check() {
+        let grid = crate::grid!["^", "", "", ">!k@"];
+
+        let diagnostics = |target| {
+            super::check(&grid, target)
+                .into_iter()
+                .map(|diagnostic| (diagnostic.position, diagnostic.severity))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            diagnostics(Dialect::Befunge93),
+            vec![((2, 3), Severity::Error)]
+        );
+        assert_eq!(
+            diagnostics(Dialect::Befunge96),
+            vec![
+                ((0, 0), Severity::Warning),
+                ((1, 3), Severity::Warning),
+                ((2, 3), Severity::Error),
+            ]
+        );
+    }
+}
@@ -0,0 +1,331 @@
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use rayon::prelude::*;
+
+use puccinia::{
+    config::Config,
+    dialect::Dialect,
+    directives::{self, Directives},
+    interpreter::Interpreter,
+};
+
+use crate::{
+    headless::{self, Ending, Geometry},
+    settings::Semantics,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Could not read `{0}`: {1}")]
+    Read(String, std::io::Error),
+    #[error("Empty reference command")]
+    EmptyReference,
+    #[error("Could not run the reference interpreter on `{0}`: {1}")]
+    Reference(String, std::io::Error),
+    #[error("No program found in the corpus")]
+    EmptyCorpus,
+    #[error("{0} of {1} programs behave differently")]
+    Differences(usize, usize),
+}
+
+type Result<T> = anyhow::Result<T, Error>;
+
+/// Bytes of output shown on either side of the first difference.
+const EXCERPT: usize = 24;
+
+/// Extensions of the program files picked up in corpus directories.
+const EXTENSIONS: [&str; 2] = ["bf", "b93"];
+
+#[derive(clap::Args)]
+pub(crate) struct Options {
+    /// Program files, or directories searched for `.bf` and `.b93` files
+    #[arg(required = true, value_name = "CORPUS")]
+    corpus: Vec<String>,
+
+    /// Command running the reference interpreter, such as `cfunge -s 93`, the path to the
+    /// program being appended to it and its input fed on stdin
+    #[arg(long, value_name = "COMMAND")]
+    reference: String,
+
+    /// File fed as input to every program, unless a `PROGRAM.in` file sits next to it or it is a
+    /// bundle bringing its own
+    #[arg(long, value_name = "PATH")]
+    input: Option<String>,
+
+    /// Semantics to run programs with. Defaults to each program's `dialect` directive, then to
+    /// the settings' `dialect`, or `befunge93`
+    #[arg(long, value_name = "DIALECT")]
+    dialect: Option<Dialect>,
+
+    /// Instructions after which a program is considered to run forever
+    #[arg(long, value_name = "N", default_value_t = 10_000_000)]
+    max_ticks: u64,
+
+    /// Seconds after which the reference interpreter is considered to run forever
+    #[arg(long, value_name = "SECONDS", default_value_t = 10)]
+    timeout: u64,
+
+    /// Number of programs run at the same time, defaults to the number of CPUs
+    #[arg(long, value_name = "N")]
+    jobs: Option<usize>,
+}
+
+/// Output of a program and how it ended.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Outcome {
+    output: Vec<u8>,
+    ending: Ending,
+}
+
+/// Runs every program of a corpus through both interpreters with the same input, reporting
+/// those whose output or ending differ.
+pub(crate) fn run(options: Options, config: &Config) -> anyhow::Result<()> {
+    let reference = options.reference.split_whitespace().collect::<Vec<_>>();
+    if reference.is_empty() {
+        return Err(Error::EmptyReference.into());
+    }
+
+    let programs = corpus(&options.corpus)?;
+    if programs.is_empty() {
+        return Err(Error::EmptyCorpus.into());
+    }
+    let input = options
+        .input
+        .as_ref()
+        .map(|path| std::fs::read_to_string(path).map_err(|err| Error::Read(path.clone(), err)))
+        .transpose()?
+        .unwrap_or_default();
+
+    let settings = Semantics::of(config);
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(options.jobs.unwrap_or_default())
+        .build()?;
+    let reports = pool.install(|| {
+        programs
+            .par_iter()
+            .enumerate()
+            .map(|(index, program)| {
+                compare(index, program, &reference, &input, &settings, &options)
+            })
+            .collect::<Vec<_>>()
+    });
+
+    let mut differences = 0;
+    for (program, report) in programs.iter().zip(reports) {
+        match report {
+            Ok(None) => eprintln!("{program}: same behaviour"),
+            Ok(Some(difference)) => {
+                differences += 1;
+                println!("{program}: {difference}");
+            }
+            Err(err) => {
+                differences += 1;
+                println!("{program}: {err}");
+            }
+        }
+    }
+
+    if differences > 0 {
+        return Err(Error::Differences(differences, programs.len()).into());
+    }
+
+    eprintln!("{} programs behave the same", programs.len());
+    Ok(())
+}
+
+/// Program files of a corpus, directories being searched recursively.
+pub(crate) fn corpus(paths: &[String]) -> Result<Vec<String>> {
+    let mut programs = Vec::new();
+    for path in paths {
+        let path = Path::new(path);
+        if path.is_dir() {
+            collect(path, &mut programs)?;
+        } else {
+            programs.push(path.to_path_buf());
+        }
+    }
+
+    Ok(programs
+        .into_iter()
+        .map(|path| path.display().to_string())
+        .collect())
+}
+
+fn collect(directory: &Path, programs: &mut Vec<PathBuf>) -> Result<()> {
+    let read = |err| Error::Read(directory.display().to_string(), err);
+    let mut entries = std::fs::read_dir(directory)
+        .map_err(read)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()
+        .map_err(read)?;
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            collect(&path, programs)?;
+        } else if path
+            .extension()
+            .is_some_and(|extension| EXTENSIONS.iter().any(|known| extension == *known))
+        {
+            programs.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs a program through both interpreters, describing how they differ if they do.
+fn compare(
+    index: usize,
+    program: &str,
+    reference: &[&str],
+    input: &str,
+    settings: &Semantics,
+    options: &Options,
+) -> anyhow::Result<Option<String>> {
+    let (grid, directives, canned) = headless::load(program, Geometry::default())?;
+    let input = match canned {
+        Some(canned) => canned,
+        None => std::fs::read_to_string(format!("{program}.in")).unwrap_or_else(|_| input.into()),
+    };
+
+    // The reference interpreter is given the bare grid, without the directives it doesn't know of
+    let copy = std::env::temp_dir().join(format!(
+        "puccinia-difftest-{}-{index}.bf",
+        std::process::id()
+    ));
+    std::fs::write(&copy, directives::to_program(&grid, &Directives::default()))
+        .map_err(|err| Error::Reference(program.to_owned(), err))?;
+    let theirs = execute_reference(reference, &copy, &input, options.timeout);
+    let _ = std::fs::remove_file(&copy);
+    let theirs = theirs.map_err(|err| Error::Reference(program.to_owned(), err))?;
+
+    let dialect = Semantics::new(options.dialect, &[])
+        .or(settings, &directives)
+        .dialect;
+    let mut interpreter = Interpreter::new(grid);
+    headless::configure(&mut interpreter, &directives, dialect, &[], None);
+    let ending = headless::run_to_end(&mut interpreter, &input, options.max_ticks);
+    let ours = Outcome {
+        output: interpreter.take_output().into_bytes(),
+        ending,
+    };
+
+    Ok(describe(&ours, &theirs))
+}
+
+/// Runs the reference interpreter on a program file, killing it after `timeout` seconds.
+fn execute_reference(
+    reference: &[&str],
+    program: &Path,
+    input: &str,
+    timeout: u64,
+) -> std::io::Result<Outcome> {
+    let mut child = Command::new(reference[0])
+        .args(&reference[1..])
+        .arg(program)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    // Pipes are drained on their own threads so that neither side blocks the other
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = input.to_owned();
+    std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let reader = std::thread::spawn(move || {
+        let mut output = Vec::new();
+        stdout.read_to_end(&mut output).map(|_| output)
+    });
+
+    let deadline = Instant::now() + Duration::from_secs(timeout);
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if Instant::now() >= deadline {
+            child.kill()?;
+            child.wait()?;
+            break None;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    };
+
+    let output = reader.join().expect("reader doesn't panic")?;
+    let ending = match status {
+        None => Ending::TimedOut,
+        Some(status) if status.success() => Ending::Ended,
+        Some(status) => Ending::Failed(status.to_string()),
+    };
+
+    Ok(Outcome { output, ending })
+}
+
+/// Tells how two runs of the same program differ, or `None` if they don't.
+fn describe(ours: &Outcome, theirs: &Outcome) -> Option<String> {
+    let mut differences = Vec::new();
+
+    // Interpreters report failures in their own way
+    if std::mem::discriminant(&ours.ending) != std::mem::discriminant(&theirs.ending) {
+        differences.push(format!(
+            "puccinia {} but the reference {}",
+            ours.ending.describe(),
+            theirs.ending.describe()
+        ));
+    }
+
+    let first = ours
+        .output
+        .iter()
+        .zip(&theirs.output)
+        .position(|(ours, theirs)| ours != theirs)
+        .or_else(|| {
+            (ours.output.len() != theirs.output.len())
+                .then(|| ours.output.len().min(theirs.output.len()))
+        });
+    if let Some(offset) = first {
+        let excerpt = |output: &[u8]| {
+            let start = offset.saturating_sub(EXCERPT);
+            let end = (offset + EXCERPT).min(output.len());
+            String::from_utf8_lossy(&output[start.min(end)..end]).into_owned()
+        };
+        differences.push(format!(
+            "output differs at byte {offset}, puccinia wrote {:?} and the reference {:?}",
+            excerpt(&ours.output),
+            excerpt(&theirs.output)
+        ));
+    }
+
+    (!differences.is_empty()).then(|| differences.join(", "))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn describe() {
+        let ours = Outcome {
+            output: b"42 \n".to_vec(),
+            ending: Ending::Ended,
+        };
+        assert_eq!(super::describe(&ours, &ours.clone()), None);
+
+        let theirs = Outcome {
+            output: b"42\n".to_vec(),
+            ending: Ending::Failed("exit status: 1".to_owned()),
+        };
+        assert_eq!(
+            super::describe(&ours, &theirs).unwrap(),
+            "puccinia ended but the reference failed (exit status: 1), output differs at byte 2, \
+             puccinia wrote \"42 \\n\" and the reference \"42\\n\""
+        );
+    }
+}
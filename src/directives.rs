@@ -0,0 +1,775 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{
+    dialect::{self, Dialect, Extension, PAGE},
+    grid::{Grid, Line},
+    interpreter::{Interpreter, Schedule},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("Unknown directive `{0}`")]
+    Unknown(String),
+    #[error("Invalid value `{1}` for directive `{0}`")]
+    Invalid(String, String),
+    #[error(transparent)]
+    Dialect(#[from] dialect::Error),
+    #[error("Tab at column {0} of line {1}, pick how to load tabs with `--tabs`")]
+    Tab(usize, usize),
+    #[error("Invalid tab policy `{0}`, expected `cell`, `error` or a tab width")]
+    TabPolicy(String),
+    #[error("Invalid include `{0}`, expected `{INCLUDE}PATH at X,Y`")]
+    Include(String),
+    #[error("Could not include `{0}`: {1}")]
+    Included(String, Box<Error>),
+    #[error("`{0}` ends up including itself")]
+    Cycle(String),
+    #[error("`{0}` overlaps the program at ({1}, {2})")]
+    Overlap(String, usize, usize),
+}
+
+pub type Result<T> = anyhow::Result<T, Error>;
+
+/// First line of a program holding directives as `key=value` pairs.
+pub const HEADER: &str = "#!mst";
+
+/// Line after the program from which every line is a `key: value` directive.
+pub const MARKER: &str = "--- mst";
+
+/// Start of a line placing another program file in this one, e.g.
+/// `;; include lib/printnum.bf at 0,20`. Such lines aren't part of the grid, and the path is
+/// relative to the including file.
+pub const INCLUDE: &str = ";; include ";
+
+/// How a program is meant to be run, kept alongside its code.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Directives {
+    pub dialect: Option<Dialect>,
+    pub seed: Option<u64>,
+    /// Minimum size of the grid
+    pub size: Option<(usize, usize)>,
+    /// How big the grid is compared to the program's bounding box
+    pub sizing: Option<Sizing>,
+    /// Where lines of different lengths wrap
+    pub lines: Option<Lines>,
+    pub extensions: Vec<Extension>,
+    /// Which instruction pointer runs next in concurrent runs
+    pub schedule: Option<Schedule>,
+    /// Output of a correct run
+    pub output: Option<String>,
+}
+
+/// How big the grid of a loaded program is, which decides where the instruction pointer wraps.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Sizing {
+    /// Bounding box of the program
+    #[default]
+    Auto,
+    /// Exactly this size, the program being padded or truncated
+    Fixed(usize, usize),
+}
+
+impl Sizing {
+    /// Size of the classic Befunge-93 page.
+    pub const CLASSIC: Sizing = Sizing::Fixed(PAGE.0, PAGE.1);
+
+    /// Resizes a program's grid, returning the number of non-empty cells dropped.
+    pub fn fit(self, grid: &mut Grid) -> usize {
+        match self {
+            Sizing::Auto => 0,
+            Sizing::Fixed(width, height) => grid.fit(width, height),
+        }
+    }
+}
+
+impl FromStr for Sizing {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let invalid = || Error::Invalid("sizing".to_owned(), value.to_owned());
+
+        match value {
+            "auto" => Ok(Sizing::Auto),
+            "classic" => Ok(Sizing::CLASSIC),
+            size => {
+                let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+                match (width.parse(), height.parse()) {
+                    (Ok(width), Ok(height)) if width > 0 && height > 0 => {
+                        Ok(Sizing::Fixed(width, height))
+                    }
+                    _ => Err(invalid()),
+                }
+            }
+        }
+    }
+}
+
+impl Display for Sizing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Sizing::Auto => write!(f, "auto"),
+            Sizing::Fixed(width, height) => write!(f, "{width}x{height}"),
+        }
+    }
+}
+
+/// Where the instruction pointer wraps on lines shorter than the longest one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Lines {
+    /// Padded with empty cells to the longest line, as in Befunge-93
+    #[default]
+    Pad,
+    /// Wrapping at their own end, as in Funge-98's Lahey-space
+    Ragged,
+}
+
+impl Lines {
+    pub fn apply(self, grid: &mut Grid) {
+        grid.set_ragged(self == Lines::Ragged);
+    }
+}
+
+impl FromStr for Lines {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "pad" => Ok(Lines::Pad),
+            "ragged" => Ok(Lines::Ragged),
+            _ => Err(Error::Invalid("lines".to_owned(), value.to_owned())),
+        }
+    }
+}
+
+impl Display for Lines {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Lines::Pad => "pad",
+            Lines::Ragged => "ragged",
+        })
+    }
+}
+
+impl Directives {
+    /// Reads `key: value` lines, or space-separated `key=value` pairs when `header` is set.
+    pub fn parse(text: &str, header: bool) -> Result<Self> {
+        let mut directives = Directives::default();
+
+        let pairs = if header {
+            text.split_whitespace()
+                .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+                .collect::<Vec<_>>()
+        } else {
+            text.lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| line.split_once(':').unwrap_or((line, "")))
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .collect()
+        };
+
+        for (key, value) in pairs {
+            directives.set(key, value)?;
+        }
+
+        Ok(directives)
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let invalid = || Error::Invalid(key.to_owned(), value.to_owned());
+
+        match key {
+            "dialect" => self.dialect = Some(value.parse()?),
+            "seed" => self.seed = Some(value.parse().map_err(|_| invalid())?),
+            "size" => {
+                let (width, height) = value.split_once('x').ok_or_else(invalid)?;
+                self.size = Some(
+                    width
+                        .parse()
+                        .ok()
+                        .zip(height.parse().ok())
+                        .ok_or_else(invalid)?,
+                );
+            }
+            "sizing" => self.sizing = Some(value.parse()?),
+            "lines" => self.lines = Some(value.parse()?),
+            "extensions" => {
+                for name in value.split(',').filter(|name| !name.is_empty()) {
+                    self.extensions.push(name.trim().parse()?);
+                }
+            }
+            "schedule" => self.schedule = Some(value.parse().map_err(|_| invalid())?),
+            "output" => self.output = Some(unescape(value)),
+            _ => return Err(Error::Unknown(key.to_owned())),
+        }
+
+        Ok(())
+    }
+
+    /// Combines two sets of directives, those of `other` taking precedence.
+    pub fn merge(self, other: Directives) -> Self {
+        Directives {
+            dialect: other.dialect.or(self.dialect),
+            seed: other.seed.or(self.seed),
+            size: other.size.or(self.size),
+            sizing: other.sizing.or(self.sizing),
+            lines: other.lines.or(self.lines),
+            extensions: [self.extensions, other.extensions].concat(),
+            schedule: other.schedule.or(self.schedule),
+            output: other.output.or(self.output),
+        }
+    }
+
+    /// Writes the directives as `key: value` lines, as read by [Directives::parse].
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+
+        if let Some(dialect) = self.dialect {
+            text.push_str(&format!("dialect: {dialect}\n"));
+        }
+        if let Some(seed) = self.seed {
+            text.push_str(&format!("seed: {seed}\n"));
+        }
+        if let Some((width, height)) = self.size {
+            text.push_str(&format!("size: {width}x{height}\n"));
+        }
+        if let Some(sizing) = self.sizing {
+            text.push_str(&format!("sizing: {sizing}\n"));
+        }
+        if let Some(lines) = self.lines {
+            text.push_str(&format!("lines: {lines}\n"));
+        }
+        if !self.extensions.is_empty() {
+            let names = self
+                .extensions
+                .iter()
+                .map(Extension::name)
+                .collect::<Vec<_>>();
+            text.push_str(&format!("extensions: {}\n", names.join(",")));
+        }
+        if let Some(schedule) = &self.schedule {
+            text.push_str(&format!("schedule: {schedule}\n"));
+        }
+        if let Some(output) = &self.output {
+            text.push_str(&format!("output: {}\n", escape(output)));
+        }
+
+        text
+    }
+
+    /// Configures an interpreter as the program asks for.
+    pub fn apply(&self, interpreter: &mut Interpreter) {
+        if let Some(dialect) = self.dialect {
+            interpreter.set_dialect(dialect);
+        }
+        if let Some(seed) = self.seed {
+            interpreter.set_seed(seed);
+        }
+        if let Some((width, height)) = self.size.filter(|(width, height)| width * height > 0) {
+            interpreter.grid_mut().grow_to(width - 1, height - 1);
+        }
+        for extension in &self.extensions {
+            interpreter.enable(*extension);
+        }
+        if let Some(schedule) = &self.schedule {
+            interpreter.set_schedule(schedule.clone());
+        }
+    }
+}
+
+/// Reverse of [unescape].
+fn escape(value: &str) -> String {
+    value.chars().fold(String::new(), |mut res, c| {
+        match c {
+            '\n' => res.push_str("\\n"),
+            '\t' => res.push_str("\\t"),
+            ' ' => res.push_str("\\s"),
+            '\\' => res.push_str("\\\\"),
+            c => res.push(c),
+        }
+        res
+    })
+}
+
+/// Expands `\n`, `\t`, `\s` (space) and `\\` so that expected output fits on a single line and
+/// keeps its surrounding spaces.
+pub fn unescape(value: &str) -> String {
+    let mut res = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        match (c, c == '\\') {
+            (_, true) => match chars.next() {
+                Some('n') => res.push('\n'),
+                Some('t') => res.push('\t'),
+                Some('s') => res.push(' '),
+                Some(other) => res.push(other),
+                None => res.push('\\'),
+            },
+            (c, false) => res.push(c),
+        }
+    }
+
+    res
+}
+
+/// How tabs in program files are loaded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tabs {
+    /// Kept as a cell of their own, which executes as a no-op
+    #[default]
+    Cell,
+    /// Replaced with spaces up to the next multiple of this many columns
+    Expand(usize),
+    /// Refused, as they are most likely an editor's doing
+    Error,
+}
+
+impl FromStr for Tabs {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "cell" => Ok(Tabs::Cell),
+            "error" => Ok(Tabs::Error),
+            width => match width.parse() {
+                Ok(width) if width > 0 => Ok(Tabs::Expand(width)),
+                _ => Err(Error::TabPolicy(value.to_owned())),
+            },
+        }
+    }
+}
+
+impl Display for Tabs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Tabs::Cell => write!(f, "cell"),
+            Tabs::Expand(width) => write!(f, "{width}"),
+            Tabs::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// What was changed while loading a program file for it to load the same on every platform.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Normalized {
+    /// Whether a leading UTF-8 byte order mark was removed
+    pub bom: bool,
+    /// Windows `\r\n` line endings
+    pub crlf: usize,
+    /// Classic Mac OS `\r` line endings
+    pub cr: usize,
+    /// Tabs expanded to spaces
+    pub tabs: usize,
+}
+
+impl Display for Normalized {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut changes = Vec::new();
+        if self.bom {
+            changes.push("removed a byte order mark".to_owned());
+        }
+        if self.crlf > 0 {
+            changes.push(format!("converted {} CRLF line ending(s)", self.crlf));
+        }
+        if self.cr > 0 {
+            changes.push(format!("converted {} CR line ending(s)", self.cr));
+        }
+        if self.tabs > 0 {
+            changes.push(format!("expanded {} tab(s)", self.tabs));
+        }
+
+        if changes.is_empty() {
+            f.write_str("nothing to normalise")
+        } else {
+            f.write_str(&changes.join(", "))
+        }
+    }
+}
+
+/// Where the cells of a loaded program are in its file, the lines holding directives or
+/// includes not being part of the grid and expanded tabs spanning several cells.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Source {
+    /// Line of the file each row of the grid was loaded from
+    rows: Vec<usize>,
+    /// Tabs expanded on each line of the file, as the column they start at in the grid and the
+    /// number of cells they span, in order
+    tabs: HashMap<usize, Vec<(usize, usize)>>,
+}
+
+impl Source {
+    /// Line and column of the file a cell was loaded from, counting from 0 and in characters.
+    /// Rows past the loaded ones, added to fit a size, are taken to follow the last one.
+    pub fn position(&self, (x, y): (usize, usize)) -> (usize, usize) {
+        let line = match (self.rows.get(y), self.rows.last()) {
+            (Some(line), _) => *line,
+            (None, Some(last)) => last + 1 + y - self.rows.len(),
+            (None, None) => y,
+        };
+
+        let mut column = x;
+        for &(start, width) in self.tabs.get(&line).into_iter().flatten() {
+            if x >= start + width {
+                column -= width - 1;
+            } else {
+                column -= x.saturating_sub(start);
+                break;
+            }
+        }
+        (column, line)
+    }
+
+    /// Cell loaded from a line and column of the file, if that line is part of the grid.
+    pub fn cell(&self, (column, line): (usize, usize)) -> Option<(usize, usize)> {
+        let y = match self.rows.is_empty() {
+            true => line,
+            false => self.rows.iter().position(|row| *row == line)?,
+        };
+
+        let mut x = column;
+        let mut shift = 0;
+        for &(start, width) in self.tabs.get(&line).into_iter().flatten() {
+            if start - shift >= column {
+                break;
+            }
+            x += width - 1;
+            shift += width - 1;
+        }
+        Some((x, y))
+    }
+}
+
+/// Removes a byte order mark, turns `\r\n` and lone `\r` line endings into `\n` and deals with
+/// tabs as asked. Content in need of none of that is borrowed as is.
+pub fn normalize(content: &[u8], tabs: Tabs) -> Result<(Cow<'_, [u8]>, Normalized)> {
+    expand(content, tabs, &mut HashMap::new())
+}
+
+/// Same as [normalize], also telling where tabs were expanded as [Source::tabs] does.
+fn expand<'a>(
+    content: &'a [u8],
+    tabs: Tabs,
+    expanded: &mut HashMap<usize, Vec<(usize, usize)>>,
+) -> Result<(Cow<'a, [u8]>, Normalized)> {
+    let mut normalized = Normalized::default();
+
+    let content = match content.strip_prefix(b"\xEF\xBB\xBF") {
+        Some(rest) => {
+            normalized.bom = true;
+            rest
+        }
+        None => content,
+    };
+
+    let clean = !content
+        .iter()
+        .any(|byte| *byte == b'\r' || (*byte == b'\t' && tabs != Tabs::Cell));
+    if clean {
+        return Ok((Cow::Borrowed(content), normalized));
+    }
+
+    let mut res = Vec::with_capacity(content.len());
+    let (mut column, mut line) = (0, 0);
+    let mut bytes = content.iter().peekable();
+    while let Some(&byte) = bytes.next() {
+        match byte {
+            b'\r' => {
+                if bytes.next_if_eq(&&b'\n').is_some() {
+                    normalized.crlf += 1;
+                } else {
+                    normalized.cr += 1;
+                }
+                res.push(b'\n');
+            }
+            b'\t' => match tabs {
+                Tabs::Cell => res.push(byte),
+                Tabs::Expand(width) => {
+                    let spaces = width - column % width;
+                    res.extend(std::iter::repeat_n(b' ', spaces));
+                    expanded.entry(line).or_default().push((column, spaces));
+                    column += spaces;
+                    normalized.tabs += 1;
+                    continue;
+                }
+                Tabs::Error => return Err(Error::Tab(column + 1, line + 1)),
+            },
+            byte => res.push(byte),
+        }
+
+        // Columns are counted in characters, continuation bytes not starting one
+        match res.last() {
+            Some(b'\n') => (column, line) = (0, line + 1),
+            Some(byte) if byte & 0xC0 != 0x80 => column += 1,
+            _ => (),
+        }
+    }
+
+    Ok((Cow::Owned(res), normalized))
+}
+
+/// Loads a program file along with its directives, from a [HEADER] first line or the lines
+/// following a [MARKER] line.
+pub fn open(path: impl AsRef<Path>) -> Result<(Grid, Directives)> {
+    open_with(path, Tabs::default()).map(|(grid, directives, ..)| (grid, directives))
+}
+
+/// Same as [open], `tabs` deciding how tabs are loaded. Also tells what was normalised and where
+/// the cells are in the file.
+pub fn open_with(path: impl AsRef<Path>, tabs: Tabs) -> Result<Loaded> {
+    let path = path.as_ref();
+    let mut chain = vec![path.canonicalize().unwrap_or_else(|_| path.to_owned())];
+    let dir = path.parent().unwrap_or(Path::new(""));
+    Grid::map(path, |content| parse_in(content, tabs, dir, &mut chain))?
+}
+
+/// Same as [open], from the content of a program file.
+pub fn parse_program(content: &[u8]) -> Result<(Grid, Directives)> {
+    parse_program_with(content, Tabs::default()).map(|(grid, directives, ..)| (grid, directives))
+}
+
+/// Same as [open_with], from the content of a program file. Included files are looked for from
+/// the working directory.
+pub fn parse_program_with(content: &[u8], tabs: Tabs) -> Result<Loaded> {
+    parse_in(content, tabs, Path::new(""), &mut Vec::new())
+}
+
+/// Loads a program and the files it includes, from `dir`. `chain` holds the files being
+/// included, which may not include one another in a loop.
+fn parse_in(content: &[u8], tabs: Tabs, dir: &Path, chain: &mut Vec<PathBuf>) -> Result<Loaded> {
+    let mut source = Source::default();
+    let (content, normalized) = expand(content, tabs, &mut source.tabs)?;
+    let (mut grid, mut directives, includes) = load(&mut source.rows, |classify| {
+        Grid::parse_with(&content, classify)
+    })?;
+
+    for (name, at) in includes {
+        let (other, other_directives, ..) = include(dir, &name, tabs, chain)
+            .map_err(|err| Error::Included(name.clone(), Box::new(err)))?;
+
+        grid.paste(&other, at)
+            .map_err(|(x, y)| Error::Overlap(name, x, y))?;
+        // Routines may rely on extensions, the rest is up to the including program
+        for extension in other_directives.extensions {
+            if !directives.extensions.contains(&extension) {
+                directives.extensions.push(extension);
+            }
+        }
+    }
+
+    Ok((grid, directives, normalized, source))
+}
+
+fn include(dir: &Path, name: &str, tabs: Tabs, chain: &mut Vec<PathBuf>) -> Result<Loaded> {
+    let path = dir.join(name);
+    let canonical = path.canonicalize()?;
+    if chain.contains(&canonical) {
+        return Err(Error::Cycle(name.to_owned()));
+    }
+
+    chain.push(canonical);
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let res = Grid::map(&path, |content| parse_in(content, tabs, dir, chain))?;
+    chain.pop();
+    res
+}
+
+/// Writes a program file as read by [parse_program], trailing blank lines left out and
+/// directives following a [MARKER] line.
+pub fn to_program(grid: &Grid, directives: &Directives) -> String {
+    let mut lines = grid.lines();
+    while lines.last().is_some_and(String::is_empty) {
+        lines.pop();
+    }
+
+    let mut program = lines.into_iter().fold(String::new(), |mut program, line| {
+        program.push_str(&line);
+        program.push('\n');
+        program
+    });
+
+    let trailer = directives.to_text();
+    if !trailer.is_empty() {
+        program.push_str(MARKER);
+        program.push('\n');
+        program.push_str(&trailer);
+    }
+
+    program
+}
+
+/// Program loaded from a file, along with what was normalised and where its cells are in it.
+pub type Loaded = (Grid, Directives, Normalized, Source);
+
+/// File to include and where its top-left corner goes.
+type Include = (String, (usize, usize));
+
+/// Reads an [INCLUDE] line, past its prefix.
+fn parse_include(text: &str) -> Result<Include> {
+    let invalid = || Error::Include(text.to_owned());
+
+    let (path, at) = text.trim().rsplit_once(" at ").ok_or_else(invalid)?;
+    let (x, y) = at.split_once(',').ok_or_else(invalid)?;
+    let at = x.trim().parse().ok().zip(y.trim().parse().ok());
+
+    match (path.trim(), at) {
+        ("", _) | (_, None) => Err(invalid()),
+        (path, Some(at)) => Ok((path.to_owned(), at)),
+    }
+}
+
+/// Reads the grid and directives of a program, `rows` getting the line of the file each row of
+/// the grid comes from.
+fn load(
+    rows: &mut Vec<usize>,
+    read: impl FnOnce(&mut dyn FnMut(&str) -> Line) -> (Grid, String),
+) -> Result<(Grid, Directives, Vec<Include>)> {
+    let mut header = None;
+    let mut number = 0;
+    let mut includes = Vec::new();
+
+    let (grid, rest) = read(&mut |line| {
+        number += 1;
+        if number == 1 {
+            if let Some(pairs) = line.strip_prefix(HEADER) {
+                header = Some(pairs.to_owned());
+                return Line::Skip;
+            }
+        }
+
+        if let Some(include) = line.strip_prefix(INCLUDE) {
+            includes.push(include.to_owned());
+            Line::Skip
+        } else if line.trim_end() == MARKER {
+            Line::Stop
+        } else {
+            rows.push(number - 1);
+            Line::Load
+        }
+    });
+
+    let mut directives = match header {
+        Some(pairs) => Directives::parse(&pairs, true)?,
+        None => Directives::default(),
+    };
+
+    if let Some((_, trailer)) = rest.split_once('\n') {
+        directives = directives.merge(Directives::parse(trailer, false)?);
+    }
+
+    let includes = includes
+        .iter()
+        .map(|include| parse_include(include))
+        .collect::<Result<_>>()?;
+
+    Ok((grid, directives, includes))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn open() {
+        let program = "#!mst dialect=96 seed=7\n1000.@\n--- mst\nextensions: multi-digit\n\
+                       size: 10x3\nsizing: classic\nlines: ragged\nschedule: weighted:2,1\noutput: 1000\\s\n";
+        let path =
+            std::env::temp_dir().join(format!("puccinia-directives-{}.bf", std::process::id()));
+        std::fs::write(&path, program).unwrap();
+
+        let (grid, directives) = super::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(grid.size(), (6, 1));
+        assert_eq!(
+            directives,
+            Directives {
+                dialect: Some(Dialect::Befunge96),
+                seed: Some(7),
+                size: Some((10, 3)),
+                sizing: Some(Sizing::Fixed(80, 25)),
+                lines: Some(Lines::Ragged),
+                extensions: vec![Extension::MultiDigit],
+                schedule: Some(Schedule::Weighted(vec![2, 1])),
+                output: Some("1000 ".to_owned()),
+            }
+        );
+
+        assert_eq!(
+            Directives::parse(&directives.to_text(), false).unwrap(),
+            directives
+        );
+        let (saved, reread) = parse_program(to_program(&grid, &directives).as_bytes()).unwrap();
+        assert_eq!((saved.lines(), reread), (grid.lines(), directives));
+        assert!(matches!(
+            Directives::parse("speed=3", true),
+            Err(Error::Unknown(key)) if key == "speed"
+        ));
+    }
+
+    #[test]
+    fn include() {
+        let dir = std::env::temp_dir().join(format!("puccinia-include-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("lib")).unwrap();
+        let write = |name: &str, content: &str| std::fs::write(dir.join(name), content).unwrap();
+
+        write("lib/print.bf", ".@\n--- mst\nextensions: quit\n");
+        write("main.bf", ";; include lib/print.bf at 2,1\n1v\n");
+        let (grid, directives) = super::open(dir.join("main.bf")).unwrap();
+        assert_eq!(grid.lines(), vec!["1v", "  .@"]);
+        assert_eq!(directives.extensions, vec![Extension::Quit]);
+
+        write("clash.bf", ";; include lib/print.bf at 1,0\n1v\n");
+        assert!(matches!(
+            super::open(dir.join("clash.bf")),
+            Err(Error::Overlap(name, 1, 0)) if name == "lib/print.bf"
+        ));
+
+        write("lib/loop.bf", ";; include ../loop.bf at 0,1\n@\n");
+        write("loop.bf", ";; include lib/loop.bf at 0,1\n@\n");
+        let err = super::open(dir.join("loop.bf")).unwrap_err();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(err
+            .to_string()
+            .ends_with("`../loop.bf` ends up including itself"));
+        assert!(matches!(
+            parse_program(b";; include lib/print.bf\n"),
+            Err(Error::Include(_))
+        ));
+    }
+
+    #[test]
+    fn normalize() {
+        let content = "\u{FEFF}>\t1.\r\n\t\u{e9}\t@\r".as_bytes();
+
+        let (normalized, report) = super::normalize(content, Tabs::Expand(4)).unwrap();
+        assert_eq!(normalized.as_ref(), ">   1.\n    \u{e9}   @\n".as_bytes());
+        assert_eq!(
+            report,
+            Normalized {
+                bom: true,
+                crlf: 1,
+                cr: 1,
+                tabs: 3,
+            }
+        );
+
+        let (normalized, _) = super::normalize(content, Tabs::Cell).unwrap();
+        assert_eq!(normalized.as_ref(), ">\t1.\n\t\u{e9}\t@\n".as_bytes());
+        assert!(matches!(
+            super::normalize(content, Tabs::Error),
+            Err(Error::Tab(2, 1))
+        ));
+        assert!(matches!(
+            super::normalize(b">1.@\n", Tabs::Error),
+            Ok((Cow::Borrowed(_), report)) if report == Normalized::default()
+        ));
+    }
+}
@@ -0,0 +1,346 @@
+use std::str::FromStr;
+
+use puccinia::{interpreter::Schedule, routines::Routine};
+
+use crate::logic::Pace;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Unknown command `{0}`")]
+    Unknown(String),
+    #[error("Usage: {0}")]
+    Usage(&'static str),
+    #[error("Cannot export `{0}`, expected `svg`, `trace`, `input`, or a `.svg` or `.json` file")]
+    Export(String),
+}
+
+type Result<T> = anyhow::Result<T, Error>;
+
+const PACE_USAGE: &str = "ticks [N|auto]";
+const SCHEDULE_USAGE: &str = "schedule round-robin|random|weighted:W0,W1,...";
+const EXPORT_USAGE: &str = "export svg|trace|input [PATH], or export PATH.svg|PATH.json";
+const GENERATE_USAGE: &str = "gen print TEXT|number N [top]|routine NAME [X,Y]";
+
+/// Command typed after `:` in the TUI.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Ex {
+    /// Save the program, to another file if given which is then the one being edited
+    Write(Option<String>),
+    /// Write an artefact to a file, named after the program if not given
+    Export(Export, Option<String>),
+    /// Edit another program, opening it in a new buffer unless it already is
+    Edit(String),
+    /// Edit the program of a buffer, numbered from 1
+    Buffer(usize),
+    Next,
+    Previous,
+    /// List open programs
+    Buffers,
+    /// Only stop at breakpoints reached by an instruction pointer and only show its trace, or
+    /// those of all of them for `None`
+    Ip(Option<u32>),
+    /// Toggle stopping runs when an instruction pointer starts or ends
+    Catch(IpEvent),
+    /// Start the current run over from the program as loaded, feeding it the same input and
+    /// keeping breakpoints and watches
+    Restart,
+    /// List the points right before the bursts of writes of the current run, or travel to one of
+    /// them, numbered from 1
+    Checkpoint(Option<usize>),
+    /// Switch to another layout of the panels shown during runs, kept for the program, or list
+    /// them for `None`
+    Layout(Option<String>),
+    /// Toggle writing input to the output as runs read it
+    Echo,
+    /// Expect runs to write that many bytes of output when forecasting how long they have left,
+    /// or as many as the `output` directive holds for `None`
+    Forecast(Option<usize>),
+    /// Change how many instructions run per frame, or only report it for `None`
+    Pace(Option<Pace>),
+    /// Change how instruction pointers take turns in the next runs, saved with the program
+    Schedule(Schedule),
+    /// Put generated code in the default register, to paste it with `p`
+    Generate(Snippet),
+    /// Toggle a breakpoint on a cell, or on the one a label of funge assembly stands for
+    Break(Breakpoint),
+    /// Feed input to the current run, or to the next one if none is in progress
+    Input(Feed),
+    /// Start a run if none is in progress, and run until a breakpoint
+    Run,
+    /// Show the value of an expression while running, kept for the program
+    Watch(String),
+    /// Stop showing the value of an expression
+    Unwatch(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Breakpoint {
+    Cell(usize, usize),
+    Label(String),
+}
+
+/// Input given to `:input`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Feed {
+    /// Text as typed, where `\n` stands for a newline
+    Text(String),
+    /// Content of a file
+    File(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum IpEvent {
+    Spawn,
+    Exit,
+}
+
+/// Code generated by `:gen`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Snippet {
+    /// Shortest line printing a text, where `\n` stands for a newline
+    Print(String),
+    /// Shortest push of a number, possibly reusing the value on top of the stack of the current
+    /// run
+    Number(i32, bool),
+    /// Routine of the library, placed with its top-left corner at a position if given
+    Routine(Routine, Option<(usize, usize)>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Export {
+    /// Image of the grid, shaded with the execution counts of the current run if any
+    Svg,
+    /// Recently executed instructions of the current run, as JSON
+    Trace,
+    /// Input read by the current run so far
+    Input,
+}
+
+impl Export {
+    /// Extension of the file exported next to the program.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Export::Svg => "svg",
+            Export::Trace => "trace.json",
+            Export::Input => "input.txt",
+        }
+    }
+}
+
+impl FromStr for Ex {
+    type Err = Error;
+
+    fn from_str(line: &str) -> Result<Self> {
+        // Texts are taken as typed, spaces included
+        if let Some(text) = line.trim_start().strip_prefix("gen print ") {
+            let text = text
+                .strip_prefix('"')
+                .and_then(|text| text.strip_suffix('"'))
+                .unwrap_or(text);
+            return Ok(Ex::Generate(Snippet::Print(text.replace("\\n", "\n"))));
+        }
+        if let Some(text) = line.trim_start().strip_prefix("input ") {
+            return Ok(Ex::Input(Feed::Text(text.replace("\\n", "\n"))));
+        }
+        if let Some(expression) = line.trim_start().strip_prefix("watch ") {
+            return Ok(Ex::Watch(expression.trim().to_owned()));
+        }
+        if let Some(expression) = line.trim_start().strip_prefix("unwatch ") {
+            return Ok(Ex::Unwatch(expression.trim().to_owned()));
+        }
+
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or_default();
+        let arguments = words.collect::<Vec<_>>();
+
+        match (command, arguments.as_slice()) {
+            ("w" | "write", []) => Ok(Ex::Write(None)),
+            ("w" | "write", [path]) => Ok(Ex::Write(Some(path.to_string()))),
+            ("w" | "write", _) => Err(Error::Usage("write [PATH]")),
+            ("export", [word]) => {
+                if let Some(format) = format(word) {
+                    return Ok(Ex::Export(format, None));
+                }
+                let format = match word.rsplit_once('.') {
+                    Some((_, "svg")) => Export::Svg,
+                    Some((_, "json")) => Export::Trace,
+                    _ => return Err(Error::Export(word.to_string())),
+                };
+                Ok(Ex::Export(format, Some(word.to_string())))
+            }
+            ("export", [word, path]) => format(word)
+                .map(|format| Ex::Export(format, Some(path.to_string())))
+                .ok_or_else(|| Error::Export(word.to_string())),
+            ("export", _) => Err(Error::Usage(EXPORT_USAGE)),
+            ("e" | "edit", [path]) => Ok(Ex::Edit(path.to_string())),
+            ("e" | "edit", _) => Err(Error::Usage("edit PATH")),
+            ("b" | "buffer", [number]) => number
+                .parse()
+                .map(Ex::Buffer)
+                .map_err(|_| Error::Usage("buffer N")),
+            ("b" | "buffer", _) => Err(Error::Usage("buffer N")),
+            ("bn" | "bnext", []) => Ok(Ex::Next),
+            ("bp" | "bprevious", []) => Ok(Ex::Previous),
+            ("ls" | "buffers", []) => Ok(Ex::Buffers),
+            ("ip", ["all"]) => Ok(Ex::Ip(None)),
+            ("ip", [id]) => id
+                .parse()
+                .map(|id| Ex::Ip(Some(id)))
+                .map_err(|_| Error::Usage("ip N|all")),
+            ("ip", _) => Err(Error::Usage("ip N|all")),
+            ("catch", ["spawn"]) => Ok(Ex::Catch(IpEvent::Spawn)),
+            ("catch", ["exit"]) => Ok(Ex::Catch(IpEvent::Exit)),
+            ("catch", _) => Err(Error::Usage("catch spawn|exit")),
+            ("restart", []) => Ok(Ex::Restart),
+            ("checkpoint", []) => Ok(Ex::Checkpoint(None)),
+            ("checkpoint", [number]) => number
+                .parse()
+                .map(|number| Ex::Checkpoint(Some(number)))
+                .map_err(|_| Error::Usage("checkpoint [N]")),
+            ("checkpoint", _) => Err(Error::Usage("checkpoint [N]")),
+            ("layout", []) => Ok(Ex::Layout(None)),
+            ("layout", [name]) => Ok(Ex::Layout(Some(name.to_string()))),
+            ("layout", _) => Err(Error::Usage("layout [NAME]")),
+            ("echo", []) => Ok(Ex::Echo),
+            ("forecast", []) => Ok(Ex::Forecast(None)),
+            ("forecast", [bytes]) => bytes
+                .parse()
+                .map(|bytes| Ex::Forecast(Some(bytes)))
+                .map_err(|_| Error::Usage("forecast [BYTES]")),
+            ("forecast", _) => Err(Error::Usage("forecast [BYTES]")),
+            ("ticks", []) => Ok(Ex::Pace(None)),
+            ("ticks", [pace]) => pace
+                .parse()
+                .map(|pace| Ex::Pace(Some(pace)))
+                .map_err(|_| Error::Usage(PACE_USAGE)),
+            ("ticks", _) => Err(Error::Usage(PACE_USAGE)),
+            ("schedule", [policy]) => policy
+                .parse()
+                .map(Ex::Schedule)
+                .map_err(|_| Error::Usage(SCHEDULE_USAGE)),
+            ("schedule", _) => Err(Error::Usage(SCHEDULE_USAGE)),
+            ("gen", ["number", value, rest @ ..]) if matches!(rest, [] | ["top"]) => value
+                .parse()
+                .map(|value| Ex::Generate(Snippet::Number(value, !rest.is_empty())))
+                .map_err(|_| Error::Usage(GENERATE_USAGE)),
+            ("gen", ["routine", name, rest @ ..]) if rest.len() < 2 => {
+                let at = rest.iter().map(|at| {
+                    let (x, y) = at.split_once(',')?;
+                    x.parse().ok().zip(y.parse().ok())
+                });
+                let at = at.collect::<Option<Vec<_>>>();
+                name.parse()
+                    .ok()
+                    .zip(at)
+                    .map(|(routine, at)| {
+                        Ex::Generate(Snippet::Routine(routine, at.first().copied()))
+                    })
+                    .ok_or(Error::Usage(GENERATE_USAGE))
+            }
+            ("gen", _) => Err(Error::Usage(GENERATE_USAGE)),
+            ("break", [target]) => {
+                let cell = target.split_once(',').and_then(|(x, y)| {
+                    let (x, y) = (x.parse().ok()?, y.parse().ok()?);
+                    Some(Breakpoint::Cell(x, y))
+                });
+                Ok(Ex::Break(
+                    cell.unwrap_or_else(|| Breakpoint::Label(target.to_string())),
+                ))
+            }
+            ("break", _) => Err(Error::Usage("break X,Y|LABEL")),
+            ("input-file", [path]) => Ok(Ex::Input(Feed::File(path.to_string()))),
+            ("input" | "input-file", _) => Err(Error::Usage("input TEXT, or input-file PATH")),
+            ("run", []) => Ok(Ex::Run),
+            ("watch", _) => Err(Error::Usage("watch EXPRESSION")),
+            ("unwatch", _) => Err(Error::Usage("unwatch EXPRESSION")),
+            (command, _) => Err(Error::Unknown(command.to_owned())),
+        }
+    }
+}
+
+fn format(word: &str) -> Option<Export> {
+    match word {
+        "svg" => Some(Export::Svg),
+        "trace" => Some(Export::Trace),
+        "input" => Some(Export::Input),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let parse = |line: &str| line.parse::<Ex>();
+        assert_eq!(parse("w"), Ok(Ex::Write(None)));
+        assert_eq!(
+            parse(" w  copy.bf "),
+            Ok(Ex::Write(Some("copy.bf".to_owned())))
+        );
+        assert_eq!(parse("export svg"), Ok(Ex::Export(Export::Svg, None)));
+        assert_eq!(
+            parse("export trace.json"),
+            Ok(Ex::Export(Export::Trace, Some("trace.json".to_owned())))
+        );
+        assert_eq!(
+            parse("export trace run"),
+            Ok(Ex::Export(Export::Trace, Some("run".to_owned())))
+        );
+        assert_eq!(
+            parse("export grid.png"),
+            Err(Error::Export("grid.png".to_owned()))
+        );
+        assert_eq!(parse("b 2"), Ok(Ex::Buffer(2)));
+        assert_eq!(parse("b two"), Err(Error::Usage("buffer N")));
+        assert_eq!(parse("bn"), Ok(Ex::Next));
+        assert_eq!(parse("ip 1"), Ok(Ex::Ip(Some(1))));
+        assert_eq!(parse("catch spawn"), Ok(Ex::Catch(IpEvent::Spawn)));
+        assert_eq!(parse("restart"), Ok(Ex::Restart));
+        assert_eq!(parse("checkpoint 2"), Ok(Ex::Checkpoint(Some(2))));
+        assert_eq!(
+            parse("layout debug"),
+            Ok(Ex::Layout(Some("debug".to_owned())))
+        );
+        assert_eq!(parse("ticks auto"), Ok(Ex::Pace(Some(Pace::Adaptive))));
+        assert_eq!(parse("ticks 0"), Err(Error::Usage(PACE_USAGE)));
+        assert_eq!(parse("forecast 4096"), Ok(Ex::Forecast(Some(4096))));
+        assert_eq!(
+            parse("gen print \"Hi, you\\n\""),
+            Ok(Ex::Generate(Snippet::Print("Hi, you\n".to_owned())))
+        );
+        assert_eq!(parse("gen print"), Err(Error::Usage(GENERATE_USAGE)));
+        assert_eq!(
+            parse("gen number -79 top"),
+            Ok(Ex::Generate(Snippet::Number(-79, true)))
+        );
+        assert_eq!(
+            parse("gen routine divide 3,20"),
+            Ok(Ex::Generate(Snippet::Routine(
+                Routine::Divide,
+                Some((3, 20))
+            )))
+        );
+        assert_eq!(
+            parse("gen routine divide 3"),
+            Err(Error::Usage(GENERATE_USAGE))
+        );
+        assert_eq!(
+            parse("break loop"),
+            Ok(Ex::Break(Breakpoint::Label("loop".to_owned())))
+        );
+        assert_eq!(parse("break 4,0"), Ok(Ex::Break(Breakpoint::Cell(4, 0))));
+        assert_eq!(
+            parse("input 12 34\\n"),
+            Ok(Ex::Input(Feed::Text("12 34\n".to_owned())))
+        );
+        assert_eq!(parse("run"), Ok(Ex::Run));
+        assert_eq!(
+            parse("unwatch stack[0] + 1"),
+            Ok(Ex::Unwatch("stack[0] + 1".to_owned()))
+        );
+        assert_eq!(parse("watch"), Err(Error::Usage("watch EXPRESSION")));
+        assert_eq!(parse("quit"), Err(Error::Unknown("quit".to_owned())));
+    }
+}
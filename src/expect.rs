@@ -0,0 +1,135 @@
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Could not read expected output `{0}`: {1}")]
+    Read(String, std::io::Error),
+    #[error("Output diverges from `{path}` at tick {tick}:\n{diff}")]
+    Diverged {
+        path: String,
+        tick: u64,
+        diff: String,
+    },
+    #[error("Program ended after {tick} ticks, {missing} byte(s) short of `{path}`:\n{diff}")]
+    Short {
+        path: String,
+        tick: u64,
+        missing: usize,
+        diff: String,
+    },
+}
+
+type Result<T> = anyhow::Result<T, Error>;
+
+/// Lines of matching output shown before the first difference.
+const CONTEXT: usize = 2;
+
+/// Output a run is expected to write, checked as it is written.
+pub(crate) struct Expectation {
+    path: String,
+    expected: String,
+    /// Bytes of the expected output written so far
+    matched: usize,
+}
+
+impl Expectation {
+    pub fn open(path: String) -> Result<Self> {
+        let expected =
+            std::fs::read_to_string(&path).map_err(|err| Error::Read(path.clone(), err))?;
+        Ok(Self::new(path, expected))
+    }
+
+    fn new(path: String, expected: String) -> Self {
+        Self {
+            path,
+            expected,
+            matched: 0,
+        }
+    }
+
+    /// Checks output written by the instruction executed at `tick`.
+    pub fn check(&mut self, output: &str, tick: u64) -> Result<()> {
+        let rest = &self.expected[self.matched..];
+        let common = rest
+            .char_indices()
+            .zip(output.chars())
+            .find(|((_, expected), actual)| expected != actual)
+            .map_or(rest.len().min(output.len()), |((index, _), _)| index);
+
+        if common == output.len() {
+            self.matched += common;
+            return Ok(());
+        }
+
+        Err(Error::Diverged {
+            path: self.path.clone(),
+            tick,
+            diff: self.diff(self.matched + common, &output[common..]),
+        })
+    }
+
+    /// Checks that nothing more was expected once the program ended after `tick` ticks.
+    pub fn finish(&self, tick: u64) -> Result<()> {
+        match self.expected.len() - self.matched {
+            0 => Ok(()),
+            missing => Err(Error::Short {
+                path: self.path.clone(),
+                tick,
+                missing,
+                diff: self.diff(self.matched, ""),
+            }),
+        }
+    }
+
+    /// Lines around the first difference, the output matching the expected one up to `offset`
+    /// and going on with `actual`.
+    fn diff(&self, offset: usize, actual: &str) -> String {
+        let (before, after) = self.expected.split_at(offset);
+        let start = before.rfind('\n').map_or(0, |index| index + 1);
+        let number = before.matches('\n').count() + 1;
+
+        let mut diff = before[..start]
+            .lines()
+            .enumerate()
+            .skip((number - 1).saturating_sub(CONTEXT))
+            .map(|(index, line)| format!("  {:>4} | {line}\n", index + 1))
+            .collect::<String>();
+
+        let line = |rest: &str| {
+            format!(
+                "{}{}",
+                &before[start..],
+                rest.lines().next().unwrap_or_default()
+            )
+        };
+        diff += &format!("- {number:>4} | {}\n", line(after));
+        diff += &format!("+ {number:>4} | {}\n", line(actual));
+        diff += &format!("         {}^", " ".repeat(before[start..].chars().count()));
+
+        diff
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check() {
+        let mut expectation = Expectation::new("out.txt".to_owned(), "1\n2\n3\n42\n".to_owned());
+        expectation.check("1\n2", 3).unwrap();
+        expectation.check("\n3\n4", 10).unwrap();
+
+        let Err(Error::Diverged { tick, diff, .. }) = expectation.check("3 ", 12) else {
+            panic!("output should diverge");
+        };
+        assert_eq!(tick, 12);
+        assert_eq!(
+            diff,
+            "     2 | 2\n     3 | 3\n-    4 | 42\n+    4 | 43 \n          ^"
+        );
+
+        let Err(Error::Short { missing, .. }) = expectation.finish(20) else {
+            panic!("output should be short");
+        };
+        assert_eq!(missing, 2);
+    }
+}
@@ -0,0 +1,78 @@
+use std::io::{BufRead, IsTerminal, Write};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[cfg(not(feature = "net"))]
+    #[error("Loading programs from URLs requires building with the `net` feature")]
+    Disabled,
+    #[cfg(feature = "net")]
+    #[error("Could not download `{0}`: {1}")]
+    Request(String, String),
+    #[cfg(feature = "net")]
+    #[error("`{0}` is larger than the {MAX_SIZE} bytes limit")]
+    TooLarge(String),
+    #[error("Declined to run `{0}`")]
+    Declined(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+type Result<T> = anyhow::Result<T, Error>;
+
+/// Largest program that is downloaded, Befunge programs rarely exceeding a few kilobytes.
+#[cfg(feature = "net")]
+const MAX_SIZE: u64 = 1 << 20;
+
+pub(crate) fn is_url(input: &str) -> bool {
+    input.starts_with("http://") || input.starts_with("https://")
+}
+
+/// Downloads a program, asking for confirmation before it is run when attached to a terminal.
+pub(crate) fn fetch(url: &str) -> Result<Vec<u8>> {
+    let content = download(url)?;
+    if std::io::stdin().is_terminal() && !confirm(url, content.len())? {
+        return Err(Error::Declined(url.to_owned()));
+    }
+    Ok(content)
+}
+
+#[cfg(feature = "net")]
+fn download(url: &str) -> Result<Vec<u8>> {
+    use std::io::Read;
+
+    let response = ureq::get(url)
+        .call()
+        .map_err(|err| Error::Request(url.to_owned(), err.to_string()))?;
+
+    let mut content = Vec::new();
+    response
+        .into_body()
+        .into_reader()
+        .take(MAX_SIZE + 1)
+        .read_to_end(&mut content)?;
+
+    if content.len() as u64 > MAX_SIZE {
+        return Err(Error::TooLarge(url.to_owned()));
+    }
+    Ok(content)
+}
+
+#[cfg(not(feature = "net"))]
+fn download(_url: &str) -> Result<Vec<u8>> {
+    Err(Error::Disabled)
+}
+
+fn confirm(url: &str, size: usize) -> Result<bool> {
+    // Batches fetch concurrently, prompts are asked one at a time
+    static PROMPT: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    let _guard = PROMPT
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    eprint!("Run `{url}` ({size} bytes)? [y/N] ");
+    std::io::stderr().flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Number of samples kept before every other one gets dropped.
+const CAPACITY: usize = 64;
+
+/// Progress of a run at a given tick.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Sample {
+    pub tick: u64,
+    /// Bytes of output written so far
+    pub bytes: usize,
+    /// Time spent running so far, pauses left out
+    pub elapsed: Duration,
+}
+
+/// Progress of a run sampled as it goes, from which its pace once past its start-up is
+/// extrapolated to tell how long it has left.
+/// Once full, every other sample is dropped, so that the whole run remains covered.
+#[derive(Clone, Debug, Default)]
+pub struct Forecast {
+    samples: Vec<Sample>,
+}
+
+/// Pace of a run and what is left of it.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Outlook {
+    pub ticks_per_byte: f64,
+    pub ticks_per_second: Option<f64>,
+    /// Ticks and time left until the run wrote as much output as expected, if known
+    pub remaining: Option<(u64, Option<Duration>)>,
+}
+
+impl Forecast {
+    /// Records the progress of the run, forgetting anything recorded after `sample.tick`.
+    pub fn record(&mut self, sample: Sample) {
+        if let Some(index) = self.samples.iter().position(|s| s.tick >= sample.tick) {
+            self.samples.truncate(index);
+        }
+
+        if self.samples.len() == CAPACITY {
+            let mut index = 0;
+            self.samples.retain(|_| {
+                index += 1;
+                index % 2 == 1
+            });
+        }
+        self.samples.push(sample);
+    }
+
+    pub fn samples(&self) -> &[Sample] {
+        &self.samples
+    }
+
+    /// Pace of the later half of the run, which start-up code such as tables being filled in
+    /// doesn't skew, extrapolated to `expected` bytes of output if given.
+    pub fn outlook(&self, expected: Option<usize>) -> Option<Outlook> {
+        let last = self.samples.last()?;
+        let middle = self.samples[self.samples.len() / 2];
+        let bytes = last
+            .bytes
+            .checked_sub(middle.bytes)
+            .filter(|bytes| *bytes > 0)?;
+        let ticks = last.tick - middle.tick;
+        let ticks_per_byte = ticks as f64 / bytes as f64;
+
+        let seconds = (last.elapsed - middle.elapsed).as_secs_f64();
+        let ticks_per_second = (seconds > 0.).then(|| ticks as f64 / seconds);
+
+        let remaining = expected.map(|expected| {
+            let ticks = (expected.saturating_sub(last.bytes) as f64 * ticks_per_byte) as u64;
+            let time = ticks_per_second.map(|pace| Duration::from_secs_f64(ticks as f64 / pace));
+            (ticks, time)
+        });
+
+        Some(Outlook {
+            ticks_per_byte,
+            ticks_per_second,
+            remaining,
+        })
+    }
+}
+
+/// Duration to the second, in its two largest units, e.g. `1h02m`.
+fn approximate(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0..60 => format!("{seconds}s"),
+        60..3600 => format!("{}m{:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h{:02}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+impl std::fmt::Display for Outlook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.0} ticks/byte", self.ticks_per_byte)?;
+        match self.remaining {
+            Some((_, Some(time))) => write!(f, ", {} left", approximate(time)),
+            Some((ticks, None)) => write!(f, ", {ticks} ticks left"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn outlook() {
+        let mut forecast = Forecast::default();
+        assert_eq!(forecast.outlook(None), None);
+
+        // A slow start-up writing nothing, then a byte every 10 ticks and 100 ticks a second
+        forecast.record(Sample::default());
+        for second in 1..=100u64 {
+            forecast.record(Sample {
+                tick: 1000 + second * 100,
+                bytes: second as usize * 10,
+                elapsed: Duration::from_secs(10 + second),
+            });
+        }
+        assert!(forecast.samples().len() <= CAPACITY);
+
+        let outlook = forecast.outlook(Some(1600)).unwrap();
+        assert_eq!(outlook.ticks_per_byte, 10.);
+        assert_eq!(outlook.ticks_per_second, Some(100.));
+        assert_eq!(
+            outlook.remaining,
+            Some((6000, Some(Duration::from_secs(60))))
+        );
+        assert_eq!(outlook.to_string(), "10 ticks/byte, 1m00s left");
+
+        // Rewinding forgets what came after
+        forecast.record(Sample {
+            tick: 1100,
+            bytes: 10,
+            elapsed: Duration::from_secs(11),
+        });
+        assert_eq!(forecast.samples().len(), 2);
+    }
+}
@@ -0,0 +1,290 @@
+use std::{
+    fmt::Write as _,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{
+    cell::{CellValue, Origin},
+    interpreter::Interpreter,
+    statistics::Kind,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Unknown frame format `{0}`, expected `ansi` or `png`")]
+    Format(String),
+    #[error("Could not create `{0}`: {1}")]
+    Directory(String, std::io::Error),
+    #[error("Could not write frame `{0}`: {1}")]
+    Write(String, std::io::Error),
+    #[error("Could not encode frame `{0}`: {1}")]
+    Encode(String, png::EncodingError),
+}
+
+pub type Result<T> = anyhow::Result<T, Error>;
+
+/// Lines of output shown under the grid of text frames.
+const OUTPUT_LINES: usize = 5;
+
+/// Width and height of a cell of image frames, in pixels, the last row and column being left
+/// blank between cells.
+const CELL: usize = 8;
+
+const BACKGROUND: [u8; 3] = [0x1e, 0x1e, 0x1e];
+const IP: [u8; 3] = [0xff, 0xd7, 0x00];
+const THREAD: [u8; 3] = [0xff, 0xf0, 0x9a];
+
+/// How frames are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// Text with ANSI escape sequences, the grid then the tick, the stack and the end of the
+    /// output, clearing the terminal first so that printing them in turn plays the run
+    #[default]
+    Ansi,
+    /// Images drawing each cell as a block coloured after what it holds
+    Png,
+}
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Ansi => "ans",
+            Format::Png => "png",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name {
+            "ansi" => Ok(Format::Ansi),
+            "png" => Ok(Format::Png),
+            _ => Err(Error::Format(name.to_owned())),
+        }
+    }
+}
+
+/// Writes frames of a run to a directory as `frame-000000.ans` or `.png` and so on, numbered
+/// in order for video tools to pick them up with a pattern such as `frame-%06d.png`.
+#[derive(Debug)]
+pub struct Dumper {
+    directory: PathBuf,
+    format: Format,
+    /// Ticks between two frames
+    every: u64,
+    /// Tick from which the next frame is written
+    next: u64,
+    written: usize,
+    /// Tick of the latest frame
+    last: Option<u64>,
+    /// Output of the run so far, only its last lines being kept
+    output: String,
+}
+
+impl Dumper {
+    /// Creates the directory if needed. Frames are written every `every` ticks, or as soon as
+    /// possible after that as runs may skip over several ticks at once.
+    pub fn new(directory: &Path, format: Format, every: u64) -> Result<Self> {
+        std::fs::create_dir_all(directory)
+            .map_err(|err| Error::Directory(directory.display().to_string(), err))?;
+        Ok(Self {
+            directory: directory.to_owned(),
+            format,
+            every: every.max(1),
+            next: 0,
+            written: 0,
+            last: None,
+            output: String::new(),
+        })
+    }
+
+    /// Takes in the output written since the previous call, writing a frame if one is due.
+    pub fn frame(&mut self, interpreter: &Interpreter, output: &str) -> Result<()> {
+        self.output.push_str(output);
+        let ticks = interpreter.ticks();
+        if ticks < self.next {
+            return Ok(());
+        }
+
+        self.next = ticks - ticks % self.every + self.every;
+        self.write(interpreter)
+    }
+
+    /// Writes the last frame of the run, unless it was just written.
+    pub fn finish(&mut self, interpreter: &Interpreter) -> Result<()> {
+        match self.last == Some(interpreter.ticks()) {
+            true => Ok(()),
+            false => self.write(interpreter),
+        }
+    }
+
+    /// Ticks between two frames.
+    pub fn every(&self) -> u64 {
+        self.every
+    }
+
+    /// Number of frames written so far.
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    fn write(&mut self, interpreter: &Interpreter) -> Result<()> {
+        let name = format!("frame-{:06}.{}", self.written, self.format.extension());
+        let path = self.directory.join(name);
+        let display = || path.display().to_string();
+        let content = match self.format {
+            Format::Ansi => ansi(interpreter, &self.output).into_bytes(),
+            Format::Png => png(interpreter).map_err(|err| Error::Encode(display(), err))?,
+        };
+        std::fs::write(&path, content).map_err(|err| Error::Write(display(), err))?;
+
+        self.written += 1;
+        self.last = Some(interpreter.ticks());
+        // Only the lines shown are kept
+        if let Some((end, _)) = self.output.rmatch_indices('\n').nth(OUTPUT_LINES) {
+            self.output.drain(..=end);
+        }
+        Ok(())
+    }
+}
+
+/// Text frame of a run, the current instruction pointer in reverse video, the others underlined
+/// and cells written by `p` in red.
+pub fn ansi(interpreter: &Interpreter, output: &str) -> String {
+    let grid = interpreter.grid();
+    let (width, height) = grid.size();
+    let ip = interpreter.position();
+    let threads = interpreter.ips().map(|ip| ip.position).collect::<Vec<_>>();
+
+    let mut frame = "\x1b[H\x1b[2J".to_owned();
+    for y in 0..height {
+        for x in 0..width {
+            let cell = grid.get(x, y);
+            let c = match char::from(cell.value) {
+                c if c.is_control() => '·',
+                c => c,
+            };
+            let style = if (x, y) == ip {
+                "7"
+            } else if threads.contains(&(x, y)) {
+                "4"
+            } else if matches!(cell.origin, Origin::Written(_)) {
+                "31"
+            } else {
+                ""
+            };
+            match style {
+                "" => frame.push(c),
+                style => {
+                    let _ = write!(frame, "\x1b[{style}m{c}\x1b[0m");
+                }
+            }
+        }
+        frame.push('\n');
+    }
+
+    let stack = interpreter
+        .stack()
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let _ = writeln!(frame, "\ntick {}  stack [{stack}]\n", interpreter.ticks());
+    let lines = output.lines().collect::<Vec<_>>();
+    for line in &lines[lines.len().saturating_sub(OUTPUT_LINES)..] {
+        let _ = writeln!(frame, "{line}");
+    }
+    frame
+}
+
+/// Image frame of a run as PNG, instruction pointers drawn in yellow over cells coloured after
+/// the kind of instruction they hold.
+pub fn png(interpreter: &Interpreter) -> anyhow::Result<Vec<u8>, png::EncodingError> {
+    let grid = interpreter.grid();
+    let (width, height) = grid.size();
+    let (columns, rows) = (width.max(1) * CELL, height.max(1) * CELL);
+    let ip = interpreter.position();
+    let threads = interpreter.ips().map(|ip| ip.position).collect::<Vec<_>>();
+
+    let mut pixels = BACKGROUND.repeat(columns * rows);
+    for y in 0..height {
+        for x in 0..width {
+            let value = grid.get(x, y).value;
+            let colour = if (x, y) == ip {
+                IP
+            } else if threads.contains(&(x, y)) {
+                THREAD
+            } else if matches!(value, CellValue::Empty) {
+                continue;
+            } else {
+                colour(Kind::of(value))
+            };
+            for row in y * CELL..(y + 1) * CELL - 1 {
+                let start = (row * columns + x * CELL) * 3;
+                for pixel in pixels[start..start + (CELL - 1) * 3].chunks_exact_mut(3) {
+                    pixel.copy_from_slice(&colour);
+                }
+            }
+        }
+    }
+
+    let mut data = Vec::new();
+    let mut encoder = png::Encoder::new(&mut data, columns as u32, rows as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&pixels)?;
+    writer.finish()?;
+    Ok(data)
+}
+
+fn colour(kind: Kind) -> [u8; 3] {
+    match kind {
+        Kind::Arithmetic => [0x56, 0x9c, 0xd6],
+        Kind::Logic => [0xc5, 0x86, 0xc0],
+        Kind::Stack => [0x4e, 0xc9, 0xb0],
+        Kind::Movement => [0x80, 0x80, 0x80],
+        Kind::Branch => [0xd7, 0xba, 0x7d],
+        Kind::Literal => [0xb5, 0xce, 0xa8],
+        Kind::String => [0xce, 0x91, 0x78],
+        Kind::Grid => [0xf4, 0x47, 0x47],
+        Kind::Input => [0x9c, 0xdc, 0xfe],
+        Kind::Output => [0x6a, 0x99, 0x55],
+        Kind::End => [0xff, 0xff, 0xff],
+        Kind::Space | Kind::Unknown => [0x3c, 0x3c, 0x3c],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{grid::Grid, interpreter::Status};
+
+    #[test]
+    fn frames() {
+        let directory = std::env::temp_dir().join(format!("mst-frames-{}", std::process::id()));
+        let mut interpreter = Interpreter::new(Grid::from("1.2.3.@".to_owned()));
+        let mut dumper = Dumper::new(&directory, Format::Ansi, 3).unwrap();
+
+        dumper.frame(&interpreter, "").unwrap();
+        while interpreter.step().unwrap() == Status::Running {
+            let output = interpreter.take_output();
+            dumper.frame(&interpreter, &output).unwrap();
+        }
+        dumper.finish(&interpreter).unwrap();
+
+        // At ticks 0, 3 and 6, then the end of the run at tick 7
+        assert_eq!(dumper.written(), 4);
+        let first = std::fs::read_to_string(directory.join("frame-000000.ans")).unwrap();
+        assert!(first.contains("\x1b[7m1\x1b[0m.2.3.@"));
+        let last = std::fs::read_to_string(directory.join("frame-000003.ans")).unwrap();
+        assert!(last.ends_with("tick 7  stack []\n\n1 2 3 \n"), "{last:?}");
+
+        let image = png(&interpreter).unwrap();
+        assert_eq!(&image[1..4], b"PNG");
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -1,30 +1,56 @@
 use std::{
+    collections::{BTreeMap, BTreeSet},
     io::Stdout,
     sync::mpsc::{self, Receiver, Sender, TryRecvError},
     time::{Duration, Instant},
 };
 
+use serde::{Deserialize, Serialize};
 use tui::style::Color;
 
-use crate::{cell::CellValue, grid::Grid};
+use puccinia::{
+    annotation::{self, Annotation, Label, Measure, Region},
+    assembler::SourceMap,
+    cell::{self, Cell, CellValue, Origin},
+    config::{self, Keymap, Panel, Size},
+    debugger::{self, Access, HistoryEntry, JournalEntry, Prediction, Stop, Target},
+    dialect::Extension,
+    forecast::Outlook,
+    golf::Metrics,
+    grid::{Changes, Grid},
+    interpreter::{Ip, Schedule, Status},
+    narrator::{self, Observation},
+    quotes::Unbalanced,
+    routines::Routine,
+    statistics::Statistics,
+    timeline::{self, Sample},
+};
+
+use crate::{ansi::Screen, history::History, logic::RunningCommand};
 
 use {
     crossterm::{
-        event::{DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent},
+        event::{
+            DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, MouseButton,
+            MouseEvent, MouseEventKind,
+        },
         execute,
         terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     },
     ellipse::Ellipse,
     tui::{
         backend::{Backend, CrosstermBackend},
-        layout::{Margin, Rect},
-        style::Style,
-        widgets::{Block, Borders, Paragraph},
+        buffer::Buffer,
+        layout::{Constraint, Direction, Layout, Margin, Rect},
+        style::{Modifier, Style},
+        text::{Span, Spans, Text},
+        widgets::{Block, Borders, Clear, Paragraph, Sparkline, Widget, Wrap},
         Frame, Terminal,
     },
 };
 
 #[derive(thiserror::Error, Debug)]
+#[allow(unused)]
 pub enum Error {
     #[error("Unknown error: {0}")]
     Unknown(String),
@@ -38,11 +64,364 @@ pub enum Error {
 
 type Result<T> = anyhow::Result<T, Error>;
 
+/// Cells of the trail of each instruction pointer, by default.
+const TRAIL_LENGTH: usize = 12;
+
+/// How the TUI presents programs and their runs.
+#[derive(clap::Args, Clone, Debug, Default)]
+pub(crate) struct Display {
+    /// Interpret ANSI escape sequences in program output, e.g. colours and cursor moves, rather
+    /// than showing them
+    #[arg(long)]
+    pub ansi: bool,
+
+    /// Use a high-contrast theme without blinking, and describe each step of runs in plain
+    /// sentences in a panel suited to screen readers
+    #[arg(long)]
+    pub accessible: bool,
+
+    /// Colours of the markers drawn over the grid: `default`, or `high-contrast` as used by
+    /// `--accessible`
+    #[arg(long, value_name = "THEME")]
+    pub theme: Option<config::Theme>,
+
+    /// Show cells as their values in hexadecimal rather than as characters, e.g. for data holding
+    /// unprintable characters, toggled with 'N'
+    #[arg(long)]
+    pub numeric: bool,
+
+    /// Highlight cells changed since the program was loaded, edited ones and those written by
+    /// `p` apart, toggled with 'C'
+    #[arg(long)]
+    pub changes: bool,
+
+    /// Draw the cells each instruction pointer went through last as a trail fading away behind
+    /// it, which is easier to follow than the highlighted IP alone when several are active or on
+    /// recordings, toggled with 'H'
+    #[arg(long)]
+    pub trail: bool,
+
+    /// Cells of the trail of each instruction pointer, as far back as the latest instructions
+    /// sent to the TUI go
+    #[arg(long, value_name = "LENGTH", default_value_t = TRAIL_LENGTH)]
+    pub trail_length: usize,
+
+    /// Panels shown during runs: `default`, `debug`, `minimal` or one of the `layouts` of the
+    /// configuration, switched between with `:layout NAME`
+    #[arg(long, value_name = "NAME")]
+    pub layout: Option<String>,
+
+    /// Panels of the layout in use
+    #[arg(skip)]
+    pub panels: config::Layout,
+
+    /// Normal mode keys rebound by the configuration
+    #[arg(skip)]
+    pub keys: Keymap,
+}
+
+/// Styles of the markers drawn over the grid.
+#[derive(Debug)]
+struct Theme {
+    ip: Style,
+    /// Instruction pointers waiting for their turn
+    threads: Style,
+    breakpoint: Style,
+    /// Cells pushed as text in string mode rather than executed
+    string: Style,
+    origin: Style,
+    bookmark: Style,
+    /// Annotated cells, and the region being selected for an annotation
+    annotation: Style,
+    selection: Style,
+    /// Outline of labelled regions, and of those holding data only
+    label: Style,
+    data: Style,
+    collaborators: [Style; 4],
+    /// Cells a paused run is predicted to go through next
+    path: Style,
+    /// Markers on the edges of the grid an instruction pointer wrapped around
+    wrap: Style,
+    /// Style of the editing cursor, the grid's own blinking cursor being drawn if unset
+    cursor: Option<Style>,
+    /// Cells changed since the program was loaded, by hand or by `p`
+    edited: Style,
+    written: Style,
+    /// Trails behind instruction pointers, from their newest cells to their oldest
+    trail: [Style; 3],
+    /// Quote most likely unbalanced on the row or column of the cursor
+    unbalanced: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        let collaborator = |color| Style::default().fg(Color::Black).bg(color);
+        Self {
+            ip: Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+            threads: Style::default().fg(Color::Black).bg(Color::LightYellow),
+            breakpoint: Style::default().bg(Color::Red),
+            string: Style::default().fg(Color::LightGreen),
+            origin: Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::UNDERLINED),
+            bookmark: Style::default().fg(Color::Black).bg(Color::LightBlue),
+            annotation: Style::default().bg(Color::DarkGray),
+            selection: Style::default().add_modifier(Modifier::REVERSED),
+            label: Style::default().fg(Color::Green),
+            data: Style::default().fg(Color::Blue),
+            collaborators: [Color::Cyan, Color::Green, Color::Magenta, Color::Blue]
+                .map(collaborator),
+            path: Style::default().fg(Color::Cyan),
+            wrap: Style::default()
+                .fg(Color::LightMagenta)
+                .add_modifier(Modifier::BOLD),
+            unbalanced: Style::default()
+                .fg(Color::Black)
+                .bg(Color::LightRed)
+                .add_modifier(Modifier::BOLD),
+            cursor: None,
+            edited: Style::default().fg(Color::LightBlue),
+            written: Style::default().fg(Color::LightRed),
+            trail: [
+                Style::default().fg(Color::Black).bg(Color::LightYellow),
+                Style::default().fg(Color::Black).bg(Color::Yellow),
+                Style::default().fg(Color::Yellow),
+            ],
+        }
+    }
+}
+
+impl Theme {
+    /// Markers telling apart through brightness and text attributes rather than hue alone.
+    fn high_contrast() -> Self {
+        let inverted = Style::default().fg(Color::Black).bg(Color::White);
+        Self {
+            ip: inverted.add_modifier(Modifier::BOLD),
+            threads: inverted.add_modifier(Modifier::UNDERLINED),
+            breakpoint: Style::default()
+                .fg(Color::White)
+                .bg(Color::Black)
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED | Modifier::ITALIC),
+            string: Style::default()
+                .fg(Color::LightYellow)
+                .add_modifier(Modifier::ITALIC),
+            origin: Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::UNDERLINED),
+            bookmark: Style::default()
+                .fg(Color::LightCyan)
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            annotation: Style::default().add_modifier(Modifier::ITALIC),
+            selection: inverted,
+            label: Style::default()
+                .fg(Color::LightGreen)
+                .add_modifier(Modifier::BOLD),
+            data: Style::default()
+                .fg(Color::LightBlue)
+                .add_modifier(Modifier::BOLD | Modifier::ITALIC),
+            collaborators: [
+                Color::LightYellow,
+                Color::LightCyan,
+                Color::LightGreen,
+                Color::White,
+            ]
+            .map(|color| Style::default().fg(Color::Black).bg(color)),
+            path: Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::UNDERLINED),
+            wrap: Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD | Modifier::REVERSED),
+            unbalanced: Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED | Modifier::SLOW_BLINK),
+            cursor: Some(
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            edited: Style::default()
+                .fg(Color::LightCyan)
+                .add_modifier(Modifier::ITALIC),
+            written: Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD | Modifier::ITALIC),
+            trail: [
+                Style::default().fg(Color::Black).bg(Color::Gray),
+                Style::default().fg(Color::White).bg(Color::DarkGray),
+                Style::default()
+                    .fg(Color::Gray)
+                    .add_modifier(Modifier::UNDERLINED),
+            ],
+        }
+    }
+}
+
 #[derive(Default, Debug)]
 struct State {
     mode: EditorMode,
     grid: Grid,
     tooltip: Option<Tooltip>,
+    run: RunState,
+    output: String,
+    display: Display,
+    theme: Theme,
+    /// Description of the latest step of the run, in accessible mode
+    narration: String,
+    /// Cursors of collaborators, by site
+    cursors: Vec<(u32, (usize, usize))>,
+    /// Cursor position last told to the logic thread
+    shared_cursor: (usize, usize),
+    bookmarks: BTreeMap<char, (usize, usize)>,
+    /// First key of a two-key normal mode command, `m` or `'` for bookmarks
+    pending: Option<char>,
+    annotations: Vec<Annotation>,
+    /// Corner of the region being selected, the cursor being the other one
+    anchor: Option<(usize, usize)>,
+    /// Whether the selection is measured, from its anchor to the cursor
+    measure: bool,
+    labels: Vec<Label>,
+    /// Lines of funge assembly the program was laid out from
+    source_map: Option<SourceMap>,
+    /// Cells the program can push as text in string mode
+    strings: BTreeSet<(usize, usize)>,
+    quotes: Vec<Unbalanced>,
+    /// Annotation or label being written, in annotate mode
+    note: Option<Note>,
+    /// Instruction typed into a data region, placed if typed again at the same cell
+    confirm: Option<((usize, usize), char)>,
+    /// Data region being edited, in table mode
+    table: Option<Table>,
+    /// Command being typed, in command mode
+    command: Option<String>,
+    history: History,
+    /// File names of the open programs
+    buffers: Vec<String>,
+    buffer: usize,
+    /// Buffer highlighted in the buffer picker
+    picked: usize,
+    /// Yanked regions by name, shared by every buffer
+    registers: BTreeMap<char, Vec<String>>,
+    /// Register picked with `"` for the next yank or paste
+    register: Option<char>,
+    /// Where the timeline was last drawn, to map clicks to ticks
+    timeline_area: Rect,
+    /// What is listed below the stack while running
+    log: Log,
+    /// Log entries scrolled past, counting from the latest one
+    log_scroll: usize,
+    /// Where the log was last drawn, to map clicks to entries
+    log_area: Rect,
+    /// Accesses to the cell last asked about
+    references: Option<References>,
+    /// Ticks the latest run to reach its end took
+    steps: Option<u64>,
+    /// Grid as drawn on the previous frame, only changed cells being drawn again
+    grid_cache: Buffer,
+}
+
+/// List shown below the stack while running.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+enum Log {
+    /// Recently executed instructions
+    #[default]
+    Backtrace,
+    /// Cells written by the program, clicking one moving the cursor there
+    Journal,
+    /// Accesses to a cell, clicking one travelling to it
+    References,
+    /// Input read by the program
+    Input,
+}
+
+/// Instructions of the trace that executed, read or wrote a cell, oldest first.
+#[derive(Debug)]
+struct References {
+    cell: (usize, usize),
+    entries: Vec<(Access, HistoryEntry)>,
+}
+
+/// Data region edited as the values of its cells rather than their characters.
+#[derive(Debug)]
+struct Table {
+    region: Region,
+    name: String,
+    /// Whether values are shown and typed in hexadecimal
+    hex: bool,
+    /// Digits typed for the cell under the cursor, placed on Enter
+    typed: String,
+}
+
+/// Text being written about a region.
+#[derive(Debug)]
+struct Note {
+    region: Region,
+    text: String,
+    /// Whether the text names the region rather than annotating it
+    label: bool,
+}
+
+/// Interpreter state as reported by the logic thread.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RunState {
+    /// Whether a run is in progress
+    pub active: bool,
+    /// Whether the run is progressing on its own
+    pub running: bool,
+    pub position: (usize, usize),
+    /// Origin `g` and `p` coordinates are relative to
+    pub storage_offset: (i32, i32),
+    pub extensions: BTreeSet<Extension>,
+    pub stack: Vec<i32>,
+    /// Every instruction pointer, in the order they run next
+    pub ips: Vec<Ip>,
+    /// Instruction pointer followed by single steps
+    pub following: Option<u32>,
+    pub ticks: u64,
+    pub status: Status,
+    pub stop: Option<Stop>,
+    /// Output produced since the last update
+    pub output: String,
+    /// When the run was brought back, number of output characters produced before `output`
+    pub rewound: Option<usize>,
+    pub breakpoints: Vec<(usize, usize)>,
+    /// Cells the run pushed as text in string mode so far
+    pub strings: Vec<(usize, usize)>,
+    /// Latest moves past an edge of the grid, oldest first
+    pub wraps: Vec<debugger::Wrap>,
+    /// Path the instruction pointer goes on to follow, while paused
+    pub ahead: Prediction,
+    /// Recently executed instructions, oldest first
+    pub history: Vec<HistoryEntry>,
+    /// Cells executed last by each instruction pointer, oldest first, for their trails
+    pub trail: Vec<(u32, (usize, usize))>,
+    /// Stack depth over the course of the run
+    pub timeline: Vec<Sample>,
+    /// Points right before the bursts of writes to the grid, oldest first
+    pub checkpoints: Vec<debugger::Checkpoint>,
+    /// Instruction mix executed so far
+    pub statistics: Statistics,
+    /// How the next instruction pointer to run is picked
+    pub schedule: Schedule,
+    /// Turns recently taken by instruction pointers, oldest first, as an id and a number of
+    /// instructions run in a row
+    pub order: Vec<(u32, usize)>,
+    /// Latest cells written by the program, oldest first
+    pub journal: Vec<JournalEntry>,
+    /// Input read by the program so far
+    pub input: String,
+    /// Watched expressions along with their values
+    pub watches: Vec<String>,
+    /// Pace of the run once past its start-up, and how long it has left
+    pub forecast: Option<Outlook>,
+    /// Code the program quit with through `q`
+    pub exit_code: Option<i32>,
+    /// Cell of the assertion the program ended on
+    pub assertion: Option<(usize, usize)>,
 }
 
 #[derive(Default, Debug)]
@@ -54,34 +433,77 @@ enum EditorMode {
     Insert,
     /// Running state
     Running,
+    /// Writing an annotation
+    Annotate,
+    /// Typing a command after `:`
+    Command,
+    /// Picking the program to edit among the open ones
+    Buffers,
+    /// Picking a routine of the library to paste
+    Routines,
+    /// Editing a data region as a table of numbers
+    Table,
+    /// Opening, closing and rearranging the panels shown during runs
+    Panels,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[allow(unused)]
 pub enum Tooltip {
     Error(String),
     Help,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 #[allow(unused)]
 pub enum Message {
     Break,
     Load(Grid),
     LogicFail(Option<String>),
     PopupToggle(Tooltip),
-    SetCell { x: usize, y: usize, v: char },
+    SetCell {
+        x: usize,
+        y: usize,
+        v: char,
+    },
+    /// Cells changed since the last update
+    Patch(Vec<((usize, usize), Cell)>),
+    /// Cursors of collaborators, by site
+    Cursors(Vec<(u32, (usize, usize))>),
+    /// Bookmarks kept next to the program
+    Bookmarks(BTreeMap<char, (usize, usize)>),
+    /// Notes on cells and regions kept next to the program
+    Annotations(Vec<Annotation>),
+    /// Named regions kept next to the program
+    Labels(Vec<Label>),
+    /// Source map kept next to a program laid out by the assembler
+    SourceMap(Option<SourceMap>),
+    /// Cells the program can push as text in string mode, as found by the analyzer
+    Strings(BTreeSet<(usize, usize)>),
+    /// Lines of the program with an odd number of quotes along them
+    Quotes(Vec<Unbalanced>),
+    /// File names of the open programs, along with the index of the one being edited
+    Buffers(Vec<String>, usize),
+    /// Rows to put in a register
+    Register(char, Vec<String>),
+    /// Instructions of the trace that executed, read or wrote a cell, oldest first
+    References((usize, usize), Vec<(Access, HistoryEntry)>),
+    /// Panels to show during runs from now on
+    Layout(config::Layout),
+    Running(Box<RunState>),
 }
 
+/// Runs the TUI.
 pub(crate) fn run(
     receiver: Receiver<Message>,
     sender: Sender<crate::logic::Message>,
+    display: Display,
 ) -> Result<()> {
-    let mut terminal = setup_terminal().map_err(|err| Error::Terminal(err))?;
+    let mut terminal = setup_terminal().map_err(Error::Terminal)?;
 
-    let res = wrapper(&mut terminal, receiver, &sender);
+    let res = wrapper(&mut terminal, receiver, &sender, display);
 
-    restore_terminal(terminal, &sender).map_err(|err| Error::Terminal(err))?;
+    restore_terminal(terminal, &sender).map_err(Error::Terminal)?;
 
     res
 }
@@ -90,15 +512,24 @@ fn wrapper<B: Backend>(
     terminal: &mut Terminal<B>,
     receiver: Receiver<Message>,
     sender: &Sender<crate::logic::Message>,
+    display: Display,
 ) -> Result<()> {
     let mut state = State {
         grid: Grid::new(10, 10),
+        theme: match display.theme {
+            Some(config::Theme::HighContrast) => Theme::high_contrast(),
+            Some(config::Theme::Default) => Theme::default(),
+            None if display.accessible => Theme::high_contrast(),
+            None => Theme::default(),
+        },
+        display,
+        history: History::load(),
         ..Default::default()
     };
 
-    main_loop(terminal, &mut state, &receiver, &sender)?;
+    main_loop(terminal, &mut state, &receiver, sender)?;
 
-    wait_for_exit().map_err(|err| Error::Terminal(err))?;
+    wait_for_exit().map_err(Error::Terminal)?;
 
     Ok(())
 }
@@ -111,14 +542,15 @@ fn setup_terminal() -> std::io::Result<Terminal<CrosstermBackend<Stdout>>> {
 
     let backend = CrosstermBackend::new(stdout);
 
-    Ok(Terminal::new(backend)?)
+    Terminal::new(backend)
 }
 
 fn restore_terminal<B: Backend + std::io::Write>(
     mut terminal: Terminal<B>,
     sender: &Sender<crate::logic::Message>,
 ) -> std::io::Result<()> {
-    sender.send(crate::logic::Message::Kill).unwrap();
+    // The logic side may already be gone, e.g. after a remote disconnection
+    let _ = sender.send(crate::logic::Message::Kill);
 
     disable_raw_mode()?;
 
@@ -154,7 +586,14 @@ fn main_loop<B: Backend>(
 
         last_frame = now;
 
-        stop = handle_events(state)?;
+        stop = handle_events(state, sender)?;
+
+        let cursor = state.grid.get_cursor();
+        if cursor != state.shared_cursor {
+            state.shared_cursor = cursor;
+            let (x, y) = cursor;
+            let _ = sender.send(crate::logic::Message::Cursor { x, y });
+        }
 
         try_receive_message(state, receiver)?;
 
@@ -162,7 +601,7 @@ fn main_loop<B: Backend>(
             .draw(|f| {
                 ui(f, state);
             })
-            .map_err(|err| Error::Terminal(err))?;
+            .map_err(Error::Terminal)?;
 
         if stop {
             break;
@@ -173,137 +612,2273 @@ fn main_loop<B: Backend>(
 }
 
 fn try_receive_message(state: &mut State, receiver: &Receiver<Message>) -> Result<()> {
-    match receiver.try_recv() {
-        Ok(msg) => match msg {
-            Message::Load(content) => {
-                state.grid = Grid::from(content);
-            }
-            Message::Break => return Err(Error::Terminated),
-            Message::LogicFail(opt_msg) => {
-                state.tooltip = opt_msg.map(|msg| Tooltip::Error(msg));
-            }
-            Message::PopupToggle(_) => todo!(),
-            Message::SetCell { x, y, v } => state.grid.set(x, y, CellValue::from(v)),
-        },
-        Err(err) => match err {
-            TryRecvError::Empty => (),
-            TryRecvError::Disconnected => return Err(Error::Channel(err)),
-        },
+    loop {
+        match receiver.try_recv() {
+            Ok(msg) => match msg {
+                Message::Load(content) => {
+                    state.grid.load(&content);
+                }
+                Message::Break => return Err(Error::Terminated),
+                Message::LogicFail(opt_msg) => {
+                    state.tooltip = opt_msg.map(Tooltip::Error);
+                }
+                Message::PopupToggle(_) => todo!(),
+                Message::SetCell { x, y, v } => state.grid.edit(x, y, CellValue::from(v)),
+                Message::Cursors(cursors) => state.cursors = cursors,
+                Message::Bookmarks(bookmarks) => state.bookmarks = bookmarks,
+                Message::Annotations(annotations) => state.annotations = annotations,
+                Message::Labels(labels) => state.labels = labels,
+                Message::SourceMap(source_map) => state.source_map = source_map,
+                Message::Strings(strings) => state.strings = strings,
+                Message::Quotes(quotes) => state.quotes = quotes,
+                Message::Register(name, rows) => {
+                    state.registers.insert(name, rows);
+                }
+                Message::Layout(layout) => state.display.panels = layout,
+                Message::Buffers(buffers, buffer) => {
+                    // Selections and pending edits belong to the program that was edited
+                    if buffer != state.buffer {
+                        state.anchor = None;
+                        state.confirm = None;
+                        state.output.clear();
+                        state.steps = None;
+                    }
+                    state.buffers = buffers;
+                    state.buffer = buffer;
+                }
+                Message::Patch(cells) => {
+                    for ((x, y), cell) in cells {
+                        state.grid.set_cell(x, y, cell);
+                    }
+                }
+                Message::References(cell, entries) => {
+                    state.references = Some(References { cell, entries });
+                    state.log = Log::References;
+                    state.log_scroll = 0;
+                }
+                Message::Running(run) => {
+                    // Runs started by commands rather than keys take the keys as well
+                    if run.active && !state.run.active && matches!(state.mode, EditorMode::Normal) {
+                        state.mode = EditorMode::Running;
+                        state.output.clear();
+                    }
+                    if let Some(kept) = run.rewound {
+                        let end = state
+                            .output
+                            .char_indices()
+                            .nth(kept)
+                            .map_or(state.output.len(), |(index, _)| index);
+                        state.output.truncate(end);
+                    }
+                    state.output.push_str(&run.output);
+                    if run.status == Status::Terminated {
+                        state.steps = Some(run.ticks);
+                    }
+                    if state.display.accessible {
+                        narrate(state, &run);
+                    }
+                    state.run = *run;
+                }
+            },
+            Err(err) => match err {
+                TryRecvError::Empty => break,
+                TryRecvError::Disconnected => return Err(Error::Channel(err)),
+            },
+        }
     }
 
     Ok(())
 }
 
+/// Describes what the run did since the last update.
+fn narrate(state: &mut State, run: &RunState) {
+    let observe = |run: &RunState| {
+        let (x, y) = run.position;
+        Observation {
+            ticks: run.ticks,
+            position: run.position,
+            instruction: char::from(state.grid.get(x, y).value),
+            stack: run.stack.clone(),
+            status: run.status,
+        }
+    };
+
+    let after = observe(run);
+    let before = if state.run.active {
+        observe(&state.run)
+    } else {
+        Observation::default()
+    };
+    if before == after && !state.narration.is_empty() {
+        return;
+    }
+
+    state.narration = narrator::narrate(&before, &after);
+    if !run.output.is_empty() {
+        state.narration += &format!(" Printed {:?}.", run.output);
+    }
+}
+
 fn ui<B: Backend>(f: &mut Frame<B>, state: &mut State) {
     let size = f.size();
 
-    f.render_widget(Block::default().title("MST").borders(Borders::ALL), size);
+    let mut title = match state.buffers.get(state.buffer) {
+        Some(name) if state.buffers.len() > 1 => {
+            format!(
+                "MST - {name} [{}/{}]",
+                state.buffer + 1,
+                state.buffers.len()
+            )
+        }
+        _ => "MST".to_owned(),
+    };
+    // What golfing sites score programs on
+    title += &format!(" - {}", Metrics::of(&state.grid));
+    if let Some(steps) = state.steps {
+        title += &format!(", {steps} steps");
+    }
+    // Full value of the cell under the cursor, its compact form being ambiguous past `ff`
+    if state.display.numeric {
+        let (x, y) = state.grid.get_cursor();
+        let value = char::from(state.grid.get(x, y).value);
+        title += &format!(
+            " - ({x}, {y}) = {} (0x{:x}) {value:?}",
+            value as u32, value as u32
+        );
+    }
+    // Where the cell under the cursor comes from, when changed cells are highlighted
+    if state.display.changes {
+        let (x, y) = state.grid.get_cursor();
+        title += &format!(" - ({x}, {y}) {}", state.grid.get(x, y).origin);
+    }
+    f.render_widget(Block::default().title(title).borders(Borders::ALL), size);
+
+    let inner = size.inner(&Margin {
+        vertical: 5,
+        horizontal: 5,
+    });
+
+    let grid_area = if state.run.active {
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Length(state.display.panels.width),
+            ])
+            .split(inner);
+
+        render_run_panel(f, state, chunks[1]);
+
+        chunks[0]
+    } else if !state.bookmarks.is_empty() || !state.labels.is_empty() {
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(0), Constraint::Length(20)])
+            .split(inner);
+
+        render_places(f, state, chunks[1]);
 
+        chunks[0]
+    } else {
+        inner
+    };
+
+    render_grid(f, state, grid_area);
+    let unbalanced = unbalanced_quotes(state)
+        .into_iter()
+        .map(|unbalanced| unbalanced.quote)
+        .collect::<Vec<_>>();
     f.render_widget(
-        state.grid.clone(),
-        size.inner(&Margin {
-            vertical: 5,
-            horizontal: 5,
-        }),
+        Markers {
+            theme: &state.theme,
+            ip: state.run.active.then_some(state.run.position),
+            threads: state.run.ips.get(1..).unwrap_or_default(),
+            breakpoints: &state.run.breakpoints,
+            quoted: &state.strings,
+            pushed: &state.run.strings,
+            unbalanced: &unbalanced,
+            ahead: &state.run.ahead.path,
+            wraps: &state.run.wraps,
+            predicted: &state.run.ahead.wraps,
+            size: state.grid.size(),
+            visible: state.grid.visible(grid_area),
+            bookmarks: &state.bookmarks,
+            annotations: &state.annotations,
+            labels: &state.labels,
+            selection: state
+                .anchor
+                .map(|anchor| Region::new(anchor, state.grid.get_cursor())),
+            cursors: &state.cursors,
+            origin: state
+                .run
+                .active
+                .then(|| offset_origin(state.run.storage_offset))
+                .flatten(),
+            literals: state
+                .run
+                .extensions
+                .contains(&Extension::MultiDigit)
+                .then_some(&state.grid),
+            cursor: state
+                .theme
+                .cursor
+                .is_some()
+                .then(|| state.grid.get_cursor()),
+            changes: state.display.changes.then_some(&state.grid),
+            trail: &state.run.trail,
+            trail_length: match state.display.trail {
+                true => state.display.trail_length,
+                false => 0,
+            },
+        },
+        grid_area,
     );
 
+    render_annotation(f, state, grid_area);
+    match state.mode {
+        EditorMode::Buffers => render_buffers(f, state),
+        EditorMode::Routines => render_routines(f, state),
+        EditorMode::Table => render_table(f, state),
+        EditorMode::Panels => render_panels(f, state),
+        _ => (),
+    }
+    render_quotes(f, state);
     render_tooltip(f, state);
+    render_measure(f, state);
+    render_command(f, state);
 }
 
-fn handle_events(state: &mut State) -> Result<bool> {
-    if let Ok(true) = crossterm::event::poll(Duration::from_millis(0)) {
-        match crossterm::event::read() {
-            Ok(Event::Key(KeyEvent { code, .. })) => match state.mode {
-                EditorMode::Normal => return handle_events_normal_mode(code, state),
-                EditorMode::Insert => {
-                    handle_events_insert_mode(code, state);
-                }
-                EditorMode::Running => {
-                    handle_events_running_mode(code, state);
-                }
-            },
-            Err(err) => return Err(Error::Terminal(err)),
-            _ => (),
+/// Updates the cached grid rendering with the cells that changed and draws it.
+fn render_grid<B: Backend>(f: &mut Frame<B>, state: &mut State, area: Rect) {
+    let cache = &mut state.grid_cache;
+
+    state.grid.set_numeric(state.display.numeric);
+    match state.grid.take_changes() {
+        Changes::Cells(cells) if cache.area == area => {
+            for position in cells {
+                state.grid.render_cell(area, cache, position);
+            }
+        }
+        _ => {
+            *cache = Buffer::empty(area);
+            state.grid.clone().render(area, cache);
         }
     }
 
-    Ok(false)
+    f.render_widget(
+        CachedGrid {
+            cache,
+            grid: (state.theme.cursor.is_none()).then_some(&state.grid),
+        },
+        area,
+    );
 }
 
-fn handle_events_running_mode(code: KeyCode, state: &mut State) {
-    match code {
-        KeyCode::Char(c) => {
-            todo!();
+/// Copies a cached grid rendering, drawing the grid's cursor on top if given.
+struct CachedGrid<'a> {
+    cache: &'a Buffer,
+    grid: Option<&'a Grid>,
+}
+
+impl Widget for CachedGrid<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let visible = area.intersection(buf.area).intersection(self.cache.area);
+        for y in visible.top()..visible.bottom() {
+            for x in visible.left()..visible.right() {
+                *buf.get_mut(x, y) = self.cache.get(x, y).clone();
+            }
         }
-        KeyCode::Esc => {
-            state.mode = EditorMode::Normal;
+
+        if let Some(grid) = self.grid {
+            grid.render_cursor(area, buf);
         }
-        _ => todo!(),
     }
 }
 
-fn handle_events_insert_mode(code: KeyCode, state: &mut State) {
-    match code {
-        KeyCode::Char(c) => {
-            state.grid.set_current(CellValue::from(c));
+/// Cell the storage offset points to, if it was moved away from the grid's origin and lands on
+/// the grid.
+fn offset_origin((x, y): (i32, i32)) -> Option<(usize, usize)> {
+    ((x, y) != (0, 0))
+        .then(|| usize::try_from(x).ok().zip(usize::try_from(y).ok()))
+        .flatten()
+}
+
+/// Highlights the instruction pointer, breakpoints and storage offset on top of a rendered grid.
+struct Markers<'a> {
+    theme: &'a Theme,
+    ip: Option<(usize, usize)>,
+    /// Instruction pointers waiting for their turn
+    threads: &'a [Ip],
+    breakpoints: &'a [(usize, usize)],
+    /// Cells the analyzer found to be pushed as text in string mode
+    quoted: &'a BTreeSet<(usize, usize)>,
+    /// Cells the run pushed as text in string mode
+    pushed: &'a [(usize, usize)],
+    /// Quotes likely left unbalanced on the lines through the cursor
+    unbalanced: &'a [(usize, usize)],
+    /// Cells a paused run goes through next
+    ahead: &'a [(usize, usize)],
+    /// Latest wraps of the run around the edges of the grid
+    wraps: &'a [debugger::Wrap],
+    /// Wraps on the path a paused run goes on to follow
+    predicted: &'a [debugger::Wrap],
+    /// Size of the grid and how much of it is visible, its edges only being marked when shown
+    size: (usize, usize),
+    visible: (usize, usize),
+    bookmarks: &'a BTreeMap<char, (usize, usize)>,
+    annotations: &'a [Annotation],
+    labels: &'a [Label],
+    selection: Option<Region>,
+    /// Cursors of collaborators, by site
+    cursors: &'a [(u32, (usize, usize))],
+    origin: Option<(usize, usize)>,
+    /// Grid whose multi-digit literals are underlined, when they are read as such
+    literals: Option<&'a Grid>,
+    /// Editing cursor, when the theme draws it
+    cursor: Option<(usize, usize)>,
+    /// Grid whose changed cells are highlighted
+    changes: Option<&'a Grid>,
+    /// Cells executed last by each instruction pointer, oldest first
+    trail: &'a [(u32, (usize, usize))],
+    /// How many of them make the trail of each, none being drawn for 0
+    trail_length: usize,
+}
+
+impl Widget for Markers<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        // Annotated rows get a marker in the gutter left of the grid
+        for annotation in self.annotations {
+            let Region { from, to } = annotation.region;
+            for y in from.1..=to.1 {
+                let (_, row) = Grid::screen_position(area, (0, y));
+                if area.left() > 0 && row < area.bottom() {
+                    let symbol = if y == from.1 { "¶" } else { "┆" };
+                    buf.set_string(area.left() - 1, row, symbol, self.theme.annotation);
+                }
+            }
         }
-        KeyCode::Esc => {
-            state.mode = EditorMode::Normal;
+
+        // Folded regions are dimmed away, only their name being left
+        let dim = Style::default().add_modifier(Modifier::DIM);
+        for label in self.labels.iter().filter(|label| label.folded) {
+            let Region { from, to } = label.region;
+            for y in from.1..=to.1 {
+                let (left, row) = Grid::screen_position(area, (from.0, y));
+                let (right, _) = Grid::screen_position(area, (to.0, y));
+                let right = right.min(area.right().saturating_sub(2));
+                if row >= area.bottom() || left > right {
+                    continue;
+                }
+
+                let fill = "░".repeat((right - left + 1) as usize);
+                buf.set_string(left, row, fill, dim);
+                if y == from.1 {
+                    let name = format!(" {} ", label.name);
+                    buf.set_stringn(left, row, name, (right - left + 1) as usize, dim);
+                }
+            }
+        }
+
+        // Wraps get the same arrow on the edge they leave by and the one they come back in from
+        let ((width, height), (columns, rows)) = (self.size, self.visible);
+        let predicted = self.predicted.iter().map(|wrap| (wrap, true));
+        for (wrap, ahead) in self.wraps.iter().map(|wrap| (wrap, false)).chain(predicted) {
+            let style = match ahead {
+                true => self.theme.wrap.add_modifier(Modifier::DIM),
+                false => self.theme.wrap,
+            };
+            let (x, y) = wrap.from;
+            let (column, row) = Grid::screen_position(area, (x, y));
+            let (right, bottom) = Grid::screen_position(area, (columns, rows));
+            let edges = match wrap.direction {
+                cell::Direction::Left | cell::Direction::Right if y < rows && columns == width => {
+                    [(area.left(), row), (right, row)]
+                }
+                cell::Direction::Up | cell::Direction::Down if x < columns && rows == height => {
+                    [(column, area.top()), (column, bottom)]
+                }
+                _ => continue,
+            };
+            let symbol = match wrap.direction {
+                cell::Direction::Left => "◂",
+                cell::Direction::Right => "▸",
+                cell::Direction::Up => "▴",
+                _ => "▾",
+            };
+            for (x, y) in edges {
+                if x < area.right() && y < area.bottom() {
+                    buf.set_string(x, y, symbol, style);
+                }
+            }
+        }
+
+        let mut mark = |position: (usize, usize), style: Style| {
+            let (x, y) = Grid::screen_position(area, position);
+            if x < area.right() && y < area.bottom() {
+                buf.get_mut(x, y).set_style(style);
+            }
+        };
+
+        for position in self.quoted.iter().chain(self.pushed) {
+            mark(*position, self.theme.string);
+        }
+        for position in self.unbalanced {
+            mark(*position, self.theme.unbalanced);
+        }
+
+        for annotation in self.annotations {
+            let Region { from, to } = annotation.region;
+            for y in from.1..=to.1 {
+                for x in from.0..=to.0 {
+                    mark((x, y), self.theme.annotation);
+                }
+            }
+        }
+
+        if let Some(grid) = self.literals {
+            let digit = |x: usize, y: usize| {
+                matches!(
+                    grid.try_get(x, y),
+                    Some(Cell {
+                        value: CellValue::Number(_),
+                        ..
+                    })
+                )
+            };
+
+            let (columns, rows) = grid.visible(area);
+            for y in 0..rows {
+                for x in 0..columns {
+                    let grouped = digit(x + 1, y)
+                        || digit(x, y + 1)
+                        || x.checked_sub(1).is_some_and(|left| digit(left, y))
+                        || y.checked_sub(1).is_some_and(|up| digit(x, up));
+                    if digit(x, y) && grouped {
+                        mark((x, y), Style::default().add_modifier(Modifier::UNDERLINED));
+                    }
+                }
+            }
+        }
+
+        if let Some(grid) = self.changes {
+            let (columns, rows) = grid.visible(area);
+            for y in 0..rows {
+                for x in 0..columns {
+                    match grid.get(x, y).origin {
+                        Origin::Loaded => (),
+                        Origin::Edited => mark((x, y), self.theme.edited),
+                        Origin::Written(_) => mark((x, y), self.theme.written),
+                    }
+                }
+            }
+        }
+
+        for label in self.labels.iter().filter(|label| !label.folded) {
+            let style = if label.data {
+                self.theme.data
+            } else {
+                self.theme.label
+            };
+            let Region { from, to } = label.region;
+            for y in from.1..=to.1 {
+                for x in from.0..=to.0 {
+                    if x == from.0 || x == to.0 || y == from.1 || y == to.1 {
+                        mark((x, y), style);
+                    }
+                }
+            }
+        }
+
+        if let Some(origin) = self.origin {
+            mark(origin, self.theme.origin);
+        }
+
+        for bookmark in self.bookmarks.values() {
+            mark(*bookmark, self.theme.bookmark);
+        }
+
+        let collaborators = &self.theme.collaborators;
+        for (site, cursor) in self.cursors {
+            mark(*cursor, collaborators[*site as usize % collaborators.len()]);
+        }
+
+        // Trails fade by thirds of their length, newer cells being drawn over older ones
+        let length = self.trail_length;
+        let mut ages = BTreeMap::new();
+        let trail = self.trail.iter().rev().filter_map(|(id, position)| {
+            let age = ages.entry(*id).or_insert(0);
+            *age += 1;
+            (*age <= length).then_some((position, *age - 1))
+        });
+        for (position, age) in trail.collect::<Vec<_>>().into_iter().rev() {
+            mark(*position, self.theme.trail[age * 3 / length]);
+        }
+
+        for position in self.ahead {
+            mark(*position, self.theme.path);
+        }
+
+        for breakpoint in self.breakpoints {
+            mark(*breakpoint, self.theme.breakpoint);
+        }
+
+        for thread in self.threads {
+            mark(thread.position, self.theme.threads);
+        }
+
+        if let Some(ip) = self.ip {
+            mark(ip, self.theme.ip);
+        }
+
+        if let Some(Region { from, to }) = self.selection {
+            for y in from.1..=to.1 {
+                for x in from.0..=to.0 {
+                    mark((x, y), self.theme.selection);
+                }
+            }
+        }
+
+        if let Some((cursor, style)) = self.cursor.zip(self.theme.cursor) {
+            mark(cursor, style);
         }
-        _ => todo!(),
     }
 }
 
-fn handle_events_normal_mode(code: KeyCode, state: &mut State) -> Result<bool> {
-    match code {
-        KeyCode::Char('q') => {
-            state.tooltip = Some(Tooltip::Error("Press 'q' to exit".to_owned()));
-            return Ok(true);
-        }
-        KeyCode::Char('i') => {
-            state.mode = EditorMode::Insert;
+fn render_run_panel<B: Backend>(f: &mut Frame<B>, state: &mut State, area: Rect) {
+    // The status gets a line for the source of programs laid out by the assembler, and panels
+    // share what is left as told by the layout
+    let panels = state.display.panels.panels.clone();
+    let rests = panels
+        .iter()
+        .filter(|(_, size)| *size == Size::Rest)
+        .count();
+    let taken = panels
+        .iter()
+        .map(|(_, size)| match size {
+            Size::Percent(percent) => *percent,
+            _ => 0,
+        })
+        .sum::<u16>();
+    let mut rest = 0;
+    let sizes = panels.iter().map(|(panel, size)| match size {
+        // The narration standing in for the timeline needs a couple more lines
+        Size::Lines(lines) if *panel == Panel::Timeline && state.display.accessible => {
+            Constraint::Length(lines + 2)
         }
-        KeyCode::Char(c @ ('h' | 'j' | 'k' | 'l')) => {
-            if let Err(err) = match c {
-                'h' => state.grid.move_cursor(-1, 0),
-                'j' => state.grid.move_cursor(0, 1),
-                'k' => state.grid.move_cursor(0, -1),
-                'l' => state.grid.move_cursor(1, 0),
-                _ => unreachable!(),
-            } {
-                state.tooltip = Some(Tooltip::Error(format!(
-                    "Invalid move (out of bounds): {err:?}"
-                )));
+        Size::Lines(lines) => Constraint::Length(*lines),
+        Size::Percent(percent) => Constraint::Percentage(*percent),
+        Size::Fit => Constraint::Length(fit(state, *panel)),
+        Size::Rest => {
+            rest += 1;
+            match rest == rests {
+                true => Constraint::Min(0),
+                false => Constraint::Percentage(100u16.saturating_sub(taken) / rests as u16),
             }
         }
-        _ => todo!(),
+    });
+    let constraints = [Constraint::Length(
+        3 + state.source_map.is_some() as u16 + state.run.forecast.is_some() as u16,
+    )]
+    .into_iter()
+    .chain(sizes)
+    .collect::<Vec<_>>();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .split(area);
+
+    let run = &state.run;
+    let status = match (run.status, run.running) {
+        (Status::Terminated, _) => match (run.exit_code, run.assertion) {
+            (_, Some((x, y))) => format!("assertion failed at ({x}, {y})"),
+            (Some(code), _) => format!("exited with code {code}"),
+            (None, None) => "terminated".to_owned(),
+        },
+        (Status::WaitingForInput, _) => "waiting for input".to_owned(),
+        (Status::Running, true) => "running".to_owned(),
+        (Status::Running, false) => "paused".to_owned(),
+    };
+
+    // Cell under the cursor, relative to the storage offset as well when it moved
+    let (x, y) = state.grid.get_cursor();
+    let cursor = match run.storage_offset {
+        (0, 0) => format!("({x}, {y})"),
+        (ox, oy) => format!("({x}, {y}) / ({}, {})", x as i32 - ox, y as i32 - oy),
+    };
+
+    // Line of funge assembly the instruction pointer is on
+    let source = state
+        .source_map
+        .as_ref()
+        .and_then(|source_map| {
+            let span = source_map.at(run.position)?;
+            Some(format!(
+                "\n{}:{} `{}`",
+                source_map.file, span.line, span.text
+            ))
+        })
+        .unwrap_or_default();
+    let forecast = run
+        .forecast
+        .map(|outlook| format!("\n{outlook}"))
+        .unwrap_or_default();
+
+    f.render_widget(
+        Paragraph::new(format!(
+            "{status} @ tick {}, cell {cursor}{source}{forecast}",
+            run.ticks
+        ))
+        .block(Block::default().title("Status").borders(Borders::ALL)),
+        chunks[0],
+    );
+
+    // Panels left out of the layout can't be clicked
+    state.timeline_area = Rect::default();
+    state.log_area = Rect::default();
+    for ((panel, _), area) in panels.into_iter().zip(chunks.iter().skip(1)) {
+        render_panel(f, state, panel, *area);
     }
+}
 
-    Ok(false)
+/// Height of a panel holding all of its lines, nothing for watches when there are none.
+fn fit(state: &State, panel: Panel) -> u16 {
+    let run = &state.run;
+    let lines = match panel {
+        Panel::Watches if run.watches.is_empty() => return 0,
+        Panel::Watches => run.watches.len(),
+        Panel::Stack if run.ips.len() > 1 => run.ips.len(),
+        Panel::Stack => run.stack.len().max(1),
+        Panel::Trace => run.history.len(),
+        Panel::Output => state.output.lines().count(),
+        _ => 5,
+    };
+    lines.min(u16::MAX as usize - 2) as u16 + 2
 }
 
-fn render_tooltip<B: Backend>(frame: &mut Frame<B>, state: &State) {
-    let size = frame.size();
+fn render_panel<B: Backend>(f: &mut Frame<B>, state: &mut State, panel: Panel, area: Rect) {
+    let run = &state.run;
+    match panel {
+        Panel::Watches => f.render_widget(
+            Paragraph::new(run.watches.join("\n"))
+                .block(Block::default().title("Watches").borders(Borders::ALL)),
+            area,
+        ),
+        // The narration replaces the timeline, which is of no use to screen readers
+        Panel::Timeline if state.display.accessible => f.render_widget(
+            Paragraph::new(state.narration.as_str())
+                .wrap(Wrap { trim: true })
+                .block(Block::default().title("Narration").borders(Borders::ALL)),
+            area,
+        ),
+        Panel::Timeline => render_timeline(f, state, area),
+        Panel::Stack if run.ips.len() > 1 => render_threads(f, state, area),
+        Panel::Stack => f.render_widget(
+            Paragraph::new(stack_lines(&run.stack))
+                .block(Block::default().title("Stack").borders(Borders::ALL)),
+            area,
+        ),
+        Panel::Trace if state.log != Log::Backtrace => render_log(f, state, area),
+        Panel::Trace => {
+            let backtrace = run
+                .history
+                .iter()
+                .rev()
+                .map(|entry| {
+                    let (x, y) = entry.position;
+                    format!("({x}, {y}) `{}`", entry.instruction)
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
 
-    if let Some(tooltip) = state.tooltip.clone() {
-        match tooltip {
-            Tooltip::Help => (),
-            Tooltip::Error(err) => {
-                let trunc = err.as_str().truncate_ellipse((size.width - 10) as usize);
-                frame.render_widget(
-                    Paragraph::new(trunc.clone()).style(Style::default().fg(Color::Red)),
-                    Rect {
-                        x: 0,
-                        y: size.bottom() - 1,
-                        width: trunc.len() as u16,
-                        height: 1,
-                    },
-                )
-            }
+            f.render_widget(
+                Paragraph::new(backtrace)
+                    .block(Block::default().title("Backtrace").borders(Borders::ALL)),
+                area,
+            );
+        }
+        Panel::Stats => render_statistics(f, run, area),
+        Panel::Output => {
+            let output = if state.display.ansi {
+                Screen::parse(&state.output).into_text()
+            } else {
+                Text::raw(state.output.as_str())
+            };
+            f.render_widget(
+                Paragraph::new(output)
+                    .wrap(Wrap { trim: false })
+                    .block(Block::default().title("Output").borders(Borders::ALL)),
+                area,
+            );
         }
+        Panel::Minimap => render_minimap(f, state, area),
+    }
+}
+
+/// Whole grid shrunk to fit, each character standing for a square of cells shaded after how
+/// many of them are filled, with the instruction pointers on top.
+fn render_minimap<B: Backend>(f: &mut Frame<B>, state: &State, area: Rect) {
+    let block = Block::default().title("Minimap").borders(Borders::ALL);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    if inner.width == 0 || inner.height == 0 {
+        return;
+    }
+
+    let (width, height) = state.grid.size();
+    let scale = width
+        .div_ceil(inner.width as usize)
+        .max(height.div_ceil(inner.height as usize))
+        .max(1);
+    let ips = state
+        .run
+        .ips
+        .iter()
+        .map(|ip| (ip.position.0 / scale, ip.position.1 / scale))
+        .collect::<Vec<_>>();
+
+    let lines = (0..height.div_ceil(scale))
+        .map(|row| {
+            let spans = (0..width.div_ceil(scale))
+                .map(|column| {
+                    if ips.contains(&(column, row)) {
+                        return Span::styled("◆", state.theme.ip);
+                    }
+                    let cells = (row * scale..((row + 1) * scale).min(height)).flat_map(|y| {
+                        (column * scale..((column + 1) * scale).min(width)).map(move |x| (x, y))
+                    });
+                    let (filled, total) = cells.fold((0, 0), |(filled, total), (x, y)| {
+                        let empty = matches!(state.grid.get(x, y).value, CellValue::Empty);
+                        (filled + !empty as usize, total + 1)
+                    });
+                    Span::raw(match filled * 3 / total.max(1) {
+                        _ if filled == 0 => " ",
+                        0 => "░",
+                        1 => "▒",
+                        _ => "▓",
+                    })
+                })
+                .collect::<Vec<_>>();
+            Spans::from(spans)
+        })
+        .collect::<Vec<_>>();
+
+    f.render_widget(Paragraph::new(lines), inner);
+}
+
+/// Cells written by the program, input it read or accesses to a cell, latest first.
+fn render_log<B: Backend>(f: &mut Frame<B>, state: &mut State, area: Rect) {
+    let (title, lines) = match (state.log, &state.references) {
+        (
+            Log::References,
+            Some(References {
+                cell: (x, y),
+                entries,
+            }),
+        ) => (
+            format!(
+                "({x}, {y}) {}, references ({})",
+                state.grid.get(*x, *y).origin,
+                entries.len()
+            ),
+            entries
+                .iter()
+                .map(|(access, entry)| match entry.ip {
+                    0 => format!("{} {}", entry.tick, access.name()),
+                    ip => format!("{} {} IP {ip}", entry.tick, access.name()),
+                })
+                .collect::<Vec<_>>(),
+        ),
+        (Log::Input, _) => (
+            format!("Input ({})", state.run.input.chars().count()),
+            state
+                .run
+                .input
+                .split_inclusive('\n')
+                .map(|line| format!("{line:?}"))
+                .collect(),
+        ),
+        _ => (
+            format!("Writes ({})", state.run.journal.len()),
+            state
+                .run
+                .journal
+                .iter()
+                .map(|entry| {
+                    let (x, y) = entry.position;
+                    match entry.ip {
+                        0 => format!("{} ({x}, {y}) {:?}→{:?}", entry.tick, entry.old, entry.new),
+                        ip => format!(
+                            "{} ({x}, {y}) {:?}→{:?} IP {ip}",
+                            entry.tick, entry.old, entry.new
+                        ),
+                    }
+                })
+                .collect(),
+        ),
+    };
+
+    let block = Block::default().title(title).borders(Borders::ALL);
+    state.log_area = block.inner(area);
+    state.log_scroll = state.log_scroll.min(lines.len().saturating_sub(1));
+
+    let lines = lines
+        .iter()
+        .rev()
+        .skip(state.log_scroll)
+        .take(state.log_area.height as usize)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+/// Values of a stack, top first, along with the characters they stand for.
+fn stack_lines(stack: &[i32]) -> String {
+    stack
+        .iter()
+        .rev()
+        .map(
+            |value| match u32::try_from(*value).ok().and_then(char::from_u32) {
+                Some(c) if !c.is_control() => format!("{value} ({c:?})"),
+                _ => value.to_string(),
+            },
+        )
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Stack, direction and position of each instruction pointer, tiled in the order they run, the
+/// followed one being marked.
+fn render_threads<B: Backend>(f: &mut Frame<B>, state: &State, area: Rect) {
+    let run = &state.run;
+    let ips = run.ips.len().min(area.height as usize / 3).max(1);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(vec![Constraint::Ratio(1, ips as u32); ips])
+        .split(area);
+
+    for (ip, area) in run.ips.iter().zip(chunks) {
+        let (x, y) = ip.position;
+        let followed = if run.following == Some(ip.id) {
+            " ◆"
+        } else {
+            ""
+        };
+        let title = format!(
+            "IP {} {} ({x}, {y}){followed}",
+            ip.id,
+            char::from(CellValue::Dir(ip.direction))
+        );
+        let style = if ip.id == run.ips[0].id {
+            Style::default().add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+
+        f.render_widget(
+            Paragraph::new(stack_lines(&ip.stack)).block(
+                Block::default()
+                    .title(Span::styled(title, style))
+                    .borders(Borders::ALL),
+            ),
+            area,
+        );
+    }
+}
+
+/// Bookmarks and labelled regions, whichever there are.
+fn render_places<B: Backend>(f: &mut Frame<B>, state: &State, area: Rect) {
+    let chunks = match (state.bookmarks.is_empty(), state.labels.is_empty()) {
+        (false, false) => Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(area),
+        _ => vec![area, area],
+    };
+
+    if !state.bookmarks.is_empty() {
+        render_bookmarks(f, state, chunks[0]);
+    }
+    if !state.labels.is_empty() {
+        render_labels(f, state, chunks[1]);
     }
 }
 
+/// Labelled regions, folded with `z`, marked as data with `D` and edited as tables with `T`.
+fn render_labels<B: Backend>(f: &mut Frame<B>, state: &State, area: Rect) {
+    let lines = state
+        .labels
+        .iter()
+        .map(|label| {
+            let fold = if label.folded { '▸' } else { '▾' };
+            let data = if label.data { " (data)" } else { "" };
+            let Region { from, to } = label.region;
+            format!("{fold} {}{data}\n  {from:?}-{to:?}", label.name)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    f.render_widget(
+        Paragraph::new(lines).block(Block::default().title("Regions").borders(Borders::ALL)),
+        area,
+    );
+}
+
+/// Bookmarks by name, set with `m` and jumped to with `'`.
+fn render_bookmarks<B: Backend>(f: &mut Frame<B>, state: &State, area: Rect) {
+    let lines = state
+        .bookmarks
+        .iter()
+        .map(|(name, (x, y))| {
+            let c = char::from(state.grid.get(*x, *y).value);
+            format!("{name} ({x}, {y}) `{c}`")
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    f.render_widget(
+        Paragraph::new(lines).block(Block::default().title("Bookmarks").borders(Borders::ALL)),
+        area,
+    );
+}
+
+/// Histogram of executed instruction kinds, most frequent first, under the order instruction
+/// pointers took turns in and their shares when there are several.
+fn render_statistics<B: Backend>(f: &mut Frame<B>, run: &RunState, area: Rect) {
+    let statistics = &run.statistics;
+    let block = Block::default().title("Instructions").borders(Borders::ALL);
+    let inner = block.inner(area);
+
+    let mut header = Vec::new();
+    if statistics.ips.len() > 1 {
+        // Latest turns are kept when they don't all fit
+        let mut order = run
+            .order
+            .iter()
+            .map(|(id, count)| match count {
+                1 => id.to_string(),
+                count => format!("{id}×{count}"),
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let width = (inner.width as usize).saturating_sub(run.schedule.to_string().len() + 1);
+        if let Some(skip) = order
+            .chars()
+            .count()
+            .checked_sub(width)
+            .filter(|skip| *skip > 0)
+        {
+            order = format!("…{}", order.chars().skip(skip + 1).collect::<String>());
+        }
+        header.push(format!("{} {order}", run.schedule));
+
+        let total = statistics.ips.values().sum::<u64>().max(1);
+        let shares = statistics
+            .ips
+            .iter()
+            .map(|(id, count)| format!("IP {id} {}%", count * 100 / total))
+            .collect::<Vec<_>>();
+        header.push(shares.join("  "));
+    }
+
+    let kinds = statistics.kinds_by_count();
+    let max = kinds.first().map(|(_, count)| *count).unwrap_or_default();
+    let count_width = max.to_string().len();
+    let bar_width = (inner.width as usize).saturating_sub(10 + 1 + count_width + 1);
+
+    let lines = kinds
+        .iter()
+        .map(|(kind, count)| {
+            let bar = (*count as usize * bar_width).div_ceil(max.max(1) as usize);
+            format!(
+                "{:<10} {:<bar_width$} {count:>count_width$}",
+                kind.name(),
+                "█".repeat(bar)
+            )
+        })
+        .collect::<Vec<_>>();
+    let lines = [header, lines].concat().join("\n");
+
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+/// Stack depth sparkline, one column per group of samples.
+fn render_timeline<B: Backend>(f: &mut Frame<B>, state: &mut State, area: Rect) {
+    let mut title = match state.run.stack.last() {
+        Some(top) => format!("Timeline (depth {}, top {top}", state.run.stack.len()),
+        None => "Timeline (empty".to_owned(),
+    };
+    match state.run.checkpoints.len() {
+        0 => title.push(')'),
+        count => title.push_str(&format!(", {count} checkpoint(s))")),
+    }
+    let block = Block::default().title(title).borders(Borders::ALL);
+    state.timeline_area = block.inner(area);
+
+    let columns = timeline_columns(state);
+    let depths = columns
+        .iter()
+        .map(|sample| sample.depth as u64)
+        .collect::<Vec<_>>();
+
+    f.render_widget(
+        Sparkline::default()
+            .block(block)
+            .data(&depths)
+            .style(Style::default().fg(Color::Cyan)),
+        area,
+    );
+
+    // Checkpoints are marked on the bottom border, under the column they fall in
+    for (column, _) in checkpoint_columns(state, &columns) {
+        let marker = Rect::new(column, area.bottom().saturating_sub(1), 1, 1);
+        f.render_widget(
+            Paragraph::new("▲").style(Style::default().fg(Color::Yellow)),
+            marker,
+        );
+    }
+}
+
+/// Terminal columns of the timeline the checkpoints fall in, along with their tick.
+fn checkpoint_columns(state: &State, columns: &[Sample]) -> Vec<(u16, u64)> {
+    let area = state.timeline_area;
+    state
+        .run
+        .checkpoints
+        .iter()
+        .filter_map(|checkpoint| {
+            let index = columns.partition_point(|sample| sample.tick <= checkpoint.tick);
+            let index = u16::try_from(index.checked_sub(1)?).ok()?;
+            Some((area.left() + index, checkpoint.tick))
+        })
+        .collect()
+}
+
+fn timeline_columns(state: &State) -> Vec<Sample> {
+    timeline::summarize(&state.run.timeline, state.timeline_area.width as usize)
+}
+
+fn handle_events(state: &mut State, sender: &Sender<crate::logic::Message>) -> Result<bool> {
+    if let Ok(true) = crossterm::event::poll(Duration::from_millis(0)) {
+        match crossterm::event::read() {
+            Ok(Event::Key(KeyEvent { code, .. })) => match state.mode {
+                EditorMode::Normal => return handle_events_normal_mode(code, state, sender),
+                EditorMode::Insert => {
+                    handle_events_insert_mode(code, state, sender);
+                }
+                EditorMode::Running => {
+                    handle_events_running_mode(code, state, sender);
+                }
+                EditorMode::Annotate => {
+                    handle_events_annotate_mode(code, state, sender);
+                }
+                EditorMode::Command => {
+                    handle_events_command_mode(code, state, sender);
+                }
+                EditorMode::Buffers => {
+                    handle_events_buffers_mode(code, state, sender);
+                }
+                EditorMode::Routines => {
+                    handle_events_routines_mode(code, state, sender);
+                }
+                EditorMode::Table => {
+                    handle_events_table_mode(code, state, sender);
+                }
+                EditorMode::Panels => {
+                    handle_events_panels_mode(code, state, sender);
+                }
+            },
+            Ok(Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Down(MouseButton::Left),
+                column,
+                row,
+                ..
+            })) if state.run.active => {
+                handle_timeline_click(column, row, state, sender);
+                handle_log_click(column, row, state, sender);
+            }
+            Ok(Event::Mouse(MouseEvent {
+                kind: kind @ (MouseEventKind::ScrollUp | MouseEventKind::ScrollDown),
+                column,
+                row,
+                ..
+            })) if state.log != Log::Backtrace && contains(state.log_area, column, row) => {
+                scroll_log(state, kind == MouseEventKind::ScrollDown, 1);
+            }
+            Err(err) => return Err(Error::Terminal(err)),
+            _ => (),
+        }
+    }
+
+    Ok(false)
+}
+
+/// Typed characters are fed to the program, arrows move the cursor and function keys control
+/// execution.
+fn handle_events_running_mode(
+    code: KeyCode,
+    state: &mut State,
+    sender: &Sender<crate::logic::Message>,
+) {
+    let command = match code {
+        KeyCode::Char(c) => RunningCommand::Input(c.to_string()),
+        KeyCode::Enter => RunningCommand::Input("\n".to_owned()),
+        KeyCode::Left | KeyCode::Down | KeyCode::Up | KeyCode::Right => {
+            let (x, y) = match code {
+                KeyCode::Left => (-1, 0),
+                KeyCode::Down => (0, 1),
+                KeyCode::Up => (0, -1),
+                _ => (1, 0),
+            };
+            // Moving past the edges is harmless here
+            let _ = state.grid.move_cursor(x, y);
+            return;
+        }
+        KeyCode::F(4) => RunningCommand::RunTo(Target::Cell(state.grid.get_cursor())),
+        KeyCode::F(5) => RunningCommand::SkipToBreakpoint,
+        KeyCode::F(6) => RunningCommand::Pause,
+        KeyCode::F(8) => RunningCommand::RunTo(Target::LoopExit),
+        KeyCode::F(10) => RunningCommand::Step,
+        KeyCode::F(11) => RunningCommand::RunTo(Target::StringEnd),
+        // Shows the cells written by the program or the input it read rather than the executed
+        // instructions
+        KeyCode::F(7) => {
+            state.log = match state.log {
+                Log::Journal => Log::Input,
+                Log::Input => Log::Backtrace,
+                _ => Log::Journal,
+            };
+            state.log_scroll = 0;
+            return;
+        }
+        // Looks for the instructions of the trace that executed, read or wrote the cell under the
+        // cursor
+        KeyCode::F(9) => RunningCommand::References(state.grid.get_cursor()),
+        KeyCode::PageUp | KeyCode::PageDown if state.log != Log::Backtrace => {
+            let page = state.log_area.height.max(1) as usize;
+            scroll_log(state, code == KeyCode::PageDown, page);
+            return;
+        }
+        // Cycles through instruction pointers for single steps to follow, then none
+        KeyCode::Tab => {
+            let ids = state
+                .run
+                .ips
+                .iter()
+                .map(|ip| ip.id)
+                .collect::<BTreeSet<_>>();
+            let next = match state.run.following {
+                Some(id) => ids.range(id + 1..).next().copied(),
+                None => ids.first().copied(),
+            };
+            RunningCommand::Follow(next)
+        }
+        // Typed characters are input, so commands are opened with a function key while running
+        KeyCode::F(2) => {
+            state.command = Some(String::new());
+            state.mode = EditorMode::Command;
+            return;
+        }
+        KeyCode::F(3) => {
+            state.picked = 0;
+            state.mode = EditorMode::Panels;
+            return;
+        }
+        KeyCode::Esc => {
+            state.mode = EditorMode::Normal;
+            RunningCommand::Stop
+        }
+        _ => return,
+    };
+
+    send_command(state, sender, command);
+}
+
+/// Clicking the timeline travels to the first tick of the clicked column.
+fn handle_timeline_click(
+    column: u16,
+    row: u16,
+    state: &mut State,
+    sender: &Sender<crate::logic::Message>,
+) {
+    let area = state.timeline_area;
+    if row == area.bottom() {
+        let columns = timeline_columns(state);
+        let checkpoint = checkpoint_columns(state, &columns)
+            .into_iter()
+            .find(|&(at, _)| at == column);
+        if let Some((_, tick)) = checkpoint {
+            send_command(state, sender, RunningCommand::TravelTo { tick });
+        }
+        return;
+    }
+    if !contains(area, column, row) {
+        return;
+    }
+
+    if let Some(sample) = timeline_columns(state).get((column - area.left()) as usize) {
+        let tick = sample.tick;
+        send_command(state, sender, RunningCommand::TravelTo { tick });
+    }
+}
+
+/// Clicking a journal entry moves the cursor to the written cell, and clicking a reference
+/// travels to the instruction.
+fn handle_log_click(
+    column: u16,
+    row: u16,
+    state: &mut State,
+    sender: &Sender<crate::logic::Message>,
+) {
+    if !contains(state.log_area, column, row) {
+        return;
+    }
+
+    let index = state.log_scroll + (row - state.log_area.top()) as usize;
+    match (state.log, &state.references) {
+        (Log::Journal, _) => {
+            if let Some(entry) = state.run.journal.iter().rev().nth(index) {
+                let (x, y) = entry.position;
+                // Written cells are always inside the grid
+                let _ = state.grid.set_cursor(x, y);
+            }
+        }
+        (Log::References, Some(References { entries, .. })) => {
+            if let Some((_, entry)) = entries.iter().rev().nth(index) {
+                let tick = entry.tick;
+                send_command(state, sender, RunningCommand::TravelTo { tick });
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Scrolls the log towards older entries, or newer ones if `older` is false.
+fn scroll_log(state: &mut State, older: bool, lines: usize) {
+    state.log_scroll = match older {
+        true => state.log_scroll + lines,
+        false => state.log_scroll.saturating_sub(lines),
+    };
+}
+
+fn contains(area: Rect, column: u16, row: u16) -> bool {
+    column >= area.left() && column < area.right() && row >= area.top() && row < area.bottom()
+}
+
+fn handle_events_insert_mode(
+    code: KeyCode,
+    state: &mut State,
+    sender: &Sender<crate::logic::Message>,
+) {
+    match code {
+        KeyCode::Char(c) => {
+            let (x, y) = state.grid.get_cursor();
+
+            // Steering control flow from a data table is almost always a mistake, so it has to be
+            // typed twice
+            let value = CellValue::from(c);
+            let steers = matches!(
+                value,
+                CellValue::Dir(_) | CellValue::If(_) | CellValue::Bridge | CellValue::End
+            );
+            let data = state
+                .labels
+                .iter()
+                .find(|label| label.data && label.region.contains((x, y)));
+            if let Some(label) = data.filter(|_| steers) {
+                if state.confirm.replace(((x, y), c)) != Some(((x, y), c)) {
+                    state.tooltip = Some(Tooltip::Error(format!(
+                        "`{c}` in data region `{}`, type it again to place it",
+                        label.name
+                    )));
+                    return;
+                }
+            }
+            state.confirm = None;
+            state.grid.set_current(value);
+
+            if sender
+                .send(crate::logic::Message::SetCell { x, y, v: c })
+                .is_err()
+            {
+                state.tooltip = Some(Tooltip::Error("Lost connection to logic".to_owned()));
+            }
+        }
+        KeyCode::Esc => {
+            state.mode = EditorMode::Normal;
+        }
+        _ => (),
+    }
+}
+
+/// Changes the innermost label under the cursor.
+fn edit_label(
+    state: &mut State,
+    sender: &Sender<crate::logic::Message>,
+    edit: impl FnOnce(&mut Label),
+) {
+    let Some(label) = annotation::label_at(&state.labels, state.grid.get_cursor()) else {
+        return;
+    };
+
+    let mut label = label.clone();
+    edit(&mut label);
+    annotation::label(&mut state.labels, label.clone());
+    if sender.send(crate::logic::Message::Label(label)).is_err() {
+        state.tooltip = Some(Tooltip::Error("Lost connection to logic".to_owned()));
+    }
+}
+
+/// Typed text goes to the annotation or label, which is saved on Enter and removed if left
+/// empty.
+fn handle_events_annotate_mode(
+    code: KeyCode,
+    state: &mut State,
+    sender: &Sender<crate::logic::Message>,
+) {
+    let Some(note) = state.note.as_mut() else {
+        state.mode = EditorMode::Normal;
+        return;
+    };
+
+    match code {
+        KeyCode::Char(c) => note.text.push(c),
+        KeyCode::Backspace => {
+            note.text.pop();
+        }
+        KeyCode::Enter => {
+            let (region, text) = (note.region, note.text.trim().to_owned());
+            let message = if note.label {
+                let existing = state.labels.iter().find(|label| label.region == region);
+                let label = Label {
+                    region,
+                    name: text,
+                    folded: existing.is_some_and(|label| label.folded),
+                    data: existing.is_some_and(|label| label.data),
+                };
+                annotation::label(&mut state.labels, label.clone());
+                crate::logic::Message::Label(label)
+            } else {
+                let text = (!text.is_empty()).then_some(text);
+                annotation::annotate(&mut state.annotations, region, text.clone());
+                crate::logic::Message::Annotate { region, text }
+            };
+
+            state.note = None;
+            state.anchor = None;
+            state.mode = EditorMode::Normal;
+            if sender.send(message).is_err() {
+                state.tooltip = Some(Tooltip::Error("Lost connection to logic".to_owned()));
+            }
+        }
+        KeyCode::Esc => {
+            state.note = None;
+            state.mode = EditorMode::Normal;
+        }
+        _ => (),
+    }
+}
+
+/// Typed text is sent to the logic thread as a command on Enter, e.g. `w copy.bf`.
+fn handle_events_command_mode(
+    code: KeyCode,
+    state: &mut State,
+    sender: &Sender<crate::logic::Message>,
+) {
+    let Some(command) = state.command.as_mut() else {
+        state.mode = EditorMode::Normal;
+        return;
+    };
+
+    match code {
+        KeyCode::Char(c) => command.push(c),
+        KeyCode::Backspace if !command.is_empty() => {
+            command.pop();
+        }
+        KeyCode::Up => {
+            if let Some(line) = state.history.previous() {
+                *command = line.to_owned();
+            }
+        }
+        KeyCode::Down => *command = state.history.next().unwrap_or_default().to_owned(),
+        KeyCode::Enter | KeyCode::Esc | KeyCode::Backspace => {
+            let line = state.command.take().unwrap_or_default();
+            state.history.reset();
+            state.mode = if state.run.active {
+                EditorMode::Running
+            } else {
+                EditorMode::Normal
+            };
+
+            if code != KeyCode::Enter || line.trim().is_empty() {
+                return;
+            }
+            if let Err(err) = state.history.push(&line) {
+                state.tooltip = Some(Tooltip::Error(err.to_string()));
+            }
+            if sender.send(crate::logic::Message::Ex(line)).is_err() {
+                state.tooltip = Some(Tooltip::Error("Lost connection to logic".to_owned()));
+            }
+        }
+        _ => (),
+    }
+}
+
+fn send_command(
+    state: &mut State,
+    sender: &Sender<crate::logic::Message>,
+    command: RunningCommand,
+) {
+    if sender
+        .send(crate::logic::Message::RunningCommand(command))
+        .is_err()
+    {
+        state.tooltip = Some(Tooltip::Error("Lost connection to logic".to_owned()));
+    }
+}
+
+fn handle_events_normal_mode(
+    code: KeyCode,
+    state: &mut State,
+    sender: &Sender<crate::logic::Message>,
+) -> Result<bool> {
+    if let Some(pending) = state.pending.take() {
+        match code {
+            KeyCode::Char(name) if pending == '"' => state.register = Some(name),
+            KeyCode::Char(name) => handle_bookmark(pending, name, state, sender),
+            _ => (),
+        }
+        return Ok(false);
+    }
+
+    // Rebound keys do what the default key of their action does
+    let code = match code {
+        KeyCode::Char(pressed) => match state.display.keys.resolve(pressed) {
+            Some(c) => KeyCode::Char(c),
+            None => return Ok(false),
+        },
+        code => code,
+    };
+
+    match code {
+        KeyCode::Char(c @ ('m' | '\'' | '"')) => state.pending = Some(c),
+        KeyCode::Char('y') => yank(state),
+        KeyCode::Char('p') => paste(state, sender),
+        KeyCode::Char('B') => {
+            state.picked = state.buffer;
+            state.mode = EditorMode::Buffers;
+        }
+        KeyCode::Char('I') => {
+            state.picked = 0;
+            state.mode = EditorMode::Routines;
+        }
+        KeyCode::Char('W') => {
+            state.picked = 0;
+            state.mode = EditorMode::Panels;
+        }
+        KeyCode::Char(':') => {
+            state.command = Some(String::new());
+            state.mode = EditorMode::Command;
+        }
+        KeyCode::Char('q') => {
+            state.tooltip = Some(Tooltip::Error("Press 'q' to exit".to_owned()));
+            return Ok(true);
+        }
+        KeyCode::Char('i') => {
+            state.mode = EditorMode::Insert;
+        }
+        KeyCode::Char('v') => {
+            state.anchor = match state.anchor {
+                Some(_) => None,
+                None => Some(state.grid.get_cursor()),
+            };
+        }
+        KeyCode::Esc => {
+            state.anchor = None;
+            state.measure = false;
+        }
+        KeyCode::Char('M') => {
+            // Measuring starts from the cursor unless something is already selected
+            state.measure = !(state.measure && state.anchor.is_some());
+            if state.measure {
+                state.anchor.get_or_insert(state.grid.get_cursor());
+            }
+        }
+        KeyCode::Char('a') => {
+            // Annotate the selection, or edit the note under the cursor if nothing is selected
+            let cursor = state.grid.get_cursor();
+            let (region, text) = match state.anchor {
+                Some(anchor) => {
+                    let region = Region::new(anchor, cursor);
+                    let text = state
+                        .annotations
+                        .iter()
+                        .find(|annotation| annotation.region == region)
+                        .map(|annotation| annotation.text.clone());
+                    (region, text.unwrap_or_default())
+                }
+                None => match annotation::at(&state.annotations, cursor) {
+                    Some(annotation) => (annotation.region, annotation.text.clone()),
+                    None => (Region::cell(cursor), String::new()),
+                },
+            };
+            state.note = Some(Note {
+                region,
+                text,
+                label: false,
+            });
+            state.mode = EditorMode::Annotate;
+        }
+        KeyCode::Char('L') => {
+            // Name the selection, or rename the region under the cursor
+            let cursor = state.grid.get_cursor();
+            let (region, text) = match state.anchor {
+                Some(anchor) => (Region::new(anchor, cursor), String::new()),
+                None => match annotation::label_at(&state.labels, cursor) {
+                    Some(label) => (label.region, label.name.clone()),
+                    None => {
+                        state.tooltip = Some(Tooltip::Error(
+                            "Select a region with 'v' to label it".to_owned(),
+                        ));
+                        return Ok(false);
+                    }
+                },
+            };
+            state.note = Some(Note {
+                region,
+                text,
+                label: true,
+            });
+            state.mode = EditorMode::Annotate;
+        }
+        KeyCode::Char('z') => edit_label(state, sender, |label| label.folded = !label.folded),
+        KeyCode::Char('D') => edit_label(state, sender, |label| label.data = !label.data),
+        KeyCode::Char('N') => state.display.numeric = !state.display.numeric,
+        KeyCode::Char('C') => state.display.changes = !state.display.changes,
+        KeyCode::Char('H') => state.display.trail = !state.display.trail,
+        KeyCode::Char('T') => {
+            let cursor = state.grid.get_cursor();
+            let data = state
+                .labels
+                .iter()
+                .find(|label| label.data && label.region.contains(cursor));
+            match data {
+                Some(label) => {
+                    state.table = Some(Table {
+                        region: label.region,
+                        name: label.name.clone(),
+                        hex: false,
+                        typed: String::new(),
+                    });
+                    state.mode = EditorMode::Table;
+                }
+                None => {
+                    state.tooltip = Some(Tooltip::Error(
+                        "Move into a data region, marked with 'D', to edit it as a table"
+                            .to_owned(),
+                    ))
+                }
+            }
+        }
+        KeyCode::Char(c @ ('h' | 'j' | 'k' | 'l')) => {
+            if let Err(err) = match c {
+                'h' => state.grid.move_cursor(-1, 0),
+                'j' => state.grid.move_cursor(0, 1),
+                'k' => state.grid.move_cursor(0, -1),
+                'l' => state.grid.move_cursor(1, 0),
+                _ => unreachable!(),
+            } {
+                state.tooltip = Some(Tooltip::Error(format!(
+                    "Invalid move (out of bounds): {err:?}"
+                )));
+            }
+        }
+        KeyCode::Char('r') => {
+            state.mode = EditorMode::Running;
+            state.output.clear();
+            send_command(state, sender, RunningCommand::Start);
+        }
+        KeyCode::Char('b') => {
+            let (x, y) = state.grid.get_cursor();
+            send_command(state, sender, RunningCommand::ToggleBreakpoint { x, y });
+        }
+        _ => (),
+    }
+
+    Ok(false)
+}
+
+/// Copies the selection, or the cell under the cursor, into the picked register.
+fn yank(state: &mut State) {
+    let cursor = state.grid.get_cursor();
+    let region = Region::new(state.anchor.take().unwrap_or(cursor), cursor);
+    let rows = (region.from.1..=region.to.1)
+        .map(|y| {
+            (region.from.0..=region.to.0)
+                .map(|x| char::from(state.grid.get(x, y).value))
+                .collect()
+        })
+        .collect();
+
+    let name = state.register.take().unwrap_or('"');
+    state.registers.insert(name, rows);
+    let (width, height) = (
+        region.to.0 - region.from.0 + 1,
+        region.to.1 - region.from.1 + 1,
+    );
+    state.tooltip = Some(Tooltip::Error(format!(
+        "Yanked {width}x{height} cells into register `{name}`"
+    )));
+}
+
+/// Writes the content of the picked register with its top left corner at the cursor, growing
+/// the grid if needed.
+fn paste(state: &mut State, sender: &Sender<crate::logic::Message>) {
+    let name = state.register.take().unwrap_or('"');
+    let Some(rows) = state.registers.get(&name).cloned() else {
+        state.tooltip = Some(Tooltip::Error(format!("Register `{name}` is empty")));
+        return;
+    };
+
+    let (left, top) = state.grid.get_cursor();
+    for (y, row) in rows.iter().enumerate() {
+        for (x, v) in row.chars().enumerate() {
+            let (x, y) = (left + x, top + y);
+            state.grid.grow_to(x, y);
+            state.grid.edit(x, y, CellValue::from(v));
+            if sender
+                .send(crate::logic::Message::SetCell { x, y, v })
+                .is_err()
+            {
+                state.tooltip = Some(Tooltip::Error("Lost connection to logic".to_owned()));
+                return;
+            }
+        }
+    }
+}
+
+/// Moves through open programs with `j` and `k` or picks one by number, switching to it on
+/// Enter.
+fn handle_events_buffers_mode(
+    code: KeyCode,
+    state: &mut State,
+    sender: &Sender<crate::logic::Message>,
+) {
+    let count = state.buffers.len().max(1);
+    let picked = match code {
+        KeyCode::Char('j') | KeyCode::Down => {
+            state.picked = (state.picked + 1) % count;
+            return;
+        }
+        KeyCode::Char('k') | KeyCode::Up => {
+            state.picked = (state.picked + count - 1) % count;
+            return;
+        }
+        KeyCode::Char(c @ '1'..='9') => c as usize - '1' as usize,
+        KeyCode::Enter => state.picked,
+        KeyCode::Esc => {
+            state.mode = EditorMode::Normal;
+            return;
+        }
+        _ => return,
+    };
+
+    state.mode = EditorMode::Normal;
+    if sender
+        .send(crate::logic::Message::Ex(format!("b {}", picked + 1)))
+        .is_err()
+    {
+        state.tooltip = Some(Tooltip::Error("Lost connection to logic".to_owned()));
+    }
+}
+
+/// Moves through the routines of the library with `j` and `k`, putting the highlighted one in
+/// the default register on Enter, generated for the cursor.
+fn handle_events_routines_mode(
+    code: KeyCode,
+    state: &mut State,
+    sender: &Sender<crate::logic::Message>,
+) {
+    let count = Routine::ALL.len();
+    match code {
+        KeyCode::Char('j') | KeyCode::Down => state.picked = (state.picked + 1) % count,
+        KeyCode::Char('k') | KeyCode::Up => state.picked = (state.picked + count - 1) % count,
+        KeyCode::Esc => state.mode = EditorMode::Normal,
+        KeyCode::Enter => {
+            state.mode = EditorMode::Normal;
+            let routine = Routine::ALL[state.picked % count];
+            let (x, y) = state.grid.get_cursor();
+            if sender
+                .send(crate::logic::Message::Ex(format!(
+                    "gen routine {routine} {x},{y}"
+                )))
+                .is_err()
+            {
+                state.tooltip = Some(Tooltip::Error("Lost connection to logic".to_owned()));
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Panels in the order they are listed in, the open ones from top to bottom then the closed ones.
+fn listed_panels(state: &State) -> Vec<(Panel, Option<Size>)> {
+    let open = &state.display.panels.panels;
+    open.iter()
+        .map(|(panel, size)| (*panel, Some(*size)))
+        .chain(
+            Panel::ALL
+                .into_iter()
+                .filter(|panel| !open.iter().any(|(open, _)| open == panel))
+                .map(|panel| (panel, None)),
+        )
+        .collect()
+}
+
+/// Rearranges the panels as they are shown, keeping the layout for the program once done.
+fn handle_events_panels_mode(
+    code: KeyCode,
+    state: &mut State,
+    sender: &Sender<crate::logic::Message>,
+) {
+    let count = Panel::ALL.len();
+    let (panel, size) = listed_panels(state)[state.picked % count];
+    let layout = &mut state.display.panels;
+    match code {
+        KeyCode::Char('j') | KeyCode::Down => state.picked = (state.picked + 1) % count,
+        KeyCode::Char('k') | KeyCode::Up => state.picked = (state.picked + count - 1) % count,
+        KeyCode::Char(c @ ('J' | 'K')) if size.is_some() => {
+            state.picked = layout.shift(state.picked, c == 'J');
+        }
+        KeyCode::Char(c @ ('+' | '-')) if size.is_some() => {
+            let size = &mut layout.panels[state.picked].1;
+            *size = size.resized(c == '+');
+        }
+        KeyCode::Char(c @ ('<' | '>')) => layout.widen(c == '>'),
+        KeyCode::Char(' ') | KeyCode::Enter => {
+            layout.toggle(panel);
+            // The selection follows the panel to the end of the open ones or among the closed
+            state.picked = listed_panels(state)
+                .iter()
+                .position(|(listed, _)| *listed == panel)
+                .unwrap_or_default();
+        }
+        KeyCode::Esc => {
+            state.mode = match state.run.active {
+                true => EditorMode::Running,
+                false => EditorMode::Normal,
+            };
+            let layout = crate::logic::Message::Layout(layout.clone());
+            if sender.send(layout).is_err() {
+                state.tooltip = Some(Tooltip::Error("Lost connection to logic".to_owned()));
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Moves around the data region being edited, typing values in decimal or hexadecimal that are
+/// placed on Enter.
+fn handle_events_table_mode(
+    code: KeyCode,
+    state: &mut State,
+    sender: &Sender<crate::logic::Message>,
+) {
+    let Some(table) = state.table.as_mut() else {
+        state.mode = EditorMode::Normal;
+        return;
+    };
+    let Region { from, to } = table.region;
+    let (x, y) = state.grid.get_cursor();
+    let radix = if table.hex { 16 } else { 10 };
+
+    let (dx, dy) = match code {
+        KeyCode::Char('h') | KeyCode::Left => (-1, 0),
+        KeyCode::Char('j') | KeyCode::Down => (0, 1),
+        KeyCode::Char('k') | KeyCode::Up => (0, -1),
+        KeyCode::Char('l') | KeyCode::Right => (1, 0),
+        KeyCode::Char('x') if table.typed.is_empty() => {
+            table.hex = !table.hex;
+            return;
+        }
+        KeyCode::Char(c) if c.is_digit(radix) => {
+            if table.typed.len() < 10 {
+                table.typed.push(c);
+            }
+            return;
+        }
+        KeyCode::Backspace => {
+            table.typed.pop();
+            return;
+        }
+        KeyCode::Esc if !table.typed.is_empty() => {
+            table.typed.clear();
+            return;
+        }
+        KeyCode::Esc => {
+            state.table = None;
+            state.mode = EditorMode::Normal;
+            return;
+        }
+        KeyCode::Enter if !table.typed.is_empty() => {
+            let typed = std::mem::take(&mut table.typed);
+            let value = u32::from_str_radix(&typed, radix)
+                .ok()
+                .and_then(char::from_u32);
+            let Some(c) = value else {
+                state.tooltip = Some(Tooltip::Error(format!("`{typed}` is not a cell value")));
+                return;
+            };
+            state.grid.set_current(CellValue::from(c));
+            if sender
+                .send(crate::logic::Message::SetCell { x, y, v: c })
+                .is_err()
+            {
+                state.tooltip = Some(Tooltip::Error("Lost connection to logic".to_owned()));
+            }
+
+            // Values are typed one after the other, row by row
+            let next = match x < to.0 {
+                true => (x + 1, y),
+                false => (from.0, if y < to.1 { y + 1 } else { from.1 }),
+            };
+            let _ = state.grid.set_cursor(next.0, next.1);
+            return;
+        }
+        _ => return,
+    };
+
+    let x = (x as i64 + dx).clamp(from.0 as i64, to.0 as i64) as usize;
+    let y = (y as i64 + dy).clamp(from.1 as i64, to.1 as i64) as usize;
+    table.typed.clear();
+    let _ = state.grid.set_cursor(x, y);
+}
+
+/// Sets the bookmark `name` at the cursor, or removes it if it is already there, after `m`.
+/// Jumps to it after `'`.
+fn handle_bookmark(
+    command: char,
+    name: char,
+    state: &mut State,
+    sender: &Sender<crate::logic::Message>,
+) {
+    if !name.is_ascii_alphabetic() {
+        state.tooltip = Some(Tooltip::Error(format!("Invalid bookmark `{name}`")));
+        return;
+    }
+
+    if command == '\'' {
+        match state.bookmarks.get(&name) {
+            Some(&(x, y)) => {
+                if state.grid.set_cursor(x, y).is_err() {
+                    state.tooltip = Some(Tooltip::Error(format!(
+                        "Bookmark `{name}` is outside of the grid"
+                    )));
+                }
+            }
+            None => state.tooltip = Some(Tooltip::Error(format!("No bookmark `{name}`"))),
+        }
+        return;
+    }
+
+    let cursor = state.grid.get_cursor();
+    let position = if state.bookmarks.get(&name) == Some(&cursor) {
+        state.bookmarks.remove(&name);
+        None
+    } else {
+        state.bookmarks.insert(name, cursor);
+        Some(cursor)
+    };
+
+    if sender
+        .send(crate::logic::Message::Bookmark { name, position })
+        .is_err()
+    {
+        state.tooltip = Some(Tooltip::Error("Lost connection to logic".to_owned()));
+    }
+}
+
+/// Shows the annotation under the cursor in a popup below it, or the one being written.
+fn render_annotation<B: Backend>(f: &mut Frame<B>, state: &State, area: Rect) {
+    let size = f.size();
+    let cursor = state.grid.get_cursor();
+
+    let text = match &state.note {
+        Some(note) => format!("{}_", note.text),
+        None => match annotation::at(&state.annotations, cursor) {
+            Some(annotation) if !state.run.running => annotation.text.clone(),
+            _ => return,
+        },
+    };
+
+    let (x, y) = Grid::screen_position(area, cursor);
+    let width = (text.chars().count() as u16 + 2)
+        .clamp(12, 42)
+        .min(size.width);
+    let height =
+        (text.chars().count() as u16 / width.saturating_sub(2).max(1) + 3).min(size.height);
+    let popup = Rect {
+        x: x.min(size.width - width),
+        y: if y + 1 + height <= size.height {
+            y + 1
+        } else {
+            y.saturating_sub(height)
+        },
+        width,
+        height,
+    };
+
+    let title = match &state.note {
+        Some(Note { label: true, .. }) => "Label",
+        Some(_) => "Annotate",
+        None => "Note",
+    };
+    f.render_widget(Clear, popup);
+    f.render_widget(
+        Paragraph::new(text)
+            .wrap(Wrap { trim: false })
+            .block(Block::default().title(title).borders(Borders::ALL)),
+        popup,
+    );
+}
+
+fn render_tooltip<B: Backend>(frame: &mut Frame<B>, state: &State) {
+    let size = frame.size();
+
+    if let Some(tooltip) = state.tooltip.clone() {
+        match tooltip {
+            Tooltip::Help => (),
+            Tooltip::Error(err) => {
+                let trunc = err.as_str().truncate_ellipse((size.width - 10) as usize);
+                frame.render_widget(
+                    Paragraph::new(trunc.clone()).style(Style::default().fg(Color::Red)),
+                    Rect {
+                        x: 0,
+                        y: size.bottom() - 1,
+                        width: trunc.len() as u16,
+                        height: 1,
+                    },
+                )
+            }
+        }
+    }
+}
+
+/// Lists open programs in a popup, the highlighted one being switched to on Enter.
+fn render_buffers<B: Backend>(f: &mut Frame<B>, state: &State) {
+    let size = f.size();
+    let lines = state
+        .buffers
+        .iter()
+        .enumerate()
+        .map(|(index, name)| {
+            let current = if index == state.buffer { '*' } else { ' ' };
+            let line = format!("{}{current} {name}", index + 1);
+            match index == state.picked {
+                true => Spans::from(Span::styled(line, state.theme.selection)),
+                false => Spans::from(line),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let width = (state.buffers.iter().map(|name| name.chars().count()).max())
+        .unwrap_or_default()
+        .saturating_add(8)
+        .clamp(20, size.width as usize) as u16;
+    let height = (lines.len() as u16 + 2).min(size.height);
+    let popup = Rect {
+        x: (size.width - width) / 2,
+        y: (size.height - height) / 2,
+        width,
+        height,
+    };
+
+    f.render_widget(Clear, popup);
+    f.render_widget(
+        Paragraph::new(lines).block(Block::default().title("Buffers").borders(Borders::ALL)),
+        popup,
+    );
+}
+
+/// Lists the routines of the library in a popup, with what they take from the stack and give
+/// back.
+fn render_routines<B: Backend>(f: &mut Frame<B>, state: &State) {
+    let size = f.size();
+    let name_width = Routine::ALL
+        .map(|routine| routine.name().len())
+        .into_iter()
+        .max();
+    let signature_width = Routine::ALL
+        .map(|routine| routine.signature().len())
+        .into_iter()
+        .max();
+    let (name_width, signature_width) = (
+        name_width.unwrap_or_default(),
+        signature_width.unwrap_or_default(),
+    );
+
+    let mut lines = Vec::new();
+    for (index, routine) in Routine::ALL.into_iter().enumerate() {
+        let line = format!(
+            "{:name_width$}  {:signature_width$}",
+            routine.name(),
+            routine.signature()
+        );
+        lines.push(match index == state.picked {
+            true => Spans::from(Span::styled(line, state.theme.selection)),
+            false => Spans::from(line),
+        });
+        lines.push(Spans::from(Span::styled(
+            format!("  {}", routine.description()),
+            Style::default().add_modifier(Modifier::DIM),
+        )));
+    }
+
+    // Descriptions wrap over as many lines as they need
+    let width = (size.width * 3 / 4).max(40).min(size.width);
+    let inner = (width as usize).saturating_sub(2).max(1);
+    let wrapped = Routine::ALL
+        .map(|routine| 1 + (routine.description().len() + 2).div_ceil(inner))
+        .into_iter()
+        .sum::<usize>();
+    let height = (wrapped as u16 + 2).min(size.height);
+    let popup = Rect {
+        x: (size.width - width) / 2,
+        y: (size.height - height) / 2,
+        width,
+        height,
+    };
+
+    f.render_widget(Clear, popup);
+    f.render_widget(
+        Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+            Block::default()
+                .title("Routines, Enter to put one in register \"")
+                .borders(Borders::ALL),
+        ),
+        popup,
+    );
+}
+
+fn render_panels<B: Backend>(f: &mut Frame<B>, state: &State) {
+    let size = f.size();
+    let lines = listed_panels(state)
+        .into_iter()
+        .enumerate()
+        .map(|(index, (panel, size))| {
+            let line = format!(
+                "{:8}  {}",
+                panel.name(),
+                size.map_or("closed".to_owned(), |size| size.to_string())
+            );
+            let style = match (index == state.picked, size) {
+                (true, _) => state.theme.selection,
+                (false, None) => Style::default().add_modifier(Modifier::DIM),
+                (false, Some(_)) => Style::default(),
+            };
+            Spans::from(Span::styled(line, style))
+        })
+        .collect::<Vec<_>>();
+
+    let width = 60.min(size.width);
+    let height = (lines.len() as u16 + 4).min(size.height);
+    let popup = Rect {
+        x: (size.width - width) / 2,
+        y: (size.height - height) / 2,
+        width,
+        height,
+    };
+
+    f.render_widget(Clear, popup);
+    f.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .title(format!(
+                    "Panels, {} columns wide",
+                    state.display.panels.width
+                ))
+                .borders(Borders::ALL),
+        ),
+        popup,
+    );
+    let help = Rect {
+        y: popup.bottom().saturating_sub(2),
+        height: 1,
+        x: popup.x + 1,
+        width: popup.width.saturating_sub(2),
+    };
+    f.render_widget(
+        Paragraph::new("Space open/close, J/K move, +/- resize, </> width")
+            .style(Style::default().add_modifier(Modifier::DIM)),
+        help,
+    );
+}
+
+/// Shows the data region being edited as a table of values, in columns of equal width headed by
+/// their x coordinate and in rows starting with their y coordinate.
+fn render_table<B: Backend>(f: &mut Frame<B>, state: &State) {
+    let Some(table) = &state.table else {
+        return;
+    };
+    let Region { from, to } = table.region;
+    let cursor = state.grid.get_cursor();
+    let value = |x: usize, y: usize| {
+        let value = state.grid.try_get(x, y).map(|cell| char::from(cell.value));
+        value.unwrap_or(' ') as u32
+    };
+    let format = |value: u32| match table.hex {
+        true => format!("{value:x}"),
+        false => value.to_string(),
+    };
+
+    let values = (from.1..=to.1).flat_map(|y| (from.0..=to.0).map(move |x| (x, y)));
+    let width = values
+        .map(|(x, y)| format(value(x, y)).len())
+        .chain([format(to.0 as u32).len(), table.typed.len()])
+        .max()
+        .unwrap_or_default()
+        .max(if table.hex { 2 } else { 3 });
+    let gutter = to.1.to_string().len();
+
+    // Only the rows and columns around the cursor are shown when the region doesn't fit
+    let size = f.size();
+    let columns =
+        (to.0 - from.0 + 1).min((size.width as usize).saturating_sub(gutter + 4) / (width + 1));
+    let rows = (to.1 - from.1 + 1).min((size.height as usize).saturating_sub(3));
+    let first = |cursor: usize, from: usize, shown: usize| {
+        from + (cursor - from).saturating_sub(shown.saturating_sub(1))
+    };
+    let (left, top) = (
+        first(cursor.0, from.0, columns),
+        first(cursor.1, from.1, rows),
+    );
+
+    let header = (left..left + columns)
+        .map(|x| format!(" {x:>width$}"))
+        .collect::<String>();
+    let mut lines = vec![Spans::from(Span::styled(
+        format!("{:gutter$}{header}", ""),
+        Style::default().add_modifier(Modifier::DIM),
+    ))];
+    for y in top..top + rows {
+        let mut spans = vec![Span::styled(
+            format!("{y:>gutter$}"),
+            Style::default().add_modifier(Modifier::DIM),
+        )];
+        for x in left..left + columns {
+            spans.push(Span::raw(" "));
+            let text = match (x, y) == cursor && !table.typed.is_empty() {
+                true => table.typed.clone(),
+                false => format(value(x, y)),
+            };
+            let text = format!("{text:>width$}");
+            spans.push(match (x, y) == cursor {
+                true => Span::styled(text, state.theme.selection),
+                false => Span::raw(text),
+            });
+        }
+        lines.push(Spans::from(spans));
+    }
+
+    let base = if table.hex { "hex" } else { "decimal" };
+    let title = format!("{} ({base}), Enter to place, x to switch", table.name);
+    let width = ((gutter + columns * (width + 1)) as u16 + 2)
+        .max(title.chars().count() as u16 + 2)
+        .min(size.width);
+    let height = (lines.len() as u16 + 2).min(size.height);
+    let popup = Rect {
+        x: (size.width - width) / 2,
+        y: (size.height - height) / 2,
+        width,
+        height,
+    };
+
+    f.render_widget(Clear, popup);
+    f.render_widget(
+        Paragraph::new(lines).block(Block::default().title(title).borders(Borders::ALL)),
+        popup,
+    );
+}
+
+/// Shows the command being typed on the bottom line, over any tooltip.
+/// Lines through the cursor with an odd number of quotes while editing, strings running past
+/// the end of lines being hard to spot.
+fn unbalanced_quotes(state: &State) -> Vec<Unbalanced> {
+    let cursor = state.grid.get_cursor();
+    match state.run.active {
+        true => Vec::new(),
+        false => (state.quotes.iter())
+            .filter(|unbalanced| unbalanced.line.contains(cursor))
+            .copied()
+            .collect(),
+    }
+}
+
+/// Tells which lines through the cursor have unbalanced quotes on the bottom line, unless
+/// something else is shown there.
+fn render_quotes<B: Backend>(frame: &mut Frame<B>, state: &State) {
+    let unbalanced = unbalanced_quotes(state);
+    if unbalanced.is_empty() || state.tooltip.is_some() {
+        return;
+    }
+
+    let report = unbalanced
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ");
+    let report = format!("Unbalanced {report}");
+    let size = frame.size();
+    frame.render_widget(
+        Paragraph::new(report.as_str()).style(state.theme.unbalanced),
+        Rect {
+            x: 0,
+            y: size.bottom() - 1,
+            width: (report.chars().count() as u16).min(size.width),
+            height: 1,
+        },
+    );
+}
+
+/// Reports the size of the selection and how far its corners are apart on the bottom line.
+fn render_measure<B: Backend>(frame: &mut Frame<B>, state: &State) {
+    let Some(anchor) = state.anchor.filter(|_| state.measure) else {
+        return;
+    };
+
+    let measure = Measure {
+        from: anchor,
+        to: state.grid.get_cursor(),
+    };
+    let size = frame.size();
+    let line = Rect {
+        y: size.bottom() - 1,
+        height: 1,
+        ..size
+    };
+    frame.render_widget(Clear, line);
+    frame.render_widget(
+        Paragraph::new(measure.to_string()).style(state.theme.selection),
+        line,
+    );
+}
+
+fn render_command<B: Backend>(frame: &mut Frame<B>, state: &State) {
+    let Some(command) = &state.command else {
+        return;
+    };
+
+    let size = frame.size();
+    let line = Rect {
+        y: size.bottom() - 1,
+        height: 1,
+        ..size
+    };
+    frame.render_widget(Clear, line);
+    frame.render_widget(Paragraph::new(format!(":{command}_")), line);
+}
+
 fn wait_for_exit() -> std::io::Result<()> {
     loop {
         match crossterm::event::read() {
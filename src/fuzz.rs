@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+
+use rayon::prelude::*;
+
+use puccinia::{
+    directives::Directives,
+    grid::Grid,
+    interpreter::{Flush, Interpreter, Status},
+    sources::{Entropy, Xorshift},
+};
+
+use crate::headless::{self, Geometry};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0} input(s) made the program fail, hang or grow its stack")]
+    Found(usize),
+}
+
+/// Numbers at the edges of what `&` reads and cells hold.
+const BOUNDARIES: [&str; 10] = [
+    "0",
+    "-1",
+    "1",
+    "255",
+    "256",
+    "65536",
+    "2147483647",
+    "-2147483648",
+    "2147483648",
+    "99999999999",
+];
+
+/// Characters unlikely to be typed, for `~`.
+const ODD: [&str; 6] = ["", "\0", "\t", "\u{7f}", "\u{ff}", "\u{1f600}"];
+
+#[derive(clap::Args)]
+pub(crate) struct Options {
+    /// Program file location, or bundle
+    input: String,
+
+    /// Number of inputs tried, the first one being empty
+    #[arg(long, value_name = "N", default_value_t = 1000)]
+    runs: usize,
+
+    /// Lines of each input, holding a number, a character or some text
+    #[arg(long, value_name = "N", default_value_t = 8)]
+    lines: usize,
+
+    /// Seed generating the inputs
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    seed: u64,
+
+    /// Instructions after which a run is considered to hang
+    #[arg(long, value_name = "N", default_value_t = 100_000)]
+    max_ticks: u64,
+
+    /// Values on the stacks past which a run is considered to grow them without bound
+    #[arg(long, value_name = "N", default_value_t = 10_000)]
+    max_stack: usize,
+}
+
+/// What went wrong with an input.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Finding {
+    Failed(String),
+    Hung,
+    /// Values on the stacks when the limit was crossed
+    Stack(usize),
+}
+
+impl Finding {
+    fn describe(&self) -> String {
+        match self {
+            Finding::Failed(reason) => format!("failed ({reason})"),
+            Finding::Hung => "ran forever".to_owned(),
+            Finding::Stack(_) => "grew its stack without bound".to_owned(),
+        }
+    }
+}
+
+/// Program run against generated inputs.
+struct Subject<'a> {
+    grid: &'a Grid,
+    directives: &'a Directives,
+    max_ticks: u64,
+    max_stack: usize,
+}
+
+impl Subject<'_> {
+    /// Runs the program on `input`, a program asking for more input than it was given being
+    /// considered to end there.
+    fn probe(&self, input: &str) -> Option<Finding> {
+        let mut interpreter = Interpreter::new(self.grid.clone());
+        headless::configure(&mut interpreter, self.directives, None, &[], None);
+        interpreter.set_fast_forward(true);
+        interpreter.set_flush(Flush::Tick);
+        interpreter.feed_input(input);
+
+        loop {
+            if interpreter.ticks() >= self.max_ticks {
+                return Some(Finding::Hung);
+            }
+            match interpreter.step() {
+                Ok(Status::Running) => (),
+                Ok(Status::Terminated | Status::WaitingForInput) => return None,
+                Err(err) => return Some(Finding::Failed(err.to_string())),
+            }
+            let stack = interpreter.ips().map(|ip| ip.stack.len()).sum::<usize>();
+            if stack > self.max_stack {
+                return Some(Finding::Stack(stack));
+            }
+        }
+    }
+}
+
+/// Input of `lines` lines, mixing boundary and random numbers, characters and text without
+/// digits, which `&` skips over.
+fn generate(entropy: &mut Xorshift, lines: usize) -> String {
+    let mut below = |bound: u64| (entropy.next_u64() % bound) as usize;
+    (0..lines)
+        .map(|_| {
+            let line = match below(5) {
+                0 => BOUNDARIES[below(BOUNDARIES.len() as u64)].to_owned(),
+                1 => (below(2001) as i32 - 1000).to_string(),
+                2 => char::from(b' ' + below(95) as u8).to_string(),
+                3 => ODD[below(ODD.len() as u64)].to_owned(),
+                _ => "abc".to_owned(),
+            };
+            line + "\n"
+        })
+        .collect()
+}
+
+/// Feeds generated inputs to a program and reports those making it fail, hang or grow its stack,
+/// along with the shortest input found for each of them.
+pub(crate) fn run(options: Options) -> anyhow::Result<()> {
+    let (grid, directives, _) = headless::load(&options.input, Geometry::default())?;
+    let subject = Subject {
+        grid: &grid,
+        directives: &directives,
+        max_ticks: options.max_ticks,
+        max_stack: options.max_stack,
+    };
+
+    let mut entropy = Xorshift::new(options.seed);
+    let inputs = (0..options.runs)
+        .map(|run| match run {
+            0 => String::new(),
+            _ => generate(&mut entropy, options.lines),
+        })
+        .collect::<Vec<_>>();
+    let findings = inputs
+        .par_iter()
+        .map(|input| subject.probe(input))
+        .collect::<Vec<_>>();
+
+    // Inputs causing each finding, and the shortest of them
+    let mut found = BTreeMap::<String, (usize, &str)>::new();
+    for (input, finding) in inputs.iter().zip(&findings) {
+        let Some(finding) = finding else {
+            continue;
+        };
+        let (count, shortest) = found.entry(finding.describe()).or_insert((0, input));
+        *count += 1;
+        if input.len() < shortest.len() {
+            *shortest = input;
+        }
+    }
+
+    for (description, (count, shortest)) in &found {
+        println!("{description}: {count} input(s), e.g. {shortest:?}");
+    }
+    let failing = found.values().map(|(count, _)| count).sum::<usize>();
+    eprintln!("{failing} of {} inputs caused trouble", inputs.len());
+
+    match failing {
+        0 => Ok(()),
+        _ => Err(Error::Found(failing).into()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn probe() {
+        // Reads a number, pushing ones forever unless it is zero
+        let grid = Grid::from("&v\nv_@\n>1<".to_owned());
+        let directives = Directives::default();
+        let subject = Subject {
+            grid: &grid,
+            directives: &directives,
+            max_ticks: 10_000,
+            max_stack: 100,
+        };
+        assert_eq!(subject.probe("0\n"), None);
+        assert_eq!(subject.probe(""), None);
+        assert_eq!(subject.probe("5\n"), Some(Finding::Stack(101)));
+
+        let subject = Subject {
+            max_stack: usize::MAX,
+            ..subject
+        };
+        assert_eq!(subject.probe("5\n"), Some(Finding::Hung));
+
+        let grid = Grid::from("&&g@".to_owned());
+        let subject = Subject {
+            grid: &grid,
+            ..subject
+        };
+        assert_eq!(subject.probe("0\n0\n"), None);
+        assert!(matches!(subject.probe("9\n9\n"), Some(Finding::Failed(_))));
+
+        let mut entropy = Xorshift::new(0);
+        let input = generate(&mut entropy, 8);
+        assert_eq!(input.matches('\n').count(), 8);
+        assert_eq!(input, generate(&mut Xorshift::new(0), 8));
+    }
+}
@@ -0,0 +1,140 @@
+use std::path::Path;
+
+use puccinia::{
+    directives::{self, HEADER, INCLUDE},
+    grid::Grid,
+    routines::Routine,
+    synthesis,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("`{0}` uses cells of its own, give where it goes with `--at X,Y`")]
+    Unplaced(Routine),
+    #[error("Could not read `{0}`: {1}")]
+    Read(String, std::io::Error),
+    #[error("Could not write `{0}`: {1}")]
+    Write(String, std::io::Error),
+    #[error("`{0}` already exists with other content")]
+    Exists(String),
+    #[error("`{0}` would overlap the program at ({1}, {2})")]
+    Overlap(Routine, usize, usize),
+}
+
+/// Code to generate.
+#[derive(clap::Subcommand)]
+pub(crate) enum Generator {
+    /// Shortest line printing a text when run from left to right
+    Print {
+        /// Text to print
+        text: String,
+    },
+    /// Shortest push of a number from digits and arithmetic
+    Number {
+        #[arg(allow_negative_numbers = true)]
+        value: i32,
+
+        /// Value known to be on top of the stack, which may be duplicated and built upon
+        #[arg(long, value_name = "N", allow_negative_numbers = true)]
+        top: Option<i32>,
+    },
+    /// Routine of the library, with what it takes from the stack and gives back, or the list of
+    /// them if none is given
+    Routine {
+        name: Option<Routine>,
+
+        /// Where its top-left corner goes, which routines using cells of their own need
+        #[arg(long, value_name = "X,Y", value_parser = position)]
+        at: Option<(usize, usize)>,
+
+        /// Program to include the routine in, from a file written to the `lib` directory next to
+        /// it
+        #[arg(long, value_name = "PROGRAM", requires_all = ["name", "at"])]
+        into: Option<String>,
+    },
+}
+
+fn position(value: &str) -> Result<(usize, usize), String> {
+    let (x, y) = value.split_once(',').ok_or("expected X,Y")?;
+    x.parse()
+        .ok()
+        .zip(y.parse().ok())
+        .ok_or_else(|| "expected X,Y".to_owned())
+}
+
+/// Writes generated code to stdout, ready to be pasted into a program.
+pub(crate) fn run(generator: Generator) -> anyhow::Result<()> {
+    let code = match generator {
+        Generator::Routine { name, at, into } => return routine(name, at, into),
+        Generator::Print { text } => synthesis::print(&text),
+        Generator::Number { value, top } => synthesis::number(value, top),
+    };
+
+    println!("{code}");
+    eprintln!("{} cells", code.chars().count());
+    Ok(())
+}
+
+/// Lists the routines, or writes one to stdout or includes it in a program.
+fn routine(
+    routine: Option<Routine>,
+    at: Option<(usize, usize)>,
+    into: Option<String>,
+) -> anyhow::Result<()> {
+    let Some(routine) = routine else {
+        for routine in Routine::ALL {
+            println!(
+                "{routine:14} {:12} {}",
+                routine.signature(),
+                routine.description()
+            );
+        }
+        return Ok(());
+    };
+    if routine.positioned() && at.is_none() {
+        return Err(Error::Unplaced(routine).into());
+    }
+
+    let (x, y) = at.unwrap_or_default();
+    let code = routine.code((x, y)).join("\n") + "\n";
+    let Some(program) = into else {
+        print!("{code}");
+        eprintln!("{routine}: {}", routine.signature());
+        return Ok(());
+    };
+
+    // The routine must fit in the program as it is before anything gets written
+    let (mut grid, _) = directives::open(&program)?;
+    grid.paste(&Grid::from(code.clone()), (x, y))
+        .map_err(|(x, y)| Error::Overlap(routine, x, y))?;
+
+    let name = match routine.positioned() {
+        true => format!("lib/{routine}-{x}-{y}.bf"),
+        false => format!("lib/{routine}.bf"),
+    };
+    let dir = Path::new(&program).parent().unwrap_or(Path::new(""));
+    let path = dir.join(&name);
+    match std::fs::read_to_string(&path) {
+        Ok(existing) if existing != code => return Err(Error::Exists(name).into()),
+        Ok(_) => (),
+        Err(_) => std::fs::create_dir_all(dir.join("lib"))
+            .and_then(|_| std::fs::write(&path, &code))
+            .map_err(|err| Error::Write(name.clone(), err))?,
+    }
+
+    // Included after the header, which must stay on the first line
+    let text =
+        std::fs::read_to_string(&program).map_err(|err| Error::Read(program.clone(), err))?;
+    let include = format!("{INCLUDE}{name} at {x},{y}\n");
+    let text = match text.split_once('\n') {
+        Some((header, rest)) if header.starts_with(HEADER) => format!("{header}\n{include}{rest}"),
+        _ => include + &text,
+    };
+    std::fs::write(&program, text).map_err(|err| Error::Write(program.clone(), err))?;
+
+    eprintln!(
+        "Included `{name}` in `{program}` at ({x}, {y}), its stack effect being `{}`",
+        routine.signature()
+    );
+    Ok(())
+}
@@ -0,0 +1,395 @@
+use std::ops::Range;
+
+use serde::Serialize;
+
+use crate::{
+    grid::Grid,
+    synthesis,
+    toml::{self, Document, Table, Value},
+};
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error(transparent)]
+    Toml(#[from] toml::Error),
+    #[error("Solution `{0}` has no `path`")]
+    MissingPath(String),
+    #[error("Solution `{0}` has an invalid `{1}`")]
+    Invalid(String, &'static str),
+}
+
+pub type Result<T> = anyhow::Result<T, Error>;
+
+/// Leaderboard file of golfing projects.
+pub const LEADERBOARD: &str = "golf.toml";
+
+/// Size of a program as golfing sites score it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Metrics {
+    /// Bytes of the smallest file holding the program, without trailing spaces, blank lines or
+    /// final newline
+    pub bytes: usize,
+    /// Columns and lines up to the last non-empty cell
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Metrics {
+    pub fn of(grid: &Grid) -> Self {
+        let mut lines = grid
+            .lines()
+            .into_iter()
+            .map(|line| line.trim_end_matches(' ').to_owned())
+            .collect::<Vec<_>>();
+        while lines.last().is_some_and(String::is_empty) {
+            lines.pop();
+        }
+
+        Self {
+            bytes: lines.iter().map(String::len).sum::<usize>() + lines.len().saturating_sub(1),
+            width: lines
+                .iter()
+                .map(|line| line.chars().count())
+                .max()
+                .unwrap_or_default(),
+            height: lines.len(),
+        }
+    }
+}
+
+impl std::fmt::Display for Metrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} bytes, {}x{}", self.bytes, self.width, self.height)
+    }
+}
+
+/// Named program of a leaderboard, along with its scores once measured.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Solution {
+    pub name: String,
+    /// Program file, relative to the leaderboard
+    pub path: String,
+    /// File fed to the program as input
+    pub input: Option<String>,
+    /// File holding the expected output, rather than the program's `output` directive
+    pub expected: Option<String>,
+    pub bytes: Option<usize>,
+    pub steps: Option<u64>,
+    /// Whether the program wrote the expected output the last time it was tested
+    pub verified: Option<bool>,
+}
+
+/// Solutions tracked by a `golf.toml` file, as `[solutions.NAME]` tables.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Leaderboard {
+    pub solutions: Vec<Solution>,
+}
+
+impl Leaderboard {
+    pub fn parse(text: &str) -> Result<Self> {
+        let document = Document::parse(text)?;
+        let solutions = document
+            .children(&["solutions"])
+            .map(|(name, table)| Solution::read(name, table))
+            .collect::<Result<_>>()?;
+        Ok(Self { solutions })
+    }
+
+    pub fn to_text(&self) -> String {
+        let tables = self.solutions.iter().map(Solution::write).collect();
+        Document { tables }.to_string()
+    }
+
+    /// Solutions sorted by size, then by steps, unmeasured ones coming last.
+    pub fn ranking(&self) -> Vec<&Solution> {
+        let mut ranking = self.solutions.iter().collect::<Vec<_>>();
+        ranking.sort_by_key(|solution| {
+            (
+                solution.bytes.unwrap_or(usize::MAX),
+                solution.steps.unwrap_or(u64::MAX),
+            )
+        });
+        ranking
+    }
+}
+
+impl Solution {
+    fn read(name: &str, table: &Table) -> Result<Self> {
+        let invalid = |key| Error::Invalid(name.to_owned(), key);
+        let string = |key| {
+            table
+                .get(key)
+                .map(|value| value.as_str().map(str::to_owned).ok_or(invalid(key)))
+                .transpose()
+        };
+        let integer = |key| {
+            table
+                .get(key)
+                .map(|value| {
+                    value
+                        .as_integer()
+                        .and_then(|integer| u64::try_from(integer).ok())
+                        .ok_or(invalid(key))
+                })
+                .transpose()
+        };
+
+        Ok(Self {
+            name: name.to_owned(),
+            path: string("path")?.ok_or_else(|| Error::MissingPath(name.to_owned()))?,
+            input: string("input")?,
+            expected: string("expected")?,
+            bytes: integer("bytes")?.map(|bytes| bytes as usize),
+            steps: integer("steps")?,
+            verified: table
+                .get("verified")
+                .map(|value| value.as_bool().ok_or(invalid("verified")))
+                .transpose()?,
+        })
+    }
+
+    fn write(&self) -> Table {
+        let mut entries = vec![("path".to_owned(), Value::String(self.path.clone()))];
+        let strings = [("input", &self.input), ("expected", &self.expected)];
+        for (key, value) in strings {
+            if let Some(value) = value {
+                entries.push((key.to_owned(), Value::String(value.clone())));
+            }
+        }
+        let integers = [
+            ("bytes", self.bytes.map(|bytes| bytes as u64)),
+            ("steps", self.steps),
+        ];
+        for (key, value) in integers {
+            if let Some(value) = value {
+                entries.push((key.to_owned(), Value::Integer(value as i64)));
+            }
+        }
+        if let Some(verified) = self.verified {
+            entries.push(("verified".to_owned(), Value::Boolean(verified)));
+        }
+
+        Table {
+            path: vec!["solutions".to_owned(), self.name.clone()],
+            entries,
+        }
+    }
+}
+
+/// What a peephole rewrite simplifies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RewriteKind {
+    /// Digits and arithmetic computing a constant, replaced by its shortest push
+    Fold,
+    /// Instructions undoing each other, such as `:$`
+    DeadPair,
+    /// Arrow pointing the way the instruction pointer already goes
+    Direction,
+}
+
+impl std::fmt::Display for RewriteKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RewriteKind::Fold => "constant folding",
+            RewriteKind::DeadPair => "dead pair",
+            RewriteKind::Direction => "redundant direction",
+        })
+    }
+}
+
+/// Replacement of a run of cells of a line, the rest of the line moving along. Rewrites assume
+/// the run is executed from left to right and nothing else relies on where cells sit, so runs
+/// have to confirm they change nothing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rewrite {
+    pub y: usize,
+    pub columns: Range<usize>,
+    pub with: String,
+    pub kind: RewriteKind,
+}
+
+impl Rewrite {
+    /// Cells saved by the rewrite.
+    pub fn saving(&self) -> usize {
+        self.columns.len() - self.with.chars().count()
+    }
+
+    pub fn apply(&self, lines: &mut [String]) {
+        let line = &mut lines[self.y];
+        let mut chars = line.chars().collect::<Vec<_>>();
+        chars.splice(self.columns.clone(), self.with.chars());
+        *line = chars.into_iter().collect();
+    }
+}
+
+/// Candidate rewrites of the straight runs of each line outside of string mode, biggest savings
+/// first.
+pub fn peephole(lines: &[String]) -> Vec<Rewrite> {
+    let mut rewrites = Vec::new();
+
+    for (y, line) in lines.iter().enumerate() {
+        let chars = line.chars().collect::<Vec<_>>();
+        let mut quoted = false;
+        let code = chars
+            .iter()
+            .map(|c| {
+                quoted ^= *c == '"';
+                !quoted && *c != '"'
+            })
+            .collect::<Vec<_>>();
+
+        for start in 0..chars.len() {
+            let rewrite = |length: usize, with: &str, kind| Rewrite {
+                y,
+                columns: start..start + length,
+                with: with.to_owned(),
+                kind,
+            };
+            let run = |length: usize| {
+                (start + length <= chars.len() && code[start..start + length].iter().all(|c| *c))
+                    .then(|| chars[start..start + length].iter().collect::<String>())
+            };
+
+            if let Some(fold) = fold(&chars[start..], &code[start..]) {
+                rewrites.push(rewrite(fold.0, &fold.1, RewriteKind::Fold));
+            }
+            match run(2).as_deref() {
+                Some(":$" | "\\\\") => rewrites.push(rewrite(2, "", RewriteKind::DeadPair)),
+                Some(pair)
+                    if pair.ends_with('$') && pair.starts_with(|c: char| c.is_ascii_digit()) =>
+                {
+                    rewrites.push(rewrite(2, "", RewriteKind::DeadPair))
+                }
+                Some(">>" | "<<") => rewrites.push(rewrite(
+                    2,
+                    &chars[start].to_string(),
+                    RewriteKind::Direction,
+                )),
+                _ => (),
+            }
+        }
+    }
+
+    rewrites.sort_by_key(|rewrite| std::cmp::Reverse(rewrite.saving()));
+    rewrites
+}
+
+/// Longest run of digits and arithmetic at the start of `chars` computing a single value on its
+/// own, along with the shortest push of that value if it is shorter.
+fn fold(chars: &[char], code: &[bool]) -> Option<(usize, String)> {
+    let mut stack = Vec::<i32>::new();
+    let mut best = None;
+
+    for (length, c) in chars.iter().enumerate() {
+        if !code[length] || compute(&mut stack, *c).is_none() {
+            break;
+        }
+        if let [value] = stack[..] {
+            let push = synthesis::number(value, None);
+            if push.len() <= length {
+                best = Some((length + 1, push));
+            }
+        }
+    }
+
+    best
+}
+
+/// Runs a digit or arithmetic instruction, failing on the others and on what can't be computed
+/// ahead of time.
+pub(crate) fn compute(stack: &mut Vec<i32>, c: char) -> Option<()> {
+    match c {
+        '0'..='9' => stack.push(c.to_digit(10)? as i32),
+        ':' => stack.push(*stack.last()?),
+        '+' | '-' | '*' | '/' | '%' => {
+            let (b, a) = (stack.pop()?, stack.pop()?);
+            stack.push(match c {
+                '+' => a.checked_add(b)?,
+                '-' => a.checked_sub(b)?,
+                '*' => a.checked_mul(b)?,
+                // Befunge-93 asks the user what dividing by zero gives
+                '/' => a.checked_div(b)?,
+                _ => a.checked_rem(b)?,
+            });
+        }
+        _ => return None,
+    }
+    Some(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn of() {
+        let mut grid = Grid::from("v  \n>1.@   \n\n".to_owned());
+        grid.grow_to(20, 6);
+        assert_eq!(
+            Metrics::of(&grid),
+            Metrics {
+                bytes: 6,
+                width: 4,
+                height: 2
+            }
+        );
+        assert_eq!(Metrics::of(&Grid::from(String::new())), Metrics::default());
+    }
+
+    #[test]
+    fn leaderboard() {
+        let text = "\
+[solutions.long]
+path = \"hello.bf\"
+bytes = 20
+
+[solutions.short]
+path = \"golfed/hello.bf\"
+input = \"hello.in\"
+bytes = 13
+steps = 13
+verified = true
+";
+        let leaderboard = Leaderboard::parse(text).unwrap();
+        assert_eq!(leaderboard.to_text(), text);
+        assert_eq!(
+            leaderboard
+                .ranking()
+                .iter()
+                .map(|solution| solution.name.as_str())
+                .collect::<Vec<_>>(),
+            ["short", "long"]
+        );
+
+        assert_eq!(
+            Leaderboard::parse("[solutions.a]\nbytes = 3"),
+            Err(Error::MissingPath("a".to_owned()))
+        );
+        assert_eq!(
+            Leaderboard::parse("[solutions.a]\npath = \"a.bf\"\nsteps = \"many\""),
+            Err(Error::Invalid("a".to_owned(), "steps"))
+        );
+    }
+
+    #[test]
+    fn peephole() {
+        let lines = vec!["55+55+*:$\"5$\">>@".to_owned()];
+        let rewrites = super::peephole(&lines);
+        let found = rewrites
+            .iter()
+            .map(|rewrite| (rewrite.columns.clone(), rewrite.with.as_str(), rewrite.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                (0..7, "5:+:*", RewriteKind::Fold),
+                (7..9, "", RewriteKind::DeadPair),
+                (13..15, ">", RewriteKind::Direction),
+            ]
+        );
+
+        let mut rewritten = lines.clone();
+        rewrites[0].apply(&mut rewritten);
+        assert_eq!(rewritten, ["5:+:*:$\"5$\">>@"]);
+    }
+}
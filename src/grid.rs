@@ -1,13 +1,26 @@
-use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{
+    collections::HashSet,
+    fs::File,
+    path::Path,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
+use serde::{Deserialize, Serialize};
 use tui::{
+    buffer::Buffer,
+    layout::Rect,
     style::{Color, Modifier, Style},
     widgets::Widget,
 };
 
-use crate::cell::{Cell, CellValue};
+use crate::cell::{Cell, CellValue, Direction, Origin};
 
-#[derive(Clone, Debug)]
+use chunks::Chunks;
+
+mod chunks;
+mod occupancy;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Grid {
     width: usize,
     height: usize,
@@ -17,77 +30,100 @@ pub struct Grid {
     corners: Option<[char; 4]>,
 
     cursor: (usize, usize),
+    #[serde(skip, default = "Instant::now")]
     last_move: Instant,
 
-    inner: Vec<Vec<Cell>>,
+    inner: Chunks,
+    /// Length of each line, when lines wrap at their own end rather than at the grid's width
+    #[serde(default)]
+    rows: Option<Vec<usize>>,
+    #[serde(skip)]
+    dirty: Dirty,
+    /// Whether cells are drawn as their values rather than as characters
+    #[serde(skip)]
+    numeric: bool,
 }
 
 impl Widget for Grid {
-    fn render(self, area: tui::layout::Rect, buf: &mut tui::buffer::Buffer) {
-        let width = std::cmp::min(2 * self.width, area.width as usize - 2) as u32;
-        let height = std::cmp::min(self.height + 1, area.height as usize - 2) as u16;
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        self.render_frame(area, buf);
 
-        let lid = self.lids.to_string().repeat(width as usize + 1);
+        let (columns, rows) = self.visible(area);
+        for y in 0..rows {
+            for x in 0..columns {
+                self.render_cell(area, buf, (x, y));
+            }
+        }
 
-        let top_lid = format!(
-            "{}{lid}{}",
-            self.corners.map(|arr| arr[0]).unwrap_or(' '),
-            self.corners.map(|arr| arr[1]).unwrap_or(' ')
-        );
+        self.render_cursor(area, buf);
+    }
+}
 
-        let bot_lid = format!(
-            "{}{lid}{}",
-            self.corners.map(|arr| arr[2]).unwrap_or(' '),
-            self.corners.map(|arr| arr[3]).unwrap_or(' ')
-        );
+/// Value of a cell in the two columns it takes on screen: its ordinal in hexadecimal up to `ff`,
+/// `++` past that, and blank for spaces so that code stands out.
+pub fn compact(value: char) -> [char; 2] {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    match value as u32 {
+        0x20 => [' ', ' '],
+        ordinal @ 0..=0xff => [
+            DIGITS[ordinal as usize >> 4] as char,
+            DIGITS[ordinal as usize & 0xf] as char,
+        ],
+        _ => ['+', '+'],
+    }
+}
 
-        buf.set_string(area.left(), area.top(), top_lid.as_str(), Style::default());
+/// What to do with a line of a program file, see [Grid::open_with].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Line {
+    Load,
+    Skip,
+    /// Stop loading, this line and the following ones not being part of the program
+    Stop,
+}
 
-        self.inner
-            .iter()
-            .map(|line| {
-                line.iter()
-                    .map(|c| char::from(c.value).to_string())
-                    .collect::<Vec<String>>()
-                    .join(" ")
-            })
-            .enumerate()
-            .for_each(|(index, line)| {
-                buf.set_string(
-                    area.left(),
-                    area.top() + index as u16 + 1,
-                    format!("{1} {0} {1}", line, self.sides),
-                    Style::default(),
-                )
-            });
+/// Cells changed since the last call to [Grid::take_changes].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Changes {
+    /// The grid was resized or replaced, everything needs to be redrawn
+    All,
+    Cells(Vec<(usize, usize)>),
+}
 
-        buf.set_string(
-            area.left(),
-            area.top() + height,
-            bot_lid.as_str(),
-            Style::default(),
-        );
+/// Number of changed cells past which the whole grid is considered changed.
+const MAX_CHANGES: usize = 4096;
 
-        let (x, y) = self.cursor;
-        let (x, y) = (area.left() + 2 + 2 * x as u16, area.top() + 1 + y as u16);
-        let val = buf.get(x, y).symbol.clone();
-        let blink = self.last_move.elapsed() < Duration::from_millis(500)
-            || SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs()
-                % 2
-                == 0;
+#[derive(Clone, Debug)]
+struct Dirty {
+    all: bool,
+    cells: HashSet<(usize, usize)>,
+}
 
-        buf.set_string(
-            x,
-            y,
-            val,
-            Style::default()
-                .fg(if blink { Color::Black } else { Color::Cyan })
-                .bg(if blink { Color::Cyan } else { Color::Black })
-                .add_modifier(Modifier::SLOW_BLINK | Modifier::BOLD),
-        );
+impl Default for Dirty {
+    /// New grids have never been drawn
+    fn default() -> Self {
+        Self {
+            all: true,
+            cells: HashSet::new(),
+        }
+    }
+}
+
+impl Dirty {
+    fn mark(&mut self, position: (usize, usize)) {
+        if self.all {
+            return;
+        }
+
+        self.cells.insert(position);
+        if self.cells.len() > MAX_CHANGES {
+            self.invalidate();
+        }
+    }
+
+    fn invalidate(&mut self) {
+        self.all = true;
+        self.cells.clear();
     }
 }
 
@@ -108,11 +144,79 @@ impl From<String> for Grid {
 }
 
 impl Grid {
+    /// Loads a program file, parsing it straight from a memory map rather than reading it into
+    /// a string first so that huge generated programs are not held in memory twice.
+    /// Chunks are all built while parsing, those of blank regions never being allocated, and the
+    /// map is released once done: building them on first access instead would keep the file
+    /// mapped for as long as the grid lives, and saving over it could then crash the process.
+    /// Invalid UTF-8 is replaced rather than rejected.
+    pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Self::open_with(path, |_| Line::Load).map(|(grid, _)| grid)
+    }
+
+    /// Same as [Grid::open], `classify` deciding what to do with each line. Lines from the one
+    /// [Line::Stop] is returned for on are returned rather than loaded.
+    pub fn open_with(
+        path: impl AsRef<Path>,
+        classify: impl FnMut(&str) -> Line,
+    ) -> std::io::Result<(Self, String)> {
+        Self::map(path, |content| Self::parse_with(content, classify))
+    }
+
+    /// Hands the content of a program file to `read` from a memory map, unmapped as soon as
+    /// `read` returns.
+    pub fn map<T>(path: impl AsRef<Path>, read: impl FnOnce(&[u8]) -> T) -> std::io::Result<T> {
+        let file = File::open(path)?;
+
+        // Mapping an empty file fails on some platforms
+        if file.metadata()?.len() == 0 {
+            return Ok(read(&[]));
+        }
+
+        // SAFETY: the map is only read while parsing, a concurrent modification of the file can
+        // at worst garble the loaded program.
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+
+        Ok(read(&map))
+    }
+
+    /// Same as [Grid::open_with], from the content of a program file.
+    pub fn parse_with(content: &[u8], mut classify: impl FnMut(&str) -> Line) -> (Self, String) {
+        let mut res = Grid::empty();
+        let mut rest = String::new();
+
+        let mut lines = content.split(|byte| *byte == b'\n').peekable();
+        while let Some(line) = lines.next() {
+            // Like `str::lines`, no empty line after a trailing newline
+            if line.is_empty() && lines.peek().is_none() {
+                break;
+            }
+
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            let line = String::from_utf8_lossy(line);
+            if !rest.is_empty() {
+                rest.push_str(&line);
+                rest.push('\n');
+                continue;
+            }
+
+            match classify(&line) {
+                Line::Load => res.add_line(Some(&line)),
+                Line::Skip => (),
+                Line::Stop => {
+                    rest.push_str(&line);
+                    rest.push('\n');
+                }
+            }
+        }
+
+        (res, rest)
+    }
+
     fn empty() -> Self {
         Self {
             width: 0,
             height: 0,
-            inner: vec![],
             ..Default::default()
         }
     }
@@ -125,8 +229,11 @@ impl Grid {
             sides: '│',
             corners: Some(['╭', '╮', '╰', '╯']),
             cursor: Default::default(),
-            inner: vec![vec![CellValue::Empty.into(); width]; height],
+            inner: Chunks::default(),
+            rows: None,
             last_move: Instant::now(),
+            dirty: Dirty::default(),
+            numeric: false,
         }
     }
 
@@ -134,37 +241,276 @@ impl Grid {
     /// Resizes grid.
     pub fn add_column(&mut self) {
         self.width += 1;
-
-        self.inner
-            .iter_mut()
-            .for_each(|row| row.push(CellValue::Empty.into()));
+        self.dirty.invalidate();
     }
 
     /// Adds a new line, either blank or filled with desired string.
     /// Resizes grid as necessary.
     pub fn add_line(&mut self, line: Option<&str>) {
+        let y = self.height;
         self.height += 1;
+        self.dirty.invalidate();
 
+        let mut length = 0;
         if let Some(line) = line {
-            let mut line = line.chars().map(Cell::from).collect::<Vec<Cell>>();
-
-            // If longer than width, resize all other rows to keep rectangular shape
-            if line.len() > self.width {
-                let size = line.len();
-                self.width = size;
-                self.inner
-                    .iter_mut()
-                    .for_each(|row| row.resize(size, CellValue::Empty.into()));
-            } else {
-                line.resize(self.width, CellValue::Empty.into());
+            for (x, c) in line.chars().enumerate() {
+                self.inner.set(x, y, Cell::from(c));
+                length = x + 1;
+            }
+
+            // If longer than width, widen the grid to keep rectangular shape
+            self.width = self.width.max(length);
+        }
+
+        if let Some(rows) = self.rows.as_mut() {
+            rows.push(length);
+        }
+    }
+
+    /// Makes horizontal moves wrap at the end of each line, as in Funge-98's Lahey-space, rather
+    /// than at the grid's width. A line ends at its last non-empty cell, and grows when cells are
+    /// set past it.
+    pub fn set_ragged(&mut self, ragged: bool) {
+        self.rows = ragged.then(|| {
+            (0..self.height)
+                .map(|y| {
+                    (0..self.width)
+                        .rev()
+                        .find(|x| !matches!(self.inner.get(*x, y).value, CellValue::Empty))
+                        .map_or(0, |x| x + 1)
+                })
+                .collect()
+        });
+    }
+
+    /// Number of cells horizontal moves go through on a line before wrapping.
+    fn row_width(&self, y: usize) -> usize {
+        match &self.rows {
+            Some(rows) => rows
+                .get(y)
+                .map_or(1, |length| (*length).clamp(1, self.width)),
+            None => self.width,
+        }
+    }
+
+    /// Replaces the content with another grid's, keeping cursor and style
+    pub fn load(&mut self, other: &Grid) {
+        self.width = other.width;
+        self.height = other.height;
+        self.inner = other.inner.clone();
+        self.rows = other.rows.clone();
+        self.dirty.invalidate();
+
+        let (x, y) = self.cursor;
+        self.cursor = (
+            x.min(self.width.saturating_sub(1)),
+            y.min(self.height.saturating_sub(1)),
+        );
+    }
+
+    /// Extends the grid so that it contains the given position
+    pub fn grow_to(&mut self, x: usize, y: usize) {
+        while x >= self.width {
+            self.add_column();
+        }
+
+        while y >= self.height {
+            self.add_line(None);
+        }
+    }
+
+    /// Copies the non-empty cells of `other` with its top-left corner at `(x, y)`, growing the
+    /// grid as needed. Fails on the first cell both grids have code in, leaving the grid as is.
+    pub fn paste(&mut self, other: &Grid, (x, y): (usize, usize)) -> Result<(), (usize, usize)> {
+        let cells = (0..other.height)
+            .flat_map(|dy| (0..other.width).map(move |dx| (dx, dy)))
+            .map(|(dx, dy)| ((x + dx, y + dy), other.get(dx, dy)))
+            .filter(|(_, cell)| !matches!(cell.value, CellValue::Empty))
+            .collect::<Vec<_>>();
+
+        if let Some((position, _)) = cells.iter().find(|((x, y), _)| {
+            self.try_get(*x, *y)
+                .is_some_and(|cell| !matches!(cell.value, CellValue::Empty))
+        }) {
+            return Err(*position);
+        }
+
+        if other.width > 0 && other.height > 0 {
+            self.grow_to(x + other.width - 1, y + other.height - 1);
+        }
+        for ((x, y), cell) in cells {
+            self.set(x, y, cell.value);
+        }
+        Ok(())
+    }
+
+    /// Resizes the grid to exactly `width` by `height`, padding it with empty cells or dropping
+    /// those past the new edges. Returns the number of non-empty cells dropped.
+    pub fn fit(&mut self, width: usize, height: usize) -> usize {
+        let mut dropped = 0;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if (x >= width || y >= height)
+                    && !matches!(self.inner.get(x, y).value, CellValue::Empty)
+                {
+                    self.inner.set(x, y, Cell::from(CellValue::Empty));
+                    dropped += 1;
+                }
+            }
+        }
+
+        self.width = width;
+        self.height = height;
+        self.dirty.invalidate();
+        if self.rows.is_some() {
+            self.set_ragged(true);
+        }
+
+        let (x, y) = self.cursor;
+        self.cursor = (
+            x.min(width.saturating_sub(1)),
+            y.min(height.saturating_sub(1)),
+        );
+
+        dropped
+    }
+
+    /// Screen coordinates of a cell when the grid is rendered in `area`
+    pub fn screen_position(area: Rect, (x, y): (usize, usize)) -> (u16, u16) {
+        (area.left() + 2 + 2 * x as u16, area.top() + 1 + y as u16)
+    }
+
+    /// Number of columns and rows that fit in `area`, grids can be much larger than the screen
+    pub fn visible(&self, area: Rect) -> (usize, usize) {
+        (
+            std::cmp::min(self.width, (area.width as usize).saturating_sub(3) / 2),
+            std::cmp::min(self.height, (area.height as usize).saturating_sub(2)),
+        )
+    }
+
+    /// Draws the lids and sides around the visible part of the grid
+    pub fn render_frame(&self, area: Rect, buf: &mut Buffer) {
+        let width = std::cmp::min(2 * self.width, area.width as usize - 2) as u32;
+        let height = std::cmp::min(self.height + 1, area.height as usize - 2) as u16;
+
+        let lid = self.lids.to_string().repeat(width as usize + 1);
+
+        let top_lid = format!(
+            "{}{lid}{}",
+            self.corners.map(|arr| arr[0]).unwrap_or(' '),
+            self.corners.map(|arr| arr[1]).unwrap_or(' ')
+        );
+
+        let bot_lid = format!(
+            "{}{lid}{}",
+            self.corners.map(|arr| arr[2]).unwrap_or(' '),
+            self.corners.map(|arr| arr[3]).unwrap_or(' ')
+        );
+
+        buf.set_string(area.left(), area.top(), top_lid.as_str(), Style::default());
+
+        let (columns, rows) = self.visible(area);
+        let side = self.sides.to_string();
+        for y in 0..rows as u16 {
+            buf.set_string(area.left(), area.top() + y + 1, &side, Style::default());
+            buf.set_string(
+                area.left() + 2 * columns as u16 + 2,
+                area.top() + y + 1,
+                &side,
+                Style::default(),
+            );
+        }
+
+        buf.set_string(
+            area.left(),
+            area.top() + height,
+            bot_lid.as_str(),
+            Style::default(),
+        );
+    }
+
+    /// Draws a single cell, if visible
+    pub fn render_cell(&self, area: Rect, buf: &mut Buffer, (x, y): (usize, usize)) {
+        let (columns, rows) = self.visible(area);
+        if x >= columns || y >= rows {
+            return;
+        }
+
+        let (screen_x, screen_y) = Self::screen_position(area, (x, y));
+        let value = char::from(self.inner.get(x, y).value);
+        if self.numeric {
+            for (dx, c) in compact(value).into_iter().enumerate() {
+                let cell = buf.get_mut(screen_x + dx as u16, screen_y);
+                cell.reset();
+                cell.set_char(c);
             }
+            return;
+        }
+        let cell = buf.get_mut(screen_x, screen_y);
+        cell.reset();
+        cell.set_char(value);
+    }
 
-            self.inner.push(line);
+    /// Draws cells as their values, see [compact], rather than as characters.
+    pub fn set_numeric(&mut self, numeric: bool) {
+        if self.numeric != numeric {
+            self.numeric = numeric;
+            self.dirty.invalidate();
+        }
+    }
+
+    /// Draws the blinking cursor on top of the rendered grid
+    pub fn render_cursor(&self, area: Rect, buf: &mut Buffer) {
+        let (columns, rows) = self.visible(area);
+        if self.cursor.0 >= columns || self.cursor.1 >= rows {
+            return;
+        }
+
+        let (x, y) = Self::screen_position(area, self.cursor);
+        let mut val = buf.get(x, y).symbol.clone();
+        if self.numeric {
+            val += &buf.get(x + 1, y).symbol;
+        }
+        let blink = self.last_move.elapsed() < Duration::from_millis(500)
+            || SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs()
+                .is_multiple_of(2);
+
+        buf.set_string(
+            x,
+            y,
+            val,
+            Style::default()
+                .fg(if blink { Color::Black } else { Color::Cyan })
+                .bg(if blink { Color::Cyan } else { Color::Black })
+                .add_modifier(Modifier::SLOW_BLINK | Modifier::BOLD),
+        );
+    }
+
+    /// Returns the cells whose value or last visit changed since the last call, and forgets them
+    pub fn take_changes(&mut self) -> Changes {
+        let dirty = std::mem::replace(
+            &mut self.dirty,
+            Dirty {
+                all: false,
+                cells: HashSet::new(),
+            },
+        );
+
+        if dirty.all {
+            Changes::All
         } else {
-            self.inner.push(vec![CellValue::Empty.into(); self.width]);
+            Changes::Cells(dirty.cells.into_iter().collect())
         }
     }
 
+    /// Considers the whole grid changed
+    pub fn invalidate(&mut self) {
+        self.dirty.invalidate();
+    }
+
     /// Moves cursor by an offset, possibly extending the grid to the right
     pub fn move_cursor(&mut self, x: i32, y: i32) -> Result<(), (i32, i32)> {
         let (og_x, og_y) = self.cursor;
@@ -189,7 +535,7 @@ impl Grid {
     pub fn set_cursor(&mut self, x: usize, y: usize) -> Result<(), (usize, usize)> {
         self.last_move = Instant::now();
 
-        if !(0..(self.width.into())).contains(&x) || !(0..(self.height.into())).contains(&y) {
+        if !(0..self.width).contains(&x) || !(0..self.height).contains(&y) {
             return Err((x, y));
         }
 
@@ -208,9 +554,22 @@ impl Grid {
         (self.width, self.height)
     }
 
+    /// Text of each row, without trailing spaces.
+    pub fn lines(&self) -> Vec<String> {
+        (0..self.height)
+            .map(|y| {
+                let row = (0..self.width)
+                    .map(|x| char::from(self.get(x, y).value))
+                    .collect::<String>();
+                row.trim_end().to_owned()
+            })
+            .collect()
+    }
+
     /// Completely clears grid
     pub fn clear(&mut self) {
-        self.inner = vec![vec![CellValue::Empty.into(); self.width]; self.height];
+        self.inner = Chunks::default();
+        self.dirty.invalidate();
     }
 
     /// Set characters for lids and walls
@@ -223,18 +582,199 @@ impl Grid {
     #[inline]
     /// Get cell value at position
     pub fn get(&self, x: usize, y: usize) -> Cell {
-        self.inner.get(y).unwrap()[x]
+        self.inner.get(x, y)
+    }
+
+    /// Get cell value at position, if inside the grid
+    pub fn try_get(&self, x: usize, y: usize) -> Option<Cell> {
+        (x < self.width && y < self.height).then(|| self.inner.get(x, y))
     }
 
     #[inline]
     /// Set cell at position to desired value
     pub fn set(&mut self, x: usize, y: usize, val: CellValue) {
-        self.inner.get_mut(y).unwrap()[x].value = val;
+        let cell = self.inner.get(x, y);
+        self.set_cell(x, y, Cell { value: val, ..cell });
+    }
+
+    /// Set cell at position, last visit included
+    pub fn set_cell(&mut self, x: usize, y: usize, cell: Cell) {
+        assert!(
+            x < self.width && y < self.height,
+            "({x}, {y}) is outside of the grid"
+        );
+
+        self.inner.set(x, y, cell);
+        self.dirty.mark((x, y));
+
+        if let Some(length) = self.rows.as_mut().and_then(|rows| rows.get_mut(y)) {
+            if !matches!(cell.value, CellValue::Empty) {
+                *length = (*length).max(x + 1);
+            }
+        }
     }
 
-    /// Set cell under cursor to desired value
+    /// Set cell at position to a value typed or pasted by hand
+    pub fn edit(&mut self, x: usize, y: usize, val: CellValue) {
+        let cell = self.inner.get(x, y);
+        self.set_cell(
+            x,
+            y,
+            Cell {
+                value: val,
+                origin: Origin::Edited,
+                ..cell
+            },
+        );
+    }
+
+    /// Set cell under cursor to a value typed by hand
     pub fn set_current(&mut self, val: CellValue) {
         let (x, y) = self.cursor;
-        self.set(x, y, val);
+        self.edit(x, y, val);
+    }
+
+    /// Position of the next cell in a direction, wrapping around edges
+    pub fn neighbour(&self, (x, y): (usize, usize), direction: Direction) -> (usize, usize) {
+        let (width, height) = (self.row_width(y), self.height);
+
+        // Past the end of a ragged line, horizontal moves wrap straight back into it
+        match direction {
+            Direction::Up => (x, (y + height - 1) % height),
+            Direction::Down => (x, (y + 1) % height),
+            Direction::Left if x == 0 || x >= width => (width - 1, y),
+            Direction::Left => (x - 1, y),
+            Direction::Right | Direction::Random if x + 1 >= width => (0, y),
+            Direction::Right | Direction::Random => (x + 1, y),
+        }
+    }
+
+    /// Number of moves in a direction from a position to a target, wrapping as
+    /// [Grid::neighbour] does.
+    pub fn distance(
+        &self,
+        (x, y): (usize, usize),
+        target: (usize, usize),
+        direction: Direction,
+    ) -> usize {
+        let (width, height) = (self.row_width(y), self.height);
+
+        match direction {
+            Direction::Right | Direction::Random if x >= width => target.0 + 1,
+            Direction::Right | Direction::Random => (target.0 + width - x) % width,
+            Direction::Left if x >= width => width - target.0,
+            Direction::Left => (x + width - target.0) % width,
+            Direction::Down => (target.1 + height - y) % height,
+            Direction::Up => (y + height - target.1) % height,
+        }
+    }
+
+    /// Position of the first non-empty cell met when moving from a position, wrapping around.
+    /// Can be the position itself, `None` if the whole line is empty.
+    pub fn next_occupied(
+        &self,
+        position: (usize, usize),
+        direction: Direction,
+    ) -> Option<(usize, usize)> {
+        self.inner.next_occupied(position, direction)
+    }
+
+    /// Mark cell at position as visited at `tick`
+    pub fn visit(&mut self, x: usize, y: usize, tick: u64) {
+        self.inner.get_mut(x, y).last_visit = Some(tick);
+        self.dirty.mark((x, y));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn changes() {
+        let mut grid = Grid::from("v\n@".to_owned());
+        assert_eq!(grid.take_changes(), Changes::All);
+        assert_eq!(grid.take_changes(), Changes::Cells(vec![]));
+
+        grid.set(0, 1, CellValue::from('.'));
+        grid.visit(0, 0, 0);
+        let Changes::Cells(mut cells) = grid.take_changes() else {
+            panic!("expected cell changes");
+        };
+        cells.sort();
+        assert_eq!(cells, vec![(0, 0), (0, 1)]);
+
+        grid.add_column();
+        assert_eq!(grid.take_changes(), Changes::All);
+    }
+
+    #[test]
+    fn numeric() {
+        assert_eq!(compact('@'), ['4', '0']);
+        assert_eq!(compact('\n'), ['0', 'a']);
+        assert_eq!(compact(' '), [' ', ' ']);
+        assert_eq!(compact('\u{1f600}'), ['+', '+']);
+
+        let mut grid = Grid::from("@\u{e9}".to_owned());
+        grid.take_changes();
+        grid.set_numeric(true);
+        assert_eq!(grid.take_changes(), Changes::All);
+        grid.set_numeric(true);
+        assert_eq!(grid.take_changes(), Changes::Cells(vec![]));
+
+        let area = Rect::new(0, 0, 10, 4);
+        let mut buf = Buffer::empty(area);
+        grid.render(area, &mut buf);
+        let row = (2..6)
+            .map(|x| buf.get(x, 1).symbol.clone())
+            .collect::<String>();
+        assert_eq!(row, "40e9");
+    }
+
+    #[test]
+    fn open() {
+        let program = ">1.v\r\n\n@ ,<\n";
+        let path = std::env::temp_dir().join(format!("puccinia-open-{}.bf", std::process::id()));
+        std::fs::write(&path, program).unwrap();
+
+        let opened = Grid::open(&path).unwrap();
+        let parsed = Grid::from(program.to_owned());
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(opened.size(), parsed.size());
+        for y in 0..parsed.height {
+            for x in 0..parsed.width {
+                assert_eq!(opened.get(x, y).value, parsed.get(x, y).value);
+            }
+        }
+    }
+
+    #[test]
+    fn fit() {
+        let mut grid = Grid::from(">1.v\n@ ,<".to_owned());
+        assert_eq!(grid.fit(80, 25), 0);
+        assert_eq!(grid.size(), (80, 25));
+        assert_eq!(grid.neighbour((79, 0), Direction::Right), (0, 0));
+
+        assert_eq!(grid.fit(2, 1), 5);
+        assert_eq!(grid.size(), (2, 1));
+        grid.fit(4, 2);
+        assert!(matches!(grid.get(3, 1).value, CellValue::Empty));
+    }
+
+    #[test]
+    fn ragged() {
+        let mut grid = Grid::from(">12\n\n3456".to_owned());
+        assert_eq!(grid.neighbour((2, 0), Direction::Right), (3, 0));
+
+        grid.set_ragged(true);
+        assert_eq!(grid.neighbour((2, 0), Direction::Right), (0, 0));
+        assert_eq!(grid.neighbour((0, 0), Direction::Left), (2, 0));
+        assert_eq!(grid.neighbour((3, 0), Direction::Left), (2, 0));
+        assert_eq!(grid.neighbour((3, 2), Direction::Right), (0, 2));
+        assert_eq!(grid.distance((3, 0), (1, 0), Direction::Right), 2);
+
+        grid.set(3, 0, CellValue::from('7'));
+        assert_eq!(grid.neighbour((2, 0), Direction::Right), (3, 0));
     }
 }
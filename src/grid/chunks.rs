@@ -0,0 +1,172 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cell::{Cell, CellValue, Direction, Origin};
+
+use super::occupancy::Occupancy;
+
+/// Side of the square chunks cells are stored in.
+const CHUNK_SIZE: usize = 32;
+
+const EMPTY: Cell = Cell {
+    value: CellValue::Empty,
+    last_visit: None,
+    origin: Origin::Loaded,
+};
+
+/// Dense block of cells.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Chunk {
+    cells: Vec<Cell>,
+}
+
+impl Default for Chunk {
+    fn default() -> Self {
+        Self {
+            cells: vec![EMPTY; CHUNK_SIZE * CHUNK_SIZE],
+        }
+    }
+}
+
+type Row = Vec<Option<Arc<Chunk>>>;
+
+/// Sparse cell storage, chunks that only ever held empty cells are not allocated.
+/// Rows of chunks and chunks are shared between clones until written to, so that a clone costs
+/// memory proportional to what changes afterwards.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(from = "Vec<Arc<Row>>", into = "Vec<Arc<Row>>")]
+pub(super) struct Chunks {
+    rows: Vec<Arc<Row>>,
+    occupancy: Occupancy,
+}
+
+impl From<Vec<Arc<Row>>> for Chunks {
+    fn from(rows: Vec<Arc<Row>>) -> Self {
+        let mut occupancy = Occupancy::default();
+        for (cy, row) in rows.iter().enumerate() {
+            for (cx, chunk) in row.iter().enumerate() {
+                for (index, cell) in chunk
+                    .iter()
+                    .flat_map(|chunk| chunk.cells.iter().enumerate())
+                {
+                    if !matches!(cell.value, CellValue::Empty) {
+                        occupancy.insert(position((cx, cy), index));
+                    }
+                }
+            }
+        }
+
+        Self { rows, occupancy }
+    }
+}
+
+impl From<Chunks> for Vec<Arc<Row>> {
+    fn from(chunks: Chunks) -> Self {
+        chunks.rows
+    }
+}
+
+/// Chunk coordinates and index inside of the chunk of a cell.
+fn locate(x: usize, y: usize) -> ((usize, usize), usize) {
+    (
+        (x / CHUNK_SIZE, y / CHUNK_SIZE),
+        (y % CHUNK_SIZE) * CHUNK_SIZE + x % CHUNK_SIZE,
+    )
+}
+
+/// Position of a cell from its chunk coordinates and index inside of the chunk.
+fn position((cx, cy): (usize, usize), index: usize) -> (usize, usize) {
+    (
+        cx * CHUNK_SIZE + index % CHUNK_SIZE,
+        cy * CHUNK_SIZE + index / CHUNK_SIZE,
+    )
+}
+
+impl Chunks {
+    pub fn get(&self, x: usize, y: usize) -> Cell {
+        let ((cx, cy), index) = locate(x, y);
+
+        self.rows
+            .get(cy)
+            .and_then(|row| row.get(cx))
+            .and_then(Option::as_ref)
+            .map_or(EMPTY, |chunk| chunk.cells[index])
+    }
+
+    /// Mutable access to a cell, allocating its chunk if needed.
+    /// Values must be changed through [Chunks::set] to keep the occupancy index up to date.
+    pub fn get_mut(&mut self, x: usize, y: usize) -> &mut Cell {
+        let ((cx, cy), index) = locate(x, y);
+
+        if self.rows.len() <= cy {
+            self.rows.resize_with(cy + 1, Default::default);
+        }
+        let row = Arc::make_mut(&mut self.rows[cy]);
+        if row.len() <= cx {
+            row.resize_with(cx + 1, || None);
+        }
+
+        let chunk = row[cx].get_or_insert_with(Default::default);
+        &mut Arc::make_mut(chunk).cells[index]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, cell: Cell) {
+        let ((cx, cy), _) = locate(x, y);
+        let allocated = self
+            .rows
+            .get(cy)
+            .and_then(|row| row.get(cx))
+            .is_some_and(Option::is_some);
+
+        let empty = matches!(cell.value, CellValue::Empty) && cell.origin == Origin::Loaded;
+        if allocated || !empty || cell.last_visit.is_some() {
+            *self.get_mut(x, y) = cell;
+        }
+
+        if matches!(cell.value, CellValue::Empty) {
+            self.occupancy.remove((x, y));
+        } else {
+            self.occupancy.insert((x, y));
+        }
+    }
+
+    /// First non-empty cell met when moving from a position, see [Occupancy::next].
+    pub fn next_occupied(
+        &self,
+        position: (usize, usize),
+        direction: Direction,
+    ) -> Option<(usize, usize)> {
+        self.occupancy.next(position, direction)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sparse() {
+        let mut chunks = Chunks::default();
+        chunks.set(10_000, 10_000, Cell::from('@'));
+        chunks.set(5_000, 5_000, Cell::from(' '));
+
+        assert_eq!(
+            chunks
+                .rows
+                .iter()
+                .flat_map(|row| row.iter())
+                .flatten()
+                .count(),
+            1
+        );
+        assert_eq!(char::from(chunks.get(10_000, 10_000).value), '@');
+        assert_eq!(char::from(chunks.get(9_999, 10_000).value), ' ');
+        assert_eq!(char::from(chunks.get(50_000, 0).value), ' ');
+
+        let copy = chunks.clone();
+        chunks.set(10_001, 10_000, Cell::from('.'));
+        assert_eq!(char::from(copy.get(10_001, 10_000).value), ' ');
+        assert_eq!(char::from(chunks.get(10_001, 10_000).value), '.');
+    }
+}
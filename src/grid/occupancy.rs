@@ -0,0 +1,67 @@
+use std::{collections::BTreeSet, sync::Arc};
+
+use crate::cell::Direction;
+
+/// Positions of non-empty cells, indexed by row and by column.
+/// Lines are shared between clones until they change.
+#[derive(Clone, Debug, Default)]
+pub(super) struct Occupancy {
+    rows: Vec<Arc<BTreeSet<usize>>>,
+    columns: Vec<Arc<BTreeSet<usize>>>,
+}
+
+fn line(lines: &mut Vec<Arc<BTreeSet<usize>>>, index: usize) -> &mut BTreeSet<usize> {
+    if lines.len() <= index {
+        lines.resize_with(index + 1, Default::default);
+    }
+    Arc::make_mut(&mut lines[index])
+}
+
+impl Occupancy {
+    fn contains(&self, (x, y): (usize, usize)) -> bool {
+        self.rows.get(y).is_some_and(|row| row.contains(&x))
+    }
+
+    pub fn insert(&mut self, (x, y): (usize, usize)) {
+        if !self.contains((x, y)) {
+            line(&mut self.rows, y).insert(x);
+            line(&mut self.columns, x).insert(y);
+        }
+    }
+
+    pub fn remove(&mut self, (x, y): (usize, usize)) {
+        if self.contains((x, y)) {
+            line(&mut self.rows, y).remove(&x);
+            line(&mut self.columns, x).remove(&y);
+        }
+    }
+
+    /// First non-empty cell met when moving from `position` in `direction`, wrapping around.
+    /// Can be `position` itself if it is the only non-empty cell of its line.
+    pub fn next(&self, (x, y): (usize, usize), direction: Direction) -> Option<(usize, usize)> {
+        fn get(lines: &[Arc<BTreeSet<usize>>], index: usize) -> &BTreeSet<usize> {
+            static EMPTY: BTreeSet<usize> = BTreeSet::new();
+            lines.get(index).map_or(&EMPTY, |line| line.as_ref())
+        }
+
+        let forward = |line: &BTreeSet<usize>, from: usize| {
+            line.range(from + 1..)
+                .next()
+                .or_else(|| line.range(..=from).next())
+                .copied()
+        };
+        let backward = |line: &BTreeSet<usize>, from: usize| {
+            line.range(..from)
+                .next_back()
+                .or_else(|| line.range(from..).next_back())
+                .copied()
+        };
+
+        match direction {
+            Direction::Right | Direction::Random => forward(get(&self.rows, y), x).map(|x| (x, y)),
+            Direction::Left => backward(get(&self.rows, y), x).map(|x| (x, y)),
+            Direction::Down => forward(get(&self.columns, x), y).map(|y| (x, y)),
+            Direction::Up => backward(get(&self.columns, x), y).map(|y| (x, y)),
+        }
+    }
+}
@@ -0,0 +1,483 @@
+use std::{
+    sync::mpsc::{Receiver, Sender, TryRecvError},
+    time::Duration,
+};
+
+use eframe::egui::{
+    self, Align2, Color32, FontId, Key, Pos2, Rect, Sense, Stroke, StrokeKind, Vec2,
+    ViewportCommand,
+};
+
+use puccinia::{
+    cell::CellValue,
+    debugger::Target,
+    grid::Grid,
+    interpreter::Status,
+    narrator::{self, Observation},
+};
+
+use crate::{
+    frontend::{Message, RunState},
+    logic::{self, RunningCommand},
+};
+
+/// Size of a cell at the default zoom, in points.
+const CELL: f32 = 18.0;
+
+/// Below this size cells are drawn as blocks rather than characters.
+const MIN_TEXT_SIZE: f32 = 6.0;
+
+const FRAME: Duration = Duration::from_millis(33);
+
+/// Glyphs marking increasingly hot cells in accessible mode.
+const HEAT_GLYPHS: [char; 3] = ['·', '○', '●'];
+
+/// Desktop counterpart of the TUI, driven by the same logic thread and messages. `accessible`
+/// switches to high-contrast colours, heat glyphs and a narration of the run.
+pub(crate) fn run(
+    receiver: Receiver<Message>,
+    sender: Sender<logic::Message>,
+    accessible: bool,
+) -> anyhow::Result<()> {
+    let app = App {
+        receiver,
+        sender,
+        grid: Grid::new(10, 10),
+        run: RunState::default(),
+        output: String::new(),
+        cursor: None,
+        zoom: 1.0,
+        pan: Vec2::splat(CELL),
+        input: String::new(),
+        watch: String::new(),
+        error: None,
+        accessible,
+        narration: String::new(),
+    };
+
+    eframe::run_native(
+        "mst",
+        eframe::NativeOptions::default(),
+        Box::new(move |creation| {
+            if accessible {
+                creation.egui_ctx.set_visuals(high_contrast());
+            }
+            Ok(Box::new(app))
+        }),
+    )
+    .map_err(|err| anyhow::anyhow!("GUI failure: {err}"))
+}
+
+struct App {
+    receiver: Receiver<Message>,
+    sender: Sender<logic::Message>,
+    grid: Grid,
+    run: RunState,
+    output: String,
+    /// Cell being edited
+    cursor: Option<(usize, usize)>,
+    zoom: f32,
+    /// Offset of the grid's top left corner in the view
+    pan: Vec2,
+    /// Line being typed for the program's input
+    input: String,
+    /// Expression being typed for the watches
+    watch: String,
+    error: Option<String>,
+    accessible: bool,
+    /// Description of the latest step of the run, in accessible mode
+    narration: String,
+}
+
+/// Dark visuals with white text and strong outlines.
+fn high_contrast() -> egui::Visuals {
+    let mut visuals = egui::Visuals::dark();
+    visuals.override_text_color = Some(Color32::WHITE);
+    visuals.panel_fill = Color32::BLACK;
+    visuals.window_fill = Color32::BLACK;
+    visuals.extreme_bg_color = Color32::BLACK;
+    for widget in [
+        &mut visuals.widgets.inactive,
+        &mut visuals.widgets.hovered,
+        &mut visuals.widgets.active,
+    ] {
+        widget.bg_stroke = Stroke::new(2.0, Color32::WHITE);
+    }
+    visuals
+}
+
+impl Drop for App {
+    fn drop(&mut self) {
+        let _ = self.sender.send(logic::Message::Kill);
+    }
+}
+
+impl eframe::App for App {
+    fn ui(&mut self, ui: &mut egui::Ui, _frame: &mut eframe::Frame) {
+        self.receive(ui.ctx());
+        ui.ctx().request_repaint_after(FRAME);
+
+        egui::Panel::right("run")
+            .default_size(260.0)
+            .show(ui, |ui| self.side_panel(ui));
+        egui::CentralPanel::default().show(ui, |ui| self.grid_view(ui));
+    }
+}
+
+impl App {
+    fn receive(&mut self, ctx: &egui::Context) {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(Message::Load(grid)) => self.grid.load(&grid),
+                Ok(Message::Break) | Err(TryRecvError::Disconnected) => {
+                    ctx.send_viewport_cmd(ViewportCommand::Close);
+                    break;
+                }
+                Ok(Message::LogicFail(error)) => self.error = error,
+                Ok(Message::PopupToggle(_))
+                | Ok(Message::Cursors(_))
+                | Ok(Message::Bookmarks(_))
+                | Ok(Message::Annotations(_))
+                | Ok(Message::Labels(_))
+                | Ok(Message::SourceMap(_))
+                | Ok(Message::Strings(_))
+                | Ok(Message::Quotes(_))
+                | Ok(Message::Register(..))
+                | Ok(Message::Buffers(..))
+                | Ok(Message::References(..))
+                | Ok(Message::Layout(..)) => (),
+                Ok(Message::SetCell { x, y, v }) => {
+                    self.grid.grow_to(x, y);
+                    self.grid.edit(x, y, CellValue::from(v));
+                }
+                Ok(Message::Patch(cells)) => {
+                    for ((x, y), cell) in cells {
+                        self.grid.set_cell(x, y, cell);
+                    }
+                }
+                Ok(Message::Running(run)) => {
+                    if let Some(kept) = run.rewound {
+                        let end = self
+                            .output
+                            .char_indices()
+                            .nth(kept)
+                            .map_or(self.output.len(), |(index, _)| index);
+                        self.output.truncate(end);
+                    }
+                    self.output.push_str(&run.output);
+                    if self.accessible {
+                        self.narrate(&run);
+                    }
+                    self.run = *run;
+                }
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+    }
+
+    /// Describes what the run did since the last update.
+    fn narrate(&mut self, run: &RunState) {
+        let observe = |run: &RunState| {
+            let (x, y) = run.position;
+            Observation {
+                ticks: run.ticks,
+                position: run.position,
+                instruction: char::from(self.grid.get(x, y).value),
+                stack: run.stack.clone(),
+                status: run.status,
+            }
+        };
+
+        let after = observe(run);
+        let before = if self.run.active {
+            observe(&self.run)
+        } else {
+            Observation::default()
+        };
+        if before != after || self.narration.is_empty() {
+            self.narration = narrator::narrate(&before, &after);
+        }
+    }
+
+    fn send(&mut self, message: logic::Message) {
+        if self.sender.send(message).is_err() {
+            self.error = Some("Lost connection to logic".to_owned());
+        }
+    }
+
+    fn command(&mut self, command: RunningCommand) {
+        self.send(logic::Message::RunningCommand(command));
+    }
+
+    fn side_panel(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal_wrapped(|ui| {
+            if !self.run.active {
+                if ui.button("Run").clicked() {
+                    self.output.clear();
+                    self.command(RunningCommand::Start);
+                    self.command(RunningCommand::SkipToBreakpoint);
+                }
+                if ui.button("Debug").clicked() {
+                    self.output.clear();
+                    self.command(RunningCommand::Start);
+                }
+                return;
+            }
+
+            if self.run.running {
+                if ui.button("Pause").clicked() {
+                    self.command(RunningCommand::Pause);
+                }
+            } else if ui.button("Continue").clicked() {
+                self.command(RunningCommand::SkipToBreakpoint);
+            }
+            if ui.button("Step").clicked() {
+                self.command(RunningCommand::Step);
+            }
+            if ui.button("Exit loop").clicked() {
+                self.command(RunningCommand::RunTo(Target::LoopExit));
+            }
+            if ui.button("Finish string").clicked() {
+                self.command(RunningCommand::RunTo(Target::StringEnd));
+            }
+            if let Some(cursor) = self.cursor {
+                if ui.button("Run to selection").clicked() {
+                    self.command(RunningCommand::RunTo(Target::Cell(cursor)));
+                }
+            }
+            if ui.button("Stop").clicked() {
+                self.command(RunningCommand::Stop);
+            }
+        });
+
+        if let Some(error) = &self.error {
+            ui.colored_label(Color32::LIGHT_RED, error);
+        }
+
+        if self.run.active {
+            let status = match (self.run.status, self.run.exit_code) {
+                (Status::Running, _) if self.run.running => "running".to_owned(),
+                (Status::Running, _) => "paused".to_owned(),
+                (Status::WaitingForInput, _) => "waiting for input".to_owned(),
+                (Status::Terminated, Some(code)) => format!("exited with code {code}"),
+                (Status::Terminated, None) => "terminated".to_owned(),
+            };
+            ui.label(format!("Tick {}, {status}", self.run.ticks));
+            if self.accessible {
+                ui.label(&self.narration);
+            }
+
+            ui.horizontal(|ui| {
+                let edit = ui.text_edit_singleline(&mut self.input);
+                let entered = edit.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+                if ui.button("Send").clicked() || entered {
+                    let line = std::mem::take(&mut self.input) + "\n";
+                    self.command(RunningCommand::Input(line));
+                }
+            });
+
+            ui.separator();
+            ui.heading("Watches");
+            for watch in &self.run.watches {
+                ui.monospace(watch);
+            }
+            ui.horizontal(|ui| {
+                let edit = ui.text_edit_singleline(&mut self.watch);
+                let entered = edit.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+                if (ui.button("Watch").clicked() || entered) && !self.watch.trim().is_empty() {
+                    let expression = std::mem::take(&mut self.watch);
+                    self.send(logic::Message::Watch(expression));
+                }
+            });
+
+            ui.separator();
+            ui.heading("Stack");
+            for value in self.run.stack.iter().rev().take(32) {
+                ui.monospace(value.to_string());
+            }
+        }
+
+        ui.separator();
+        ui.heading("Output");
+        egui::ScrollArea::vertical()
+            .stick_to_bottom(true)
+            .show(ui, |ui| ui.monospace(&self.output));
+    }
+
+    fn grid_view(&mut self, ui: &mut egui::Ui) {
+        let (response, painter) = ui.allocate_painter(ui.available_size(), Sense::click_and_drag());
+        let origin = response.rect.min;
+
+        // Zooming keeps the cell under the pointer in place
+        if let Some(pointer) = response.hover_pos() {
+            let (zoom, scroll) = ui.input(|i| (i.zoom_delta(), i.smooth_scroll_delta));
+            if zoom != 1.0 {
+                let old = CELL * self.zoom;
+                self.zoom = (self.zoom * zoom).clamp(0.05, 8.0);
+                let world = (pointer - origin - self.pan) / old;
+                self.pan = pointer - origin - world * CELL * self.zoom;
+            } else {
+                self.pan += scroll;
+            }
+        }
+        self.pan += response.drag_delta();
+
+        let size = CELL * self.zoom;
+        let (width, height) = self.grid.size();
+        let cell_at = |pos: Pos2| {
+            let local = (pos - origin - self.pan) / size;
+            (local.x >= 0.0 && local.y >= 0.0)
+                .then_some((local.x as usize, local.y as usize))
+                .filter(|&(x, y)| x < width && y < height)
+        };
+
+        if let Some(position) = response.interact_pointer_pos().and_then(cell_at) {
+            if response.clicked() {
+                self.cursor = Some(position);
+            } else if response.secondary_clicked() {
+                let (x, y) = position;
+                self.command(RunningCommand::ToggleBreakpoint { x, y });
+            }
+        }
+
+        if !self.run.active {
+            self.edit(ui);
+        }
+
+        // Only visible cells are drawn, so that huge grids stay smooth
+        let first = |pan: f32| ((-pan / size).floor().max(0.0)) as usize;
+        let last = |pan: f32, extent: f32, count: usize| {
+            (((extent - pan) / size).ceil().max(0.0) as usize).min(count)
+        };
+        let columns = first(self.pan.x)..last(self.pan.x, response.rect.width(), width);
+        let rows = first(self.pan.y)..last(self.pan.y, response.rect.height(), height);
+
+        painter.rect_stroke(
+            Rect::from_min_size(
+                origin + self.pan,
+                Vec2::new(width as f32, height as f32) * size,
+            ),
+            0.0,
+            Stroke::new(1.0, Color32::DARK_GRAY),
+            StrokeKind::Outside,
+        );
+
+        for y in rows {
+            for x in columns.clone() {
+                let rect = Rect::from_min_size(
+                    origin + self.pan + Vec2::new(x as f32, y as f32) * size,
+                    Vec2::splat(size),
+                );
+                let cell = self.grid.get(x, y);
+
+                let heat = cell.heat(self.run.ticks);
+                if self.run.active && heat > 0 {
+                    if self.accessible {
+                        // Glyphs in the corner rather than shades that are hard to tell apart
+                        let level = (heat as usize * HEAT_GLYPHS.len() / (i8::MAX as usize + 1))
+                            .min(HEAT_GLYPHS.len() - 1);
+                        painter.text(
+                            rect.right_top(),
+                            Align2::RIGHT_TOP,
+                            HEAT_GLYPHS[level],
+                            FontId::monospace(size * 0.4),
+                            Color32::LIGHT_BLUE,
+                        );
+                    } else {
+                        let alpha = (heat as u16 * 2).min(255) as u8;
+                        painter.rect_filled(
+                            rect,
+                            0.0,
+                            Color32::from_rgba_unmultiplied(255, 80, 0, alpha),
+                        );
+                    }
+                }
+                let ip = self.run.active && self.run.position == (x, y);
+                if ip {
+                    let fill = if self.accessible {
+                        Color32::WHITE
+                    } else {
+                        Color32::YELLOW
+                    };
+                    painter.rect_filled(rect, 0.0, fill);
+                }
+
+                let c = char::from(cell.value);
+                let color = match (ip, self.accessible) {
+                    (true, _) => Color32::BLACK,
+                    (false, true) => Color32::WHITE,
+                    (false, false) => Color32::LIGHT_GRAY,
+                };
+                if size >= MIN_TEXT_SIZE {
+                    painter.text(
+                        rect.center(),
+                        Align2::CENTER_CENTER,
+                        c,
+                        FontId::monospace(size * 0.8),
+                        color,
+                    );
+                } else if c != ' ' {
+                    painter.rect_filled(rect, 0.0, color.gamma_multiply(0.5));
+                }
+
+                if self.run.breakpoints.contains(&(x, y)) {
+                    let stroke = if self.accessible {
+                        Stroke::new(3.0, Color32::YELLOW)
+                    } else {
+                        Stroke::new(2.0, Color32::RED)
+                    };
+                    painter.rect_stroke(rect, 0.0, stroke, StrokeKind::Inside);
+                }
+                if self.cursor == Some((x, y)) {
+                    painter.rect_stroke(
+                        rect,
+                        0.0,
+                        Stroke::new(1.5, Color32::WHITE),
+                        StrokeKind::Inside,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Typing writes to the selected cell and moves right, arrows move the selection.
+    fn edit(&mut self, ui: &mut egui::Ui) {
+        let Some((mut x, mut y)) = self.cursor else {
+            return;
+        };
+        // Keys belong to the input field when it has focus
+        if ui.memory(|memory| memory.focused().is_some()) {
+            return;
+        }
+
+        let events = ui.input(|i| i.events.clone());
+        for event in events {
+            match event {
+                egui::Event::Text(text) => {
+                    for v in text.chars() {
+                        self.grid.grow_to(x, y);
+                        self.grid.edit(x, y, CellValue::from(v));
+                        self.send(logic::Message::SetCell { x, y, v });
+                        x += 1;
+                    }
+                }
+                egui::Event::Key {
+                    key, pressed: true, ..
+                } => match key {
+                    Key::ArrowLeft => x = x.saturating_sub(1),
+                    Key::ArrowRight => x += 1,
+                    Key::ArrowUp => y = y.saturating_sub(1),
+                    Key::ArrowDown => y += 1,
+                    Key::Escape => {
+                        self.cursor = None;
+                        return;
+                    }
+                    _ => (),
+                },
+                _ => (),
+            }
+        }
+
+        self.cursor = Some((x, y));
+    }
+}
@@ -0,0 +1,981 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufWriter, IsTerminal, Write},
+    path::Path,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    terminal,
+};
+
+use puccinia::{
+    analyzer::Severity,
+    bundle::{self, Bundle},
+    cell::NullaryOperator,
+    config::Config,
+    debugger::{Debugger, Stop, DEFAULT_HISTORY},
+    dialect::{Dialect, Extension},
+    directives::{self, Directives, Lines, Normalized, Sizing, Source, Tabs},
+    frames::{self, Dumper},
+    grid::Grid,
+    interpreter::{Flush, Interpreter, Schedule, Status},
+    narrator::{self, Observation},
+    renderer::{self, Control, Frame, Renderer},
+    report::{Entry, Report},
+    script::{self, Player, Script},
+    trace::Step,
+};
+
+use crate::{
+    batch,
+    expect::{self, Expectation},
+    fetch, remote,
+    settings::Semantics,
+    sound::{self, Sound},
+    summary::Summary,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Could not load `{0}`: {1}")]
+    Load(String, directives::Error),
+    #[error("Could not load bundle `{0}`: {1}")]
+    Bundle(String, bundle::Error),
+    #[error(transparent)]
+    Fetch(#[from] fetch::Error),
+    #[error(transparent)]
+    Sound(#[from] sound::Error),
+    #[error(transparent)]
+    Expect(#[from] expect::Error),
+    #[error("Could not load input script `{0}`: {1}")]
+    Script(String, script::Error),
+    #[error("Program requested input at tick {tick}, cell ({}, {}), with none left", .position.0, .position.1)]
+    EndOfInput { tick: u64, position: (usize, usize) },
+    #[error("Could not write statistics to `{0}`: {1}")]
+    Statistics(String, std::io::Error),
+    #[error("Could not write loops to `{0}`: {1}")]
+    Loops(String, std::io::Error),
+    #[error("Could not write heatmap to `{0}`: {1}")]
+    Heatmap(String, std::io::Error),
+    #[error("Could not write summary to `{0}`: {1}")]
+    Summary(String, std::io::Error),
+    #[error("Could not read input from `{0}`: {1}")]
+    Input(String, std::io::Error),
+    #[error("Could not write input to `{0}`: {1}")]
+    SaveInput(String, std::io::Error),
+    #[error("Could not write trace to `{0}`: {1}")]
+    Trace(String, std::io::Error),
+    #[error("Could not write diagnostics to `{0}`: {1}")]
+    Diagnostics(String, std::io::Error),
+    #[error("`--{0}` only applies to a single program")]
+    SingleProgram(&'static str),
+    #[error("{0} of {1} programs failed")]
+    Failures(usize, usize),
+    #[error("Output differs from the expected output, got {0:?}")]
+    UnexpectedOutput(String),
+}
+
+type Result<T> = anyhow::Result<T>;
+
+#[derive(clap::Args)]
+pub(crate) struct Options {
+    /// Program file locations or URLs, several programs being run concurrently. Bundles created
+    /// by `pack` bring their own input and settings
+    #[arg(required = true, value_name = "INPUT")]
+    inputs: Vec<String>,
+
+    /// Number of programs run at the same time, defaults to the number of CPUs
+    #[arg(long, value_name = "N")]
+    jobs: Option<usize>,
+
+    /// Wait for a frontend to attach on this address and let it drive execution
+    #[arg(long, value_name = "ADDRESS")]
+    debug_listen: Option<String>,
+
+    /// Number of executed instructions shown in the backtrace when the program fails
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_HISTORY)]
+    history: usize,
+
+    /// Print the instruction mix to stderr once the program ends
+    #[arg(long)]
+    stats: bool,
+
+    /// Write the instruction mix as JSON to this file once the program ends
+    #[arg(long, value_name = "PATH")]
+    stats_json: Option<String>,
+
+    /// Print ticks executed, wall time, output bytes, deepest stack, grid writes and why the
+    /// program ended to stderr once it ends
+    #[arg(long)]
+    summary: bool,
+
+    /// Write the same summary as `--summary` as JSON to this file once the program ends, even
+    /// when it failed
+    #[arg(long, value_name = "PATH")]
+    summary_json: Option<String>,
+
+    /// Print the loops the program spent its time in to stderr once it ends
+    #[arg(long)]
+    loops: bool,
+
+    /// Write the loops as folded stacks, as taken by flamegraph tools, to this file once the
+    /// program ends
+    #[arg(long, value_name = "PATH")]
+    loops_folded: Option<String>,
+
+    /// Write per-cell execution counts and last visit ticks to this file once the program ends,
+    /// as CSV if it ends in `.csv` and JSON otherwise
+    #[arg(long, value_name = "PATH")]
+    heatmap: Option<String>,
+
+    /// When program output is written: `tick`, `newline`, `input` or a number of buffered bytes.
+    /// Output is always written when the program waits for input or ends
+    #[arg(long, value_name = "POLICY", default_value = "4096")]
+    flush: Flush,
+
+    /// Write everything the program read with `&` and `~` to this file once it ends, to replay
+    /// an interactive session with `run < PATH`
+    #[arg(long, value_name = "PATH")]
+    save_input: Option<String>,
+
+    /// Write input to the output as the program reads it, so that output piped elsewhere reads
+    /// like the session did
+    #[arg(long)]
+    echo_input: bool,
+
+    /// Compare output with the content of this file as it is written, stopping the program at the
+    /// first difference and showing the tick it happened at along with the lines around it. Runs
+    /// one instruction at a time
+    #[arg(long, value_name = "PATH")]
+    expect: Option<String>,
+
+    /// Write every executed instruction to this file as a line of JSON, along with the stack it
+    /// left, for comparing runs with `trace-diff`. Runs one instruction at a time
+    #[arg(long, value_name = "PATH")]
+    trace: Option<String>,
+
+    /// Write why the run failed to this file for editors and CI to annotate the program with, as
+    /// JSON or as SARIF if it ends with `.sarif`. Runs that succeed write an empty list
+    #[arg(long, value_name = "PATH")]
+    diagnostics: Option<String>,
+
+    /// Write frames of the run to this directory as it goes, for assembling videos with external
+    /// tools, named `frame-000000.ans` or `.png` and so on
+    #[arg(long, value_name = "DIRECTORY")]
+    dump_frames: Option<String>,
+
+    /// Ticks between two dumped frames, the last one being written whenever the run ends
+    #[arg(long, value_name = "N", default_value_t = 1, requires = "dump_frames")]
+    every: u64,
+
+    /// What dumped frames are: `ansi` text showing the grid, the stack and the end of the output,
+    /// or `png` images drawing cells as blocks coloured after what they hold
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        default_value = "ansi",
+        requires = "dump_frames"
+    )]
+    frame_format: frames::Format,
+
+    /// Semantics to follow: `befunge93`, or the quirks of the intermediate `befunge96` and
+    /// `befunge97` revisions. Defaults to the program's `dialect` directive, then to the settings,
+    /// or `befunge93`
+    #[arg(long, value_name = "DIALECT")]
+    dialect: Option<Dialect>,
+
+    /// Opt-in extension to enable on top of the program's `extensions` directive, can be
+    /// repeated: `multi-digit`, `concurrent`, `system-info`, `quit` or `assert`. Defaults to
+    /// those of the settings for programs enabling none
+    #[arg(long, value_name = "EXTENSION")]
+    extension: Vec<Extension>,
+
+    /// Which instruction pointer runs next with the concurrent extension: `round-robin`,
+    /// `random`, or `weighted:W0,W1,...` to favour some by id, for finding out whether a program
+    /// relies on the order they take turns in. Defaults to the program's `schedule` directive, or
+    /// `round-robin`
+    #[arg(long, value_name = "POLICY")]
+    schedule: Option<Schedule>,
+
+    #[command(flatten)]
+    geometry: Geometry,
+
+    #[command(flatten)]
+    system: System,
+
+    /// Feed the content of this file as input rather than stdin, overriding that of bundles and of
+    /// the configuration. The program fails as soon as it asks for more
+    #[arg(long = "input", value_name = "PATH", conflicts_with = "input_script")]
+    input_file: Option<String>,
+
+    /// Seconds to wait for a line of stdin when the program asks for input, after which it fails
+    /// rather than blocking, for CI runs whose stdin never closes
+    #[arg(long, value_name = "SECONDS")]
+    input_timeout: Option<u64>,
+
+    /// Feed input from this script rather than stdin, with lines such as `after 500 ticks: 5\n`,
+    /// `after 250 ms: q` or `now: abc`
+    #[arg(long, value_name = "PATH")]
+    input_script: Option<String>,
+
+    /// Make `~` read a single keypress as soon as it is pressed rather than a whole line, for
+    /// interactive programs such as games. Only applies when stdin is a terminal
+    #[arg(long)]
+    raw_input: bool,
+
+    /// Describe every executed instruction and stack change to stderr in plain sentences, for
+    /// following a run with a screen reader
+    #[arg(long)]
+    narrate: bool,
+
+    /// Play executed instructions or output bytes as tones, when built with the `sound`
+    /// feature: `instructions` or `output`
+    #[arg(long, value_name = "MODE")]
+    sound: Option<sound::Mode>,
+}
+
+/// Command line and environment variables programs are given, which they read with `y`.
+#[derive(clap::Args, Clone, Debug, Default)]
+pub(crate) struct System {
+    /// Environment variable to pass to programs, can be repeated, `PREFIX*` passing those
+    /// starting with `PREFIX`. None are passed by default
+    #[arg(long = "env", value_name = "NAME")]
+    allowed: Vec<String>,
+
+    /// Arguments to pass to programs after their name, written after `--`. Passing arguments or
+    /// environment variables enables the `system-info` extension
+    #[arg(last = true, value_name = "ARGS")]
+    arguments: Vec<String>,
+}
+
+impl System {
+    /// Gives `program` its name, arguments and allowed environment variables.
+    pub fn apply(&self, interpreter: &mut Interpreter, program: &str) {
+        if !self.arguments.is_empty() || !self.allowed.is_empty() {
+            interpreter.enable(Extension::SystemInfo);
+        }
+
+        let arguments = std::iter::once(program.to_owned()).chain(self.arguments.iter().cloned());
+        interpreter.set_arguments(arguments.collect());
+
+        let allowed = |name: &str| {
+            self.allowed
+                .iter()
+                .any(|allowed| match allowed.strip_suffix('*') {
+                    Some(prefix) => name.starts_with(prefix),
+                    None => name == allowed,
+                })
+        };
+        let mut variables = std::env::vars()
+            .filter(|(name, _)| allowed(name))
+            .collect::<Vec<_>>();
+        variables.sort();
+        interpreter.set_environment(variables);
+    }
+}
+
+/// Shape of the grid programs are loaded into, overriding their directives.
+#[derive(clap::Args, Clone, Copy, Debug, Default)]
+pub(crate) struct Geometry {
+    /// Size of the grid: `auto` to fit the program, `classic` for the 80x25 Befunge-93 page, or
+    /// `WxH`. Defaults to the program's `sizing` directive, or `auto`
+    #[arg(long, value_name = "SIZE")]
+    size: Option<Sizing>,
+
+    /// Where shorter lines wrap: `pad` to the longest line as in Befunge-93, or `ragged` to wrap
+    /// at their own end as in Funge-98. Defaults to the program's `lines` directive, or `pad`
+    #[arg(long, value_name = "POLICY")]
+    lines: Option<Lines>,
+
+    /// How tabs are loaded: `cell` to keep them as no-op cells, a tab width to expand them to
+    /// spaces, or `error` to refuse programs holding some
+    #[arg(long, value_name = "POLICY", default_value_t)]
+    tabs: Tabs,
+}
+
+impl Geometry {
+    /// Loads a program file and shapes its grid, along with notes on what had to be normalised
+    /// or dropped.
+    pub fn open(self, path: &str) -> directives::Result<(Grid, Directives, Vec<String>)> {
+        Ok(self.shape(directives::open_with(path, self.tabs)?))
+    }
+
+    /// Where the cells of a program file are in it, for diagnostics to point into it. Bundles
+    /// and fetched programs have no file to point into, cells then being taken as is.
+    pub fn source(self, path: &str) -> Source {
+        directives::open_with(path, self.tabs)
+            .map(|(.., source)| source)
+            .unwrap_or_default()
+    }
+
+    /// Same as [Geometry::open], from the content of a program file.
+    pub fn parse(self, content: &[u8]) -> directives::Result<(Grid, Directives, Vec<String>)> {
+        Ok(self.shape(directives::parse_program_with(content, self.tabs)?))
+    }
+
+    fn shape(
+        self,
+        (mut grid, directives, normalized, _): directives::Loaded,
+    ) -> (Grid, Directives, Vec<String>) {
+        let mut notes = Vec::new();
+        if normalized != Normalized::default() {
+            notes.push(normalized.to_string());
+        }
+        notes.extend(self.apply(&mut grid, &directives));
+
+        (grid, directives, notes)
+    }
+
+    /// Shapes the grid of a program, describing the cells that didn't fit if any.
+    pub fn apply(self, grid: &mut Grid, directives: &Directives) -> Option<String> {
+        let sizing = self.size.or(directives.sizing).unwrap_or_default();
+        let dropped = sizing.fit(grid);
+        self.lines
+            .or(directives.lines)
+            .unwrap_or_default()
+            .apply(grid);
+
+        (dropped > 0).then(|| format!("{dropped} cell(s) outside of {sizing} were dropped"))
+    }
+}
+
+/// Loads a program file or a `.mstpkg` bundle, along with the input the bundle comes with.
+/// Either can be downloaded from a URL. What had to be changed for the program to fit `geometry`
+/// is reported on stderr.
+pub(crate) fn load(
+    input: &str,
+    geometry: Geometry,
+) -> anyhow::Result<(Grid, Directives, Option<String>), Error> {
+    let content = fetch::is_url(input)
+        .then(|| fetch::fetch(input))
+        .transpose()?;
+
+    if bundle::is_bundle(input) {
+        let bundle = match content {
+            Some(content) => Bundle::from_reader(content.as_slice()),
+            None => Bundle::read(input),
+        }
+        .map_err(|err| Error::Bundle(input.to_owned(), err))?;
+        let (mut grid, directives) = bundle
+            .load()
+            .map_err(|err| Error::Bundle(input.to_owned(), err))?;
+        if let Some(warning) = geometry.apply(&mut grid, &directives) {
+            eprintln!("{input}: {warning}");
+        }
+        return Ok((grid, directives, bundle.input));
+    }
+
+    let (grid, directives, notes) = match content {
+        Some(content) => geometry.parse(&content),
+        None => geometry.open(input),
+    }
+    .map_err(|err| Error::Load(input.to_owned(), err))?;
+    for note in notes {
+        eprintln!("{input}: {note}");
+    }
+    Ok((grid, directives, None))
+}
+
+fn open_script(path: String) -> anyhow::Result<Script, Error> {
+    Script::open(&path).map_err(|err| Error::Script(path, err))
+}
+
+/// Configures an interpreter from a program's directives, overridden by command line options.
+pub(crate) fn configure(
+    interpreter: &mut Interpreter,
+    directives: &Directives,
+    dialect: Option<Dialect>,
+    extensions: &[Extension],
+    schedule: Option<&Schedule>,
+) {
+    directives.apply(interpreter);
+    if let Some(dialect) = dialect {
+        interpreter.set_dialect(dialect);
+    }
+    for extension in extensions {
+        interpreter.enable(*extension);
+    }
+    if let Some(schedule) = schedule {
+        interpreter.set_schedule(schedule.clone());
+    }
+}
+
+/// How a run fed all of its input up front ended.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Ending {
+    Ended,
+    Failed(String),
+    TimedOut,
+}
+
+impl Ending {
+    pub fn describe(&self) -> String {
+        match self {
+            Ending::Ended => "ended".to_owned(),
+            Ending::Failed(reason) => format!("failed ({reason})"),
+            Ending::TimedOut => "ran forever".to_owned(),
+        }
+    }
+}
+
+/// Runs a program with all of its input available from the start, giving up after `max_ticks`,
+/// its output being left to take from the interpreter.
+pub(crate) fn run_to_end(interpreter: &mut Interpreter, input: &str, max_ticks: u64) -> Ending {
+    interpreter.set_fast_forward(true);
+    interpreter.set_flush(Flush::Tick);
+    interpreter.feed_input(input);
+
+    loop {
+        if interpreter.ticks() >= max_ticks {
+            return Ending::TimedOut;
+        }
+        match interpreter.step() {
+            Ok(Status::Running) => (),
+            Ok(Status::Terminated) => return Ending::Ended,
+            Ok(Status::WaitingForInput) => {
+                return Ending::Failed("input requested after the end of it".to_owned())
+            }
+            Err(err) => return Ending::Failed(err.to_string()),
+        }
+    }
+}
+
+/// Fails if the program has an `output` directive that `output` doesn't match.
+pub(crate) fn check_output(directives: &Directives, output: &[u8]) -> Result<()> {
+    match &directives.output {
+        Some(expected) if expected.as_bytes() != output => {
+            Err(Error::UnexpectedOutput(String::from_utf8_lossy(output).into_owned()).into())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Writes to another writer, counting the bytes written and keeping a copy of them if asked to.
+struct Tee<W> {
+    inner: W,
+    written: usize,
+    copy: Option<Vec<u8>>,
+}
+
+impl<W: Write> Write for Tee<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.written += written;
+        if let Some(copy) = self.copy.as_mut() {
+            copy.extend_from_slice(&buf[..written]);
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Runs a program to completion without the TUI, using stdin and stdout for I/O.
+pub(crate) fn run(mut options: Options, config: &Config) -> Result<()> {
+    if options.inputs.len() > 1 {
+        return run_batch(options, config);
+    }
+    let input = options.inputs.remove(0);
+
+    if let Some(address) = options.debug_listen {
+        return remote::listen(
+            &address,
+            input,
+            options.extension,
+            Semantics::of(config),
+            options.geometry,
+        );
+    }
+
+    let script = options.input_script.map(open_script).transpose()?;
+    let sound = options.sound.map(Sound::open).transpose()?;
+    let expectation = options.expect.map(Expectation::open).transpose()?;
+    let frames = options
+        .dump_frames
+        .map(|directory| Dumper::new(Path::new(&directory), options.frame_format, options.every))
+        .transpose()?;
+    let trace = options
+        .trace
+        .map(|path| File::create(&path).map_err(|err| Error::Trace(path, err)))
+        .transpose()?
+        .map(BufWriter::new);
+    let (grid, directives, mut canned) = load(&input, options.geometry)?;
+    // The project's input file for the program stands in for the input of a bundle, and an input
+    // file for both
+    if let Some(path) = options.input_file.as_deref().map(Path::new).or(config
+        .input(Path::new(&input))
+        .filter(|_| canned.is_none() && script.is_none()))
+    {
+        let name = path.display().to_string();
+        canned = Some(std::fs::read_to_string(path).map_err(|err| Error::Input(name, err))?);
+    }
+    let bundled = canned.is_some();
+    let mut interpreter = Interpreter::new(grid);
+    // Profiles, narration, sound and traces need to see every executed cell
+    let profiling = options.loops || options.loops_folded.is_some() || options.heatmap.is_some();
+    let audible = options.sound == Some(sound::Mode::Instructions);
+    interpreter.set_fast_forward(!profiling && !options.narrate && !audible && trace.is_none());
+    interpreter.set_echo(options.echo_input);
+    // Narrated and expected output is told right after the instruction writing it
+    interpreter.set_flush(if options.narrate || expectation.is_some() {
+        Flush::Tick
+    } else {
+        options.flush
+    });
+    let semantics =
+        Semantics::new(options.dialect, &options.extension).or(&Semantics::of(config), &directives);
+    configure(
+        &mut interpreter,
+        &directives,
+        semantics.dialect,
+        &semantics.extensions,
+        options.schedule.as_ref(),
+    );
+    options.system.apply(&mut interpreter, &input);
+
+    // Traces are written from the latest executed instruction
+    let history = match trace {
+        Some(_) => options.history.max(1),
+        None => options.history,
+    };
+    let mut debugger = Debugger::new(interpreter).with_history(history);
+    if options.loops || options.loops_folded.is_some() {
+        debugger = debugger.with_loop_profile();
+    }
+    if options.heatmap.is_some() {
+        debugger = debugger.with_heatmap();
+    }
+
+    let mut stdout = Tee {
+        inner: std::io::stdout().lock(),
+        written: 0,
+        copy: directives.output.is_some().then(Vec::new),
+    };
+    let raw_input = options.raw_input && std::io::stdin().is_terminal();
+    let lines = options
+        .input_timeout
+        .map(|seconds| (read_lines(), Duration::from_secs(seconds)));
+    let player = script.as_ref().map(Player::new);
+    let narrator = options.narrate.then(std::io::stderr);
+    let started = Instant::now();
+    let observers = Observers {
+        narrator,
+        sound,
+        expectation,
+        trace,
+        frames,
+    };
+    let res = execute(&mut debugger, &mut stdout, player, observers, |awaited| {
+        // A bundle's input, or the given or configured one, replaces stdin
+        if bundled {
+            return Ok(canned.take());
+        }
+
+        if raw_input && matches!(awaited, Some(NullaryOperator::Ascii)) {
+            return read_key();
+        }
+
+        // Stdin that doesn't give a line in time is considered over
+        if let Some((lines, timeout)) = &lines {
+            return Ok(lines.recv_timeout(*timeout).ok());
+        }
+
+        let mut line = String::new();
+        let read = std::io::stdin().lock().read_line(&mut line)?;
+        Ok((read > 0).then_some(line))
+    });
+    let elapsed = started.elapsed();
+
+    if res.is_err() && !debugger.history().is_empty() {
+        eprintln!("Backtrace, most recent first:");
+        for entry in debugger.history().iter().rev() {
+            eprintln!("  {entry}");
+        }
+    }
+
+    if options.stats {
+        eprint!("{}", debugger.statistics());
+    }
+
+    if let Some(path) = options.save_input {
+        std::fs::write(&path, debugger.consumed_input())
+            .map_err(|err| Error::SaveInput(path, err))?;
+    }
+
+    if let Some(path) = options.stats_json {
+        let json = serde_json::to_string_pretty(debugger.statistics())?;
+        std::fs::write(&path, json).map_err(|err| Error::Statistics(path, err))?;
+    }
+
+    if let Some(report) = debugger.loop_profile().map(|loops| loops.report()) {
+        if options.loops {
+            eprint!("{report}");
+        }
+
+        if let Some(path) = options.loops_folded {
+            std::fs::write(&path, report.folded()).map_err(|err| Error::Loops(path, err))?;
+        }
+    }
+
+    if let Some((path, heatmap)) = options.heatmap.zip(debugger.heatmap()) {
+        let content = if path.ends_with(".csv") {
+            heatmap.to_csv()
+        } else {
+            serde_json::to_string_pretty(heatmap)?
+        };
+        std::fs::write(&path, content).map_err(|err| Error::Heatmap(path, err))?;
+    }
+
+    let res = res.and_then(|()| check_output(&directives, &stdout.copy.unwrap_or_default()));
+
+    if options.summary || options.summary_json.is_some() {
+        let summary = Summary::of(&debugger, stdout.written, elapsed, &res);
+        if options.summary {
+            eprint!("{summary}");
+        }
+        if let Some(path) = options.summary_json {
+            let json = serde_json::to_string_pretty(&summary)?;
+            std::fs::write(&path, json).map_err(|err| Error::Summary(path, err))?;
+        }
+    }
+
+    if let Some(path) = options.diagnostics {
+        let mut report = Report::default();
+        if let Err(err) = &res {
+            let source = options.geometry.source(&input);
+            let entry = |code| Entry::new(&input, Severity::Error, code, err);
+            report.diagnostics.push(match err.downcast_ref::<Error>() {
+                Some(Error::EndOfInput { position, .. }) => {
+                    entry("end-of-input").at(*position, &source)
+                }
+                Some(Error::UnexpectedOutput(_)) => entry("unexpected-output"),
+                _ => entry("runtime").at(debugger.interpreter().position(), &source),
+            });
+        }
+        write_report(&path, &report)?;
+    }
+
+    // Wrappers are told how the program ended through the exit status
+    let exit_code = debugger.interpreter().exit_code().filter(|code| *code != 0);
+    if let (Ok(()), Some(code)) = (&res, exit_code) {
+        stdout.inner.flush()?;
+        std::process::exit(code);
+    }
+
+    res
+}
+
+/// Writes diagnostics to `path`, as SARIF if it ends with `.sarif` and as JSON otherwise.
+pub(crate) fn write_report(path: &str, report: &Report) -> Result<()> {
+    let content = match path.ends_with(".sarif") {
+        true => serde_json::to_string_pretty(&report.to_sarif())?,
+        false => report.to_json(),
+    };
+    std::fs::write(path, content + "\n").map_err(|err| Error::Diagnostics(path.to_owned(), err))?;
+    Ok(())
+}
+
+/// Runs several programs, see [batch::run].
+fn run_batch(options: Options, config: &Config) -> Result<()> {
+    let single = [
+        ("debug-listen", options.debug_listen.is_some()),
+        ("stats-json", options.stats_json.is_some()),
+        ("summary", options.summary),
+        ("summary-json", options.summary_json.is_some()),
+        ("loops", options.loops),
+        ("loops-folded", options.loops_folded.is_some()),
+        ("heatmap", options.heatmap.is_some()),
+        ("raw-input", options.raw_input),
+        ("narrate", options.narrate),
+        ("sound", options.sound.is_some()),
+        ("expect", options.expect.is_some()),
+        ("save-input", options.save_input.is_some()),
+        ("input", options.input_file.is_some()),
+        ("input-timeout", options.input_timeout.is_some()),
+        ("trace", options.trace.is_some()),
+        ("diagnostics", options.diagnostics.is_some()),
+        ("dump-frames", options.dump_frames.is_some()),
+    ];
+    if let Some((option, _)) = single.into_iter().find(|(_, set)| *set) {
+        return Err(Error::SingleProgram(option).into());
+    }
+
+    batch::run(
+        &options.inputs,
+        batch::Settings {
+            jobs: options.jobs,
+            history: options.history,
+            flush: options.flush,
+            dialect: options.dialect,
+            extensions: options.extension,
+            config: Semantics::of(config),
+            schedule: options.schedule,
+            echo: options.echo_input,
+            geometry: options.geometry,
+            system: options.system,
+            stats: options.stats,
+            script: options.input_script.map(open_script).transpose()?,
+        },
+    )
+}
+
+/// Lines of stdin, read as they come in by a thread of their own so that waiting for them can
+/// time out.
+fn read_lines() -> mpsc::Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut stdin = std::io::stdin().lock();
+        loop {
+            let mut line = String::new();
+            match stdin.read_line(&mut line) {
+                Ok(read) if read > 0 => {
+                    if sender.send(line).is_err() {
+                        break;
+                    }
+                }
+                _ => break,
+            }
+        }
+    });
+    receiver
+}
+
+/// Reads a single keypress from the terminal, `None` meaning end of input on Ctrl-D.
+fn read_key() -> std::io::Result<Option<String>> {
+    terminal::enable_raw_mode()?;
+    let key = loop {
+        match crossterm::event::read() {
+            Ok(Event::Key(KeyEvent {
+                code,
+                modifiers,
+                kind: KeyEventKind::Press,
+                ..
+            })) => {
+                let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+                break Ok(match code {
+                    // Raw mode disables the signal Ctrl-C usually sends
+                    KeyCode::Char('c') if ctrl => {
+                        Err(std::io::Error::from(std::io::ErrorKind::Interrupted))
+                    }
+                    KeyCode::Char('d') if ctrl => Ok(None),
+                    KeyCode::Char(c) => Ok(Some(c)),
+                    KeyCode::Enter => Ok(Some('\n')),
+                    KeyCode::Tab => Ok(Some('\t')),
+                    KeyCode::Backspace => Ok(Some('\x08')),
+                    KeyCode::Esc => Ok(Some('\x1b')),
+                    _ => continue,
+                });
+            }
+            Ok(_) => (),
+            Err(err) => break Err(err),
+        }
+    };
+    terminal::disable_raw_mode()?;
+
+    Ok(key??.map(String::from))
+}
+
+/// Instructions run between two writes of the output, when no input script needs a finer grain.
+const TICKS_PER_FRAME: usize = 1024;
+
+/// What follows a run instruction by instruction besides its output, each of them making it run
+/// one instruction at a time.
+#[derive(Default)]
+pub(crate) struct Observers<N> {
+    /// Where every instruction is described
+    pub narrator: Option<N>,
+    /// Where the run is played
+    pub sound: Option<Sound>,
+    /// Output checked as it is written, the run ending at the first difference
+    pub expectation: Option<Expectation>,
+    /// Where every instruction is written as a [Step]
+    pub trace: Option<BufWriter<File>>,
+    /// Where frames of the run are written, which only makes it run as many instructions at a
+    /// time as there are between two frames
+    pub frames: Option<Dumper>,
+}
+
+/// Runs a program to completion, writing its output to `stdout` and calling `read` for more
+/// input when it runs out, with the instruction waiting for it. `read` returns `None` once there
+/// is no more. A `player` replaces `read` altogether.
+pub(crate) fn execute(
+    debugger: &mut Debugger,
+    stdout: &mut impl Write,
+    mut player: Option<Player>,
+    observers: Observers<impl Write>,
+    read: impl FnMut(Option<NullaryOperator>) -> std::io::Result<Option<String>>,
+) -> Result<()> {
+    let Observers {
+        narrator,
+        sound,
+        expectation,
+        trace,
+        frames,
+    } = observers;
+    if let Some(input) = player.as_mut().and_then(|player| player.poll(0)) {
+        debugger.feed_input(&input);
+    }
+    // Scripts are played against every tick, narration describes each of them and so do sound
+    // and traces, and differences with the expected output are told at the tick they happen
+    let audible = sound
+        .as_ref()
+        .is_some_and(|sound| sound.mode() == sound::Mode::Instructions);
+    let ticks_per_frame = if player.is_some()
+        || narrator.is_some()
+        || audible
+        || expectation.is_some()
+        || trace.is_some()
+    {
+        1
+    } else {
+        frames.as_ref().map_or(TICKS_PER_FRAME, |frames| {
+            TICKS_PER_FRAME.min(frames.every().try_into().unwrap_or(TICKS_PER_FRAME))
+        })
+    };
+
+    let mut stream = Stream {
+        stdout,
+        player,
+        narrator: narrator.map(|writer| (writer, Observation::of(debugger.interpreter()))),
+        sound,
+        expectation,
+        divergence: None,
+        trace: trace.map(|writer| (writer, None)),
+        frames,
+        read,
+    };
+    renderer::run(debugger, &mut stream, ticks_per_frame).map_err(|err| match err {
+        renderer::Error::EndOfInput => anyhow::Error::from(Error::EndOfInput {
+            tick: debugger.interpreter().ticks(),
+            position: debugger.interpreter().position(),
+        }),
+        err => err.into(),
+    })?;
+
+    if let Some(err) = stream.divergence {
+        return Err(Error::Expect(err).into());
+    }
+    if let Some(expectation) = &stream.expectation {
+        expectation
+            .finish(debugger.interpreter().ticks())
+            .map_err(Error::Expect)?;
+    }
+    Ok(())
+}
+
+/// Renders a run as its bare output, along with its narration if any.
+struct Stream<'a, W, N, R> {
+    stdout: &'a mut W,
+    player: Option<Player>,
+    /// Where to narrate the run, and what it was last seen doing
+    narrator: Option<(N, Observation)>,
+    sound: Option<Sound>,
+    expectation: Option<Expectation>,
+    /// First difference with the expected output
+    divergence: Option<expect::Error>,
+    /// Where to write the trace, and the tick of the latest step written
+    trace: Option<(BufWriter<File>, Option<u64>)>,
+    frames: Option<Dumper>,
+    read: R,
+}
+
+impl<W, N, R> Renderer for Stream<'_, W, N, R>
+where
+    W: Write,
+    N: Write,
+    R: FnMut(Option<NullaryOperator>) -> std::io::Result<Option<String>>,
+{
+    fn begin(&mut self, debugger: &Debugger) -> std::io::Result<()> {
+        if let Some(frames) = self.frames.as_mut() {
+            frames
+                .frame(debugger.interpreter(), "")
+                .map_err(std::io::Error::other)?;
+        }
+        Ok(())
+    }
+
+    fn frame(&mut self, frame: &Frame<'_>) -> std::io::Result<Control> {
+        if !frame.output.is_empty() {
+            self.stdout.write_all(frame.output.as_bytes())?;
+        }
+        if let Some((writer, last)) = self.narrator.as_mut() {
+            let observation = Observation::of(frame.debugger.interpreter());
+            if observation != *last {
+                writeln!(writer, "{}", narrator::narrate(last, &observation))?;
+                if !frame.output.is_empty() {
+                    writeln!(writer, "Printed {:?}.", frame.output)?;
+                }
+                *last = observation;
+            }
+        }
+        if let Some(sound) = self.sound.as_mut() {
+            sound.play(frame);
+        }
+        if let Some((writer, traced)) = self.trace.as_mut() {
+            // Instructions waiting for input don't execute until it arrives
+            if let Some(step) = Step::last(frame.debugger).filter(|step| Some(step.tick) != *traced)
+            {
+                serde_json::to_writer(&mut *writer, &step)?;
+                writeln!(writer)?;
+                *traced = Some(step.tick);
+            }
+        }
+        if let Some(frames) = self.frames.as_mut() {
+            frames
+                .frame(frame.debugger.interpreter(), frame.output)
+                .map_err(std::io::Error::other)?;
+        }
+        if let Some(expectation) = self.expectation.as_mut() {
+            let debugger = frame.debugger;
+            let tick = debugger.history().back().map_or_else(
+                || debugger.interpreter().ticks().saturating_sub(1),
+                |entry| entry.tick,
+            );
+            if let Err(err) = expectation.check(frame.output, tick) {
+                self.stdout.flush()?;
+                self.divergence = Some(err);
+                return Ok(Control::Stop);
+            }
+        }
+        if matches!(frame.stop, Some(Stop::WaitingForInput)) {
+            self.stdout.flush()?;
+        }
+
+        let ticks = frame.debugger.interpreter().ticks();
+        Ok(
+            match self.player.as_mut().and_then(|player| player.poll(ticks)) {
+                Some(input) => Control::Feed(input),
+                None => Control::Continue,
+            },
+        )
+    }
+
+    fn input(&mut self, debugger: &Debugger) -> std::io::Result<Option<String>> {
+        let interpreter = debugger.interpreter();
+        match self.player.as_mut() {
+            Some(player) => Ok(player.wait(interpreter.ticks())),
+            None => (self.read)(interpreter.awaited_input()),
+        }
+    }
+
+    fn end(&mut self, debugger: &Debugger, _result: &renderer::Result<()>) -> std::io::Result<()> {
+        self.stdout.flush()?;
+        if let Some(frames) = self.frames.as_mut() {
+            frames
+                .finish(debugger.interpreter())
+                .map_err(std::io::Error::other)?;
+        }
+        if let Some((writer, _)) = self.trace.as_mut() {
+            writer.flush()?;
+        }
+        if let Some(sound) = &self.sound {
+            sound.finish();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use serde::{Serialize, Serializer};
+
+/// How a single cell was executed during a run.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct CellProfile {
+    pub executions: u64,
+    /// Tick at which the cell was last executed
+    pub last_visit: u64,
+}
+
+/// Per-cell execution counts of a run, only holding executed cells.
+#[derive(Clone, Debug, Default)]
+pub struct Heatmap {
+    ticks: u64,
+    cells: HashMap<(usize, usize), CellProfile>,
+}
+
+/// Flattened cell profile, as exported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Entry {
+    pub x: usize,
+    pub y: usize,
+    pub executions: u64,
+    pub last_visit: u64,
+}
+
+impl Heatmap {
+    /// Records that the cell at `position` was executed at `tick`.
+    pub fn record(&mut self, tick: u64, position: (usize, usize)) {
+        self.ticks = self.ticks.max(tick + 1);
+
+        let cell = self.cells.entry(position).or_default();
+        cell.executions += 1;
+        cell.last_visit = tick;
+    }
+
+    pub fn get(&self, position: (usize, usize)) -> Option<CellProfile> {
+        self.cells.get(&position).copied()
+    }
+
+    /// Executed cells in reading order.
+    pub fn entries(&self) -> Vec<Entry> {
+        let mut entries = self
+            .cells
+            .iter()
+            .map(|(&(x, y), cell)| Entry {
+                x,
+                y,
+                executions: cell.executions,
+                last_visit: cell.last_visit,
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|entry| (entry.y, entry.x));
+        entries
+    }
+
+    pub fn to_csv(&self) -> String {
+        self.entries().iter().fold(
+            "x,y,executions,last_visit\n".to_owned(),
+            |mut csv, entry| {
+                csv.push_str(&format!(
+                    "{},{},{},{}\n",
+                    entry.x, entry.y, entry.executions, entry.last_visit
+                ));
+                csv
+            },
+        )
+    }
+}
+
+impl Serialize for Heatmap {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Export {
+            ticks: u64,
+            cells: Vec<Entry>,
+        }
+
+        Export {
+            ticks: self.ticks,
+            cells: self.entries(),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn export() {
+        let mut heatmap = Heatmap::default();
+        for (tick, position) in [(1, 0), (0, 1), (1, 0)].into_iter().enumerate() {
+            heatmap.record(tick as u64, position);
+        }
+
+        assert_eq!(
+            heatmap.get((1, 0)),
+            Some(CellProfile {
+                executions: 2,
+                last_visit: 2
+            })
+        );
+        assert_eq!(
+            heatmap.to_csv(),
+            "x,y,executions,last_visit\n1,0,2,2\n0,1,1,1\n"
+        );
+        assert_eq!(
+            serde_json::to_value(&heatmap).unwrap(),
+            serde_json::json!({
+                "ticks": 3,
+                "cells": [
+                    { "x": 1, "y": 0, "executions": 2, "last_visit": 2 },
+                    { "x": 0, "y": 1, "executions": 1, "last_visit": 1 },
+                ],
+            })
+        );
+    }
+}
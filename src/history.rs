@@ -0,0 +1,125 @@
+use std::path::{Path, PathBuf};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Could not save the command history to `{0}`: {1}")]
+    Write(String, std::io::Error),
+}
+
+type Result<T> = anyhow::Result<T, Error>;
+
+/// Commands kept, the oldest being dropped first.
+const LIMIT: usize = 500;
+
+/// History of the commands typed after `:`, one per line, in `$XDG_STATE_HOME/mst/history` or
+/// `~/.local/state/mst/history`.
+fn path() -> Option<PathBuf> {
+    let home = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| Path::new(&home).join(".local").join("state"))
+        })?;
+    Some(home.join("mst").join("history"))
+}
+
+/// Commands typed after `:` in this session and the previous ones, recalled with the arrow keys.
+#[derive(Debug, Default)]
+pub(crate) struct History {
+    /// Where the commands are saved, nowhere when no home directory is known
+    path: Option<PathBuf>,
+    lines: Vec<String>,
+    /// Command being recalled, counted from the latest
+    recalled: Option<usize>,
+}
+
+impl History {
+    /// Commands of the previous sessions, none if they can't be read.
+    pub fn load() -> Self {
+        Self::at(path())
+    }
+
+    fn at(path: Option<PathBuf>) -> Self {
+        let text = path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .unwrap_or_default();
+        let lines = text.lines().map(str::to_owned).collect::<Vec<_>>();
+        Self {
+            lines: lines[lines.len().saturating_sub(LIMIT)..].to_vec(),
+            path,
+            recalled: None,
+        }
+    }
+
+    /// Adds a command unless it repeats the latest one, and saves the history.
+    pub fn push(&mut self, line: &str) -> Result<()> {
+        self.recalled = None;
+        let line = line.trim();
+        if line.is_empty() || self.lines.last().is_some_and(|last| last == line) {
+            return Ok(());
+        }
+        self.lines.push(line.to_owned());
+        if self.lines.len() > LIMIT {
+            self.lines.remove(0);
+        }
+
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let error = |err| Error::Write(path.display().to_string(), err);
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory).map_err(error)?;
+        }
+        let mut text = self.lines.join("\n");
+        text.push('\n');
+        std::fs::write(path, text).map_err(error)
+    }
+
+    /// Command before the one being recalled, the latest if none is, staying on the oldest.
+    pub fn previous(&mut self) -> Option<&str> {
+        let recalled = match self.recalled {
+            Some(recalled) => (recalled + 1).min(self.lines.len().checked_sub(1)?),
+            None => 0,
+        };
+        let line = self.lines.len().checked_sub(recalled + 1)?;
+        self.recalled = Some(recalled);
+        Some(&self.lines[line])
+    }
+
+    /// Command after the one being recalled, `None` once past the latest.
+    pub fn next(&mut self) -> Option<&str> {
+        self.recalled = self.recalled.and_then(|recalled| recalled.checked_sub(1));
+        let recalled = self.recalled?;
+        Some(&self.lines[self.lines.len() - 1 - recalled])
+    }
+
+    /// Stops recalling commands, for the next one to start from the latest.
+    pub fn reset(&mut self) {
+        self.recalled = None;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn history() {
+        let path = std::env::temp_dir().join(format!("mst-history-{}", std::process::id()));
+        let mut history = History::at(Some(path.clone()));
+        for line in ["break loop", "run", "run", " ", "checkpoint 1"] {
+            history.push(line).unwrap();
+        }
+
+        let mut history = History::at(Some(path.clone()));
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(history.next(), None);
+        assert_eq!(history.previous(), Some("checkpoint 1"));
+        assert_eq!(history.previous(), Some("run"));
+        assert_eq!(history.previous(), Some("break loop"));
+        assert_eq!(history.previous(), Some("break loop"));
+        assert_eq!(history.next(), Some("run"));
+        assert_eq!(history.next(), Some("checkpoint 1"));
+        assert_eq!(history.next(), None);
+    }
+}
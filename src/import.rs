@@ -0,0 +1,146 @@
+use std::{fs::File, io::BufReader, str::FromStr};
+
+use puccinia::{
+    annotation::{Label, Region},
+    bitmap::Bitmap,
+    brainfuck,
+    sidecar::Sidecar,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Unknown language `{0}`, expected `brainfuck` or `image`")]
+    Unknown(String),
+    #[error("Could not read `{0}`: {1}")]
+    Read(String, std::io::Error),
+    #[error("Could not write `{0}`: {1}")]
+    Write(String, std::io::Error),
+    #[error("In `{0}`: {1}")]
+    Brainfuck(String, brainfuck::Error),
+    #[error("Could not decode `{0}`: {1}")]
+    Image(String, png::DecodingError),
+}
+
+/// Language programs are imported from, or images turned into blocks of data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Language {
+    Brainfuck,
+    /// PNG image, one cell per pixel
+    Image,
+}
+
+impl FromStr for Language {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "brainfuck" => Ok(Language::Brainfuck),
+            "image" => Ok(Language::Image),
+            _ => Err(Error::Unknown(name.to_owned())),
+        }
+    }
+}
+
+#[derive(clap::Args)]
+pub(crate) struct Options {
+    /// Program file location
+    input: String,
+
+    /// Language the program is written in: `brainfuck`, or `image` for a PNG file turned into a
+    /// block of cells to place in programs that draw it
+    #[arg(long, value_name = "LANGUAGE")]
+    from: Language,
+
+    /// Where to write the Befunge-93 program, defaults to stdout. Its sidecar then marks the
+    /// tape or the image as data
+    #[arg(short, long, value_name = "PATH")]
+    output: Option<String>,
+
+    /// Cells of the Brainfuck tape
+    #[arg(long, value_name = "N", default_value_t = 1000)]
+    tape: usize,
+
+    /// Brightness from 0 to 255 under which image pixels are inked
+    #[arg(long, value_name = "LEVEL", default_value_t = 128)]
+    threshold: u8,
+
+    /// Cell of inked pixels
+    #[arg(long, value_name = "CHAR", default_value_t = '#')]
+    ink: char,
+
+    /// Cell of the other pixels
+    #[arg(long, value_name = "CHAR", default_value_t = ' ')]
+    paper: char,
+
+    /// Ink light pixels rather than dark ones
+    #[arg(long)]
+    invert: bool,
+
+    /// Scale images down to this many cells across, keeping their proportions
+    #[arg(long, value_name = "N")]
+    width: Option<usize>,
+}
+
+/// Decodes a PNG file.
+fn decode(path: &str) -> anyhow::Result<Bitmap, Error> {
+    let file = File::open(path).map_err(|err| Error::Read(path.to_owned(), err))?;
+    let mut decoder = png::Decoder::new(BufReader::new(file));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let decoding = |err| Error::Image(path.to_owned(), err);
+
+    let mut reader = decoder.read_info().map_err(decoding)?;
+    let mut data = vec![0; reader.output_buffer_size().unwrap_or_default()];
+    let info = reader.next_frame(&mut data).map_err(decoding)?;
+    Ok(Bitmap::from_pixels(
+        info.width as usize,
+        info.height as usize,
+        info.color_type.samples(),
+        &data[..info.buffer_size()],
+    ))
+}
+
+/// Transpiles a program written in another language to Befunge-93, or lays out an image as
+/// cells.
+pub(crate) fn run(options: Options) -> anyhow::Result<()> {
+    let (program, data, name) = match options.from {
+        Language::Brainfuck => {
+            let source = std::fs::read_to_string(&options.input)
+                .map_err(|err| Error::Read(options.input.clone(), err))?;
+            let transpiled = brainfuck::transpile(&source, options.tape)
+                .map_err(|err| Error::Brainfuck(options.input.clone(), err))?;
+            (transpiled.program, transpiled.tape, "tape")
+        }
+        Language::Image => {
+            let mut bitmap = decode(&options.input)?;
+            if let Some(width) = options.width {
+                bitmap = bitmap.shrink(width);
+            }
+            if options.invert {
+                bitmap = bitmap.inverted();
+            }
+            let text = bitmap.to_text(options.threshold, options.ink, options.paper);
+            let corner = (
+                bitmap.width.saturating_sub(1),
+                bitmap.height.saturating_sub(1),
+            );
+            (text, Region::new((0, 0), corner), "image")
+        }
+    };
+
+    match &options.output {
+        Some(path) => {
+            std::fs::write(path, &program).map_err(|err| Error::Write(path.clone(), err))?;
+            let mut sidecar = Sidecar::load(path)?;
+            sidecar.labels.retain(|label| label.name != name);
+            sidecar.labels.push(Label {
+                region: data,
+                name: name.to_owned(),
+                folded: false,
+                data: true,
+            });
+            sidecar.save(path)?;
+        }
+        None => print!("{program}"),
+    }
+    Ok(())
+}
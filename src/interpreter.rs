@@ -0,0 +1,1274 @@
+use std::{
+    collections::{BTreeSet, VecDeque},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cell::{
+        BinaryOperator, Cell, CellValue, Direction, IfDir, NullaryOperator, Operator, Origin,
+        TernaryOperator, UnaryOperator,
+    },
+    dialect::{Dialect, Extension},
+    grid::Grid,
+    sources::{Clock, Entropy, SystemClock, Xorshift},
+};
+
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Cannot run an empty grid")]
+    EmptyGrid,
+    #[error("Out of bounds grid access at ({0}, {1})")]
+    OutOfBounds(i32, i32),
+    #[error("Value {0} cannot be stored in a cell")]
+    InvalidValue(i32),
+    #[error(
+        "Unknown flush policy `{0}`, expected `tick`, `newline`, `input` or a number of bytes"
+    )]
+    FlushPolicy(String),
+    #[error(
+        "Unknown schedule `{0}`, expected `round-robin`, `random` or `weighted:` followed by \
+         comma-separated weights"
+    )]
+    Schedule(String),
+    #[error("Run cancelled at tick {0}")]
+    Cancelled(u64),
+    #[error("Assertion failed at ({0}, {1}), tick {2}")]
+    Assertion(usize, usize, u64),
+}
+
+pub type Result<T> = anyhow::Result<T, Error>;
+
+/// Ticks between two checks of the [Cancellation] handle.
+pub const CANCELLATION_INTERVAL: u64 = 1024;
+
+/// Handle aborting a run from another thread, the interpreter failing with
+/// [Error::Cancelled] at the next check once cancelled.
+#[derive(Clone, Debug, Default)]
+pub struct Cancellation(Arc<AtomicBool>);
+
+impl Cancellation {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Execution state of the interpreter.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Status {
+    #[default]
+    Running,
+    /// An input instruction is waiting for more data to be fed
+    WaitingForInput,
+    /// An `@` has been reached
+    Terminated,
+}
+
+/// When written output becomes available through [Interpreter::take_output].
+/// Output is always flushed when the program waits for input, ends or fails.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Flush {
+    /// After every instruction
+    #[default]
+    Tick,
+    /// Once at least this many bytes are buffered
+    Bytes(usize),
+    /// Up to the last newline written
+    Newline,
+    /// Only when the program waits for input, ends or fails
+    Input,
+}
+
+impl FromStr for Flush {
+    type Err = Error;
+
+    fn from_str(policy: &str) -> Result<Self> {
+        match policy {
+            "tick" => Ok(Flush::Tick),
+            "newline" => Ok(Flush::Newline),
+            "input" => Ok(Flush::Input),
+            bytes => bytes
+                .parse()
+                .map(Flush::Bytes)
+                .map_err(|_| Error::FlushPolicy(policy.to_owned())),
+        }
+    }
+}
+
+/// Which instruction pointer runs next once there are several of them.
+#[derive(Default, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Schedule {
+    /// Each in turn, as in Funge-98
+    #[default]
+    RoundRobin,
+    /// Any of them, the same one possibly running several times in a row
+    Random,
+    /// Any of them, with chances proportional to their weight, indexed by id and defaulting to 1
+    Weighted(Vec<u32>),
+}
+
+impl FromStr for Schedule {
+    type Err = Error;
+
+    fn from_str(policy: &str) -> Result<Self> {
+        match policy {
+            "round-robin" => Ok(Schedule::RoundRobin),
+            "random" => Ok(Schedule::Random),
+            _ => policy
+                .strip_prefix("weighted:")
+                .and_then(|weights| {
+                    weights
+                        .split(',')
+                        .map(|weight| weight.trim().parse().ok())
+                        .collect::<Option<Vec<_>>>()
+                })
+                .map(Schedule::Weighted)
+                .ok_or_else(|| Error::Schedule(policy.to_owned())),
+        }
+    }
+}
+
+impl std::fmt::Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Schedule::RoundRobin => f.write_str("round-robin"),
+            Schedule::Random => f.write_str("random"),
+            Schedule::Weighted(weights) => {
+                let weights = weights.iter().map(u32::to_string).collect::<Vec<_>>();
+                write!(f, "weighted:{}", weights.join(","))
+            }
+        }
+    }
+}
+
+/// Instruction pointer, along with its own stack. Several of them take turns once `t` is
+/// executed with the concurrent extension.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ip {
+    /// Identifier, the first instruction pointer being 0 and the others numbered as they start
+    pub id: u32,
+    pub position: (usize, usize),
+    pub direction: Direction,
+    pub string_mode: bool,
+    pub stack: Vec<i32>,
+}
+
+/// Cell changed by a `p` instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Write {
+    pub position: (usize, usize),
+    pub old: char,
+    pub new: char,
+}
+
+/// Befunge-93 interpreter operating on a [Grid].
+#[derive(Clone, Debug)]
+pub struct Interpreter {
+    grid: Grid,
+
+    /// Instruction pointer executing the next instruction
+    ip: Ip,
+    /// Other instruction pointers, in the order they run next
+    waiting: VecDeque<Ip>,
+    /// Identifier of the next instruction pointer to start
+    next_id: u32,
+    schedule: Schedule,
+    /// Cell read by the last instruction
+    read: Option<(usize, usize)>,
+    /// Cell changed by the last instruction
+    written: Option<Write>,
+    /// Origin of `g` and `p` coordinates, only moved by Funge-98 blocks which Befunge-93 lacks
+    storage_offset: (i32, i32),
+
+    input: VecDeque<char>,
+    /// Output written but not flushed yet
+    buffer: String,
+    output: String,
+    /// Characters of output already taken
+    taken: usize,
+    flush: Flush,
+
+    ticks: u64,
+    status: Status,
+    clock: Box<dyn Clock>,
+    entropy: Box<dyn Entropy>,
+    /// Whether runs of empty cells are jumped over
+    fast_forward: bool,
+    /// Whether consumed input is written to the output
+    echo: bool,
+    cancellation: Option<Cancellation>,
+    /// Tick at which the cancellation handle is checked next
+    next_check: u64,
+    dialect: Dialect,
+    extensions: BTreeSet<Extension>,
+    /// Program name and command line arguments, as pushed by `y`
+    arguments: Vec<String>,
+    /// Environment variables pushed by `y`
+    environment: Vec<(String, String)>,
+    /// Code the program ended with through `q`
+    exit_code: Option<i32>,
+    /// Assertion of the `assert` extension the program ended on
+    assertion: Option<(usize, usize)>,
+}
+
+impl Interpreter {
+    pub fn new(grid: Grid) -> Self {
+        Self::with_clock(grid, SystemClock)
+    }
+
+    /// Same as [Interpreter::new], telling the time with `clock` rather than the system clock
+    /// and seeding `?` with it.
+    pub fn with_clock(grid: Grid, clock: impl Clock + 'static) -> Self {
+        let seed = clock.now().as_nanos() as u64;
+        Self::with_sources(grid, clock, Xorshift::new(seed))
+    }
+
+    /// Same as [Interpreter::new], with the time and randomness supplied by the embedder.
+    pub fn with_sources(
+        grid: Grid,
+        clock: impl Clock + 'static,
+        entropy: impl Entropy + 'static,
+    ) -> Self {
+        Self {
+            grid,
+            ip: Ip {
+                id: 0,
+                position: (0, 0),
+                direction: Direction::Right,
+                string_mode: false,
+                stack: Vec::new(),
+            },
+            waiting: VecDeque::new(),
+            next_id: 1,
+            schedule: Schedule::default(),
+            read: None,
+            written: None,
+            storage_offset: (0, 0),
+            input: VecDeque::new(),
+            buffer: String::new(),
+            output: String::new(),
+            taken: 0,
+            flush: Flush::default(),
+            ticks: 0,
+            status: Status::Running,
+            clock: Box::new(clock),
+            entropy: Box::new(entropy),
+            fast_forward: false,
+            echo: false,
+            cancellation: None,
+            next_check: 0,
+            dialect: Dialect::default(),
+            extensions: BTreeSet::new(),
+            arguments: Vec::new(),
+            environment: Vec::new(),
+            exit_code: None,
+            assertion: None,
+        }
+    }
+
+    /// Executes the instruction under the instruction pointer and moves it.
+    pub fn step(&mut self) -> Result<Status> {
+        if self.status == Status::Terminated {
+            return Ok(self.status);
+        }
+
+        let (width, height) = self.grid.size();
+        if width == 0 || height == 0 {
+            return Err(Error::EmptyGrid);
+        }
+
+        if self.ticks >= self.next_check {
+            if self
+                .cancellation
+                .as_ref()
+                .is_some_and(Cancellation::is_cancelled)
+            {
+                return Err(Error::Cancelled(self.ticks));
+            }
+            self.next_check = self.ticks + CANCELLATION_INTERVAL;
+        }
+
+        let (x, y) = self.ip.position;
+        let value = self.grid.get(x, y).value;
+
+        self.status = Status::Running;
+        self.read = None;
+        self.written = None;
+
+        if self.ip.string_mode {
+            match value {
+                CellValue::StringMode => self.ip.string_mode = false,
+                other => self.push(char::from(other) as i32),
+            }
+        } else if let Err(err) = self.execute(value) {
+            self.flush();
+            return Err(err);
+        }
+
+        self.flush_if_due();
+
+        if self.status == Status::WaitingForInput {
+            return Ok(self.status);
+        }
+
+        self.grid.visit(x, y, self.ticks);
+        self.ticks += 1;
+
+        if self.status == Status::Running {
+            self.advance();
+
+            // Other instruction pointers would otherwise miss the turns taken by the skipped cells
+            if self.fast_forward && !self.ip.string_mode && self.waiting.is_empty() {
+                self.skip_empty();
+            }
+        }
+
+        // Instruction pointers take turns, an `@` only stopping the one reaching it while there
+        // are others left
+        if let Some(next) = self.next_ip() {
+            let current = std::mem::replace(&mut self.ip, next);
+            if self.status == Status::Terminated {
+                self.status = Status::Running;
+            } else {
+                self.waiting.push_back(current);
+            }
+        }
+
+        Ok(self.status)
+    }
+
+    /// Takes the instruction pointer to run next out of the waiting ones, or `None` if the
+    /// current one runs again.
+    fn next_ip(&mut self) -> Option<Ip> {
+        if self.waiting.is_empty() {
+            return None;
+        }
+
+        let mut weights = match &self.schedule {
+            Schedule::RoundRobin => return self.waiting.pop_front(),
+            Schedule::Random => self.ips().map(|_| 1).collect::<Vec<u64>>(),
+            Schedule::Weighted(weights) => self
+                .ips()
+                .map(|ip| weights.get(ip.id as usize).copied().unwrap_or(1) as u64)
+                .collect(),
+        };
+        // An instruction pointer reaching `@` is gone
+        if self.status == Status::Terminated {
+            weights[0] = 0;
+        }
+
+        // Weights all being 0 falls back to the first waiting instruction pointer
+        let total = weights.iter().sum::<u64>();
+        let mut pick = self.random() % total.max(1);
+        let chosen = weights
+            .iter()
+            .position(|weight| match pick.checked_sub(*weight) {
+                Some(rest) => {
+                    pick = rest;
+                    false
+                }
+                None => true,
+            })
+            .unwrap_or(1);
+
+        match chosen {
+            0 => None,
+            index => self.waiting.remove(index - 1),
+        }
+    }
+
+    /// Starts a new instruction pointer going the other way from the current one, which runs
+    /// before its parent as in Funge-98.
+    fn split(&mut self) {
+        let direction = self.ip.direction.reversed();
+        let child = Ip {
+            id: self.next_id,
+            position: self.grid.neighbour(self.ip.position, direction),
+            direction,
+            ..self.ip.clone()
+        };
+
+        self.next_id += 1;
+        self.waiting.push_front(child);
+    }
+
+    /// Pushes the system information of Funge-98, or only its `n`th cell for a positive `n` as
+    /// if picked from the stack once pushed.
+    fn system_info(&mut self) {
+        let n = self.pop();
+        let (width, height) = self.grid.size();
+        let (x, y) = self.ip.position;
+        let (dx, dy) = match self.ip.direction {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right | Direction::Random => (1, 0),
+        };
+        let now = self.clock.now().as_secs();
+        let (year, month, day) = civil(now / 86_400);
+        let seconds = (now % 86_400) as i32;
+
+        // Strings of a series end with a null character, as does the series
+        let strings = |strings: &[String]| {
+            let mut cells = vec![0];
+            for string in strings.iter().rev() {
+                cells.push(0);
+                cells.extend(string.chars().rev().map(|c| c as i32));
+            }
+            cells
+        };
+        let variables = self
+            .environment
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect::<Vec<_>>();
+
+        // From the bottom of the stack up
+        let mut cells = strings(&variables);
+        cells.extend(strings(&self.arguments));
+        cells.extend([
+            self.ip.stack.len() as i32,
+            1,
+            seconds / 3600 * 256 * 256 + seconds / 60 % 60 * 256 + seconds % 60,
+            (year - 1900) * 256 * 256 + month * 256 + day,
+            // Vectors have their y component on top
+            width as i32 - 1,
+            height as i32 - 1,
+            0,
+            0,
+            self.storage_offset.0,
+            self.storage_offset.1,
+            dx,
+            dy,
+            x as i32,
+            y as i32,
+            0,
+            self.ip.id as i32,
+            2,
+            '/' as i32,
+            0,
+            // Version and handprint, `MST` in base 256
+            1,
+            0x004D_5354,
+            4,
+            i32::from(self.extensions.contains(&Extension::Concurrent)),
+        ]);
+
+        match usize::try_from(n) {
+            Ok(n @ 1..) => {
+                let stack = self.ip.stack.iter().chain(&cells);
+                let cell = stack.rev().nth(n - 1).copied().unwrap_or_default();
+                self.push(cell);
+            }
+            _ => self.ip.stack.extend(cells),
+        }
+    }
+
+    /// Jumps over the run of empty cells in front of the instruction pointer, each of them still
+    /// counting as a tick.
+    fn skip_empty(&mut self) {
+        let (x, y) = self.ip.position;
+        if !matches!(self.grid.get(x, y).value, CellValue::Empty) {
+            return;
+        }
+
+        let Some(target) = self.grid.next_occupied(self.ip.position, self.ip.direction) else {
+            return;
+        };
+
+        let distance = self
+            .grid
+            .distance(self.ip.position, target, self.ip.direction);
+
+        self.ticks += distance as u64;
+        self.ip.position = target;
+    }
+
+    /// Makes single steps jump over runs of empty cells instead of executing them one by one.
+    /// Ticks are still counted for each of them, but nothing else observes them, breakpoints on
+    /// empty cells included. Only done while a single instruction pointer runs, so concurrent
+    /// runs interleave the same either way.
+    pub fn set_fast_forward(&mut self, enabled: bool) {
+        self.fast_forward = enabled;
+    }
+
+    fn execute(&mut self, value: CellValue) -> Result<()> {
+        match value {
+            CellValue::Char('t') if self.extensions.contains(&Extension::Concurrent) => {
+                self.split()
+            }
+            CellValue::Char('y') if self.extensions.contains(&Extension::SystemInfo) => {
+                self.system_info()
+            }
+            CellValue::Char('q') if self.extensions.contains(&Extension::Quit) => {
+                self.exit_code = Some(self.pop());
+                self.waiting.clear();
+                self.status = Status::Terminated;
+            }
+            CellValue::Char('A') if self.extensions.contains(&Extension::Assert) => {
+                if self.pop() == 0 {
+                    // The program ends, the instruction pointer staying on the assertion
+                    self.waiting.clear();
+                    self.status = Status::Terminated;
+                    let (x, y) = self.ip.position;
+                    self.assertion = Some((x, y));
+                    return Err(Error::Assertion(x, y, self.ticks));
+                }
+            }
+            CellValue::Empty | CellValue::Char(_) => (),
+            CellValue::Number(n) if self.extensions.contains(&Extension::MultiDigit) => {
+                let n = self.read_literal(n);
+                self.push(n)
+            }
+            CellValue::Number(n) => self.push(n as i32),
+            CellValue::StringMode => self.ip.string_mode = true,
+            CellValue::Bridge => self.advance(),
+            CellValue::End => self.status = Status::Terminated,
+            CellValue::Dir(Direction::Random) => {
+                self.ip.direction = match self.random() % 4 {
+                    0 => Direction::Up,
+                    1 => Direction::Down,
+                    2 => Direction::Left,
+                    _ => Direction::Right,
+                }
+            }
+            CellValue::Dir(dir) => self.ip.direction = dir,
+            CellValue::If(dir) => {
+                let zero = self.pop() == 0;
+                self.ip.direction = match (dir, zero) {
+                    (IfDir::Horizontal, true) => Direction::Right,
+                    (IfDir::Horizontal, false) => Direction::Left,
+                    (IfDir::Vertical, true) => Direction::Down,
+                    (IfDir::Vertical, false) => Direction::Up,
+                };
+            }
+            CellValue::Op(op) => self.execute_operator(op)?,
+        }
+
+        Ok(())
+    }
+
+    fn execute_operator(&mut self, op: Operator) -> Result<()> {
+        match op {
+            Operator::Nullary(NullaryOperator::Integer) => match self.read_integer() {
+                Some(n) => self.push(n),
+                None => self.status = Status::WaitingForInput,
+            },
+            Operator::Nullary(NullaryOperator::Ascii) => match self.input.pop_front() {
+                Some(c) => {
+                    if self.echo {
+                        self.buffer.push(c);
+                    }
+                    self.push(c as i32)
+                }
+                None => self.status = Status::WaitingForInput,
+            },
+            Operator::Unary(op) => {
+                let a = self.pop();
+                match op {
+                    UnaryOperator::Negate => {
+                        self.push(if a == 0 { self.dialect.truth() } else { 0 })
+                    }
+                    UnaryOperator::Duplicate => {
+                        self.push(a);
+                        self.push(a);
+                    }
+                    UnaryOperator::Pop => (),
+                    UnaryOperator::WriteNumber => self.buffer.push_str(&format!("{a} ")),
+                    UnaryOperator::WriteASCII => self
+                        .buffer
+                        .push(char::from_u32(a as u32).unwrap_or(char::REPLACEMENT_CHARACTER)),
+                }
+            }
+            Operator::Binary(op) => {
+                let b = self.pop();
+                let a = self.pop();
+                match op {
+                    BinaryOperator::Greater => self.push((a > b) as i32),
+                    BinaryOperator::Add => self.push(a.wrapping_add(b)),
+                    BinaryOperator::Subtract => self.push(a.wrapping_sub(b)),
+                    BinaryOperator::Multiply => self.push(a.wrapping_mul(b)),
+                    BinaryOperator::Divide => self.push(a.checked_div(b).unwrap_or(0)),
+                    BinaryOperator::Modulo => self.push(a.checked_rem(b).unwrap_or(0)),
+                    BinaryOperator::Swap => {
+                        self.push(b);
+                        self.push(a);
+                    }
+                    BinaryOperator::Get => {
+                        let (x, y) = self.checked_position(a, b)?;
+                        self.read = Some((x, y));
+                        self.push(char::from(self.grid.get(x, y).value) as i32);
+                    }
+                }
+            }
+            Operator::Ternary(TernaryOperator::Put) => {
+                let y = self.pop();
+                let x = self.pop();
+                let v = self.pop();
+                let (x, y) = self.checked_position(x, y)?;
+                let c = u32::try_from(v)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or(Error::InvalidValue(v))?;
+                self.written = Some(Write {
+                    position: (x, y),
+                    old: char::from(self.grid.get(x, y).value),
+                    new: c,
+                });
+                let cell = self.grid.get(x, y);
+                self.grid.set_cell(
+                    x,
+                    y,
+                    Cell {
+                        value: CellValue::from(c),
+                        origin: Origin::Written(self.ticks),
+                        ..cell
+                    },
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Moves the instruction pointer one cell in its current direction, wrapping around edges.
+    fn advance(&mut self) {
+        self.ip.position = self.grid.neighbour(self.ip.position, self.ip.direction);
+    }
+
+    /// Reads the run of digits starting with `first` in the direction of travel, leaving the
+    /// instruction pointer on its last digit.
+    fn read_literal(&mut self, first: u32) -> i32 {
+        let start = self.ip.position;
+        let mut value = first as i32;
+
+        loop {
+            let next = self.grid.neighbour(self.ip.position, self.ip.direction);
+            let CellValue::Number(digit) = self.grid.get(next.0, next.1).value else {
+                break;
+            };
+            // A line made of digits only would be read forever
+            if next == start {
+                break;
+            }
+
+            value = value.saturating_mul(10).saturating_add(digit as i32);
+            self.ip.position = next;
+        }
+
+        value
+    }
+
+    /// Absolute position of coordinates relative to the storage offset.
+    fn checked_position(&self, x: i32, y: i32) -> Result<(usize, usize)> {
+        let (width, height) = self.grid.size();
+        let (x, y) = (
+            x.wrapping_add(self.storage_offset.0),
+            y.wrapping_add(self.storage_offset.1),
+        );
+
+        if (0..width as i32).contains(&x) && (0..height as i32).contains(&y) {
+            Ok((x as usize, y as usize))
+        } else {
+            Err(Error::OutOfBounds(x, y))
+        }
+    }
+
+    /// Reads a possibly negative decimal integer from the input queue, skipping leading garbage.
+    /// Leaves the queue untouched if no digit is available yet.
+    fn read_integer(&mut self) -> Option<i32> {
+        let start = self.input.iter().position(|c| c.is_ascii_digit())?;
+        let negative = start > 0 && self.input[start - 1] == '-';
+
+        let skipped = self.input.drain(..start).collect::<String>();
+        if self.echo {
+            self.buffer.push_str(&skipped);
+        }
+
+        let mut value: i32 = 0;
+        while let Some(c) = self.input.front().copied() {
+            let Some(digit) = c.to_digit(10) else {
+                break;
+            };
+            value = value.saturating_mul(10).saturating_add(digit as i32);
+            self.input.pop_front();
+            if self.echo {
+                self.buffer.push(c);
+            }
+        }
+
+        Some(if negative { -value } else { value })
+    }
+
+    /// Draws from the entropy source backing the `?` instruction.
+    fn random(&mut self) -> u64 {
+        self.entropy.next_u64()
+    }
+
+    fn push(&mut self, value: i32) {
+        self.ip.stack.push(value);
+    }
+
+    /// Pops the top of the stack, an empty stack yields zeroes.
+    fn pop(&mut self) -> i32 {
+        self.ip.stack.pop().unwrap_or_default()
+    }
+
+    /// Appends data to be consumed by `&` and `~`.
+    pub fn feed_input(&mut self, input: &str) {
+        self.input.extend(input.chars());
+    }
+
+    /// Number of input characters not consumed yet.
+    pub fn pending_input(&self) -> usize {
+        self.input.len()
+    }
+
+    /// Drops input that was not consumed yet.
+    pub fn clear_input(&mut self) {
+        self.input.clear();
+    }
+
+    /// Moves buffered output to the output taken through [Interpreter::take_output].
+    pub fn flush(&mut self) {
+        self.output.push_str(&self.buffer);
+        self.buffer.clear();
+    }
+
+    fn flush_if_due(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        if self.status != Status::Running {
+            self.flush();
+            return;
+        }
+
+        match self.flush {
+            Flush::Tick => self.flush(),
+            Flush::Bytes(bytes) if self.buffer.len() >= bytes => self.flush(),
+            Flush::Newline => {
+                if let Some(end) = self.buffer.rfind('\n') {
+                    self.output.extend(self.buffer.drain(..=end));
+                }
+            }
+            Flush::Bytes(_) | Flush::Input => (),
+        }
+    }
+
+    /// Makes `?` follow the same sequence of directions on every run.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.entropy.reseed(seed);
+    }
+
+    pub fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    /// Follows the semantics of another dialect, growing the grid to the dialect's page if it has
+    /// one.
+    pub fn set_dialect(&mut self, dialect: Dialect) {
+        self.dialect = dialect;
+
+        if let Some((width, height)) = dialect.page() {
+            self.grid.grow_to(width - 1, height - 1);
+        }
+    }
+
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    pub fn enable(&mut self, extension: Extension) {
+        self.extensions.insert(extension);
+    }
+
+    pub fn extensions(&self) -> &BTreeSet<Extension> {
+        &self.extensions
+    }
+
+    /// Sets what `y` pushes as the command line, the program name coming first.
+    pub fn set_arguments(&mut self, arguments: Vec<String>) {
+        self.arguments = arguments;
+    }
+
+    /// Sets the environment variables `y` pushes.
+    pub fn set_environment(&mut self, environment: Vec<(String, String)>) {
+        self.environment = environment;
+    }
+
+    /// Picks which instruction pointer runs next, random schedules following the seed.
+    pub fn set_schedule(&mut self, schedule: Schedule) {
+        self.schedule = schedule;
+    }
+
+    pub fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+
+    /// Writes input consumed by `&` and `~` to the output as it is read, the way a terminal
+    /// shows what is typed.
+    pub fn set_echo(&mut self, echo: bool) {
+        self.echo = echo;
+    }
+
+    /// Lets `cancellation` abort runs, checked every [CANCELLATION_INTERVAL] ticks so that
+    /// runaway programs can be stopped from another thread.
+    pub fn set_cancellation(&mut self, cancellation: Cancellation) {
+        self.cancellation = Some(cancellation);
+        self.next_check = self.ticks;
+    }
+
+    pub fn set_flush(&mut self, flush: Flush) {
+        self.flush = flush;
+    }
+
+    /// Takes all output flushed since the last call.
+    pub fn take_output(&mut self) -> String {
+        self.taken += self.output.chars().count();
+        std::mem::take(&mut self.output)
+    }
+
+    /// Number of output characters taken so far through [Interpreter::take_output].
+    pub fn taken_output(&self) -> usize {
+        self.taken
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    pub fn grid_mut(&mut self) -> &mut Grid {
+        &mut self.grid
+    }
+
+    pub fn position(&self) -> (usize, usize) {
+        self.ip.position
+    }
+
+    /// Cell read by `g` during the last step, if any.
+    pub fn read(&self) -> Option<(usize, usize)> {
+        self.read
+    }
+
+    /// Cell changed by `p` during the last step, if any.
+    pub fn written(&self) -> Option<Write> {
+        self.written
+    }
+
+    /// Instruction pointer executing the next instruction.
+    pub fn ip(&self) -> &Ip {
+        &self.ip
+    }
+
+    /// Every instruction pointer, in the order they run next.
+    pub fn ips(&self) -> impl Iterator<Item = &Ip> {
+        std::iter::once(&self.ip).chain(&self.waiting)
+    }
+
+    /// Origin `g` and `p` coordinates are relative to.
+    pub fn storage_offset(&self) -> (i32, i32) {
+        self.storage_offset
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.ip.direction
+    }
+
+    pub fn stack(&self) -> &[i32] {
+        &self.ip.stack
+    }
+
+    pub fn string_mode(&self) -> bool {
+        self.ip.string_mode
+    }
+
+    /// Code the program ended with, if it quit with `q` rather than reaching `@`.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    /// Cell of the assertion that failed, if the program ended on one.
+    pub fn failed_assertion(&self) -> Option<(usize, usize)> {
+        self.assertion
+    }
+
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    /// Instruction waiting for input, `&` reading a number and `~` a single character.
+    pub fn awaited_input(&self) -> Option<NullaryOperator> {
+        if self.status != Status::WaitingForInput {
+            return None;
+        }
+
+        let (x, y) = self.ip.position;
+        match self.grid.get(x, y).value {
+            CellValue::Op(Operator::Nullary(op)) => Some(op),
+            _ => None,
+        }
+    }
+}
+
+/// Year, month and day of a number of days since the Unix epoch.
+fn civil(days: u64) -> (i32, i32, i32) {
+    // Days since the 1st of March of year 0, years starting in March so that leap days end them
+    let days = days as i64 + 719_468;
+    let (era, day_of_era) = (days / 146_097, days % 146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = era * 400 + year_of_era + i64::from(month <= 2);
+    (year as i32, month as i32, day as i32)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sources::FixedClock;
+
+    use super::*;
+
+    fn run(program: &str, input: &str) -> Interpreter {
+        let mut interpreter = Interpreter::new(Grid::from(program.to_owned()));
+        interpreter.feed_input(input);
+
+        for _ in 0..10_000 {
+            match interpreter.step().unwrap() {
+                Status::Running => (),
+                _ => break,
+            }
+        }
+
+        interpreter
+    }
+
+    #[test]
+    fn hello_world() {
+        let mut interpreter = run(r#""!dlroW ,olleH">:#,_@"#, "");
+        assert_eq!(interpreter.status(), Status::Terminated);
+        assert_eq!(interpreter.take_output(), "Hello, World!");
+    }
+
+    #[test]
+    fn arithmetic() {
+        let mut interpreter = run("93-.94*.92/.92%.23`.@", "");
+        assert_eq!(interpreter.take_output(), "6 36 4 1 0 ");
+    }
+
+    #[test]
+    fn wrapping_and_if() {
+        let mut interpreter = run("<@.1", "");
+        assert_eq!(interpreter.take_output(), "1 ");
+
+        let mut interpreter = run("v\n0\n|\n7\n.\n@", "");
+        assert_eq!(interpreter.take_output(), "7 ");
+
+        let mut interpreter = run("1#@_2.@", "");
+        assert_eq!(interpreter.status(), Status::Terminated);
+        assert_eq!(interpreter.take_output(), "");
+    }
+
+    #[test]
+    fn self_modification() {
+        let mut interpreter = run("\"@\"80p5. ", "");
+        assert_eq!(interpreter.status(), Status::Terminated);
+        assert_eq!(interpreter.take_output(), "5 ");
+        assert_eq!(char::from(interpreter.grid().get(8, 0).value), '@');
+    }
+
+    #[test]
+    fn input() {
+        let mut interpreter = run("&&+.~,@", "12 -4\nx");
+        assert_eq!(interpreter.take_output(), "8 \n");
+
+        let mut interpreter = run("&.@", "");
+        assert_eq!(interpreter.status(), Status::WaitingForInput);
+        interpreter.feed_input("42");
+        while interpreter.step().unwrap() == Status::Running {}
+        assert_eq!(interpreter.take_output(), "42 ");
+
+        // Consumed input is echoed as it is read, the `x` never being read
+        let mut interpreter = Interpreter::new(Grid::from("&&+.~,@".to_owned()));
+        interpreter.set_echo(true);
+        interpreter.feed_input("12 -4\nx");
+        while interpreter.step().unwrap() == Status::Running {}
+        assert_eq!(interpreter.take_output(), "12 -48 \n\n");
+    }
+
+    #[test]
+    fn fast_forward() {
+        let program = format!(">{0}1v\n@.{0}<", " ".repeat(50));
+
+        let plain = run(&program, "");
+
+        let mut interpreter = Interpreter::new(Grid::from(program));
+        interpreter.set_fast_forward(true);
+        let mut steps = 0;
+        while interpreter.step().unwrap() == Status::Running {
+            steps += 1;
+        }
+
+        assert_eq!(interpreter.ticks(), plain.ticks());
+        assert_eq!(interpreter.take_output(), "1 ");
+        assert!(steps < 10);
+    }
+
+    #[test]
+    fn fast_forward_concurrent() {
+        let output = |fast_forward| {
+            let mut interpreter = Interpreter::new(Grid::from("t   9.@ @.+11".to_owned()));
+            interpreter.enable(Extension::Concurrent);
+            interpreter.set_fast_forward(fast_forward);
+            while interpreter.step().unwrap() == Status::Running {}
+            interpreter.take_output()
+        };
+
+        assert_eq!(output(true), output(false));
+    }
+
+    #[test]
+    fn flush() {
+        let mut interpreter = Interpreter::new(Grid::from("1.25*,2.3.~@".to_owned()));
+        interpreter.set_flush("newline".parse().unwrap());
+
+        let mut outputs = Vec::new();
+        while interpreter.step().unwrap() == Status::Running {
+            outputs.push(interpreter.take_output());
+        }
+        outputs.retain(|output| !output.is_empty());
+        assert_eq!(outputs, vec!["1 \n"]);
+        assert_eq!(interpreter.status(), Status::WaitingForInput);
+        assert_eq!(interpreter.take_output(), "2 3 ");
+
+        assert_eq!("16".parse(), Ok(Flush::Bytes(16)));
+        assert!("often".parse::<Flush>().is_err());
+    }
+
+    #[test]
+    fn dialect() {
+        let mut interpreter = Interpreter::new(Grid::from("<0!.@".to_owned()));
+        interpreter.set_dialect("96".parse().unwrap());
+        interpreter.step().unwrap();
+        // `<` wraps to the end of the page rather than the end of the program
+        assert_eq!(interpreter.position(), (79, 0));
+
+        let mut interpreter = Interpreter::new(Grid::from("0!.@".to_owned()));
+        interpreter.set_dialect(Dialect::Befunge96);
+        while interpreter.step().unwrap() == Status::Running {}
+        assert_eq!(interpreter.take_output(), "-1 ");
+    }
+
+    #[test]
+    fn multi_digit() {
+        let program = "1000.v\n   @.<";
+
+        let mut interpreter = run(program, "");
+        assert_eq!(interpreter.take_output(), "0 0 ");
+
+        let mut interpreter = Interpreter::new(Grid::from(program.to_owned()));
+        interpreter.enable("multi-digit".parse().unwrap());
+        while interpreter.step().unwrap() == Status::Running {}
+        assert_eq!(interpreter.take_output(), "1000 0 ");
+        assert_eq!(interpreter.ticks(), 6);
+    }
+
+    #[test]
+    fn concurrent() {
+        let program = "1.t2.@";
+
+        let mut interpreter = run(program, "");
+        assert_eq!(interpreter.take_output(), "1 2 ");
+
+        // The child goes left from `t` with a copy of the emptied stack, so it prints a 0
+        let mut interpreter = Interpreter::new(Grid::from(program.to_owned()));
+        interpreter.enable(Extension::Concurrent);
+        for _ in 0..3 {
+            interpreter.step().unwrap();
+        }
+        assert_eq!(
+            interpreter
+                .ips()
+                .map(|ip| (ip.id, ip.position))
+                .collect::<Vec<_>>(),
+            vec![(1, (1, 0)), (0, (3, 0))]
+        );
+        while interpreter.step().unwrap() == Status::Running {}
+        assert_eq!(interpreter.take_output(), "1 0 2 ");
+        assert_eq!(interpreter.ips().count(), 1);
+    }
+
+    #[test]
+    fn schedule() {
+        assert_eq!("random".parse(), Ok(Schedule::Random));
+        assert_eq!("weighted:3,0".parse(), Ok(Schedule::Weighted(vec![3, 0])));
+        assert_eq!(
+            "weighted:a".parse::<Schedule>(),
+            Err(Error::Schedule("weighted:a".to_owned()))
+        );
+
+        // Starved of turns, the parent only runs once its child is gone
+        let mut interpreter = Interpreter::new(Grid::from("1.t2.@".to_owned()));
+        interpreter.enable(Extension::Concurrent);
+        interpreter.set_schedule(Schedule::Weighted(vec![0, 1]));
+        while interpreter.step().unwrap() == Status::Running {}
+        assert_eq!(interpreter.take_output(), "1 0 2 ");
+
+        // Whichever runs first, the child ends right away
+        for seed in 0..20 {
+            let mut interpreter = Interpreter::new(Grid::from("t1.@".to_owned()));
+            interpreter.enable(Extension::Concurrent);
+            interpreter.set_schedule(Schedule::Random);
+            interpreter.set_seed(seed);
+            while interpreter.step().unwrap() == Status::Running {}
+            assert_eq!(interpreter.take_output(), "1 ");
+        }
+    }
+
+    #[test]
+    fn sources() {
+        // Always picking the same direction, `?` sends the pointer down onto the `2`
+        #[derive(Clone, Debug)]
+        struct Constant;
+        impl Entropy for Constant {
+            fn next_u64(&mut self) -> u64 {
+                1
+            }
+            fn reseed(&mut self, _: u64) {}
+            fn boxed_clone(&self) -> Box<dyn Entropy> {
+                Box::new(self.clone())
+            }
+        }
+
+        let grid = crate::grid!["v", "?1.@", "2", ".", "@"];
+        let mut interpreter =
+            Interpreter::with_sources(grid.clone(), FixedClock::default(), Constant);
+        while interpreter.step().unwrap() == Status::Running {}
+        assert_eq!(interpreter.take_output(), "2 ");
+
+        // The same time seeds the same sequence
+        let outputs = (0..2).map(|_| {
+            let clock = FixedClock(std::time::Duration::from_secs(42));
+            let mut interpreter = Interpreter::with_clock(Grid::from("??????@".to_owned()), clock);
+            (0..100).map(|_| interpreter.random()).collect::<Vec<_>>()
+        });
+        let outputs = outputs.collect::<Vec<_>>();
+        assert_eq!(outputs[0], outputs[1]);
+    }
+
+    #[test]
+    fn system_info() {
+        // 2024-02-29 13:05:09
+        let clock = FixedClock(std::time::Duration::from_secs(1_709_211_909));
+        let grid = Grid::from("45*y.37*y.1y.0y@".to_owned());
+        let mut interpreter = Interpreter::with_clock(grid, clock);
+        interpreter.enable(Extension::SystemInfo);
+        interpreter.set_arguments(vec!["prog".to_owned(), "ab".to_owned()]);
+        interpreter.set_environment(vec![("K".to_owned(), "v".to_owned())]);
+        while interpreter.step().unwrap() == Status::Running {}
+
+        assert_eq!(interpreter.take_output(), "8127005 853257 0 ");
+        let strings = "\0\0v=K\0\0ba\0gorp".chars().map(|c| c as i32);
+        assert!(interpreter
+            .stack()
+            .starts_with(&strings.collect::<Vec<_>>()));
+        assert_eq!(interpreter.stack()[14], 0);
+    }
+
+    #[test]
+    fn quit() {
+        let run = |program: &str, extensions: &[Extension]| {
+            let mut interpreter = Interpreter::new(Grid::from(program.to_owned()));
+            for extension in extensions {
+                interpreter.enable(*extension);
+            }
+            while interpreter.step().unwrap() == Status::Running {}
+            interpreter
+        };
+
+        let interpreter = run("3q@", &[]);
+        assert_eq!(
+            (interpreter.exit_code(), interpreter.stack()),
+            (None, &[3][..])
+        );
+        assert_eq!(run("3q@", &[Extension::Quit]).exit_code(), Some(3));
+
+        // Every instruction pointer stops, the other one looping on the second row
+        let interpreter = run("t7q  v\n     <", &[Extension::Quit, Extension::Concurrent]);
+        assert_eq!(interpreter.exit_code(), Some(7));
+        assert_eq!(interpreter.ips().count(), 1);
+    }
+
+    #[test]
+    fn assert() {
+        let mut interpreter = Interpreter::new(Grid::from("1A.0A@".to_owned()));
+        interpreter.enable(Extension::Assert);
+        while let Ok(Status::Running) = interpreter.step() {}
+        assert_eq!(interpreter.status(), Status::Terminated);
+        assert_eq!(interpreter.failed_assertion(), Some((4, 0)));
+
+        // `A` is a no-op without the extension
+        let mut interpreter = Interpreter::new(Grid::from("1A0A@".to_owned()));
+        while interpreter.step().unwrap() == Status::Running {}
+        assert_eq!(interpreter.failed_assertion(), None);
+        assert_eq!(interpreter.stack(), &[1, 0]);
+    }
+
+    #[test]
+    fn origin() {
+        let mut grid = Grid::from("\"A\"20p @".to_owned());
+        grid.edit(6, 0, CellValue::from('@'));
+        grid.edit(7, 0, CellValue::Empty);
+
+        let mut interpreter = Interpreter::new(grid);
+        while interpreter.step().unwrap() == Status::Running {}
+        let grid = interpreter.grid();
+        let origins = [0, 2, 6, 7].map(|x| grid.get(x, 0).origin);
+        assert_eq!(
+            origins,
+            [
+                Origin::Loaded,
+                Origin::Written(5),
+                Origin::Edited,
+                Origin::Edited
+            ]
+        );
+        assert_eq!(
+            grid.get(2, 0).origin.to_string(),
+            "written by `p` at tick 5"
+        );
+    }
+
+    #[test]
+    fn cancellation() {
+        let cancellation = Cancellation::default();
+        let mut interpreter = Interpreter::new(Grid::from(">".to_owned()));
+        interpreter.set_cancellation(cancellation.clone());
+        for _ in 0..2 * CANCELLATION_INTERVAL {
+            interpreter.step().unwrap();
+        }
+
+        let handle = std::thread::spawn(move || cancellation.cancel());
+        handle.join().unwrap();
+        let cancelled = (0..=CANCELLATION_INTERVAL).find_map(|_| interpreter.step().err());
+        assert_eq!(cancelled, Some(Error::Cancelled(2 * CANCELLATION_INTERVAL)));
+        assert!(interpreter.step().is_err());
+    }
+}
@@ -0,0 +1,161 @@
+use std::path::Path;
+
+use puccinia::{
+    analyzer::Severity,
+    golf::{self, Leaderboard, Metrics, Solution},
+    interpreter::Interpreter,
+    report::{Entry, Report},
+};
+
+use crate::headless::{self, Ending, Geometry};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Could not read `{0}`: {1}")]
+    Read(String, std::io::Error),
+    #[error("Could not write `{0}`: {1}")]
+    Write(String, std::io::Error),
+    #[error("Invalid leaderboard `{0}`: {1}")]
+    Invalid(String, golf::Error),
+    #[error("No solution named `{0}`")]
+    Unknown(String),
+    #[error("Solution `{0}` has no expected output, give it an `expected` file or an `output` directive")]
+    NoExpectation(String),
+    #[error("{0} of {1} solutions failed")]
+    Failures(usize, usize),
+}
+
+#[derive(clap::Args)]
+pub(crate) struct Options {
+    /// Solutions to update, all of them by default
+    #[arg(value_name = "NAME")]
+    names: Vec<String>,
+
+    /// Leaderboard file, with a `[solutions.NAME]` table holding the `path` of each program along
+    /// with its `input` and `expected` output files if any
+    #[arg(long, value_name = "PATH", default_value = golf::LEADERBOARD)]
+    file: String,
+
+    /// Instructions after which a solution is considered to run forever
+    #[arg(long, value_name = "N", default_value_t = 10_000_000)]
+    max_ticks: u64,
+
+    /// Write the solutions that failed to this file for editors and CI, as JSON or as SARIF if
+    /// it ends with `.sarif`
+    #[arg(long, value_name = "PATH")]
+    diagnostics: Option<String>,
+}
+
+/// Measures the solutions of a leaderboard and records their scores in it, checking that they
+/// write their expected output when `verify` is set.
+pub(crate) fn run(options: Options, verify: bool) -> anyhow::Result<()> {
+    let text = std::fs::read_to_string(&options.file)
+        .map_err(|err| Error::Read(options.file.clone(), err))?;
+    let mut leaderboard =
+        Leaderboard::parse(&text).map_err(|err| Error::Invalid(options.file.clone(), err))?;
+    if let Some(name) = options.names.iter().find(|name| {
+        !leaderboard
+            .solutions
+            .iter()
+            .any(|solution| solution.name == **name)
+    }) {
+        return Err(Error::Unknown(name.clone()).into());
+    }
+
+    let root = Path::new(&options.file)
+        .parent()
+        .unwrap_or(Path::new("."))
+        .to_path_buf();
+    let mut failures = 0;
+    let mut updated = 0;
+    let mut report = Report::default();
+    for solution in leaderboard
+        .solutions
+        .iter_mut()
+        .filter(|solution| options.names.is_empty() || options.names.contains(&solution.name))
+    {
+        updated += 1;
+        let path = root.join(&solution.path).display().to_string();
+        let (code, message) = match update(solution, &root, verify, options.max_ticks) {
+            Ok(true) => continue,
+            Ok(false) => ("wrong-output", "wrong output".to_owned()),
+            Err(err) => ("solution", err.to_string()),
+        };
+        failures += 1;
+        eprintln!("{}: {message}", solution.name);
+        let message = format!("Solution `{}`: {message}", solution.name);
+        report
+            .diagnostics
+            .push(Entry::new(&path, Severity::Error, code, message));
+    }
+    if let Some(path) = &options.diagnostics {
+        headless::write_report(path, &report)?;
+    }
+
+    std::fs::write(&options.file, leaderboard.to_text())
+        .map_err(|err| Error::Write(options.file.clone(), err))?;
+
+    println!(
+        "{:<4} {:<20} {:>8} {:>10}  verified",
+        "rank", "solution", "bytes", "steps"
+    );
+    for (rank, solution) in leaderboard.ranking().into_iter().enumerate() {
+        let score = |score: Option<u64>| score.map_or("-".to_owned(), |score| score.to_string());
+        let verified = match solution.verified {
+            Some(true) => "yes",
+            Some(false) => "no",
+            None => "-",
+        };
+        println!(
+            "{:<4} {:<20} {:>8} {:>10}  {verified}",
+            rank + 1,
+            solution.name,
+            score(solution.bytes.map(|bytes| bytes as u64)),
+            score(solution.steps),
+        );
+    }
+
+    if verify && failures > 0 {
+        return Err(Error::Failures(failures, updated).into());
+    }
+    Ok(())
+}
+
+/// Runs a solution and records its scores, returning whether it wrote the expected output if
+/// `verify` is set.
+fn update(
+    solution: &mut Solution,
+    root: &Path,
+    verify: bool,
+    max_ticks: u64,
+) -> anyhow::Result<bool> {
+    let read = |path: &str| {
+        let path = root.join(path);
+        std::fs::read_to_string(&path).map_err(|err| Error::Read(path.display().to_string(), err))
+    };
+
+    let path = root.join(&solution.path).display().to_string();
+    let (grid, directives, canned) = headless::load(&path, Geometry::default())?;
+    let input = match &solution.input {
+        Some(input) => read(input)?,
+        None => canned.unwrap_or_default(),
+    };
+    let expected = match &solution.expected {
+        Some(expected) => Some(read(expected)?),
+        None => directives.output.clone(),
+    };
+
+    solution.bytes = Some(Metrics::of(&grid).bytes);
+    let mut interpreter = Interpreter::new(grid);
+    headless::configure(&mut interpreter, &directives, None, &[], None);
+    let ending = headless::run_to_end(&mut interpreter, &input, max_ticks);
+    solution.steps = Some(interpreter.ticks());
+
+    if !verify {
+        return Ok(true);
+    }
+    let expected = expected.ok_or_else(|| Error::NoExpectation(solution.name.clone()))?;
+    let verified = ending == Ending::Ended && interpreter.take_output() == expected;
+    solution.verified = Some(verified);
+    Ok(verified)
+}
@@ -0,0 +1,40 @@
+pub mod analyzer;
+pub mod annotation;
+#[cfg(any(test, feature = "test-util"))]
+pub mod arbitrary;
+pub mod assembler;
+pub mod bitmap;
+pub mod brainfuck;
+pub mod builder;
+pub mod bundle;
+pub mod cell;
+pub mod config;
+pub mod debugger;
+pub mod decompiler;
+pub mod dialect;
+pub mod directives;
+pub mod forecast;
+pub mod frames;
+pub mod golf;
+pub mod grid;
+pub mod heatmap;
+pub mod interpreter;
+pub mod loops;
+pub mod narrator;
+pub mod quotes;
+pub mod renderer;
+pub mod report;
+pub mod routines;
+pub mod script;
+pub mod sidecar;
+pub mod sources;
+pub mod statistics;
+pub mod suite;
+pub mod svg;
+pub mod synthesis;
+pub mod templates;
+pub mod timeline;
+pub mod toml;
+pub mod trace;
+pub mod upgrader;
+pub mod watch;
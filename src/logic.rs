@@ -1,9 +1,73 @@
 use std::{
-    sync::mpsc::{Receiver, Sender},
-    time::Duration,
+    collections::{BTreeMap, HashSet},
+    fmt::Display,
+    io::ErrorKind,
+    path::Path,
+    str::FromStr,
+    sync::mpsc::{Receiver, Sender, TryRecvError},
+    time::{Duration, Instant},
 };
 
-use crate::{cell::CellValue, frontend, grid::Grid, Args};
+use serde::{Deserialize, Serialize};
+
+use puccinia::{
+    analyzer,
+    annotation::{self, Label, Region},
+    cell::CellValue,
+    config::Layout,
+    debugger::{Debugger, Prediction, Stop, Target, DEFAULT_HISTORY},
+    dialect::Extension,
+    directives::{self, Directives},
+    forecast::{Forecast, Sample},
+    grid::{Changes, Grid},
+    interpreter::Interpreter,
+    quotes,
+    sidecar::Sidecar,
+    svg, synthesis,
+    watch::Watch,
+};
+
+use crate::{
+    collab::{Session, Update},
+    control::{self, Command, Reply, Snapshot},
+    ex::{self, Breakpoint, Ex, Export, Feed, IpEvent, Snippet},
+    frontend::{self, RunState},
+    headless::Geometry,
+    settings::Semantics,
+};
+
+/// Instructions executed per frame while running, unless told otherwise.
+const TICKS_PER_FRAME: usize = 20;
+
+/// Instructions run at once by the adaptive pace before checking the time left in the frame.
+const ADAPTIVE_CHUNK: usize = 64;
+
+/// Time the adaptive pace spends running each frame, the rest of [ADAPTIVE_FRAME] being left to
+/// sending the run state and drawing it.
+const ADAPTIVE_BUDGET: Duration = Duration::from_millis(10);
+
+/// Frame time of the adaptive pace, for 60 frames per second.
+const ADAPTIVE_FRAME: Duration = Duration::from_micros(16_667);
+
+/// Executed instructions remembered for trace exports, only the latest ones being shown.
+const TRACE_LENGTH: usize = 100_000;
+
+/// Latest grid writes sent to the frontend's journal panel.
+const JOURNAL_SHOWN: usize = 1000;
+
+/// Executed instructions the order instruction pointers took turns in is shown for.
+const ORDER_LENGTH: usize = 64;
+
+/// Ticks the path of a paused run is predicted for.
+const PREDICTION_TICKS: u64 = 64;
+
+/// Latest executed instructions sent to the frontend for the trails of instruction pointers.
+const TRAIL_SHOWN: usize = 256;
+
+/// Number of timeline samples sent to the frontend.
+const TIMELINE_RESOLUTION: usize = 256;
+
+const FRAME: Duration = Duration::from_millis(33);
 
 #[derive(thiserror::Error, Clone, Debug)]
 #[allow(unused)]
@@ -12,14 +76,19 @@ pub enum Error {
     Unknown(String),
     #[error("Load error: {0:?}")]
     FileError(FileError),
+    #[error("Invalid pace `{0}`, expected a positive number of instructions per frame or `auto`")]
+    Pace(String),
 }
 
 #[derive(Clone, Debug)]
+#[allow(unused)]
 pub enum FileError {
     FileNotFound(String),
+    /// Program that could be read but not loaded, along with why
+    Invalid(String, String),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 #[allow(unused)]
 pub enum Message {
     Kill,
@@ -31,64 +100,1369 @@ pub enum Message {
         y: usize,
         v: char,
     },
+    /// Cursor moved, shared with collaborators
+    Cursor {
+        x: usize,
+        y: usize,
+    },
     RunningCommand(RunningCommand),
+    /// Show the value of an expression such as `stack[0]` while running
+    Watch(String),
+    /// Mark a cell with a letter, or remove the bookmark without a position
+    Bookmark {
+        name: char,
+        position: Option<(usize, usize)>,
+    },
+    /// Attach a note to a region, or remove it without text
+    Annotate {
+        region: Region,
+        text: Option<String>,
+    },
+    /// Name a region or fold it, an empty name removing the label
+    Label(Label),
+    /// Command typed after `:`, such as `w` or `export svg`
+    Ex(String),
+    /// Open a program in a new buffer, left in the background
+    Open(String),
+    /// Write input to the output as runs read it
+    EchoInput(bool),
+    /// Change how many instructions run per frame
+    Pace(Pace),
+    /// Layouts of the configuration, switched to with `:layout`
+    Layouts(BTreeMap<String, Layout>),
+    /// Panels as rearranged in the TUI, kept for the program
+    Layout(Layout),
+    /// Commands of a file given with `--cmd-file`, named first, one per line
+    Script(String, String),
+    /// Dialect and extensions of the settings, for programs leaving them out
+    Settings(Semantics),
 }
 
-#[derive(Debug)]
+/// How many instructions run between two frames while running.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Pace {
+    /// A fixed number of instructions, drawn about 30 times per second
+    Ticks(usize),
+    /// As many instructions as fit in a frame at 60 frames per second
+    Adaptive,
+}
+
+impl Default for Pace {
+    fn default() -> Self {
+        Pace::Ticks(TICKS_PER_FRAME)
+    }
+}
+
+impl FromStr for Pace {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Error> {
+        match s {
+            "auto" => Ok(Pace::Adaptive),
+            s => match s.parse() {
+                Ok(0) | Err(_) => Err(Error::Pace(s.to_owned())),
+                Ok(ticks) => Ok(Pace::Ticks(ticks)),
+            },
+        }
+    }
+}
+
+impl Display for Pace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pace::Ticks(ticks) => write!(f, "{ticks}"),
+            Pace::Adaptive => write!(f, "auto"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub enum RunningCommand {
+    /// Start a new run on the current grid, paused on the first instruction
     Start,
     Step,
     SkipToBreakpoint,
+    /// Run until the target is reached, or a breakpoint on the way
+    RunTo(Target),
+    Pause,
+    /// End the current run, restoring the grid as it was before running
+    Stop,
+    ToggleBreakpoint {
+        x: usize,
+        y: usize,
+    },
+    /// Feed data to the program's input
+    Input(String),
+    /// Bring the run back (or forward) to the given tick
+    TravelTo {
+        tick: u64,
+    },
+    /// Make single steps run until the instruction pointer with this id executed an instruction,
+    /// or any instruction pointer for `None`
+    Follow(Option<u32>),
+    /// Look for the instructions of the trace that executed, read or wrote a cell
+    References((usize, usize)),
 }
 
 #[derive(Debug)]
 struct State {
     grid: Grid,
-    stack: Vec<i32>,
+    debugger: Option<Debugger>,
+    breakpoints: HashSet<(usize, usize)>,
+    running: bool,
+    /// Instruction pointer followed by single steps
+    following: Option<u32>,
+    /// Instruction pointer alone stopping at breakpoints and shown in the trace
+    ip_filter: Option<u32>,
+    /// Whether runs stop when an instruction pointer starts, and when one ends
+    ip_events: (bool, bool),
+    /// Whether consumed input is written to the output
+    echo: bool,
+    pace: Pace,
+    /// Instructions run during the latest frame
+    last_frame: u64,
+    /// How the program asks to be run
+    directives: Directives,
+    /// Enabled for every run
+    extensions: Vec<Extension>,
+    /// Dialect and extensions of the settings
+    settings: Semantics,
+    /// Collaborative edition of the grid
+    session: Option<Session>,
+    watches: Vec<Watch>,
+    /// Program file, along with what is kept next to it
+    sidecar: Option<(String, Sidecar)>,
+    /// Programs open at once, that of the current one being left empty while it is edited
+    buffers: Vec<Buffer>,
+    current: usize,
+    /// Shape of the grids of programs opened later on
+    geometry: Geometry,
+    progress: Progress,
+    /// Bytes of output runs are expected to write, set with `:forecast` rather than by the
+    /// `output` directive
+    expected: Option<usize>,
+    /// Layouts of the configuration, on top of the presets
+    layouts: BTreeMap<String, Layout>,
+    /// Input given with `:input` before any run, fed to the next one
+    queued: String,
+}
+
+/// Pace of the current run, sampled each time it is sent to the frontend.
+#[derive(Debug, Default)]
+struct Progress {
+    forecast: Forecast,
+    /// Time spent running, and when it was last accounted for if it still is
+    elapsed: Duration,
+    since: Option<Instant>,
+}
+
+impl Progress {
+    fn record(&mut self, tick: u64, bytes: usize, running: bool) {
+        let now = Instant::now();
+        if let Some(since) = self.since {
+            self.elapsed += now - since;
+        }
+        self.since = running.then_some(now);
+        self.forecast.record(Sample {
+            tick,
+            bytes,
+            elapsed: self.elapsed,
+        });
+    }
+}
+
+/// Program open in the background, switched to with `:bn`, `:bp` or `:b N`.
+#[derive(Debug, Default)]
+struct Buffer {
+    grid: Grid,
+    directives: Directives,
+    breakpoints: HashSet<(usize, usize)>,
+    watches: Vec<Watch>,
+    sidecar: Option<(String, Sidecar)>,
 }
 
 type Result<T> = anyhow::Result<T>;
 
+/// Serves a frontend, and external processes sending commands through `control` if any.
+/// Without an input, the grid is the one shared by the `session` being joined.
+/// `geometry` overrides the shape of the grid asked for by the program.
 pub(crate) fn run(
-    args: Args,
+    input: Option<String>,
+    extensions: Vec<Extension>,
+    geometry: Geometry,
     sender: Sender<crate::frontend::Message>,
     receiver: Receiver<Message>,
+    control: Option<Receiver<control::Request>>,
+    session: Option<Session>,
 ) -> Result<()> {
+    let (grid, directives, notes) = match &input {
+        Some(input) => geometry
+            .open(input.as_str())
+            .map_err(|err| match err {
+                directives::Error::Io(_) => FileError::FileNotFound(input.clone()),
+                err => FileError::Invalid(input.clone(), err.to_string()),
+            })
+            .map_err(Error::FileError)?,
+        None => Default::default(),
+    };
+    if !notes.is_empty() {
+        sender.send(frontend::Message::LogicFail(Some(notes.join(", "))))?;
+    }
+    let sidecar = match input.map(|input| Sidecar::load(&input).map(|sidecar| (input, sidecar))) {
+        Some(Ok(sidecar)) => Some(sidecar),
+        Some(Err(err)) => {
+            sender.send(frontend::Message::LogicFail(Some(err.to_string())))?;
+            None
+        }
+        None => None,
+    };
+    let (breakpoints, watches) = match &sidecar {
+        Some((_, sidecar)) => (
+            sidecar.breakpoints.iter().copied().collect(),
+            watches(sidecar, &sender)?,
+        ),
+        None => Default::default(),
+    };
     let mut state = State {
-        grid: Grid::from(std::fs::read_to_string(args.input.as_str()).map_err(|_| {
-            Error::FileError(FileError::FileNotFound(args.input.as_str().to_owned()))
-        })?),
-        stack: Vec::new(),
+        grid,
+        directives,
+        debugger: None,
+        breakpoints,
+        running: false,
+        following: None,
+        ip_filter: None,
+        ip_events: (false, false),
+        echo: false,
+        pace: Pace::default(),
+        last_frame: 0,
+        extensions,
+        settings: Semantics::default(),
+        session,
+        watches,
+        sidecar,
+        buffers: vec![Buffer::default()],
+        current: 0,
+        geometry,
+        progress: Progress::default(),
+        expected: None,
+        layouts: BTreeMap::new(),
+        queued: String::new(),
     };
 
-    sender.send(frontend::Message::Load(state.grid.clone()))?;
+    state.show(&sender)?;
 
     // Event loop
     let mut exit = false;
     while !exit {
+        let started = Instant::now();
+
         // Handle all queued events
-        while let Ok(message) = receiver.try_recv() {
-            match message {
-                Message::Kill => {
+        loop {
+            match receiver.try_recv() {
+                Ok(Message::Kill) | Err(TryRecvError::Disconnected) => {
                     exit = true;
                     break;
                 }
-                Message::GetGrid => {
+                Ok(Message::GetGrid) => {
                     sender.send(frontend::Message::Break)?;
                 }
-                Message::SetCell { x, y, v } => state.grid.set(x, y, CellValue::from(v)),
-                Message::RunningCommand(command) => match command {
-                    RunningCommand::Start => state.stack.clear(),
-                    RunningCommand::Step => todo!(),
-                    RunningCommand::SkipToBreakpoint => todo!(),
+                Ok(Message::SetCell { x, y, v }) => {
+                    state.grid.grow_to(x, y);
+                    state.grid.edit(x, y, CellValue::from(v));
+                    if let Some(session) = state.session.as_mut() {
+                        session.set((x, y), v);
+                    }
+                    state.send_strings(&sender)?;
+                }
+                Ok(Message::Cursor { x, y }) => {
+                    if let Some(session) = state.session.as_mut() {
+                        session.move_cursor((x, y));
+                    }
+                }
+                Ok(Message::RunningCommand(command)) => state.command(command, &sender)?,
+                Ok(Message::Bookmark { name, position }) => {
+                    state.edit_sidecar(&sender, |sidecar| match position {
+                        Some(position) => sidecar.bookmarks.insert(name, position),
+                        None => sidecar.bookmarks.remove(&name),
+                    })?
+                }
+                Ok(Message::Annotate { region, text }) => state
+                    .edit_sidecar(&sender, |sidecar| {
+                        annotation::annotate(&mut sidecar.annotations, region, text)
+                    })?,
+                Ok(Message::Label(label)) => state.edit_sidecar(&sender, |sidecar| {
+                    annotation::label(&mut sidecar.labels, label)
+                })?,
+                Ok(Message::Script(path, script)) => {
+                    let report = state.script(&path, &script, &sender);
+                    sender.send(frontend::Message::LogicFail(Some(report)))?;
+                }
+                Ok(Message::Ex(line)) => {
+                    let report = line
+                        .parse()
+                        .map_err(|err: ex::Error| err.to_string())
+                        .and_then(|ex| state.ex(ex, &sender));
+                    let report = report.unwrap_or_else(|err| err);
+                    sender.send(frontend::Message::LogicFail(Some(report)))?;
+                }
+                Ok(Message::Open(path)) => {
+                    if let Err(err) = state.open(path, &sender) {
+                        sender.send(frontend::Message::LogicFail(Some(err)))?;
+                    }
+                }
+                Ok(Message::EchoInput(echo)) => state.echo = echo,
+                Ok(Message::Pace(pace)) => state.pace = pace,
+                Ok(Message::Layouts(layouts)) => state.layouts = layouts,
+                Ok(Message::Settings(settings)) => {
+                    state.settings = settings;
+                    state.send_strings(&sender)?;
+                }
+                Ok(Message::Layout(layout)) => {
+                    state.edit_sidecar(&sender, |sidecar| sidecar.layout = Some(layout))?
+                }
+                Ok(Message::Watch(expression)) => match expression.parse() {
+                    Ok(watch) => {
+                        state.watch(watch, &sender)?;
+                    }
+                    Err(err) => {
+                        let error = format!("Invalid watch `{expression}`: {err}");
+                        sender.send(frontend::Message::LogicFail(Some(error)))?;
+                    }
                 },
+                Err(TryRecvError::Empty) => break,
             }
         }
 
-        std::thread::sleep(Duration::from_secs(1));
+        state.receive_edits(&sender)?;
+
+        for request in control.iter().flat_map(|control| control.try_iter()) {
+            let reply = state.control(request.command, &sender)?;
+            // The connection may have closed in the meantime
+            let _ = request.reply.send(reply);
+        }
+
+        if state.running {
+            let stop = state.advance(started);
+            state.sync(&sender, stop)?;
+        }
+
+        match state.pace {
+            Pace::Ticks(_) => std::thread::sleep(FRAME),
+            Pace::Adaptive => std::thread::sleep(ADAPTIVE_FRAME.saturating_sub(started.elapsed())),
+        }
     }
 
-    sender.send(frontend::Message::Break)?;
+    // The frontend may already be gone when it is the one asking to stop
+    let _ = sender.send(frontend::Message::Break);
 
     Ok(())
 }
+
+/// Watches kept next to a program, telling about those that don't parse anymore and dropping
+/// repeated ones.
+fn watches(sidecar: &Sidecar, sender: &Sender<frontend::Message>) -> Result<Vec<Watch>> {
+    let mut watches = Vec::<Watch>::new();
+    for expression in &sidecar.watches {
+        match expression.parse::<Watch>() {
+            Ok(watch) if watches.iter().any(|watched| watched.same(&watch)) => (),
+            Ok(watch) => watches.push(watch),
+            Err(err) => {
+                let error = format!("Invalid watch `{expression}` in the sidecar file: {err}");
+                sender.send(frontend::Message::LogicFail(Some(error)))?;
+            }
+        }
+    }
+    Ok(watches)
+}
+
+impl State {
+    /// Shows the value of an expression while running, unless it already is, telling whether it
+    /// was added.
+    fn watch(&mut self, watch: Watch, sender: &Sender<frontend::Message>) -> Result<bool> {
+        if self.watches.iter().any(|watched| watched.same(&watch)) {
+            return Ok(false);
+        }
+        self.watches.push(watch);
+        self.persist(sender)?;
+        self.sync(sender, None)?;
+        Ok(true)
+    }
+
+    /// Keeps the breakpoints and watches next to the program, for later sessions.
+    fn persist(&mut self, sender: &Sender<frontend::Message>) -> Result<()> {
+        let breakpoints = self.breakpoints.iter().copied().collect();
+        let watches = self.watches.iter().map(ToString::to_string).collect();
+        self.edit_sidecar(sender, |sidecar| {
+            sidecar.breakpoints = breakpoints;
+            sidecar.watches = watches;
+        })
+    }
+
+    /// Changes what is kept next to the program and saves it, if there is a program file.
+    fn edit_sidecar<T>(
+        &mut self,
+        sender: &Sender<frontend::Message>,
+        edit: impl FnOnce(&mut Sidecar) -> T,
+    ) -> Result<()> {
+        let Some((input, sidecar)) = self.sidecar.as_mut() else {
+            return Ok(());
+        };
+
+        edit(sidecar);
+        if let Err(err) = sidecar.save(input) {
+            let error = format!("Could not save the sidecar file: {err}");
+            sender.send(frontend::Message::LogicFail(Some(error)))?;
+        }
+        Ok(())
+    }
+
+    /// Runs the commands of a script in order, skipping blank lines and comments starting with
+    /// `#`, up to the first one that fails.
+    fn script(&mut self, path: &str, script: &str, sender: &Sender<frontend::Message>) -> String {
+        let mut count = 0;
+        for (number, line) in script.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let ex = line.parse().map_err(|err: ex::Error| err.to_string());
+            if let Err(err) = ex.and_then(|ex| self.ex(ex, sender)) {
+                return format!("{path}:{}: {err}", number + 1);
+            }
+            count += 1;
+        }
+        format!("Ran {count} command(s) from `{path}`")
+    }
+
+    /// Dialect and extensions runs of the program being edited get on top of its directives.
+    fn semantics(&self) -> Semantics {
+        Semantics::new(None, &self.extensions).or(&self.settings, &self.directives)
+    }
+
+    /// Starts a new run on the grid as it is being edited, paused on the first instruction.
+    fn start(&mut self) {
+        let mut interpreter = Interpreter::new(self.grid.clone());
+        self.directives.apply(&mut interpreter);
+        let semantics = self.semantics();
+        if let Some(dialect) = semantics.dialect {
+            interpreter.set_dialect(dialect);
+        }
+        for extension in semantics.extensions {
+            interpreter.enable(extension);
+        }
+        let mut debugger = Debugger::new(interpreter)
+            .with_history(TRACE_LENGTH)
+            .with_heatmap();
+        self.progress = Progress::default();
+        debugger.set_breakpoints(self.breakpoints.iter().copied());
+        debugger.set_ip_filter(self.ip_filter);
+        debugger.set_ip_events(self.ip_events.0, self.ip_events.1);
+        debugger.interpreter_mut().set_echo(self.echo);
+        debugger.interpreter_mut().grid_mut().invalidate();
+        debugger.feed_input(&std::mem::take(&mut self.queued));
+        self.debugger = Some(debugger);
+        self.running = false;
+    }
+
+    fn command(
+        &mut self,
+        command: RunningCommand,
+        sender: &Sender<frontend::Message>,
+    ) -> Result<()> {
+        let stop = match command {
+            RunningCommand::Start => {
+                self.start();
+                None
+            }
+            RunningCommand::Step => {
+                self.running = false;
+                self.step()
+            }
+            RunningCommand::SkipToBreakpoint => {
+                self.running = self.debugger.is_some();
+                None
+            }
+            RunningCommand::RunTo(target) => {
+                if let Some(debugger) = self.debugger.as_mut() {
+                    debugger.run_to(Some(target));
+                    self.running = true;
+                }
+                None
+            }
+            RunningCommand::Pause => {
+                self.running = false;
+                if let Some(debugger) = self.debugger.as_mut() {
+                    debugger.run_to(None);
+                }
+                None
+            }
+            RunningCommand::Stop => {
+                self.debugger = None;
+                self.running = false;
+                sender.send(frontend::Message::Load(self.grid.clone()))?;
+                return Ok(());
+            }
+            RunningCommand::ToggleBreakpoint { x, y } => {
+                if !self.breakpoints.remove(&(x, y)) {
+                    self.breakpoints.insert((x, y));
+                }
+                if let Some(debugger) = self.debugger.as_mut() {
+                    debugger.set_breakpoints(self.breakpoints.iter().copied());
+                }
+                self.persist(sender)?;
+                None
+            }
+            RunningCommand::Input(input) => {
+                if let Some(debugger) = self.debugger.as_mut() {
+                    debugger.feed_input(&input);
+                }
+                None
+            }
+            RunningCommand::TravelTo { tick } => {
+                self.running = false;
+                let Some(debugger) = self.debugger.as_mut() else {
+                    return Ok(());
+                };
+
+                let rewound = tick < debugger.interpreter().ticks();
+                let stop = match debugger.travel_to(tick) {
+                    Ok(stop) => Some(Ok(stop)),
+                    Err(err) => Some(Err(err.to_string())),
+                };
+                debugger.interpreter_mut().grid_mut().invalidate();
+
+                return self.sync_with(sender, stop, rewound);
+            }
+            RunningCommand::Follow(id) => {
+                self.following = id;
+                None
+            }
+            RunningCommand::References(cell) => {
+                if let Some(debugger) = &self.debugger {
+                    let references = debugger
+                        .references(cell)
+                        .map(|(access, entry)| (access, *entry))
+                        .collect();
+                    sender.send(frontend::Message::References(cell, references))?;
+                }
+                None
+            }
+        };
+
+        self.sync(sender, stop)
+    }
+
+    /// Applies the edits of collaborators, only shown once the current run ends if any.
+    fn receive_edits(&mut self, sender: &Sender<frontend::Message>) -> Result<()> {
+        let Some(session) = self.session.as_mut() else {
+            return Ok(());
+        };
+
+        for update in session.receive(&mut self.grid) {
+            match update {
+                Update::Cursors => sender.send(frontend::Message::Cursors(session.cursors()))?,
+                Update::Malformed(site, err) => {
+                    let error = format!("Ignoring malformed message from site {site}: {err}");
+                    sender.send(frontend::Message::LogicFail(Some(error)))?;
+                }
+                _ if self.debugger.is_some() => (),
+                Update::Load => sender.send(frontend::Message::Load(self.grid.clone()))?,
+                Update::Cell((x, y)) => sender.send(frontend::Message::SetCell {
+                    x,
+                    y,
+                    v: char::from(self.grid.get(x, y).value),
+                })?,
+            }
+        }
+
+        self.send_strings(sender)
+    }
+
+    /// Sends the cells the program being edited can push as text in string mode.
+    fn send_strings(&self, sender: &Sender<frontend::Message>) -> Result<()> {
+        let extensions = [
+            self.directives.extensions.clone(),
+            self.semantics().extensions,
+        ]
+        .concat();
+        let analysis = analyzer::analyze(&self.grid, &extensions);
+        let strings = analysis.quoted().iter().copied().collect();
+        sender.send(frontend::Message::Strings(strings))?;
+        let unbalanced = quotes::check(&self.grid, &analysis);
+        sender.send(frontend::Message::Quotes(unbalanced))?;
+        Ok(())
+    }
+
+    /// Sends the program being edited to the frontend, along with what is kept next to it.
+    fn show(&mut self, sender: &Sender<frontend::Message>) -> Result<()> {
+        let sidecar = self
+            .sidecar
+            .as_ref()
+            .map(|(_, sidecar)| sidecar.clone())
+            .unwrap_or_default();
+
+        sender.send(frontend::Message::Load(self.grid.clone()))?;
+        sender.send(frontend::Message::Bookmarks(sidecar.bookmarks))?;
+        sender.send(frontend::Message::Annotations(sidecar.annotations))?;
+        sender.send(frontend::Message::Labels(sidecar.labels))?;
+        sender.send(frontend::Message::SourceMap(sidecar.source_map))?;
+        if let Some(layout) = sidecar.layout {
+            sender.send(frontend::Message::Layout(layout))?;
+        }
+        self.send_strings(sender)?;
+        sender.send(frontend::Message::Buffers(
+            self.buffer_names(),
+            self.current,
+        ))?;
+        self.sync(sender, None)
+    }
+
+    /// File name of every open program, in buffer order.
+    fn buffer_names(&self) -> Vec<String> {
+        let name = |sidecar: &Option<(String, Sidecar)>| match sidecar {
+            Some((input, _)) => input.clone(),
+            None => "[shared]".to_owned(),
+        };
+
+        self.buffers
+            .iter()
+            .enumerate()
+            .map(|(index, buffer)| match index == self.current {
+                true => name(&self.sidecar),
+                false => name(&buffer.sidecar),
+            })
+            .collect()
+    }
+
+    /// Loads a program into a new buffer, returning its index.
+    fn open(
+        &mut self,
+        path: String,
+        sender: &Sender<frontend::Message>,
+    ) -> std::result::Result<usize, String> {
+        // Like in other editors, missing files are created once written
+        let (grid, directives, notes) = match self.geometry.open(path.as_str()) {
+            Err(directives::Error::Io(err)) if err.kind() == ErrorKind::NotFound => {
+                (Grid::new(10, 10), Directives::default(), Vec::new())
+            }
+            opened => opened.map_err(|err| format!("Could not open `{path}`: {err}"))?,
+        };
+        let sidecar = Sidecar::load(&path).map_err(|err| err.to_string())?;
+        if !notes.is_empty() {
+            let notes = format!("{path}: {}", notes.join(", "));
+            let _ = sender.send(frontend::Message::LogicFail(Some(notes)));
+        }
+        let watches = watches(&sidecar, sender).map_err(|err| err.to_string())?;
+
+        self.buffers.push(Buffer {
+            grid,
+            directives,
+            breakpoints: sidecar.breakpoints.iter().copied().collect(),
+            watches,
+            sidecar: Some((path, sidecar)),
+        });
+        let _ = sender.send(frontend::Message::Buffers(
+            self.buffer_names(),
+            self.current,
+        ));
+
+        Ok(self.buffers.len() - 1)
+    }
+
+    /// Edits another open program, the current one being kept as it is in its buffer.
+    fn switch(
+        &mut self,
+        index: usize,
+        sender: &Sender<frontend::Message>,
+    ) -> std::result::Result<String, String> {
+        if index >= self.buffers.len() {
+            return Err(format!("No buffer {}", index + 1));
+        }
+        if self.debugger.is_some() {
+            return Err("Stop the run before switching buffers".to_owned());
+        }
+        if self.session.is_some() {
+            return Err("Buffers cannot be switched while sharing the grid".to_owned());
+        }
+
+        if index != self.current {
+            self.buffers[self.current] = Buffer {
+                grid: std::mem::take(&mut self.grid),
+                directives: std::mem::take(&mut self.directives),
+                breakpoints: std::mem::take(&mut self.breakpoints),
+                watches: std::mem::take(&mut self.watches),
+                sidecar: self.sidecar.take(),
+            };
+
+            let buffer = std::mem::take(&mut self.buffers[index]);
+            self.grid = buffer.grid;
+            self.directives = buffer.directives;
+            self.breakpoints = buffer.breakpoints;
+            self.watches = buffer.watches;
+            self.sidecar = buffer.sidecar;
+            self.current = index;
+        }
+
+        self.show(sender).map_err(|err| err.to_string())?;
+        Ok(format!(
+            "Buffer {}/{}: {}",
+            index + 1,
+            self.buffers.len(),
+            self.buffer_names()[index]
+        ))
+    }
+
+    /// Runs a command typed after `:`, telling what was done or why it failed.
+    fn ex(
+        &mut self,
+        ex: Ex,
+        sender: &Sender<frontend::Message>,
+    ) -> std::result::Result<String, String> {
+        let input = self.sidecar.as_ref().map(|(input, _)| input.clone());
+        let count = self.buffers.len();
+
+        match ex {
+            Ex::Edit(path) => {
+                let index = match self.buffer_names().iter().position(|name| *name == path) {
+                    Some(index) => index,
+                    None => self.open(path, sender)?,
+                };
+                self.switch(index, sender)
+            }
+            Ex::Buffer(number) => self.switch(number.wrapping_sub(1), sender),
+            Ex::Next => self.switch((self.current + 1) % count, sender),
+            Ex::Previous => self.switch((self.current + count - 1) % count, sender),
+            Ex::Buffers => Ok(self
+                .buffer_names()
+                .iter()
+                .enumerate()
+                .map(|(index, name)| match index == self.current {
+                    true => format!("{}* {name}", index + 1),
+                    false => format!("{} {name}", index + 1),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")),
+            Ex::Ip(id) => {
+                self.ip_filter = id;
+                if let Some(debugger) = self.debugger.as_mut() {
+                    debugger.set_ip_filter(id);
+                }
+                let _ = self.sync(sender, None);
+                Ok(match id {
+                    Some(id) => format!("Following IP {id}"),
+                    None => "Following every IP".to_owned(),
+                })
+            }
+            Ex::Catch(event) => {
+                let (caught, name) = match event {
+                    IpEvent::Spawn => (&mut self.ip_events.0, "started"),
+                    IpEvent::Exit => (&mut self.ip_events.1, "ending"),
+                };
+                *caught = !*caught;
+                let report = match *caught {
+                    true => format!("Stopping when an IP is {name}"),
+                    false => format!("No longer stopping when an IP is {name}"),
+                };
+                if let Some(debugger) = self.debugger.as_mut() {
+                    debugger.set_ip_events(self.ip_events.0, self.ip_events.1);
+                }
+                Ok(report)
+            }
+            Ex::Restart => {
+                // Typed input is replayed, the seed being that of the directives again
+                let Some(input) = self.debugger.as_ref().map(|run| run.input().to_owned()) else {
+                    return Err("No run to restart, start one with 'r'".to_owned());
+                };
+                self.start();
+                if let Some(debugger) = self.debugger.as_mut() {
+                    debugger.feed_input(&input);
+                }
+                // The frontend drops the output of the previous run as for a rewind
+                self.sync_with(sender, None, true)
+                    .map_err(|err| err.to_string())?;
+                Ok(match input.chars().count() {
+                    0 => "Restarted the run".to_owned(),
+                    count => format!("Restarted the run, replaying {count} character(s) of input"),
+                })
+            }
+            Ex::Checkpoint(number) => {
+                let Some(debugger) = self.debugger.as_mut() else {
+                    return Err("No run to travel in, start one with 'r'".to_owned());
+                };
+                let checkpoints = debugger.checkpoints().copied().collect::<Vec<_>>();
+                let Some(number) = number else {
+                    return Ok(match checkpoints.is_empty() {
+                        true => "No bursts of writes to the grid so far".to_owned(),
+                        false => checkpoints
+                            .iter()
+                            .enumerate()
+                            .map(|(index, checkpoint)| {
+                                format!(
+                                    "{}: tick {} before {} writes",
+                                    index + 1,
+                                    checkpoint.tick,
+                                    checkpoint.writes
+                                )
+                            })
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                    });
+                };
+                let Some(checkpoint) = number.checked_sub(1).and_then(|i| checkpoints.get(i))
+                else {
+                    return Err(format!(
+                        "No checkpoint {number}, there are {}",
+                        checkpoints.len()
+                    ));
+                };
+
+                self.running = false;
+                let rewound = checkpoint.tick < debugger.interpreter().ticks();
+                let stop = debugger
+                    .travel_to(checkpoint.tick)
+                    .map_err(|err| err.to_string())?;
+                debugger.interpreter_mut().grid_mut().invalidate();
+                self.sync_with(sender, Some(Ok(stop)), rewound)
+                    .map_err(|err| err.to_string())?;
+                Ok(format!(
+                    "Travelled to tick {}, before {} writes",
+                    checkpoint.tick, checkpoint.writes
+                ))
+            }
+            Ex::Layout(None) => Ok(format!(
+                "Layouts: {}",
+                Layout::names(&self.layouts).join(", ")
+            )),
+            Ex::Layout(Some(name)) => {
+                let layout = Layout::find(&name, &self.layouts).map_err(|err| err.to_string())?;
+                sender
+                    .send(frontend::Message::Layout(layout.clone()))
+                    .map_err(|err| err.to_string())?;
+                self.edit_sidecar(sender, |sidecar| sidecar.layout = Some(layout))
+                    .map_err(|err| err.to_string())?;
+                Ok(format!("Using layout `{name}`"))
+            }
+            Ex::Echo => {
+                self.echo = !self.echo;
+                if let Some(debugger) = self.debugger.as_mut() {
+                    debugger.interpreter_mut().set_echo(self.echo);
+                }
+                Ok(match self.echo {
+                    true => "Echoing input to the output".to_owned(),
+                    false => "No longer echoing input".to_owned(),
+                })
+            }
+            Ex::Forecast(expected) => {
+                self.expected = expected;
+                Ok(match self.expected_output() {
+                    Some(bytes) => format!("Forecasting runs to end after {bytes} byte(s)"),
+                    None => {
+                        "Only forecasting the pace of runs, their output being unknown".to_owned()
+                    }
+                })
+            }
+            Ex::Pace(pace) => {
+                self.pace = pace.unwrap_or(self.pace);
+                Ok(match self.pace {
+                    Pace::Ticks(ticks) => format!("Running {ticks} instruction(s) per frame"),
+                    Pace::Adaptive => format!(
+                        "Running as many instructions as fit in a frame, {} in the latest one",
+                        self.last_frame
+                    ),
+                })
+            }
+            Ex::Generate(snippet) => {
+                // Positioned routines only work where they were generated for
+                let mut place = None;
+                let rows = match snippet {
+                    Snippet::Print(text) => vec![synthesis::print(&text)],
+                    Snippet::Number(value, reuse) => {
+                        let top = match reuse {
+                            true => Some(
+                                self.debugger
+                                    .as_ref()
+                                    .and_then(|debugger| {
+                                        debugger.interpreter().stack().last().copied()
+                                    })
+                                    .ok_or("The stack of the current run is empty")?,
+                            ),
+                            false => None,
+                        };
+                        vec![synthesis::number(value, top)]
+                    }
+                    Snippet::Routine(routine, at) => {
+                        if routine.positioned() {
+                            place = Some(at.ok_or_else(|| {
+                                format!(
+                                    "`{routine}` uses cells of its own, give where it goes with \
+                                     `gen routine {routine} X,Y`"
+                                )
+                            })?);
+                        }
+                        routine.code(at.unwrap_or_default())
+                    }
+                };
+                let mut report = match rows.as_slice() {
+                    [code] => format!("`{code}` ({} cells)", code.chars().count()),
+                    rows => {
+                        let width = rows.iter().map(|row| row.chars().count()).max();
+                        format!("A {}x{} block", width.unwrap_or_default(), rows.len())
+                    }
+                };
+                report.push_str(" is in register `\"`, paste it with `p`");
+                if let Some((x, y)) = place {
+                    report.push_str(&format!(" at ({x}, {y})"));
+                }
+                sender
+                    .send(frontend::Message::Register('"', rows))
+                    .map_err(|err| err.to_string())?;
+                Ok(report)
+            }
+            Ex::Break(breakpoint) => {
+                let (position, on) = match breakpoint {
+                    Breakpoint::Cell(x, y) => ((x, y), String::new()),
+                    Breakpoint::Label(label) => {
+                        let position = self
+                            .sidecar
+                            .as_ref()
+                            .and_then(|(_, sidecar)| sidecar.source_map.as_ref())
+                            .ok_or("The program wasn't laid out by `asm`, it has no source map")?
+                            .labels
+                            .get(&label)
+                            .copied()
+                            .ok_or_else(|| format!("No label `{label}` in the source map"))?;
+                        (position, format!(" on `{label}`"))
+                    }
+                };
+
+                let (x, y) = position;
+                let report = match self.breakpoints.remove(&position) {
+                    true => format!("Removed the breakpoint{on} at ({x}, {y})"),
+                    false => {
+                        self.breakpoints.insert(position);
+                        format!("Breakpoint{on} at ({x}, {y})")
+                    }
+                };
+                if let Some(debugger) = self.debugger.as_mut() {
+                    debugger.set_breakpoints(self.breakpoints.iter().copied());
+                }
+                let _ = self.persist(sender);
+                let _ = self.sync(sender, None);
+                Ok(report)
+            }
+            Ex::Input(feed) => {
+                let input = match feed {
+                    Feed::Text(text) => text,
+                    Feed::File(path) => std::fs::read_to_string(&path)
+                        .map_err(|err| format!("Could not read `{path}`: {err}"))?,
+                };
+                let count = input.chars().count();
+                match self.debugger.as_mut() {
+                    Some(debugger) => {
+                        debugger.feed_input(&input);
+                        Ok(format!("Fed {count} character(s) of input"))
+                    }
+                    None => {
+                        self.queued.push_str(&input);
+                        Ok(format!(
+                            "Queued {count} character(s) of input for the next run"
+                        ))
+                    }
+                }
+            }
+            Ex::Watch(expression) => {
+                let watch = expression
+                    .parse::<Watch>()
+                    .map_err(|err| format!("Invalid watch `{expression}`: {err}"))?;
+                let report = match self.watch(watch, sender).map_err(|err| err.to_string())? {
+                    true => format!("Watching `{expression}`"),
+                    false => format!("Already watching `{expression}`"),
+                };
+                Ok(report)
+            }
+            Ex::Unwatch(expression) => {
+                let watch = expression
+                    .parse::<Watch>()
+                    .map_err(|err| format!("Invalid watch `{expression}`: {err}"))?;
+                let index = self
+                    .watches
+                    .iter()
+                    .position(|watched| watched.same(&watch))
+                    .ok_or_else(|| format!("Not watching `{expression}`"))?;
+                self.watches.remove(index);
+                let _ = self.persist(sender);
+                let _ = self.sync(sender, None);
+                Ok(format!("Stopped watching `{expression}`"))
+            }
+            Ex::Run => {
+                if self.debugger.is_none() {
+                    self.start();
+                }
+                self.running = true;
+                Ok("Running until a breakpoint".to_owned())
+            }
+            Ex::Schedule(schedule) => {
+                let report = format!("Instruction pointers now take turns as `{schedule}`");
+                self.directives.schedule = Some(schedule);
+                Ok(report)
+            }
+            Ex::Write(path) => {
+                let path = path
+                    .or(input.clone())
+                    .ok_or("No file name, use `:w PATH`")?;
+                std::fs::write(&path, directives::to_program(&self.grid, &self.directives))
+                    .map_err(|err| format!("Could not write `{path}`: {err}"))?;
+
+                // Saving as another file switches to it, bookmarks and notes included
+                if input.as_ref() != Some(&path) {
+                    let sidecar = self.sidecar.take().unwrap_or_default().1;
+                    self.sidecar = Some((path.clone(), sidecar));
+                    if let Some((_, sidecar)) = &self.sidecar {
+                        sidecar
+                            .save(&path)
+                            .map_err(|err| format!("Could not save the sidecar file: {err}"))?;
+                    }
+                }
+
+                Ok(format!("Wrote `{path}`"))
+            }
+            Ex::Export(export, path) => {
+                let path = path
+                    .or_else(|| {
+                        let input = Path::new(input.as_ref()?);
+                        Some(
+                            input
+                                .with_extension(export.extension())
+                                .display()
+                                .to_string(),
+                        )
+                    })
+                    .ok_or("No file name, give the path to export to")?;
+
+                let (content, report) = match export {
+                    Export::Svg => {
+                        let content = match &self.debugger {
+                            Some(debugger) => {
+                                let interpreter = debugger.interpreter();
+                                svg::render(
+                                    interpreter.grid(),
+                                    debugger.heatmap(),
+                                    Some(interpreter.position()),
+                                )
+                            }
+                            None => svg::render(&self.grid, None, None),
+                        };
+                        (content, format!("Exported the grid to `{path}`"))
+                    }
+                    Export::Trace => {
+                        let debugger = self
+                            .debugger
+                            .as_ref()
+                            .ok_or("No run to export the trace of")?;
+                        let content = serde_json::to_string_pretty(debugger.history())
+                            .map_err(|err| err.to_string())?;
+                        let report = format!(
+                            "Exported the last {} instructions to `{path}`",
+                            debugger.history().len()
+                        );
+                        (content + "\n", report)
+                    }
+                    Export::Input => {
+                        let debugger = self
+                            .debugger
+                            .as_ref()
+                            .ok_or("No run to export the input of")?;
+                        let input = debugger.consumed_input();
+                        let report = format!(
+                            "Exported {} character(s) of input to `{path}`",
+                            input.chars().count()
+                        );
+                        (input, report)
+                    }
+                };
+
+                std::fs::write(&path, content)
+                    .map_err(|err| format!("Could not write `{path}`: {err}"))?;
+                Ok(report)
+            }
+        }
+    }
+
+    /// Applies a command from the control interface, see [control].
+    fn control(&mut self, command: Command, sender: &Sender<frontend::Message>) -> Result<Reply> {
+        let command = match command {
+            Command::Start => RunningCommand::Start,
+            Command::Resume => {
+                if self.debugger.is_none() {
+                    self.command(RunningCommand::Start, sender)?;
+                }
+                RunningCommand::SkipToBreakpoint
+            }
+            Command::Pause => RunningCommand::Pause,
+            Command::Step if self.debugger.is_none() => {
+                return Ok(Reply::Error("No run in progress".to_owned()))
+            }
+            Command::Step => RunningCommand::Step,
+            Command::Stop => RunningCommand::Stop,
+            Command::Input { data } => RunningCommand::Input(data),
+            Command::Poke { x, y, value } => {
+                match self.debugger.as_mut() {
+                    Some(debugger) => {
+                        let grid = debugger.interpreter_mut().grid_mut();
+                        grid.grow_to(x, y);
+                        grid.edit(x, y, CellValue::from(value));
+                        self.sync(sender, None)?;
+                    }
+                    None => {
+                        self.grid.grow_to(x, y);
+                        self.grid.edit(x, y, CellValue::from(value));
+                        sender.send(frontend::Message::Load(self.grid.clone()))?;
+                    }
+                }
+                return Ok(Reply::State(self.snapshot()));
+            }
+            Command::State => return Ok(Reply::State(self.snapshot())),
+        };
+
+        self.command(command, sender)?;
+        Ok(Reply::State(self.snapshot()))
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        match &self.debugger {
+            Some(debugger) => {
+                let interpreter = debugger.interpreter();
+                Snapshot {
+                    active: true,
+                    running: self.running,
+                    position: interpreter.position(),
+                    stack: interpreter.stack().to_vec(),
+                    ticks: interpreter.ticks(),
+                    status: interpreter.status(),
+                    grid: interpreter.grid().lines(),
+                }
+            }
+            None => Snapshot {
+                grid: self.grid.lines(),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Runs a single instruction of the followed instruction pointer, others running theirs in
+    /// the meantime, or the next instruction if it is not followed or gone.
+    fn step(&mut self) -> Option<std::result::Result<Stop, String>> {
+        loop {
+            let interpreter = self.debugger.as_ref()?.interpreter();
+            let ran = interpreter.ip().id;
+            let followed = self
+                .following
+                .filter(|id| interpreter.ips().any(|ip| ip.id == *id));
+
+            let stop = self.resume(1);
+            if stop.is_some() || followed.is_none_or(|id| id == ran) {
+                return stop;
+            }
+        }
+    }
+
+    /// Runs the instructions of a frame that `started` at the given time, as many as the pace
+    /// allows.
+    fn advance(&mut self, started: Instant) -> Option<std::result::Result<Stop, String>> {
+        let ticks = |state: &Self| {
+            state
+                .debugger
+                .as_ref()
+                .map_or(0, |debugger| debugger.interpreter().ticks())
+        };
+        let before = ticks(self);
+
+        let stop = match self.pace {
+            Pace::Ticks(budget) => self.resume(budget),
+            // Runs in chunks until the frame's share of running is spent
+            Pace::Adaptive => loop {
+                let stop = self.resume(ADAPTIVE_CHUNK);
+                if stop.is_some() || started.elapsed() >= ADAPTIVE_BUDGET {
+                    break stop;
+                }
+            },
+        };
+
+        self.last_frame = ticks(self) - before;
+        stop
+    }
+
+    /// Runs up to `budget` instructions, pausing when the debugger hands control back.
+    fn resume(&mut self, budget: usize) -> Option<std::result::Result<Stop, String>> {
+        let debugger = self.debugger.as_mut()?;
+
+        let stop = match debugger.resume(budget) {
+            Ok(stop) => stop.map(Ok),
+            Err(err) => Some(Err(err.to_string())),
+        };
+
+        // Input requests don't pause so that typed input is consumed as soon as it arrives
+        if let Some(
+            Ok(
+                Stop::Breakpoint(_)
+                | Stop::Reached
+                | Stop::Terminated
+                | Stop::Spawned(_)
+                | Stop::Exited(_),
+            )
+            | Err(_),
+        ) = stop
+        {
+            self.running = false;
+        }
+
+        stop
+    }
+
+    /// Bytes of output runs are expected to write, if known.
+    fn expected_output(&self) -> Option<usize> {
+        self.expected
+            .or_else(|| self.directives.output.as_ref().map(|output| output.len()))
+    }
+
+    /// Sends the current run state to the frontend.
+    fn sync(
+        &mut self,
+        sender: &Sender<frontend::Message>,
+        stop: Option<std::result::Result<Stop, String>>,
+    ) -> Result<()> {
+        self.sync_with(sender, stop, false)
+    }
+
+    /// Same as [State::sync], `rewound` telling the frontend that the run was brought back and
+    /// part of its output replayed.
+    fn sync_with(
+        &mut self,
+        sender: &Sender<frontend::Message>,
+        stop: Option<std::result::Result<Stop, String>>,
+        rewound: bool,
+    ) -> Result<()> {
+        let mut breakpoints = self.breakpoints.iter().copied().collect::<Vec<_>>();
+        breakpoints.sort();
+        let expected = self.expected_output();
+
+        let Some(debugger) = self.debugger.as_mut() else {
+            sender.send(frontend::Message::Running(Box::new(RunState {
+                breakpoints,
+                ..Default::default()
+            })))?;
+            return Ok(());
+        };
+
+        if let Some(Err(err)) = &stop {
+            sender.send(frontend::Message::LogicFail(Some(err.clone())))?;
+        }
+
+        let filter = self.ip_filter;
+        let history = debugger
+            .history()
+            .iter()
+            .rev()
+            .filter(|entry| filter.is_none_or(|id| entry.ip == id))
+            .take(DEFAULT_HISTORY)
+            .copied()
+            .collect::<Vec<_>>();
+        let history = history.into_iter().rev().collect();
+        let trail = debugger.history().iter().rev();
+        let trail = trail
+            .filter(|entry| filter.is_none_or(|id| entry.ip == id))
+            .take(TRAIL_SHOWN)
+            .map(|entry| (entry.ip, entry.position))
+            .collect::<Vec<_>>();
+        let trail = trail.into_iter().rev().collect();
+        // Consecutive instructions of the same instruction pointer are a single turn
+        let ids = debugger.history().iter().map(|entry| entry.ip);
+        let ids = ids.skip(debugger.history().len().saturating_sub(ORDER_LENGTH));
+        let order = ids
+            .collect::<Vec<_>>()
+            .chunk_by(|a, b| a == b)
+            .map(|turn| (turn[0], turn.len()))
+            .collect();
+        let schedule = debugger.interpreter().schedule().clone();
+        let input = debugger.consumed_input();
+        let journal = debugger.journal();
+        let journal = journal
+            .iter()
+            .skip(journal.len().saturating_sub(JOURNAL_SHOWN))
+            .copied()
+            .collect();
+        let rewound = rewound.then(|| debugger.interpreter().taken_output());
+        let timeline = debugger.timeline().summary(TIMELINE_RESOLUTION);
+        let checkpoints = debugger.checkpoints().copied().collect();
+        let statistics = debugger.statistics().clone();
+        let mut strings = debugger.strings().iter().copied().collect::<Vec<_>>();
+        strings.sort();
+        let wraps = debugger.wraps().iter().copied().collect();
+        // Where a paused run goes next, there being no time to see it when running on its own
+        let ahead = match self.running {
+            true => Prediction::default(),
+            false => debugger.predict(PREDICTION_TICKS),
+        };
+        let watches = self
+            .watches
+            .iter()
+            .map(|watch| watch.describe(debugger.interpreter()))
+            .collect();
+        let interpreter = debugger.interpreter_mut();
+        let output = interpreter.take_output();
+        self.progress.record(
+            interpreter.ticks(),
+            interpreter.taken_output(),
+            self.running,
+        );
+        let forecast = self.progress.forecast.outlook(expected);
+
+        // Only changed cells are sent, the whole grid being sent after structural changes
+        match interpreter.grid_mut().take_changes() {
+            Changes::All => sender.send(frontend::Message::Load(interpreter.grid().clone()))?,
+            Changes::Cells(cells) if !cells.is_empty() => {
+                let grid = interpreter.grid();
+                let cells = cells
+                    .into_iter()
+                    .map(|(x, y)| ((x, y), grid.get(x, y)))
+                    .collect();
+                sender.send(frontend::Message::Patch(cells))?;
+            }
+            Changes::Cells(_) => (),
+        }
+        sender.send(frontend::Message::Running(Box::new(RunState {
+            active: true,
+            running: self.running,
+            position: interpreter.position(),
+            storage_offset: interpreter.storage_offset(),
+            extensions: interpreter.extensions().clone(),
+            stack: interpreter.stack().to_vec(),
+            ips: interpreter.ips().cloned().collect(),
+            following: self.following,
+            ticks: interpreter.ticks(),
+            status: interpreter.status(),
+            stop: stop.and_then(|stop| stop.ok()),
+            output,
+            rewound,
+            breakpoints,
+            strings,
+            wraps,
+            ahead,
+            history,
+            trail,
+            timeline,
+            checkpoints,
+            statistics,
+            schedule,
+            order,
+            journal,
+            input,
+            watches,
+            forecast,
+            exit_code: interpreter.exit_code(),
+            assertion: interpreter.failed_assertion(),
+        })))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::{BTreeSet, HashMap};
+
+use serde::Serialize;
+
+use crate::cell::Direction;
+
+/// Instruction pointer state, loops being closed when one comes back.
+pub type State = ((usize, usize), Direction);
+
+/// Loop found in the trace of a run.
+#[derive(Clone, Debug, Serialize)]
+pub struct Loop {
+    /// State at which iterations start and end
+    pub header: State,
+    /// Cells executed as part of the loop, including those of nested loops
+    pub cells: BTreeSet<(usize, usize)>,
+    pub iterations: u64,
+    /// Ticks spent in complete iterations, including nested loops
+    pub ticks: u64,
+    /// Header of the innermost loop this one was first seen iterating in
+    #[serde(skip)]
+    pub parent: Option<State>,
+}
+
+/// Aggregates the trace of a run into loops.
+///
+/// The current path of the instruction pointer is kept as a stack of states. Reaching a state
+/// already on the path closes an iteration of the loop headed by that state and pops everything
+/// executed since, so inner loops are closed before the outer ones and each state is popped at
+/// most once per push.
+#[derive(Clone, Debug, Default)]
+pub struct LoopProfile {
+    path: Vec<(State, u64)>,
+    indices: HashMap<State, usize>,
+    loops: HashMap<State, Loop>,
+    ticks: u64,
+}
+
+impl LoopProfile {
+    /// Records that the instruction at `state` was executed at `tick`.
+    pub fn record(&mut self, tick: u64, state: State) {
+        self.ticks = self.ticks.max(tick + 1);
+
+        let Some(&index) = self.indices.get(&state) else {
+            self.indices.insert(state, self.path.len());
+            self.path.push((state, tick));
+            return;
+        };
+
+        let start = std::mem::replace(&mut self.path[index].1, tick);
+        let body = self.path.split_off(index + 1);
+
+        let mut cells = BTreeSet::from([state.0]);
+        for (inner, _) in body {
+            self.indices.remove(&inner);
+            cells.insert(inner.0);
+
+            if let Some(nested) = self.loops.get_mut(&inner) {
+                if nested.parent.is_none() {
+                    nested.parent = Some(state);
+                }
+                cells.extend(nested.cells.iter().copied());
+            }
+        }
+
+        let entry = self.loops.entry(state).or_insert_with(|| Loop {
+            header: state,
+            cells: BTreeSet::new(),
+            iterations: 0,
+            ticks: 0,
+            parent: None,
+        });
+        entry.iterations += 1;
+        entry.ticks += tick - start;
+        entry.cells.extend(cells);
+    }
+
+    /// Loops nested in one another, the most expensive first at each level.
+    pub fn report(&self) -> Report {
+        let mut children = HashMap::<Option<State>, Vec<&Loop>>::new();
+        for entry in self.loops.values() {
+            // A loop can't be its own ancestor, ignore parents that would make it one
+            let parent = entry
+                .parent
+                .filter(|parent| !self.is_ancestor(entry.header, *parent));
+            children.entry(parent).or_default().push(entry);
+        }
+
+        fn build(
+            children: &HashMap<Option<State>, Vec<&Loop>>,
+            parent: Option<State>,
+        ) -> Vec<Node> {
+            let mut nodes = children
+                .get(&parent)
+                .into_iter()
+                .flatten()
+                .map(|entry| Node {
+                    entry: (*entry).clone(),
+                    children: build(children, Some(entry.header)),
+                })
+                .collect::<Vec<_>>();
+            nodes.sort_by_key(|node| std::cmp::Reverse(node.entry.ticks));
+            nodes
+        }
+
+        Report {
+            ticks: self.ticks,
+            loops: build(&children, None),
+        }
+    }
+
+    fn is_ancestor(&self, state: State, mut of: State) -> bool {
+        let mut seen = 0;
+        loop {
+            if of == state {
+                return true;
+            }
+            match self.loops.get(&of).and_then(|entry| entry.parent) {
+                // Guards against parent cycles, which are broken at an arbitrary point
+                Some(parent) if seen < self.loops.len() => of = parent,
+                _ => return false,
+            }
+            seen += 1;
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Node {
+    #[serde(flatten)]
+    pub entry: Loop,
+    pub children: Vec<Node>,
+}
+
+/// Loop tree of a run, displayed as an indented flame view.
+#[derive(Clone, Debug, Serialize)]
+pub struct Report {
+    pub ticks: u64,
+    pub loops: Vec<Node>,
+}
+
+impl Report {
+    /// Folded stacks as consumed by flamegraph tools, weighted by ticks.
+    pub fn folded(&self) -> String {
+        fn fold(node: &Node, prefix: &str, out: &mut String) {
+            let ((x, y), _) = node.entry.header;
+            let name = format!("{prefix}loop ({x}, {y})");
+
+            let nested = node
+                .children
+                .iter()
+                .map(|child| child.entry.ticks)
+                .sum::<u64>();
+            let own = node.entry.ticks.saturating_sub(nested);
+            if own > 0 {
+                out.push_str(&format!("{name} {own}\n"));
+            }
+
+            for child in &node.children {
+                fold(child, &format!("{name};"), out);
+            }
+        }
+
+        let mut out = String::new();
+
+        let looping = self.loops.iter().map(|node| node.entry.ticks).sum::<u64>();
+        if self.ticks > looping {
+            out.push_str(&format!("straight-line {}\n", self.ticks - looping));
+        }
+
+        for node in &self.loops {
+            fold(node, "", &mut out);
+        }
+
+        out
+    }
+}
+
+/// Width of the share bar of the flame view.
+const BAR: usize = 20;
+
+impl std::fmt::Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn write_node(
+            f: &mut std::fmt::Formatter<'_>,
+            node: &Node,
+            total: u64,
+            depth: usize,
+        ) -> std::fmt::Result {
+            let entry = &node.entry;
+            let share = entry.ticks as f64 / total.max(1) as f64;
+            let ((x, y), _) = entry.header;
+
+            writeln!(
+                f,
+                "{:<BAR$} {:>5.1}% {}{}-cell loop at ({x}, {y}), iterated {} times",
+                "█".repeat((share * BAR as f64).round() as usize),
+                share * 100.,
+                "  ".repeat(depth),
+                entry.cells.len(),
+                entry.iterations,
+            )?;
+
+            for child in &node.children {
+                write_node(f, child, total, depth + 1)?;
+            }
+
+            Ok(())
+        }
+
+        if self.loops.is_empty() {
+            return writeln!(f, "No loops in {} ticks", self.ticks);
+        }
+
+        writeln!(f, "Loops over {} ticks:", self.ticks)?;
+        for root in &self.loops {
+            write_node(f, root, self.ticks, 0)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn nested() {
+        let mut profile = LoopProfile::default();
+        let mut tick = 0;
+        let mut visit = |profile: &mut LoopProfile, x: usize| {
+            profile.record(tick, ((x, 0), Direction::Right));
+            tick += 1;
+        };
+
+        // 0 then three times (1, twice (2, 3))
+        visit(&mut profile, 0);
+        for _ in 0..3 {
+            visit(&mut profile, 1);
+            for _ in 0..2 {
+                visit(&mut profile, 2);
+                visit(&mut profile, 3);
+            }
+        }
+        visit(&mut profile, 1);
+
+        let report = profile.report();
+        assert_eq!(report.ticks, 17);
+        assert_eq!(report.loops.len(), 1);
+
+        let outer = &report.loops[0];
+        assert_eq!(outer.entry.header.0, (1, 0));
+        assert_eq!(outer.entry.iterations, 3);
+        assert_eq!(outer.entry.ticks, 15);
+        assert_eq!(outer.entry.cells.len(), 3);
+
+        let inner = &outer.children[0].entry;
+        assert_eq!(inner.header.0, (2, 0));
+        assert_eq!(inner.iterations, 3);
+        assert_eq!(inner.ticks, 6);
+
+        assert_eq!(
+            report.folded(),
+            "straight-line 2\nloop (1, 0) 9\nloop (1, 0);loop (2, 0) 6\n"
+        );
+    }
+}
@@ -0,0 +1,315 @@
+use std::{
+    collections::HashMap,
+    io::{BufReader, Write},
+};
+
+use serde_json::{json, Value};
+
+use puccinia::{
+    analyzer::{self, Analysis, Severity},
+    cell::CellValue,
+    directives::{self, Directives, Source, Tabs},
+    grid::Grid,
+    sidecar::Sidecar,
+};
+
+use crate::protocol::{read_message, write_message, Result};
+
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Serves the Language Server Protocol over stdio until the client exits.
+pub(crate) fn run() -> Result<()> {
+    let mut reader = BufReader::new(std::io::stdin());
+    let mut server = Server::new(std::io::stdout());
+
+    while let Some(message) = read_message(&mut reader)? {
+        if !server.handle(message)? {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Open document along with its analysis.
+struct Document {
+    text: String,
+    grid: Grid,
+    /// Where the cells of the grid are in the text
+    source: Source,
+    analysis: Analysis,
+}
+
+impl Document {
+    /// Analyzes a document as programs are loaded, its directives and includes left out of the
+    /// grid. Data regions are read from the sidecar of local files.
+    fn new(uri: &str, text: String) -> Self {
+        let (grid, directives, source) =
+            match directives::parse_program_with(text.as_bytes(), Tabs::default()) {
+                Ok((grid, directives, _, source)) => (grid, directives, source),
+                Err(_) => (
+                    Grid::from(text.clone()),
+                    Directives::default(),
+                    Source::default(),
+                ),
+            };
+        let data = uri
+            .strip_prefix("file://")
+            .and_then(|path| Sidecar::load(path).ok())
+            .map(|sidecar| sidecar.data())
+            .unwrap_or_default();
+        let analysis = analyzer::analyze_with_data(&grid, &data, &directives.extensions);
+
+        Self {
+            text,
+            grid,
+            source,
+            analysis,
+        }
+    }
+
+    /// Converts an LSP position (UTF-16 based) to a grid cell, if it points inside the grid.
+    fn cell(&self, position: &Value) -> Option<(usize, usize)> {
+        let line = position["line"].as_u64()? as usize;
+        let character = position["character"].as_u64()? as usize;
+
+        let mut units = 0;
+        let column = self.text.lines().nth(line)?.chars().position(|c| {
+            units += c.len_utf16();
+            units > character
+        })?;
+
+        let (x, y) = self.source.cell((column, line))?;
+        let (width, height) = self.grid.size();
+        (x < width && y < height).then_some((x, y))
+    }
+
+    /// Converts a grid cell to a single character LSP range.
+    fn range(&self, cell: (usize, usize)) -> Value {
+        let (column, line) = self.source.position(cell);
+        let start = self
+            .text
+            .lines()
+            .nth(line)
+            .map(|text| {
+                text.chars()
+                    .take(column)
+                    .map(char::len_utf16)
+                    .sum::<usize>()
+            })
+            .unwrap_or(column);
+
+        json!({
+            "start": { "line": line, "character": start },
+            "end": { "line": line, "character": start + 1 },
+        })
+    }
+}
+
+struct Server<W: Write> {
+    writer: W,
+    documents: HashMap<String, Document>,
+}
+
+impl<W: Write> Server<W> {
+    fn new(writer: W) -> Self {
+        Self {
+            writer,
+            documents: HashMap::new(),
+        }
+    }
+
+    /// Handles a single message, returns false once the client asked to exit.
+    fn handle(&mut self, message: Value) -> Result<bool> {
+        let method = message["method"].as_str().unwrap_or_default().to_owned();
+        let params = &message["params"];
+
+        let result = match method.as_str() {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "hoverProvider": true,
+                    "documentHighlightProvider": true,
+                },
+                "serverInfo": { "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "textDocument/didOpen" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.update(uri, text.to_owned())?;
+                return Ok(true);
+            }
+            "textDocument/didChange" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                let text = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str());
+                if let Some(text) = text {
+                    self.update(uri, text.to_owned())?;
+                }
+                return Ok(true);
+            }
+            "textDocument/didClose" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                self.documents.remove(uri);
+                self.notify(
+                    "textDocument/publishDiagnostics",
+                    json!({ "uri": uri, "diagnostics": [] }),
+                )?;
+                return Ok(true);
+            }
+            "textDocument/hover" => Ok(self.hover(params)),
+            "textDocument/documentHighlight" => Ok(self.highlights(params)),
+            "shutdown" => Ok(Value::Null),
+            "exit" => return Ok(false),
+            other => Err(format!("Unsupported method `{other}`")),
+        };
+
+        // Notifications never get a response
+        if message.get("id").is_none() {
+            return Ok(true);
+        }
+
+        let mut response = json!({ "jsonrpc": "2.0", "id": message["id"] });
+        match result {
+            Ok(result) => response["result"] = result,
+            Err(message) => {
+                response["error"] = json!({ "code": METHOD_NOT_FOUND, "message": message })
+            }
+        }
+
+        write_message(&mut self.writer, &response)?;
+
+        Ok(true)
+    }
+
+    fn update(&mut self, uri: &str, text: String) -> Result<()> {
+        let document = Document::new(uri, text);
+
+        let diagnostics = document
+            .analysis
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                json!({
+                    "range": document.range(diagnostic.position),
+                    "severity": match diagnostic.severity {
+                        Severity::Error => 1,
+                        Severity::Warning => 2,
+                        Severity::Info => 3,
+                    },
+                    "source": env!("CARGO_PKG_NAME"),
+                    "message": diagnostic.message,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        self.documents.insert(uri.to_owned(), document);
+
+        self.notify(
+            "textDocument/publishDiagnostics",
+            json!({ "uri": uri, "diagnostics": diagnostics }),
+        )
+    }
+
+    fn document_cell(&self, params: &Value) -> Option<(&Document, (usize, usize))> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let document = self.documents.get(uri)?;
+        let cell = document.cell(&params["position"])?;
+
+        Some((document, cell))
+    }
+
+    fn hover(&self, params: &Value) -> Value {
+        let Some((document, (x, y))) = self.document_cell(params) else {
+            return Value::Null;
+        };
+
+        let value = document.grid.get(x, y).value;
+        if let CellValue::Empty = value {
+            return Value::Null;
+        }
+
+        let reachability = if document.analysis.is_reachable((x, y)) {
+            ""
+        } else {
+            "\n\n*Never executed*"
+        };
+        let quoted = if document.analysis.is_quoted((x, y)) {
+            "\n\n*Pushed as text in string mode*"
+        } else {
+            ""
+        };
+
+        json!({
+            "contents": {
+                "kind": "markdown",
+                "value": format!(
+                    "`{}` ({x}, {y})\n\n{}{reachability}{quoted}",
+                    char::from(value),
+                    value.documentation()
+                ),
+            },
+            "range": document.range((x, y)),
+        })
+    }
+
+    /// Highlights the cell's control-flow predecessors as reads and its successors as writes.
+    fn highlights(&self, params: &Value) -> Value {
+        let Some((document, cell)) = self.document_cell(params) else {
+            return Value::Null;
+        };
+
+        let analysis = &document.analysis;
+        let highlight = |position: &(usize, usize), kind: i64| json!({ "range": document.range(*position), "kind": kind });
+
+        let highlights = analysis
+            .predecessors(cell)
+            .into_iter()
+            .flatten()
+            .map(|position| highlight(position, 2))
+            .chain(
+                analysis
+                    .successors(cell)
+                    .into_iter()
+                    .flatten()
+                    .map(|position| highlight(position, 3)),
+            )
+            .collect::<Vec<_>>();
+
+        Value::Array(highlights)
+    }
+
+    fn notify(&mut self, method: &str, params: Value) -> Result<()> {
+        write_message(
+            &mut self.writer,
+            &json!({ "jsonrpc": "2.0", "method": method, "params": params }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn directives() {
+        let document = Document::new(
+            "untitled:a.bf",
+            "#!mst seed=1\n1.@\n--- mst\noutput: 1\\s\n".to_owned(),
+        );
+
+        // Neither the header nor the trailer are analyzed as code
+        assert!(document.analysis.diagnostics.is_empty());
+        assert_eq!(document.cell(&json!({ "line": 0, "character": 0 })), None);
+        assert_eq!(
+            document.cell(&json!({ "line": 1, "character": 2 })),
+            Some((2, 0))
+        );
+        assert_eq!(
+            document.range((2, 0))["start"],
+            json!({ "line": 1, "character": 2 })
+        );
+    }
+}
@@ -1,38 +1,312 @@
-mod cell;
+mod ansi;
+mod asm;
+mod batch;
+mod check;
+mod collab;
+mod conformance;
+mod control;
+mod dap;
+mod decompile;
+mod difftest;
+mod ex;
+mod expect;
+mod fetch;
 mod frontend;
-mod grid;
+mod fuzz;
+mod generator;
+#[cfg(feature = "gui")]
+mod gui;
+mod headless;
+mod history;
+mod import;
+mod leaderboard;
 mod logic;
+mod lsp;
+mod minify;
+mod mutate;
+mod pack;
+mod prompt;
+mod protocol;
+mod remote;
+mod repl;
+mod scorecard;
+mod settings;
+mod skeleton;
+mod sound;
+mod summary;
+mod tracediff;
+mod upgrade;
 
 use std::{sync::mpsc, thread::JoinHandle};
 
 use anyhow::bail;
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 
 use anyhow::Result;
+use collab::Session;
 use crossterm::terminal::disable_raw_mode;
+use puccinia::{config::Layout, dialect::Extension};
+use settings::Semantics;
 
 #[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 /// Minesweeper TUI editor and runner
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Input file location
-    input: String,
+    #[arg(required = true)]
+    input: Option<String>,
+
+    /// More programs to open in buffers, switched to with `:bn`, `:bp` or `B`
+    #[arg(value_name = "MORE")]
+    buffers: Vec<String>,
+
+    /// Opt-in extension to enable in runs, can be repeated: `multi-digit`, `concurrent`,
+    /// `system-info`, `quit` or `assert`
+    #[arg(long, value_name = "EXTENSION")]
+    extension: Vec<Extension>,
+
+    #[command(flatten)]
+    geometry: headless::Geometry,
+
+    #[command(flatten)]
+    display: frontend::Display,
+
+    /// Accept JSON commands from other processes on this TCP address, or Unix socket for
+    /// `unix:PATH`, to pause, resume, feed input, poke cells and query state
+    #[arg(long, value_name = "ADDRESS")]
+    control: Option<String>,
+
+    /// Let other instances edit the grid along with this one by joining on this address
+    #[arg(long, value_name = "ADDRESS")]
+    host: Option<String>,
+
+    /// Expression shown along with its value while running, can be repeated: `stack[0]`,
+    /// `cell(10, 3)`, `stack.len()` or `ticks`, combined with arithmetic and comparisons
+    #[arg(long, value_name = "EXPR")]
+    watch: Vec<String>,
+
+    /// Write input to the output panel as runs read it, toggled with `:echo`
+    #[arg(long)]
+    echo_input: bool,
+
+    /// Instructions run per frame, or `auto` for as many as keep the TUI at 60 frames per
+    /// second, changed with `:ticks`
+    #[arg(long, value_name = "N|auto", default_value_t)]
+    ticks_per_frame: logic::Pace,
+
+    /// Commands to run at startup as if typed after `:`, one per line, such as `break 4,2`,
+    /// `input 42\n` and `run`
+    #[arg(long, value_name = "PATH")]
+    cmd_file: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run programs without the TUI
+    Run(Box<headless::Options>),
+    /// Debug a program from an interactive command line
+    Debug(repl::Options),
+    /// Open the TUI on a program served by `run --debug-listen`
+    Attach {
+        /// Address of the remote instance
+        address: String,
+
+        #[command(flatten)]
+        display: frontend::Display,
+    },
+    /// Edit the grid shared by an instance started with `--host`
+    Join {
+        /// Address of the hosting instance
+        address: String,
+
+        /// Opt-in extension to enable in runs, can be repeated: `multi-digit`, `concurrent`,
+        /// `system-info`, `quit` or `assert`
+        #[arg(long, value_name = "EXTENSION")]
+        extension: Vec<Extension>,
+
+        #[command(flatten)]
+        display: frontend::Display,
+    },
+    /// Open a program in the desktop frontend
+    #[cfg(feature = "gui")]
+    Gui {
+        /// Input file location
+        input: String,
+
+        /// Opt-in extension to enable in runs, can be repeated: `multi-digit`, `concurrent`,
+        /// `system-info`, `quit` or `assert`
+        #[arg(long, value_name = "EXTENSION")]
+        extension: Vec<Extension>,
+
+        /// Use a high-contrast theme marking visited cells with glyphs rather than a colour
+        /// gradient, and describe each step of runs in plain sentences
+        #[arg(long)]
+        accessible: bool,
+    },
+    /// Report constructs that would behave differently in another dialect
+    Check(check::Options),
+    /// Print the pseudocode of a program, as labelled blocks with their stack effects
+    Decompile(decompile::Options),
+    /// Lay out a funge assembly file, instructions written one per line with labels and jumps,
+    /// as a program
+    Asm(asm::Options),
+    /// Rewrite a Befunge-93 program for Funge-98, using its shorter pushes and reporting the
+    /// instructions that behave differently there
+    Upgrade(upgrade::Options),
+    /// Transpile a program written in another language, such as Brainfuck, to Befunge-93
+    Import(import::Options),
+    /// Run the bundled conformance suite and Mycology-style test programs, summarising the
+    /// checks passed in each section
+    Conformance(conformance::Options),
+    /// Bundle a program with its input, expected output and settings into a `.mstpkg` file
+    Pack(pack::Options),
+    /// Run a corpus of programs through a reference interpreter too, reporting those whose
+    /// output or ending differ
+    Difftest(difftest::Options),
+    /// Align two traces written by `run --trace`, reporting the first step they differ at along
+    /// with the stacks
+    TraceDiff(tracediff::Options),
+    /// Delete or replace each cell of a program in turn, reporting those its expected output
+    /// relies on
+    Mutate(mutate::Options),
+    /// Feed random and boundary inputs to a program, reporting those making it fail, hang or
+    /// grow its stack without bound
+    FuzzInput(fuzz::Options),
+    /// Compare the size and step count of two versions of a program, as golfing sites score
+    /// them
+    GolfReport(scorecard::Options),
+    /// Create a program from a template with its routing laid out and placeholders annotated
+    New(skeleton::Options),
+    /// Generate code to paste into programs
+    Gen {
+        #[command(subcommand)]
+        generator: generator::Generator,
+    },
+    /// Strip trailing whitespace and simplify straight runs of instructions, keeping only the
+    /// rewrites that leave the output of runs unchanged
+    Minify(minify::Options),
+    /// Check that the solutions of a `golf.toml` leaderboard write their expected output,
+    /// recording their scores
+    Test(leaderboard::Options),
+    /// Record the size and step count of the solutions of a `golf.toml` leaderboard
+    Bench(leaderboard::Options),
+    /// Serve the Debug Adapter Protocol over stdio
+    Dap,
+    /// Serve the Language Server Protocol over stdio
+    Lsp,
 }
 
 fn main() -> Result<()> {
-    let default_panic_hook = std::panic::take_hook();
-    std::panic::set_hook(Box::new(move |info| {
-        disable_raw_mode().unwrap();
-        default_panic_hook(info);
-    }));
+    // Settings of the user and of the project fill in what the command line leaves out
+    let config = settings::load()?;
+    let matches = settings::apply(Args::command(), &config).get_matches();
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
 
-    let args = Args::parse();
+    let (input, session, extensions, mut display) = match args.command {
+        Some(Command::Run(options)) => return headless::run(*options, &config),
+        Some(Command::Attach {
+            address,
+            mut display,
+        }) => {
+            install_panic_hook();
+            display.keys = config.keys;
+            if let Some(name) = &display.layout {
+                display.panels = Layout::find(name, &config.layouts)?;
+            }
+            return remote::attach(&address, display);
+        }
+        Some(Command::Debug(options)) => return repl::run(options, &config),
+        Some(Command::Check(options)) => return check::run(options),
+        Some(Command::Decompile(options)) => return decompile::run(options),
+        Some(Command::Asm(options)) => return asm::run(options),
+        Some(Command::Upgrade(options)) => return upgrade::run(options),
+        Some(Command::Import(options)) => return import::run(options),
+        Some(Command::Conformance(options)) => return conformance::run(options),
+        #[cfg(feature = "gui")]
+        Some(Command::Gui {
+            input,
+            extension,
+            accessible,
+        }) => return gui(input, extension, Semantics::of(&config), accessible),
+        Some(Command::Pack(options)) => return pack::run(options, &config),
+        Some(Command::Difftest(options)) => return difftest::run(options, &config),
+        Some(Command::TraceDiff(options)) => return tracediff::run(options),
+        Some(Command::Mutate(options)) => return mutate::run(options),
+        Some(Command::FuzzInput(options)) => return fuzz::run(options),
+        Some(Command::GolfReport(options)) => return scorecard::run(options),
+        Some(Command::New(options)) => return skeleton::run(options),
+        Some(Command::Gen { generator }) => return generator::run(generator),
+        Some(Command::Minify(options)) => return minify::run(options),
+        Some(Command::Test(options)) => return leaderboard::run(options, true),
+        Some(Command::Bench(options)) => return leaderboard::run(options, false),
+        Some(Command::Dap) => return Ok(dap::run()?),
+        Some(Command::Lsp) => return Ok(lsp::run()?),
+        Some(Command::Join {
+            address,
+            extension,
+            display,
+        }) => (None, Some(Session::join(&address)?), extension, display),
+        None => (
+            Some(args.input.expect("clap enforces the input argument")),
+            args.host.as_deref().map(Session::host).transpose()?,
+            args.extension,
+            args.display,
+        ),
+    };
+
+    let settings = Semantics::of(&config);
+    display.keys = config.keys;
+    if let Some(name) = &display.layout {
+        display.panels = Layout::find(name, &config.layouts)?;
+    }
+    install_panic_hook();
 
     let (frontend_sender, frontend_receiver) = mpsc::channel();
     let (logic_sender, logic_receiver) = mpsc::channel();
 
-    let handler = std::thread::spawn(move || logic::run(args, frontend_sender, logic_receiver));
+    let control = match &args.control {
+        Some(address) => {
+            let (sender, receiver) = mpsc::channel();
+            control::listen(address, sender)?;
+            Some(receiver)
+        }
+        None => None,
+    };
+
+    for expression in args.watch {
+        logic_sender.send(logic::Message::Watch(expression))?;
+    }
+    for path in args.buffers {
+        logic_sender.send(logic::Message::Open(path))?;
+    }
+    if args.echo_input {
+        logic_sender.send(logic::Message::EchoInput(true))?;
+    }
+    logic_sender.send(logic::Message::Pace(args.ticks_per_frame))?;
+    logic_sender.send(logic::Message::Settings(settings))?;
+    logic_sender.send(logic::Message::Layouts(config.layouts))?;
+    if let Some(path) = args.cmd_file {
+        let script = std::fs::read_to_string(&path)
+            .map_err(|err| anyhow::anyhow!("Could not read `{path}`: {err}"))?;
+        logic_sender.send(logic::Message::Script(path, script))?;
+    }
+
+    let handler = std::thread::spawn(move || {
+        logic::run(
+            input,
+            extensions,
+            args.geometry,
+            frontend_sender,
+            logic_receiver,
+            control,
+            session,
+        )
+    });
 
-    if let Err(err) = frontend::run(frontend_receiver, logic_sender) {
+    if let Err(err) = frontend::run(frontend_receiver, logic_sender, display) {
         join_handler(handler)?;
         bail!("{err}");
     }
@@ -42,6 +316,43 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// Runs the logic thread behind the desktop frontend.
+#[cfg(feature = "gui")]
+fn gui(
+    input: String,
+    extensions: Vec<Extension>,
+    settings: Semantics,
+    accessible: bool,
+) -> Result<()> {
+    let (frontend_sender, frontend_receiver) = mpsc::channel();
+    let (logic_sender, logic_receiver) = mpsc::channel();
+    logic_sender.send(logic::Message::Settings(settings))?;
+
+    let handler = std::thread::spawn(move || {
+        logic::run(
+            Some(input),
+            extensions,
+            headless::Geometry::default(),
+            frontend_sender,
+            logic_receiver,
+            None,
+            None,
+        )
+    });
+
+    gui::run(frontend_receiver, logic_sender, accessible)?;
+    join_handler(handler)
+}
+
+/// Makes sure the terminal is usable again after a panic in the TUI.
+fn install_panic_hook() {
+    let default_panic_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        disable_raw_mode().unwrap();
+        default_panic_hook(info);
+    }));
+}
+
 fn join_handler<T>(handler: JoinHandle<T>) -> Result<()> {
     if let Err(err) = handler.join() {
         if let Some(err) = err.downcast_ref::<logic::Error>() {
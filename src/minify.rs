@@ -0,0 +1,145 @@
+use std::collections::HashSet;
+
+use puccinia::{
+    directives::{self, Directives},
+    golf::{self, Metrics},
+    grid::Grid,
+    interpreter::Interpreter,
+};
+
+use crate::headless::{self, Ending, Geometry};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Could not read `{0}`: {1}")]
+    Read(String, std::io::Error),
+    #[error("Could not write `{0}`: {1}")]
+    Write(String, std::io::Error),
+    #[error("The program doesn't end on its own with input {0:?}, so there is nothing to compare rewrites against")]
+    Unfinished(String),
+}
+
+#[derive(clap::Args)]
+pub(crate) struct Options {
+    /// Program file location, or bundle bringing its own input
+    input: String,
+
+    /// File fed to the program as input when checking rewrites, can be repeated to check them
+    /// against several runs
+    #[arg(long, value_name = "PATH")]
+    stdin: Vec<String>,
+
+    /// Where to write the minified program, defaults to stdout
+    #[arg(short, long, value_name = "PATH")]
+    output: Option<String>,
+
+    /// Only strip whitespace, without rewriting instructions
+    #[arg(long)]
+    no_peephole: bool,
+
+    /// Instructions after which a run is considered to go on forever
+    #[arg(long, value_name = "N", default_value_t = 1_000_000)]
+    max_ticks: u64,
+}
+
+/// Output and ending of a run, which rewrites must keep.
+type Behaviour = (String, Ending);
+
+/// Strips trailing whitespace, then applies every peephole rewrite that keeps the output and
+/// ending of the program's runs.
+pub(crate) fn run(options: Options) -> anyhow::Result<()> {
+    let (grid, directives, canned) = headless::load(&options.input, Geometry::default())?;
+    let inputs = match options.stdin.is_empty() {
+        true => vec![canned.unwrap_or_default()],
+        false => options
+            .stdin
+            .iter()
+            .map(|path| std::fs::read_to_string(path).map_err(|err| Error::Read(path.clone(), err)))
+            .collect::<Result<_, _>>()?,
+    };
+
+    let runs = inputs
+        .into_iter()
+        .map(|input| {
+            let behaviour = behaviour(&grid, &directives, &input, options.max_ticks);
+            match behaviour.1 {
+                Ending::Ended => Ok((input, behaviour)),
+                _ => Err(Error::Unfinished(input)),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let before = Metrics::of(&grid);
+    let mut lines = grid.lines();
+    if !options.no_peephole {
+        lines = rewrite(lines, &directives, &runs, options.max_ticks);
+    }
+
+    let grid = Grid::from(lines.join("\n"));
+    let program = directives::to_program(&grid, &directives);
+    eprintln!("{before} -> {}", Metrics::of(&grid));
+    match &options.output {
+        Some(path) => {
+            std::fs::write(path, program).map_err(|err| Error::Write(path.clone(), err))?
+        }
+        None => print!("{program}"),
+    }
+
+    Ok(())
+}
+
+/// Applies the biggest peephole rewrite keeping the behaviour of every run, until none does.
+fn rewrite(
+    mut lines: Vec<String>,
+    directives: &Directives,
+    runs: &[(String, Behaviour)],
+    max_ticks: u64,
+) -> Vec<String> {
+    let mut tried = HashSet::new();
+    loop {
+        let accepted = golf::peephole(&lines).into_iter().find_map(|rewrite| {
+            let mut rewritten = lines.clone();
+            rewrite.apply(&mut rewritten);
+            if !tried.insert(rewritten.clone()) {
+                return None;
+            }
+
+            let grid = reload(&rewritten, directives)?;
+            runs.iter()
+                .all(|(input, expected)| {
+                    behaviour(&grid, directives, input, max_ticks) == *expected
+                })
+                .then_some((rewrite, rewritten))
+        });
+
+        let Some((rewrite, rewritten)) = accepted else {
+            break;
+        };
+        eprintln!(
+            "({}, {}) {}: `{}` saves {} cell(s)",
+            rewrite.columns.start,
+            rewrite.y,
+            rewrite.kind,
+            rewrite.with,
+            rewrite.saving()
+        );
+        lines = rewritten;
+    }
+
+    lines
+}
+
+/// Grid of rewritten lines, laid out the way loading the program would.
+fn reload(lines: &[String], directives: &Directives) -> Option<Grid> {
+    let program = directives::to_program(&Grid::from(lines.join("\n")), directives);
+    directives::parse_program(program.as_bytes())
+        .ok()
+        .map(|(grid, _)| grid)
+}
+
+fn behaviour(grid: &Grid, directives: &Directives, input: &str, max_ticks: u64) -> Behaviour {
+    let mut interpreter = Interpreter::new(grid.clone());
+    headless::configure(&mut interpreter, directives, None, &[], None);
+    let ending = headless::run_to_end(&mut interpreter, input, max_ticks);
+    (interpreter.take_output(), ending)
+}
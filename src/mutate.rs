@@ -0,0 +1,218 @@
+use rayon::prelude::*;
+
+use puccinia::{
+    cell::CellValue,
+    directives::Directives,
+    grid::Grid,
+    interpreter::Interpreter,
+    sources::{Entropy, Xorshift},
+};
+
+use crate::headless::{self, Ending, Geometry};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Could not read `{0}`: {1}")]
+    Read(String, std::io::Error),
+    #[error("Nothing to check mutants against, add an `output` directive or use `--expected`")]
+    NoExpectation,
+    #[error("The program itself doesn't produce the expected output")]
+    Failing,
+}
+
+/// Befunge-93 instructions cells are replaced with.
+const INSTRUCTIONS: &str = "0123456789+-*/%!`><^v?_|\":\\$.,#gp&~@";
+
+#[derive(clap::Args)]
+pub(crate) struct Options {
+    /// Program file location, or bundle bringing its own input and expected output
+    input: String,
+
+    /// File holding the output of a correct run, defaults to the program's `output` directive
+    #[arg(long, value_name = "PATH")]
+    expected: Option<String>,
+
+    /// File fed to the program as input
+    #[arg(long, value_name = "PATH")]
+    stdin: Option<String>,
+
+    /// Random instructions tried in each cell on top of deleting it
+    #[arg(long, value_name = "N", default_value_t = 4)]
+    replacements: usize,
+
+    /// Seed picking the random instructions
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    seed: u64,
+
+    /// Instructions after which a mutant is considered to run forever, and so to fail
+    #[arg(long, value_name = "N", default_value_t = 1_000_000)]
+    max_ticks: u64,
+}
+
+/// How much a program relies on a cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Role {
+    /// The program still passes without it
+    Removable,
+    /// The program still passes with another instruction there
+    Replaceable(char),
+    /// Every mutation broke the program
+    LoadBearing,
+}
+
+/// Program along with what a run of it must write.
+struct Subject<'a> {
+    grid: &'a Grid,
+    directives: &'a Directives,
+    input: &'a str,
+    expected: &'a str,
+    max_ticks: u64,
+}
+
+impl Subject<'_> {
+    /// Whether the program still produces the expected output once `cell` holds `value`.
+    fn passes_with(&self, cell: Option<((usize, usize), char)>) -> bool {
+        let mut grid = self.grid.clone();
+        if let Some(((x, y), value)) = cell {
+            grid.set(x, y, CellValue::from(value));
+        }
+
+        let mut interpreter = Interpreter::new(grid);
+        headless::configure(&mut interpreter, self.directives, None, &[], None);
+        headless::run_to_end(&mut interpreter, self.input, self.max_ticks) == Ending::Ended
+            && interpreter.take_output() == self.expected
+    }
+
+    /// Tries deleting the cell, then each of the `replacements`.
+    fn role(&self, position: (usize, usize), replacements: &[char]) -> Role {
+        if self.passes_with(Some((position, ' '))) {
+            return Role::Removable;
+        }
+        replacements
+            .iter()
+            .find(|replacement| self.passes_with(Some((position, **replacement))))
+            .map_or(Role::LoadBearing, |replacement| {
+                Role::Replaceable(*replacement)
+            })
+    }
+}
+
+/// Perturbs every cell of a program in turn and reports which of them its expected output
+/// relies on.
+pub(crate) fn run(options: Options) -> anyhow::Result<()> {
+    let (grid, directives, canned) = headless::load(&options.input, Geometry::default())?;
+    let read =
+        |path: &String| std::fs::read_to_string(path).map_err(|err| Error::Read(path.clone(), err));
+    let expected = match &options.expected {
+        Some(path) => read(path)?,
+        None => directives.output.clone().ok_or(Error::NoExpectation)?,
+    };
+    let input = match &options.stdin {
+        Some(path) => read(path)?,
+        None => canned.unwrap_or_default(),
+    };
+
+    let subject = Subject {
+        grid: &grid,
+        directives: &directives,
+        input: &input,
+        expected: &expected,
+        max_ticks: options.max_ticks,
+    };
+    if !subject.passes_with(None) {
+        return Err(Error::Failing.into());
+    }
+
+    let mutations = mutations(&grid, options.replacements, options.seed);
+    let roles = mutations
+        .par_iter()
+        .map(|(position, replacements)| subject.role(*position, replacements))
+        .collect::<Vec<_>>();
+
+    for ((position, _), role) in mutations.iter().zip(&roles) {
+        let (x, y) = *position;
+        let instruction = char::from(grid.get(x, y).value);
+        match role {
+            Role::Removable => println!("({x}, {y}) `{instruction}`: removable"),
+            Role::Replaceable(replacement) => {
+                println!("({x}, {y}) `{instruction}`: replaceable with `{replacement}`")
+            }
+            Role::LoadBearing => println!("({x}, {y}) `{instruction}`: load-bearing"),
+        }
+    }
+
+    let load_bearing = roles
+        .iter()
+        .filter(|role| **role == Role::LoadBearing)
+        .count();
+    let removable = roles
+        .iter()
+        .filter(|role| **role == Role::Removable)
+        .count();
+    eprintln!(
+        "{load_bearing} of {} cells are load-bearing, {removable} can be removed",
+        roles.len()
+    );
+
+    Ok(())
+}
+
+/// Non-empty cells of a grid along with the instructions tried in each of them, other than the
+/// one already there.
+fn mutations(grid: &Grid, replacements: usize, seed: u64) -> Vec<((usize, usize), Vec<char>)> {
+    let mut entropy = Xorshift::new(seed);
+    let instructions = INSTRUCTIONS.chars().collect::<Vec<_>>();
+    let (width, height) = grid.size();
+
+    (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .filter_map(|(x, y)| {
+            let current = char::from(grid.get(x, y).value);
+            if current == ' ' {
+                return None;
+            }
+
+            let mut candidates = instructions
+                .iter()
+                .copied()
+                .filter(|instruction| *instruction != current)
+                .collect::<Vec<_>>();
+            let picked = (0..replacements.min(candidates.len()))
+                .map(|_| {
+                    let index = entropy.next_u64() as usize % candidates.len();
+                    candidates.swap_remove(index)
+                })
+                .collect();
+            Some(((x, y), picked))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn role() {
+        let grid = Grid::from("12*.9@".to_owned());
+        let directives = Directives::default();
+        let subject = Subject {
+            grid: &grid,
+            directives: &directives,
+            input: "",
+            expected: "2 ",
+            max_ticks: 1000,
+        };
+        assert!(subject.passes_with(None));
+
+        // Nothing reads the `9`, and `!` pushes 1 on an empty stack
+        assert_eq!(subject.role((4, 0), &['8']), Role::Removable);
+        assert_eq!(subject.role((3, 0), &[',', '$']), Role::LoadBearing);
+        assert_eq!(subject.role((0, 0), &['3', '!']), Role::Replaceable('!'));
+
+        let mutations = mutations(&grid, 3, 0);
+        assert_eq!(mutations.len(), 6);
+        assert!(mutations.iter().all(|((x, y), picked)| picked.len() == 3
+            && !picked.contains(&char::from(grid.get(*x, *y).value))));
+    }
+}
@@ -0,0 +1,119 @@
+use crate::interpreter::{Interpreter, Status};
+
+/// State of a run as narrated.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Observation {
+    pub ticks: u64,
+    pub position: (usize, usize),
+    /// Instruction under the instruction pointer
+    pub instruction: char,
+    pub stack: Vec<i32>,
+    pub status: Status,
+}
+
+impl Observation {
+    pub fn of(interpreter: &Interpreter) -> Self {
+        let (x, y) = interpreter.position();
+        Self {
+            ticks: interpreter.ticks(),
+            position: (x, y),
+            instruction: char::from(interpreter.grid().get(x, y).value),
+            stack: interpreter.stack().to_vec(),
+            status: interpreter.status(),
+        }
+    }
+}
+
+/// Values listed in a sentence before the rest are only counted.
+const LISTED: usize = 8;
+
+/// Describes in plain sentences what a run did between two observations, so that it can be
+/// followed with a screen reader rather than by watching the grid.
+pub fn narrate(before: &Observation, after: &Observation) -> String {
+    let mut sentences = Vec::new();
+
+    let (x, y) = before.position;
+    match after.ticks.saturating_sub(before.ticks) {
+        0 => (),
+        1 => sentences.push(format!(
+            "Executed {} at column {x}, row {y}.",
+            describe(before.instruction)
+        )),
+        ticks => sentences.push(format!("Ran {ticks} instructions.")),
+    }
+
+    let kept = before
+        .stack
+        .iter()
+        .zip(&after.stack)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let popped = &before.stack[kept..];
+    let pushed = &after.stack[kept..];
+    if !popped.is_empty() {
+        sentences.push(format!("Popped {}.", list(popped.iter().rev())));
+    }
+    if !pushed.is_empty() {
+        sentences.push(format!("Pushed {}.", list(pushed.iter())));
+    }
+
+    let (x, y) = after.position;
+    sentences.push(match after.status {
+        Status::Terminated => "Program terminated.".to_owned(),
+        Status::WaitingForInput => format!(
+            "Waiting for input on {} at column {x}, row {y}.",
+            describe(after.instruction)
+        ),
+        Status::Running => format!(
+            "Now at column {x}, row {y}, on {}.",
+            describe(after.instruction)
+        ),
+    });
+
+    sentences.join(" ")
+}
+
+/// Spoken name of a cell, spaces and symbols being easy to miss otherwise.
+fn describe(instruction: char) -> String {
+    match instruction {
+        ' ' => "an empty cell".to_owned(),
+        c => format!("`{c}`"),
+    }
+}
+
+fn list<'a>(values: impl ExactSizeIterator<Item = &'a i32>) -> String {
+    let count = values.len();
+    let mut listed = values.take(LISTED).map(i32::to_string).collect::<Vec<_>>();
+
+    if count > LISTED {
+        listed.push(format!("{} more", count - LISTED));
+    }
+    match listed.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} and {last}", rest.join(", ")),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::grid::Grid;
+
+    #[test]
+    fn narrate() {
+        let mut interpreter = Interpreter::new(Grid::from("23+ @".to_owned()));
+        interpreter.step().unwrap();
+        interpreter.step().unwrap();
+
+        let before = Observation::of(&interpreter);
+        interpreter.step().unwrap();
+        let after = Observation::of(&interpreter);
+
+        assert_eq!(
+            super::narrate(&before, &after),
+            "Executed `+` at column 2, row 0. Popped 3 and 2. Pushed 5. Now at column 3, row 0, \
+             on an empty cell."
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use std::path::Path;
+
+use puccinia::{
+    bundle::{self, Bundle},
+    config::Config,
+    dialect::{Dialect, Extension},
+    directives::{self, Directives},
+};
+
+use crate::settings::Semantics;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Could not read `{0}`: {1}")]
+    Read(String, std::io::Error),
+    #[error("Could not write `{0}`: {1}")]
+    Write(String, bundle::Error),
+}
+
+#[derive(clap::Args)]
+pub(crate) struct Options {
+    /// Program file location
+    input: String,
+
+    /// Bundle location, defaults to the program's with the `.mstpkg` extension
+    #[arg(short, long, value_name = "PATH")]
+    out: Option<String>,
+
+    /// File fed to the program as input when the bundle is run
+    #[arg(long, value_name = "PATH")]
+    stdin: Option<String>,
+
+    /// File holding the output of a correct run, checked when the bundle is run
+    #[arg(long, value_name = "PATH")]
+    expected: Option<String>,
+
+    /// Seed of the random number generator
+    #[arg(long, value_name = "N")]
+    seed: Option<u64>,
+
+    /// Semantics to run the program with, defaults to the settings' `dialect` unless the program
+    /// has a `dialect` directive
+    #[arg(long, value_name = "DIALECT")]
+    dialect: Option<Dialect>,
+
+    /// Opt-in extension to run the program with, can be repeated: `multi-digit`, `concurrent`,
+    /// `system-info`, `quit` or `assert`. Defaults to the settings' `extensions` unless the
+    /// program has an `extensions` directive
+    #[arg(long, value_name = "EXTENSION")]
+    extension: Vec<Extension>,
+}
+
+/// Packs a program along with its input and settings into a bundle runnable with `run`.
+pub(crate) fn run(options: Options, config: &Config) -> anyhow::Result<()> {
+    let read = |path: String| std::fs::read_to_string(&path).map_err(|err| Error::Read(path, err));
+
+    let program = read(options.input.clone())?;
+    // Whatever the program leaves out is filled in from the settings, the bundle being run
+    // without them
+    let (_, own) = directives::parse_program(program.as_bytes())?;
+    let semantics =
+        Semantics::new(options.dialect, &options.extension).or(&Semantics::of(config), &own);
+    let bundle = Bundle {
+        program,
+        directives: Directives {
+            dialect: semantics.dialect,
+            seed: options.seed,
+            extensions: semantics.extensions,
+            output: options.expected.map(read).transpose()?,
+            ..Default::default()
+        },
+        input: options.stdin.map(read).transpose()?,
+    };
+
+    let out = options.out.unwrap_or_else(|| {
+        Path::new(&options.input)
+            .with_extension(bundle::EXTENSION)
+            .to_string_lossy()
+            .into_owned()
+    });
+    bundle
+        .write(&out)
+        .map_err(|err| Error::Write(out.clone(), err))?;
+
+    eprintln!("Packed `{}` into `{out}`", options.input);
+    Ok(())
+}
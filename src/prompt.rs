@@ -0,0 +1,215 @@
+use std::io::{BufRead, IsTerminal, Write};
+
+use crossterm::{
+    cursor::MoveToColumn,
+    event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    queue,
+    style::Print,
+    terminal::{self, Clear, ClearType},
+};
+
+use crate::history::History;
+
+/// What a key does to the line being typed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Edit {
+    Typing,
+    Submit,
+    /// Ctrl-C, the line is dropped and a new one started
+    Cancel,
+    /// Ctrl-D on an empty line
+    End,
+}
+
+/// Line being typed, along with the commands it can be replaced with.
+#[derive(Debug, Default)]
+struct Line {
+    text: Vec<char>,
+    cursor: usize,
+    /// What was being typed before recalling commands, restored once past the latest
+    draft: Option<Vec<char>>,
+}
+
+impl Line {
+    fn set(&mut self, text: &str) {
+        self.text = text.chars().collect();
+        self.cursor = self.text.len();
+    }
+
+    fn edit(&mut self, key: KeyEvent, history: &mut History) -> Edit {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Enter => return Edit::Submit,
+            KeyCode::Char('c') if ctrl => return Edit::Cancel,
+            KeyCode::Char('d') if ctrl && self.text.is_empty() => return Edit::End,
+            KeyCode::Char('d') if ctrl && self.cursor < self.text.len() => {
+                self.text.remove(self.cursor);
+            }
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::Char('e') if ctrl => self.cursor = self.text.len(),
+            KeyCode::Char('u') if ctrl => {
+                self.text.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            KeyCode::Char(_) if ctrl => (),
+            KeyCode::Char(c) => {
+                self.text.insert(self.cursor, c);
+                self.cursor += 1;
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.text.remove(self.cursor);
+            }
+            KeyCode::Delete if self.cursor < self.text.len() => {
+                self.text.remove(self.cursor);
+            }
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.text.len()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.text.len(),
+            KeyCode::Up => {
+                if let Some(line) = history.previous().map(str::to_owned) {
+                    self.draft.get_or_insert_with(|| self.text.clone());
+                    self.set(&line);
+                }
+            }
+            KeyCode::Down => match history.next().map(str::to_owned) {
+                Some(line) => self.set(&line),
+                None => {
+                    if let Some(draft) = self.draft.take() {
+                        self.cursor = draft.len();
+                        self.text = draft;
+                    }
+                }
+            },
+            _ => (),
+        }
+        Edit::Typing
+    }
+}
+
+/// Reads commands from stdin, with line editing and recall of the ones typed earlier in the
+/// session using the arrow keys when it is a terminal.
+pub(crate) struct Prompt {
+    prompt: &'static str,
+    history: History,
+}
+
+impl Prompt {
+    pub fn new(prompt: &'static str) -> Self {
+        Self {
+            prompt,
+            history: History::default(),
+        }
+    }
+
+    /// Reads a line, `None` meaning end of input.
+    pub fn read(&mut self) -> std::io::Result<Option<String>> {
+        let mut stdout = std::io::stdout();
+        if !std::io::stdin().is_terminal() {
+            print!("{}", self.prompt);
+            stdout.flush()?;
+
+            let mut line = String::new();
+            let read = std::io::stdin().lock().read_line(&mut line)?;
+            return Ok((read > 0).then_some(line));
+        }
+
+        terminal::enable_raw_mode()?;
+        let line = self.edit(&mut stdout);
+        terminal::disable_raw_mode()?;
+
+        let line = line?;
+        if let Some(line) = &line {
+            // Nothing is saved without a path, so this can't fail
+            let _ = self.history.push(line);
+        }
+        self.history.reset();
+        Ok(line)
+    }
+
+    fn edit(&mut self, stdout: &mut impl Write) -> std::io::Result<Option<String>> {
+        let mut line = Line::default();
+        loop {
+            let width = self.prompt.chars().count() + line.cursor;
+            queue!(
+                stdout,
+                MoveToColumn(0),
+                Clear(ClearType::UntilNewLine),
+                Print(self.prompt),
+                Print(line.text.iter().collect::<String>()),
+                MoveToColumn(width as u16),
+            )?;
+            stdout.flush()?;
+
+            let Event::Key(key) = crossterm::event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match line.edit(key, &mut self.history) {
+                Edit::Typing => (),
+                Edit::Submit => {
+                    write!(stdout, "\r\n")?;
+                    return Ok(Some(line.text.into_iter().collect()));
+                }
+                Edit::Cancel => {
+                    write!(stdout, "^C\r\n")?;
+                    self.history.reset();
+                    line = Line::default();
+                }
+                Edit::End => {
+                    write!(stdout, "\r\n")?;
+                    return Ok(None);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn press(line: &mut Line, history: &mut History, keys: &[KeyCode]) -> Option<Edit> {
+        keys.iter()
+            .map(|code| line.edit(KeyEvent::from(*code), history))
+            .last()
+    }
+
+    #[test]
+    fn line() {
+        let mut history = History::default();
+        history.push("step 3").unwrap();
+        history.push("continue").unwrap();
+
+        let mut line = Line::default();
+        let text = "brak 1 2".chars().map(KeyCode::Char).collect::<Vec<_>>();
+        press(&mut line, &mut history, &text);
+        press(
+            &mut line,
+            &mut history,
+            &[KeyCode::Home, KeyCode::Right, KeyCode::Right],
+        );
+        press(
+            &mut line,
+            &mut history,
+            &[KeyCode::Char('e'), KeyCode::Up, KeyCode::Up],
+        );
+        press(
+            &mut line,
+            &mut history,
+            &[KeyCode::Backspace, KeyCode::Char('1')],
+        );
+        assert_eq!(line.text.iter().collect::<String>(), "step 1");
+
+        // Going past the latest command brings back what was being typed
+        press(&mut line, &mut history, &[KeyCode::Down, KeyCode::Down]);
+        assert_eq!(
+            press(&mut line, &mut history, &[KeyCode::Enter]),
+            Some(Edit::Submit)
+        );
+        assert_eq!(line.text.iter().collect::<String>(), "break 1 2");
+    }
+}
@@ -0,0 +1,56 @@
+use std::io::{BufRead, Write};
+
+use serde_json::Value;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Protocol error: {0}")]
+    Protocol(String),
+}
+
+pub type Result<T> = anyhow::Result<T, Error>;
+
+/// Reads a single `Content-Length` framed message, `None` on end of stream.
+pub fn read_message(reader: &mut impl BufRead) -> Result<Option<Value>> {
+    let mut length = None;
+
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = Some(
+                value
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|err| Error::Protocol(format!("Invalid content length: {err}")))?,
+            );
+        }
+    }
+
+    let length = length.ok_or_else(|| Error::Protocol("Missing content length".to_owned()))?;
+    let mut buffer = vec![0; length];
+    reader.read_exact(&mut buffer)?;
+
+    serde_json::from_slice(&buffer)
+        .map(Some)
+        .map_err(|err| Error::Protocol(format!("Invalid JSON: {err}")))
+}
+
+/// Writes a single `Content-Length` framed message.
+pub fn write_message(writer: &mut impl Write, message: &Value) -> Result<()> {
+    let content = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{content}", content.len())?;
+    writer.flush()?;
+
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{analyzer::Analysis, cell::CellValue, grid::Grid};
+
+/// Row or column of the grid, which instruction pointers go along both ways.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Line {
+    Row(usize),
+    Column(usize),
+}
+
+impl Line {
+    pub fn contains(self, (x, y): (usize, usize)) -> bool {
+        match self {
+            Line::Row(row) => row == y,
+            Line::Column(column) => column == x,
+        }
+    }
+}
+
+impl std::fmt::Display for Line {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Line::Row(y) => write!(f, "row {y}"),
+            Line::Column(x) => write!(f, "column {x}"),
+        }
+    }
+}
+
+/// Line holding an odd number of `"` along it, so that instruction pointers going through wrap
+/// around its ends still in string mode and push the code they come back to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Unbalanced {
+    pub line: Line,
+    /// Quote most likely to be the stray one
+    pub quote: (usize, usize),
+    pub quotes: usize,
+}
+
+impl std::fmt::Display for Unbalanced {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (x, y) = self.quote;
+        write!(
+            f,
+            "{} `\"` on {}, likely the one at ({x}, {y})",
+            self.quotes, self.line
+        )
+    }
+}
+
+/// Checks a line for unbalanced quotes.
+///
+/// Quotes only count along the lines instruction pointers cross them on as found by the
+/// analysis, those it can't reach yet counting along their row as strings mostly are. The stray
+/// quote is guessed as the one leaving the shortest strings once taken out, the others being
+/// paired in order, as strings tend to be short next to the code around them.
+pub fn unbalanced(grid: &Grid, analysis: &Analysis, line: Line) -> Option<Unbalanced> {
+    let (width, height) = grid.size();
+    let cells = match line {
+        Line::Row(y) if y < height => (0..width).map(|x| (x, y)).collect::<Vec<_>>(),
+        Line::Column(x) if x < width => (0..height).map(|y| (x, y)).collect(),
+        _ => return None,
+    };
+    let along = |position| match (analysis.axes(position), line) {
+        ((false, false), Line::Row(_)) => true,
+        ((horizontal, _), Line::Row(_)) => horizontal,
+        ((_, vertical), Line::Column(_)) => vertical,
+    };
+    let quotes = cells
+        .into_iter()
+        .enumerate()
+        .filter(|(_, (x, y))| matches!(grid.get(*x, *y).value, CellValue::StringMode))
+        .filter(|(_, position)| along(*position))
+        .collect::<Vec<_>>();
+    if quotes.len() % 2 == 0 {
+        return None;
+    }
+
+    let quoted = |stray: usize| {
+        let paired = quotes
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != stray)
+            .map(|(_, (offset, _))| offset)
+            .collect::<Vec<_>>();
+        paired
+            .chunks_exact(2)
+            .map(|pair| pair[1] - pair[0] - 1)
+            .sum::<usize>()
+    };
+    // Ties go to the last quote, the one a string opened by mistake tends to start with
+    let stray = (0..quotes.len())
+        .rev()
+        .min_by_key(|stray| quoted(*stray))
+        .unwrap_or_default();
+    Some(Unbalanced {
+        line,
+        quote: quotes[stray].1,
+        quotes: quotes.len(),
+    })
+}
+
+/// Checks every row and column of the grid.
+pub fn check(grid: &Grid, analysis: &Analysis) -> Vec<Unbalanced> {
+    let (width, height) = grid.size();
+    let rows = (0..height).map(Line::Row);
+    rows.chain((0..width).map(Line::Column))
+        .filter_map(|line| unbalanced(grid, analysis, line))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::analyzer;
+
+    #[test]
+    fn quotes() {
+        let grid = Grid::from(["v  \"ab\" \"cd\"", "\"", "\"", ">\"!dlrow\"   ,,,\"@"].join("\n"));
+        let analysis = analyzer::analyze(&grid, &[]);
+
+        // Quotes crossed going down count along their column only, the others along their row
+        assert_eq!(unbalanced(&grid, &analysis, Line::Row(1)), None);
+        assert_eq!(unbalanced(&grid, &analysis, Line::Column(0)), None);
+        assert_eq!(
+            check(&grid, &analysis),
+            [Unbalanced {
+                line: Line::Row(3),
+                quote: (15, 3),
+                quotes: 3,
+            }]
+        );
+        assert_eq!(
+            check(&grid, &analysis)[0].to_string(),
+            "3 `\"` on row 3, likely the one at (15, 3)"
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, Receiver, Sender},
+};
+
+use puccinia::dialect::Extension;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{frontend, headless::Geometry, logic, settings::Semantics};
+
+type Result<T> = anyhow::Result<T>;
+
+/// Waits for a frontend to attach on `address`, then serves the program's logic to it.
+pub(crate) fn listen(
+    address: &str,
+    input: String,
+    extensions: Vec<Extension>,
+    settings: Semantics,
+    geometry: Geometry,
+) -> Result<()> {
+    let listener = TcpListener::bind(address)?;
+    eprintln!("Waiting for a frontend on {}", listener.local_addr()?);
+
+    let (stream, peer) = listener.accept()?;
+    eprintln!("Frontend attached from {peer}");
+
+    let (frontend_sender, frontend_receiver) = mpsc::channel();
+    let (logic_sender, logic_receiver) = mpsc::channel();
+    logic_sender.send(logic::Message::Settings(settings))?;
+
+    // Nothing is drawn on this side, so the terminal is free to report to
+    let malformed = |err| {
+        eprintln!("Ignoring malformed message: {err}");
+        None
+    };
+    bridge::<logic::Message, frontend::Message>(
+        stream,
+        logic_sender,
+        frontend_receiver,
+        malformed,
+    )?;
+
+    logic::run(
+        Some(input),
+        extensions,
+        geometry,
+        frontend_sender,
+        logic_receiver,
+        None,
+        None,
+    )
+}
+
+/// Runs the TUI against the logic served by a remote instance.
+pub(crate) fn attach(address: &str, display: frontend::Display) -> Result<()> {
+    let stream = TcpStream::connect(address)?;
+
+    let (frontend_sender, frontend_receiver) = mpsc::channel();
+    let (logic_sender, logic_receiver) = mpsc::channel();
+
+    // Writing to the terminal would garble the TUI drawn on it
+    let malformed = |err| {
+        let error = format!("Ignoring malformed message: {err}");
+        Some(frontend::Message::LogicFail(Some(error)))
+    };
+    bridge::<frontend::Message, logic::Message>(
+        stream,
+        frontend_sender,
+        logic_receiver,
+        malformed,
+    )?;
+
+    Ok(frontend::run(frontend_receiver, logic_sender, display)?)
+}
+
+/// Forwards messages between local channels and a stream, one JSON document per line.
+/// Channels get disconnected when the stream closes and vice versa. Malformed lines are skipped,
+/// `malformed` telling what to send in their place if anything.
+fn bridge<I, O>(
+    stream: TcpStream,
+    incoming: Sender<I>,
+    outgoing: Receiver<O>,
+    malformed: fn(serde_json::Error) -> Option<I>,
+) -> Result<()>
+where
+    I: DeserializeOwned + Send + 'static,
+    O: Serialize + Send + 'static,
+{
+    let reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    std::thread::spawn(move || {
+        for line in reader.lines().map_while(std::io::Result::ok) {
+            let message = match serde_json::from_str(&line) {
+                Ok(message) => message,
+                Err(err) => match malformed(err) {
+                    Some(message) => message,
+                    None => continue,
+                },
+            };
+            if incoming.send(message).is_err() {
+                break;
+            }
+        }
+    });
+
+    std::thread::spawn(move || {
+        for message in outgoing {
+            let sent = serde_json::to_string(&message)
+                .map_err(std::io::Error::from)
+                .and_then(|line| writeln!(writer, "{line}"));
+
+            if sent.is_err() {
+                break;
+            }
+        }
+
+        let _ = writer.shutdown(std::net::Shutdown::Both);
+    });
+
+    Ok(())
+}
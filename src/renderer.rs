@@ -0,0 +1,153 @@
+use std::io;
+
+use crate::{
+    debugger::{Debugger, Stop},
+    interpreter,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Interpreter(#[from] interpreter::Error),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("Program requested input after the end of input")]
+    EndOfInput,
+}
+
+pub type Result<T> = anyhow::Result<T, Error>;
+
+/// State of a run handed to a [Renderer].
+#[non_exhaustive]
+pub struct Frame<'a> {
+    pub debugger: &'a Debugger,
+    /// Output produced since the previous frame
+    pub output: &'a str,
+    /// Why the frame ended early, `None` when its instruction budget ran out
+    pub stop: Option<Stop>,
+}
+
+/// What the run loop does after a frame.
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Control {
+    Continue,
+    /// Feed data to the program's input, then continue
+    Feed(String),
+    /// End the run before the program terminates
+    Stop,
+}
+
+/// Presents a run driven by [run], implemented by frontends whatever they draw to.
+pub trait Renderer {
+    /// Called once before the first instruction.
+    fn begin(&mut self, _debugger: &Debugger) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called after every frame of instructions and whenever the program stops.
+    fn frame(&mut self, frame: &Frame<'_>) -> io::Result<Control>;
+
+    /// Input for a program waiting for it, `None` meaning that there is no more.
+    fn input(&mut self, debugger: &Debugger) -> io::Result<Option<String>>;
+
+    /// Called once the run is over, whether it succeeded or not.
+    fn end(&mut self, _debugger: &Debugger, _result: &Result<()>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs a program until it terminates, handing it to `renderer` every `ticks_per_frame`
+/// instructions as well as on breakpoints and input requests.
+pub fn run(
+    debugger: &mut Debugger,
+    renderer: &mut (impl Renderer + ?Sized),
+    ticks_per_frame: usize,
+) -> Result<()> {
+    renderer.begin(debugger)?;
+
+    let result = frames(debugger, renderer, ticks_per_frame.max(1));
+
+    renderer.end(debugger, &result)?;
+    result
+}
+
+fn frames(
+    debugger: &mut Debugger,
+    renderer: &mut (impl Renderer + ?Sized),
+    ticks_per_frame: usize,
+) -> Result<()> {
+    loop {
+        let resumed = debugger.resume(ticks_per_frame);
+
+        let output = debugger.interpreter_mut().take_output();
+        let stop = resumed.as_ref().ok().copied().flatten();
+        let control = renderer.frame(&Frame {
+            debugger,
+            output: &output,
+            stop,
+        })?;
+        resumed?;
+
+        let fed = match control {
+            Control::Continue => false,
+            Control::Feed(input) => {
+                debugger.feed_input(&input);
+                true
+            }
+            Control::Stop => return Ok(()),
+        };
+
+        match stop {
+            Some(Stop::Terminated) => return Ok(()),
+            Some(Stop::WaitingForInput) if !fed => match renderer.input(debugger)? {
+                Some(input) => debugger.feed_input(&input),
+                None => return Err(Error::EndOfInput),
+            },
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{grid::Grid, interpreter::Interpreter};
+
+    #[derive(Default)]
+    struct Recorder {
+        output: String,
+        frames: usize,
+        ended: bool,
+    }
+
+    impl Renderer for Recorder {
+        fn frame(&mut self, frame: &Frame<'_>) -> io::Result<Control> {
+            self.frames += 1;
+            self.output.push_str(frame.output);
+            Ok(Control::Continue)
+        }
+
+        fn input(&mut self, _debugger: &Debugger) -> io::Result<Option<String>> {
+            Ok(Some("21".to_owned()))
+        }
+
+        fn end(&mut self, _debugger: &Debugger, result: &Result<()>) -> io::Result<()> {
+            self.ended = result.is_ok();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn run() {
+        let grid = Grid::from("&2*.@".to_owned());
+        let mut debugger = Debugger::new(Interpreter::new(grid));
+        let mut recorder = Recorder::default();
+
+        super::run(&mut debugger, &mut recorder, 2).unwrap();
+
+        assert_eq!(recorder.output, "42 ");
+        assert!(recorder.frames >= 3);
+        assert!(recorder.ended);
+    }
+}
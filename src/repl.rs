@@ -0,0 +1,636 @@
+use std::str::FromStr;
+
+use puccinia::{
+    cell::CellValue,
+    config::Config,
+    debugger::{Debugger, Stop, Target, DEFAULT_HISTORY},
+    dialect::{Dialect, Extension},
+    interpreter::{self, Interpreter, Schedule},
+    sidecar::Sidecar,
+    watch::{self, Watch},
+};
+
+use crate::{fetch, headless, prompt::Prompt, settings::Semantics};
+
+/// Instructions executed between two output flushes when continuing.
+const SLICE: usize = 10_000;
+
+const HELP: &str = "\
+break X Y          set a breakpoint at (X, Y)
+delete X Y         remove the breakpoint at (X, Y)
+info breakpoints   list breakpoints
+info statistics    show the instruction mix executed so far
+info loops         show the loops executed so far and their share of ticks
+info watches       show the value of every watch
+info writes [X Y]  show the cells written by `p`, or only writes to (X, Y), latest first
+ip N               only stop at breakpoints reached by IP N and only show its instructions in
+                   backtraces, in concurrent runs; `ip all` to follow every one again
+catch spawn|exit   toggle stopping when an IP is started or ends
+watch EXPR         show EXPR whenever the program stops, e.g. `stack[0]`, `cell(10, 3)`,
+                   `stack.len()` or `ticks`, with arithmetic and comparisons
+unwatch N          remove the Nth watch
+step [N]           execute N instructions (default 1)
+continue           run until a breakpoint, an input request or the end
+advance X Y        continue until the instruction pointer reaches (X, Y)
+until              continue until the instruction pointer leaves the current loop
+finish             continue until the current or next string ends
+print stack        show the stack, top first
+print cell X Y     show the value of the cell at (X, Y)
+set cell X Y 'C'   change the cell at (X, Y) to C
+input TEXT         feed a line of input to the program
+backtrace          show recently executed instructions
+references X Y     show the instructions of the backtrace that executed, read or wrote (X, Y)
+quit               leave the debugger
+An empty line repeats the last command. Breakpoints and watches are kept in the program's
+`.mst` sidecar file for later sessions.";
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Unknown command `{0}`, try `help`")]
+    UnknownCommand(String),
+    #[error("Usage: {0}")]
+    Usage(&'static str),
+    #[error("({0}, {1}) is outside of the grid")]
+    OutOfBounds(usize, usize),
+    #[error("Runtime error: {0}")]
+    Interpreter(#[from] interpreter::Error),
+    #[error("Invalid watch: {0}")]
+    Watch(#[from] watch::Error),
+    #[error("No watch number {0}")]
+    NoWatch(usize),
+}
+
+type Result<T> = anyhow::Result<T, Error>;
+
+#[derive(clap::Args)]
+pub(crate) struct Options {
+    /// Program file location
+    input: String,
+
+    /// Number of executed instructions remembered for `backtrace`
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_HISTORY)]
+    history: usize,
+
+    /// Semantics to follow: `befunge93`, `befunge96` or `befunge97`. Defaults to the program's
+    /// `dialect` directive, then to the settings' `dialect`, or `befunge93`
+    #[arg(long, value_name = "DIALECT")]
+    dialect: Option<Dialect>,
+
+    /// Opt-in extension to enable on top of the program's `extensions` directive, can be
+    /// repeated: `multi-digit`, `concurrent`, `system-info`, `quit` or `assert`. Defaults to the
+    /// settings' `extensions` when the program has none
+    #[arg(long, value_name = "EXTENSION")]
+    extension: Vec<Extension>,
+
+    /// Which instruction pointer runs next with the concurrent extension: `round-robin`,
+    /// `random` or `weighted:W0,W1,...`. Defaults to the program's `schedule` directive, or
+    /// `round-robin`
+    #[arg(long, value_name = "POLICY")]
+    schedule: Option<Schedule>,
+
+    #[command(flatten)]
+    geometry: headless::Geometry,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    Break(usize, usize),
+    Delete(usize, usize),
+    InfoBreakpoints,
+    InfoStatistics,
+    InfoLoops,
+    InfoWatches,
+    InfoWrites(Option<(usize, usize)>),
+    Ip(Option<u32>),
+    Catch(IpEvent),
+    Watch(Watch),
+    Unwatch(usize),
+    Step(usize),
+    Continue,
+    RunTo(Target),
+    PrintStack,
+    PrintCell(usize, usize),
+    SetCell(usize, usize, char),
+    Input(String),
+    Backtrace,
+    References(usize, usize),
+    Help,
+    Quit,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum IpEvent {
+    Spawn,
+    Exit,
+}
+
+impl FromStr for Command {
+    type Err = Error;
+
+    fn from_str(line: &str) -> Result<Self> {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or_default();
+
+        let position = |words: &mut std::str::SplitWhitespace, usage| -> Result<(usize, usize)> {
+            let x = words.next().and_then(|word| word.parse().ok());
+            let y = words.next().and_then(|word| word.parse().ok());
+            x.zip(y).ok_or(Error::Usage(usage))
+        };
+
+        Ok(match command {
+            "break" | "b" => {
+                let (x, y) = position(&mut words, "break X Y")?;
+                Command::Break(x, y)
+            }
+            "delete" | "d" => {
+                let (x, y) = position(&mut words, "delete X Y")?;
+                Command::Delete(x, y)
+            }
+            "info" | "i" => match words.next() {
+                Some("breakpoints" | "b") => Command::InfoBreakpoints,
+                Some("statistics" | "stats" | "s") => Command::InfoStatistics,
+                Some("loops" | "l") => Command::InfoLoops,
+                Some("watches" | "w") => Command::InfoWatches,
+                Some("writes") => match words.clone().next() {
+                    Some(_) => {
+                        Command::InfoWrites(Some(position(&mut words, "info writes [X Y]")?))
+                    }
+                    None => Command::InfoWrites(None),
+                },
+                _ => {
+                    return Err(Error::Usage(
+                        "info breakpoints | info statistics | info loops | info watches | \
+                         info writes",
+                    ))
+                }
+            },
+            "ip" => match words.next() {
+                Some("all") => Command::Ip(None),
+                Some(id) => Command::Ip(Some(id.parse().map_err(|_| Error::Usage("ip N|all"))?)),
+                None => return Err(Error::Usage("ip N|all")),
+            },
+            "catch" => match words.next() {
+                Some("spawn") => Command::Catch(IpEvent::Spawn),
+                Some("exit") => Command::Catch(IpEvent::Exit),
+                _ => return Err(Error::Usage("catch spawn|exit")),
+            },
+            "watch" | "w" => match skip_words(line, 1).trim_end() {
+                "" => return Err(Error::Usage("watch EXPR")),
+                expression => Command::Watch(expression.parse()?),
+            },
+            "unwatch" => match words.next().and_then(|word| word.parse().ok()) {
+                Some(index) => Command::Unwatch(index),
+                None => return Err(Error::Usage("unwatch N")),
+            },
+            "step" | "s" => Command::Step(match words.next() {
+                Some(count) => count.parse().map_err(|_| Error::Usage("step [N]"))?,
+                None => 1,
+            }),
+            "continue" | "c" => Command::Continue,
+            "advance" => {
+                let position = position(&mut words, "advance X Y")?;
+                Command::RunTo(Target::Cell(position))
+            }
+            "until" | "u" => Command::RunTo(Target::LoopExit),
+            "finish" => Command::RunTo(Target::StringEnd),
+            "print" | "p" => match words.next() {
+                Some("stack") => Command::PrintStack,
+                Some("cell") => {
+                    let (x, y) = position(&mut words, "print cell X Y")?;
+                    Command::PrintCell(x, y)
+                }
+                _ => return Err(Error::Usage("print stack | print cell X Y")),
+            },
+            "set" => {
+                const USAGE: &str = "set cell X Y 'C'";
+
+                if words.next() != Some("cell") {
+                    return Err(Error::Usage(USAGE));
+                }
+                let (x, y) = position(&mut words, USAGE)?;
+
+                // The value is taken verbatim from the rest of the line so that `' '` works
+                let rest = skip_words(line, 4).trim_end();
+
+                let value = match rest.chars().collect::<Vec<_>>().as_slice() {
+                    ['\'', c, '\''] | [c] => *c,
+                    _ => return Err(Error::Usage(USAGE)),
+                };
+
+                Command::SetCell(x, y, value)
+            }
+            "input" => Command::Input(skip_words(line, 1).to_owned()),
+            "backtrace" | "bt" => Command::Backtrace,
+            "references" | "refs" => {
+                let (x, y) = position(&mut words, "references X Y")?;
+                Command::References(x, y)
+            }
+            "help" | "h" => Command::Help,
+            "quit" | "q" => Command::Quit,
+            other => return Err(Error::UnknownCommand(other.to_owned())),
+        })
+    }
+}
+
+/// Remainder of a line after its first `count` words.
+fn skip_words(line: &str, count: usize) -> &str {
+    (0..count).fold(line.trim_start(), |rest, _| {
+        rest.trim_start_matches(|c: char| !c.is_whitespace())
+            .trim_start()
+    })
+}
+
+/// Runs an interactive debugging session on stdin and stdout.
+pub(crate) fn run(options: Options, config: &Config) -> anyhow::Result<()> {
+    let (grid, directives, _) = headless::load(&options.input, options.geometry)?;
+    let semantics =
+        Semantics::new(options.dialect, &options.extension).or(&Semantics::of(config), &directives);
+    let mut interpreter = Interpreter::new(grid);
+    headless::configure(
+        &mut interpreter,
+        &directives,
+        semantics.dialect,
+        &semantics.extensions,
+        options.schedule.as_ref(),
+    );
+    let mut debugger = Debugger::new(interpreter)
+        .with_history(options.history)
+        .with_loop_profile();
+
+    println!(
+        "Debugging `{}`, type `help` for a list of commands",
+        options.input
+    );
+
+    // Breakpoints and watches are kept next to the program, for later sessions
+    let mut sidecar = match fetch::is_url(&options.input) {
+        true => None,
+        false => match Sidecar::load(&options.input) {
+            Ok(sidecar) => Some(sidecar),
+            Err(err) => {
+                println!("Could not read the sidecar file: {err}");
+                None
+            }
+        },
+    };
+    let mut watches = Vec::<Watch>::new();
+    if let Some(sidecar) = &sidecar {
+        debugger.set_breakpoints(sidecar.breakpoints.iter().copied());
+        for expression in &sidecar.watches {
+            match expression.parse::<Watch>() {
+                Ok(watch) if watches.iter().any(|watched| watched.same(&watch)) => (),
+                Ok(watch) => watches.push(watch),
+                Err(err) => println!("Invalid watch `{expression}` in the sidecar file: {err}"),
+            }
+        }
+    }
+
+    let mut prompt = Prompt::new("(mst) ");
+    let mut last = String::new();
+
+    while let Some(line) = prompt.read()? {
+        let line = match line.trim() {
+            "" => last.clone(),
+            line => line.to_owned(),
+        };
+        if line.is_empty() {
+            continue;
+        }
+        last = line.clone();
+
+        let result = line.parse::<Command>().and_then(|command| {
+            // Watches are shown whenever the program stops
+            let moves = matches!(
+                command,
+                Command::Step(_) | Command::Continue | Command::RunTo(_)
+            );
+            let keep_going = execute(&mut debugger, &mut watches, command)?;
+            if moves {
+                print_watches(&debugger, &watches);
+            }
+            Ok(keep_going)
+        });
+        if let Some(sidecar) = sidecar.as_mut() {
+            persist(&options.input, sidecar, &debugger, &watches);
+        }
+        match result {
+            Ok(true) => (),
+            Ok(false) => break,
+            Err(err) => println!("{err}"),
+        }
+    }
+
+    Ok(())
+}
+
+/// Saves the breakpoints and watches next to the program when they changed.
+fn persist(program: &str, sidecar: &mut Sidecar, debugger: &Debugger, watches: &[Watch]) {
+    let breakpoints = debugger.breakpoints().iter().copied().collect();
+    let watches = watches.iter().map(ToString::to_string).collect();
+    if sidecar.breakpoints == breakpoints && sidecar.watches == watches {
+        return;
+    }
+
+    sidecar.breakpoints = breakpoints;
+    sidecar.watches = watches;
+    if let Err(err) = sidecar.save(program) {
+        println!("Could not save the sidecar file: {err}");
+    }
+}
+
+/// Executes a single command, returns false when the session should end.
+fn execute(debugger: &mut Debugger, watches: &mut Vec<Watch>, command: Command) -> Result<bool> {
+    match command {
+        Command::Break(x, y) => {
+            check_bounds(debugger, x, y)?;
+            if !debugger.breakpoints().contains(&(x, y)) {
+                debugger.toggle_breakpoint((x, y));
+            }
+            println!("Breakpoint set at ({x}, {y})");
+        }
+        Command::Delete(x, y) => {
+            if debugger.breakpoints().contains(&(x, y)) {
+                debugger.toggle_breakpoint((x, y));
+                println!("Breakpoint at ({x}, {y}) deleted");
+            } else {
+                println!("No breakpoint at ({x}, {y})");
+            }
+        }
+        Command::InfoBreakpoints => {
+            let mut breakpoints = debugger.breakpoints().iter().collect::<Vec<_>>();
+            breakpoints.sort_by_key(|(x, y)| (y, x));
+
+            if breakpoints.is_empty() {
+                println!("No breakpoints");
+            }
+            for (x, y) in breakpoints {
+                println!("({x}, {y}) {}", describe_cell(debugger, *x, *y));
+            }
+        }
+        Command::InfoStatistics => print!("{}", debugger.statistics()),
+        Command::InfoWatches => {
+            if watches.is_empty() {
+                println!("No watches");
+            }
+            print_watches(debugger, watches);
+        }
+        Command::InfoWrites(cell) => {
+            let writes = debugger.journal().iter().rev();
+            let mut writes = writes
+                .filter(|write| cell.is_none_or(|cell| write.position == cell))
+                .peekable();
+            if writes.peek().is_none() {
+                println!("No writes");
+            }
+            for write in writes {
+                println!("{write}");
+            }
+        }
+        Command::Ip(id) => {
+            debugger.set_ip_filter(id);
+            match id {
+                Some(id) => println!("Following IP {id}"),
+                None => println!("Following every IP"),
+            }
+        }
+        Command::Catch(event) => {
+            let (mut spawn, mut exit) = debugger.ip_events();
+            let (caught, name) = match event {
+                IpEvent::Spawn => (&mut spawn, "started"),
+                IpEvent::Exit => (&mut exit, "ending"),
+            };
+            *caught = !*caught;
+            match *caught {
+                true => println!("Stopping when an IP is {name}"),
+                false => println!("No longer stopping when an IP is {name}"),
+            }
+            debugger.set_ip_events(spawn, exit);
+        }
+        Command::Watch(watch) => {
+            if let Some(index) = watches.iter().position(|watched| watched.same(&watch)) {
+                println!("Already watched as {index}: {}", watches[index]);
+                return Ok(true);
+            }
+            println!(
+                "{}: {}",
+                watches.len(),
+                watch.describe(debugger.interpreter())
+            );
+            watches.push(watch);
+        }
+        Command::Unwatch(index) => {
+            if index >= watches.len() {
+                return Err(Error::NoWatch(index));
+            }
+            println!("Removed watch {index}: {}", watches.remove(index));
+        }
+        Command::InfoLoops => {
+            if let Some(loops) = debugger.loop_profile() {
+                print!("{}", loops.report());
+            }
+        }
+        Command::Step(count) => {
+            for _ in 0..count {
+                let stop = debugger.step();
+                flush_output(debugger);
+
+                match stop? {
+                    Stop::Step | Stop::Breakpoint(_) => (),
+                    stop => {
+                        report(debugger, stop);
+                        return Ok(true);
+                    }
+                }
+            }
+            report(debugger, Stop::Step);
+        }
+        Command::Continue => resume(debugger)?,
+        Command::RunTo(target) => {
+            if let Target::Cell((x, y)) = target {
+                check_bounds(debugger, x, y)?;
+            }
+            debugger.run_to(Some(target));
+            resume(debugger)?;
+        }
+        Command::PrintStack => {
+            let stack = debugger.interpreter().stack();
+            if stack.is_empty() {
+                println!("Stack is empty");
+            }
+            for (index, value) in stack.iter().rev().enumerate() {
+                println!("[{index}] {}", describe(*value));
+            }
+        }
+        Command::PrintCell(x, y) => {
+            check_bounds(debugger, x, y)?;
+            println!("({x}, {y}) {}", describe_cell(debugger, x, y));
+        }
+        Command::SetCell(x, y, value) => {
+            check_bounds(debugger, x, y)?;
+            debugger
+                .interpreter_mut()
+                .grid_mut()
+                .set(x, y, CellValue::from(value));
+            println!("({x}, {y}) {}", describe_cell(debugger, x, y));
+        }
+        Command::Input(text) => {
+            debugger.feed_input(&format!("{text}\n"));
+        }
+        Command::Backtrace => {
+            let filter = debugger.ip_filter();
+            let history = debugger.history().iter().rev();
+            let history = history.filter(|entry| filter.is_none_or(|id| entry.ip == id));
+            for (depth, entry) in history.enumerate() {
+                println!("#{depth:<3} {entry}");
+            }
+        }
+        Command::References(x, y) => {
+            let mut references = debugger.references((x, y)).peekable();
+            if references.peek().is_none() {
+                println!("No references to ({x}, {y}) in the backtrace");
+            }
+            for (access, entry) in references {
+                println!("{:<8} {entry}", access.name());
+            }
+        }
+        Command::Help => println!("{HELP}"),
+        Command::Quit => return Ok(false),
+    }
+
+    Ok(true)
+}
+
+/// Runs until the debugger hands control back.
+fn resume(debugger: &mut Debugger) -> Result<()> {
+    loop {
+        let stop = debugger.resume(SLICE);
+        flush_output(debugger);
+
+        if let Some(stop) = stop? {
+            report(debugger, stop);
+            return Ok(());
+        }
+    }
+}
+
+fn report(debugger: &Debugger, stop: Stop) {
+    let interpreter = debugger.interpreter();
+    let (x, y) = interpreter.position();
+
+    match stop {
+        Stop::Step | Stop::Reached => println!("({x}, {y}) {}", describe_cell(debugger, x, y)),
+        Stop::Breakpoint(_) => {
+            println!(
+                "Breakpoint hit at ({x}, {y}) {}",
+                describe_cell(debugger, x, y)
+            )
+        }
+        Stop::WaitingForInput => {
+            println!("Program is waiting for input at ({x}, {y}), feed it with `input TEXT`")
+        }
+        Stop::Terminated => match interpreter.exit_code() {
+            Some(code) => println!(
+                "Program ended with exit code {code} after {} ticks",
+                interpreter.ticks()
+            ),
+            None => println!("Program ended after {} ticks", interpreter.ticks()),
+        },
+        Stop::Spawned(id) => println!(
+            "IP {id} started, ({x}, {y}) {}",
+            describe_cell(debugger, x, y)
+        ),
+        Stop::Exited(id) => println!(
+            "IP {id} ended, ({x}, {y}) {}",
+            describe_cell(debugger, x, y)
+        ),
+    }
+}
+
+fn print_watches(debugger: &Debugger, watches: &[Watch]) {
+    for (index, watch) in watches.iter().enumerate() {
+        println!("{index}: {}", watch.describe(debugger.interpreter()));
+    }
+}
+
+fn flush_output(debugger: &mut Debugger) {
+    let output = debugger.interpreter_mut().take_output();
+    if !output.is_empty() {
+        print!("{output}");
+        if !output.ends_with('\n') {
+            println!();
+        }
+    }
+}
+
+fn check_bounds(debugger: &Debugger, x: usize, y: usize) -> Result<()> {
+    let (width, height) = debugger.interpreter().grid().size();
+    if x < width && y < height {
+        Ok(())
+    } else {
+        Err(Error::OutOfBounds(x, y))
+    }
+}
+
+fn describe_cell(debugger: &Debugger, x: usize, y: usize) -> String {
+    let value = debugger.interpreter().grid().get(x, y).value;
+    format!("`{}` {}", char::from(value), value.documentation())
+}
+
+fn describe(value: i32) -> String {
+    match u32::try_from(value).ok().and_then(char::from_u32) {
+        Some(c) if !c.is_control() => format!("{value} ({c:?})"),
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!("b 3 4".parse::<Command>().unwrap(), Command::Break(3, 4));
+        assert_eq!("step".parse::<Command>().unwrap(), Command::Step(1));
+        assert_eq!("ip 2".parse::<Command>().unwrap(), Command::Ip(Some(2)));
+        assert_eq!("ip all".parse::<Command>().unwrap(), Command::Ip(None));
+        assert_eq!(
+            "info writes 3 0".parse::<Command>().unwrap(),
+            Command::InfoWrites(Some((3, 0)))
+        );
+        assert_eq!(
+            "catch exit".parse::<Command>().unwrap(),
+            Command::Catch(IpEvent::Exit)
+        );
+        assert_eq!("s 10".parse::<Command>().unwrap(), Command::Step(10));
+        assert_eq!(
+            "print cell 1 2".parse::<Command>().unwrap(),
+            Command::PrintCell(1, 2)
+        );
+        assert_eq!(
+            "set cell 1 2 '<'".parse::<Command>().unwrap(),
+            Command::SetCell(1, 2, '<')
+        );
+        assert_eq!(
+            "set cell 1 2 ' '".parse::<Command>().unwrap(),
+            Command::SetCell(1, 2, ' ')
+        );
+        assert_eq!(
+            "set cell 1 2 v".parse::<Command>().unwrap(),
+            Command::SetCell(1, 2, 'v')
+        );
+        assert_eq!(
+            "input 12 3".parse::<Command>().unwrap(),
+            Command::Input("12 3".to_owned())
+        );
+        assert_eq!(
+            "advance 3 4".parse::<Command>().unwrap(),
+            Command::RunTo(Target::Cell((3, 4)))
+        );
+        assert_eq!(
+            "watch cell(1, 2) + 1".parse::<Command>().unwrap(),
+            Command::Watch("cell(1, 2) + 1".parse().unwrap())
+        );
+        assert!("print".parse::<Command>().is_err());
+        assert!("jump".parse::<Command>().is_err());
+    }
+}
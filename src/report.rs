@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{analyzer::Severity, directives::Source};
+
+/// Version of the schema of [Report], bumped when it changes in ways readers would trip on.
+pub const VERSION: u32 = 1;
+
+/// Diagnostics of a command for editors and CI to annotate programs with, written as JSON:
+///
+/// ```text
+/// {"version": 1, "diagnostics": [{"file": "hello.bf", "position": [3, 0], "line": 1,
+///   "column": 4, "severity": "error", "code": "dialect", "message": "..."}]}
+/// ```
+///
+/// `severity` is `error`, `warning` or `info`. `position` is the cell the diagnostic is about,
+/// `line` and `column` where it is in the file counting from 1, and all three are left out of
+/// diagnostics about a program as a whole.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Report {
+    pub version: u32,
+    pub diagnostics: Vec<Entry>,
+}
+
+/// Diagnostic of a [Report].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub file: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<(usize, usize)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// In characters rather than bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    pub severity: Severity,
+    /// What kind of diagnostic it is, e.g. `dialect` for constructs of another dialect or
+    /// `runtime` for runs that failed
+    pub code: String,
+    pub message: String,
+}
+
+impl Default for Report {
+    fn default() -> Self {
+        Self {
+            version: VERSION,
+            diagnostics: Vec::new(),
+        }
+    }
+}
+
+impl Entry {
+    pub fn new(file: &str, severity: Severity, code: &str, message: impl ToString) -> Self {
+        Self {
+            file: file.to_owned(),
+            position: None,
+            line: None,
+            column: None,
+            severity,
+            code: code.to_owned(),
+            message: message.to_string(),
+        }
+    }
+
+    /// Points the diagnostic at a cell, `source` telling where it is in the program file.
+    pub fn at(mut self, position: (usize, usize), source: &Source) -> Self {
+        let (column, line) = source.position(position);
+        self.position = Some(position);
+        self.line = Some(line + 1);
+        self.column = Some(column + 1);
+        self
+    }
+}
+
+impl Report {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Same diagnostics as a SARIF 2.1.0 log, as read by code scanning services.
+    pub fn to_sarif(&self) -> Value {
+        let mut rules = self
+            .diagnostics
+            .iter()
+            .map(|entry| entry.code.as_str())
+            .collect::<Vec<_>>();
+        rules.sort();
+        rules.dedup();
+
+        let results = self
+            .diagnostics
+            .iter()
+            .map(|entry| {
+                let mut location = json!({
+                    "physicalLocation": { "artifactLocation": { "uri": entry.file } },
+                });
+                if let Some((line, column)) = entry.line.zip(entry.column) {
+                    location["physicalLocation"]["region"] =
+                        json!({ "startLine": line, "startColumn": column });
+                }
+                json!({
+                    "ruleId": entry.code,
+                    "level": match entry.severity {
+                        Severity::Error => "error",
+                        Severity::Warning => "warning",
+                        Severity::Info => "note",
+                    },
+                    "message": { "text": entry.message },
+                    "locations": [location],
+                })
+            })
+            .collect::<Vec<_>>();
+
+        json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": rules.iter().map(|id| json!({ "id": id })).collect::<Vec<_>>(),
+                    },
+                },
+                "results": results,
+            }],
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::directives::{self, Tabs};
+
+    #[test]
+    fn report() {
+        let dir = std::env::temp_dir().join(format!("puccinia-report-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("lib.bf"), "@").unwrap();
+        let program = "#!mst seed=1\n;; include lib.bf at 0,2\n>1v\n\t @\n";
+        std::fs::write(dir.join("a.bf"), program).unwrap();
+        let (grid, .., source) = directives::open_with(dir.join("a.bf"), Tabs::Expand(4)).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(grid.lines(), vec![">1v", "     @", "@"]);
+        assert_eq!(source.cell((2, 3)), Some((5, 1)));
+        assert_eq!(source.cell((0, 1)), None);
+
+        // The header and include lines are left out of the grid and the tab spans 4 cells
+        let report = Report {
+            diagnostics: vec![
+                Entry::new("a.bf", Severity::Error, "runtime", "Out of bounds").at((5, 1), &source),
+                Entry::new("a.bf", Severity::Warning, "dialect", "Funge-98 only"),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            (report.diagnostics[0].line, report.diagnostics[0].column),
+            (Some(4), Some(3))
+        );
+
+        let json = report.to_json();
+        assert!(!json.contains("\"line\": null"));
+        assert_eq!(serde_json::from_str::<Report>(&json).unwrap(), report);
+
+        let sarif = report.to_sarif();
+        let results = &sarif["runs"][0]["results"];
+        assert_eq!(results[0]["level"], "error");
+        assert_eq!(
+            results[0]["locations"][0]["physicalLocation"]["region"]["startLine"],
+            4
+        );
+        assert!(results[1]["locations"][0]["physicalLocation"]["region"].is_null());
+        assert_eq!(
+            sarif["runs"][0]["tool"]["driver"]["rules"][0]["id"],
+            "dialect"
+        );
+    }
+}
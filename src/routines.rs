@@ -0,0 +1,171 @@
+use std::str::FromStr;
+
+use crate::synthesis;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Unknown routine `{0}`, expected `print-signed`, `read-number` or `divide`")]
+    Unknown(String),
+}
+
+/// Block of code doing a common chore, for programs to include rather than write again.
+///
+/// All routines follow the same calling convention: they are entered on their top-left cell going
+/// right, and leave going right past the end of their top row. In between, they replace the
+/// values they take from the top of the stack with the ones they give back, as told by their
+/// [signature](Routine::signature), and leave the rest of the stack alone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Routine {
+    /// Prints a number without the trailing space of `.`, with a `-` if negative
+    PrintSigned,
+    /// Reads a line of digits, telling whether it was a number
+    ReadNumber,
+    /// Divides by subtracting, for a quotient and remainder in one go
+    Divide,
+}
+
+impl Routine {
+    pub const ALL: [Routine; 3] = [Routine::PrintSigned, Routine::ReadNumber, Routine::Divide];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Routine::PrintSigned => "print-signed",
+            Routine::ReadNumber => "read-number",
+            Routine::Divide => "divide",
+        }
+    }
+
+    /// Values the routine takes and gives back, as `before -- after` with the top of the stack
+    /// on the right.
+    pub fn signature(self) -> &'static str {
+        match self {
+            Routine::PrintSigned => "n --",
+            Routine::ReadNumber => "-- n ok",
+            Routine::Divide => "a b -- q r",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Routine::PrintSigned => "Print `n` in decimal, with a `-` if negative and no space",
+            Routine::ReadNumber => {
+                "Read a line of digits as `n`, `ok` being 0 and `n` 0 if it was empty or had \
+                 anything else"
+            }
+            Routine::Divide => {
+                "Divide `a` >= 0 by `b` > 0 with `-` alone, storing `b` in the cell below the \
+                 top-left corner"
+            }
+        }
+    }
+
+    /// Whether the code depends on where the routine is placed, as it reads and writes cells of
+    /// its own.
+    pub fn positioned(self) -> bool {
+        self == Routine::Divide
+    }
+
+    /// Rows of the routine with its top-left corner at `at`, which only matters to
+    /// [positioned](Routine::positioned) ones.
+    pub fn code(self, at: (usize, usize)) -> Vec<String> {
+        let rows: &[&str] = match self {
+            Routine::PrintSigned => &[
+                ":0\\`#v_      >01-\\>:55+%\\55+/:#v_$>:1+   #v_$",
+                "     >\"-\",0\\-^    ^            <  ^  ,+\"0\"<",
+            ],
+            Routine::ReadNumber => &[
+                "01- >~:55+-#v_$:0\\`!\\:0\\`+\\               >",
+                "",
+                "            >\"0\"-:9`#v_:0\\`#v_\\:0\\`+55+*+v",
+                "    ^                                    <",
+                "                     >      >$$>~55+-#v_00^",
+                "                               ^      <",
+            ],
+            Routine::Divide => {
+                // The divisor is kept below the top-left corner, as the quotient, the remainder
+                // and the divisor can't all be juggled on the stack. That cell starts as a `0`
+                // for it not to be taken as free when placing the routine
+                let (x, y) = (at.0 as i32, at.1 as i32 + 1);
+                let cell = synthesis::number(x, None) + &synthesis::number(y, None);
+                let top = format!("{cell}p0\\>:{cell}g\\`!#v_");
+                let turn = cell.len() + 2;
+                let step = format!("{cell}g-\\1+\\").chars().rev().collect::<String>();
+                return vec![top, format!("0{:turn$}^{step}<", "")];
+            }
+        };
+        rows.iter().map(|row| row.to_string()).collect()
+    }
+}
+
+impl FromStr for Routine {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Routine::ALL
+            .into_iter()
+            .find(|routine| routine.name() == name)
+            .ok_or_else(|| Error::Unknown(name.to_owned()))
+    }
+}
+
+impl std::fmt::Display for Routine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        cell::CellValue,
+        grid::Grid,
+        interpreter::{Interpreter, Status},
+    };
+
+    /// Runs `setup` followed by the routine, returning the stack and the output.
+    fn call(routine: Routine, setup: &str, input: &str) -> (Vec<i32>, String) {
+        let at = (setup.len(), 0);
+        let code = Grid::from(routine.code(at).join("\n"));
+        let mut grid = Grid::from(setup.to_owned());
+        grid.paste(&code, at).unwrap();
+        let end = at.0 + code.size().0;
+        grid.grow_to(end, 0);
+        grid.set(end, 0, CellValue::End);
+
+        let mut interpreter = Interpreter::new(grid);
+        interpreter.feed_input(input);
+        for _ in 0..100_000 {
+            if interpreter.step().unwrap() != Status::Running {
+                break;
+            }
+        }
+        (interpreter.stack().to_vec(), interpreter.take_output())
+    }
+
+    #[test]
+    fn routines() {
+        for (n, output) in [("0", "0"), ("7", "7"), ("-4050", "-4050")] {
+            let setup = format!("9{}", synthesis::number(n.parse().unwrap(), None));
+            assert_eq!(
+                call(Routine::PrintSigned, &setup, ""),
+                (vec![9], output.to_owned())
+            );
+        }
+
+        for (input, stack) in [
+            ("2024\n", vec![9, 2024, 1]),
+            ("0\n", vec![9, 0, 1]),
+            ("\n", vec![9, 0, 0]),
+            ("12a3\n", vec![9, 0, 0]),
+        ] {
+            assert_eq!(call(Routine::ReadNumber, "9", input).0, stack, "{input:?}");
+        }
+
+        for (a, b, stack) in [(17, 5, [9, 3, 2]), (4, 7, [9, 0, 4]), (12, 3, [9, 4, 0])] {
+            let setup = format!("9{}{}", synthesis::number(a, None), b);
+            assert_eq!(call(Routine::Divide, &setup, "").0, stack);
+        }
+        assert_eq!("divide".parse(), Ok(Routine::Divide));
+    }
+}
@@ -0,0 +1,149 @@
+use puccinia::{golf::Metrics, interpreter::Interpreter};
+
+use crate::headless::{self, Ending, Geometry};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Could not read `{0}`: {1}")]
+    Read(String, std::io::Error),
+}
+
+#[derive(clap::Args)]
+pub(crate) struct Options {
+    /// Original program
+    before: String,
+
+    /// Golfed program
+    after: String,
+
+    /// File fed to both programs as input, unless they are bundles bringing their own
+    #[arg(long, value_name = "PATH")]
+    stdin: Option<String>,
+
+    /// Instructions after which a program is considered to run forever
+    #[arg(long, value_name = "N", default_value_t = 10_000_000)]
+    max_ticks: u64,
+}
+
+/// Size of a program and how its run went.
+struct Score {
+    metrics: Metrics,
+    output: String,
+    ending: Ending,
+    steps: u64,
+}
+
+impl Score {
+    fn of(path: &str, input: &str, max_ticks: u64) -> anyhow::Result<Self> {
+        let (grid, directives, canned) = headless::load(path, Geometry::default())?;
+        let metrics = Metrics::of(&grid);
+
+        let mut interpreter = Interpreter::new(grid);
+        headless::configure(&mut interpreter, &directives, None, &[], None);
+        let input = canned.as_deref().unwrap_or(input);
+        let ending = headless::run_to_end(&mut interpreter, input, max_ticks);
+
+        Ok(Self {
+            metrics,
+            output: interpreter.take_output(),
+            ending,
+            steps: interpreter.ticks(),
+        })
+    }
+}
+
+/// Compares the size and step count of two versions of a program, checking that they still
+/// behave the same.
+pub(crate) fn run(options: Options) -> anyhow::Result<()> {
+    let input = options
+        .stdin
+        .map(|path| std::fs::read_to_string(&path).map_err(|err| Error::Read(path, err)))
+        .transpose()?
+        .unwrap_or_default();
+    let before = Score::of(&options.before, &input, options.max_ticks)?;
+    let after = Score::of(&options.after, &input, options.max_ticks)?;
+
+    print!("{}", report(&before, &after));
+    Ok(())
+}
+
+fn report(before: &Score, after: &Score) -> String {
+    let change = |before: u64, after: u64| match after as i64 - before as i64 {
+        0 => "=".to_owned(),
+        change => format!("{change:+}"),
+    };
+    let row = |name: &str, before: u64, after: u64| {
+        format!(
+            "{name:<12} {before:>10} {after:>10} {:>8}\n",
+            change(before, after)
+        )
+    };
+    let size = |metrics: &Metrics| format!("{}x{}", metrics.width, metrics.height);
+    let (before_metrics, after_metrics) = (&before.metrics, &after.metrics);
+
+    let mut report = format!(
+        "{:<12} {:>10} {:>10} {:>8}\n",
+        "", "before", "after", "change"
+    );
+    report += &row(
+        "bytes",
+        before_metrics.bytes as u64,
+        after_metrics.bytes as u64,
+    );
+    report += &format!(
+        "{:<12} {:>10} {:>10} {:>8}\n",
+        "bounding box",
+        size(before_metrics),
+        size(after_metrics),
+        change(
+            (before_metrics.width * before_metrics.height) as u64,
+            (after_metrics.width * after_metrics.height) as u64
+        ),
+    );
+    report += &row("steps", before.steps, after.steps);
+
+    report += &match (&before.ending, &after.ending) {
+        (Ending::Ended, Ending::Ended) if before.output == after.output => {
+            "Both versions write the same output\n".to_owned()
+        }
+        (Ending::Ended, Ending::Ended) => format!(
+            "Outputs differ: {:?} before, {:?} after\n",
+            before.output, after.output
+        ),
+        (before, after) => format!(
+            "The original {} and the golfed version {}\n",
+            before.describe(),
+            after.describe()
+        ),
+    };
+
+    report
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn report() {
+        let score = |bytes, width, output: &str, steps| Score {
+            metrics: Metrics {
+                bytes,
+                width,
+                height: 1,
+            },
+            output: output.to_owned(),
+            ending: Ending::Ended,
+            steps,
+        };
+
+        assert_eq!(
+            super::report(&score(9, 9, "3 ", 12), &score(5, 5, "3 ", 12)),
+            "                 before      after   change\n\
+             bytes                 9          5       -4\n\
+             bounding box        9x1        5x1       -4\n\
+             steps                12         12        =\n\
+             Both versions write the same output\n"
+        );
+    }
+}
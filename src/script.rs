@@ -0,0 +1,177 @@
+use std::{
+    collections::VecDeque,
+    path::Path,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use crate::directives;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("Line {0}: expected `after N ticks: INPUT`, `after N ms: INPUT` or `now: INPUT`")]
+    Syntax(usize),
+}
+
+pub type Result<T> = anyhow::Result<T, Error>;
+
+/// When an entry of a script is fed, relative to the previous one or the start of the run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Trigger {
+    Now,
+    Ticks(u64),
+    Delay(Duration),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub trigger: Trigger,
+    pub input: String,
+}
+
+/// Input fed to a program over the course of its run, one entry per line:
+///
+/// ```text
+/// # Comment
+/// after 500 ticks: 5\n
+/// after 250 ms: q
+/// now: \s
+/// ```
+///
+/// Input is escaped like the `output` directive, see [directives::unescape].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Script {
+    pub entries: Vec<Entry>,
+}
+
+impl Script {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        std::fs::read_to_string(path)?.parse()
+    }
+}
+
+impl FromStr for Script {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let entries = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+            .map(|(index, line)| {
+                let (trigger, input) = line.split_once(':').ok_or(Error::Syntax(index + 1))?;
+                let trigger = parse_trigger(trigger.trim()).ok_or(Error::Syntax(index + 1))?;
+                Ok(Entry {
+                    trigger,
+                    input: directives::unescape(input.strip_prefix(' ').unwrap_or(input)),
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Script { entries })
+    }
+}
+
+fn parse_trigger(trigger: &str) -> Option<Trigger> {
+    if trigger == "now" {
+        return Some(Trigger::Now);
+    }
+
+    let mut words = trigger.strip_prefix("after ")?.split_whitespace();
+    let amount = words.next()?.parse().ok()?;
+    let trigger = match words.next()? {
+        "tick" | "ticks" => Trigger::Ticks(amount),
+        "ms" => Trigger::Delay(Duration::from_millis(amount)),
+        "s" => Trigger::Delay(Duration::from_secs(amount)),
+        _ => return None,
+    };
+
+    words.next().is_none().then_some(trigger)
+}
+
+/// Plays a script back against a run.
+#[derive(Debug)]
+pub struct Player {
+    entries: VecDeque<Entry>,
+    /// Tick and time the previous entry was fed at
+    since: (u64, Instant),
+}
+
+impl Player {
+    pub fn new(script: &Script) -> Self {
+        Self {
+            entries: script.entries.iter().cloned().collect(),
+            since: (0, Instant::now()),
+        }
+    }
+
+    /// Input of the entries that are due at `ticks`.
+    pub fn poll(&mut self, ticks: u64) -> Option<String> {
+        let mut input: Option<String> = None;
+
+        while let Some(entry) = self.entries.front() {
+            let due = match entry.trigger {
+                Trigger::Now => true,
+                Trigger::Ticks(n) => ticks.saturating_sub(self.since.0) >= n,
+                Trigger::Delay(delay) => self.since.1.elapsed() >= delay,
+            };
+            if !due {
+                break;
+            }
+
+            let entry = self.entries.pop_front().expect("entries has a front");
+            input.get_or_insert_with(String::new).push_str(&entry.input);
+            self.since = (ticks, Instant::now());
+        }
+
+        input
+    }
+
+    /// Input of the next entry for a program that cannot progress without it, waiting out its
+    /// delay. Tick counts cannot be reached while waiting and are skipped.
+    pub fn wait(&mut self, ticks: u64) -> Option<String> {
+        let entry = self.entries.pop_front()?;
+        if let Trigger::Delay(delay) = entry.trigger {
+            std::thread::sleep(delay.saturating_sub(self.since.1.elapsed()));
+        }
+
+        self.since = (ticks, Instant::now());
+        Some(entry.input)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn play() {
+        let script = "# Comment\nafter 5 ticks: 1\\n\n\nnow:  2\nafter 1 s: 3"
+            .parse::<Script>()
+            .unwrap();
+        assert_eq!(
+            script
+                .entries
+                .iter()
+                .map(|entry| entry.trigger)
+                .collect::<Vec<_>>(),
+            vec![
+                Trigger::Ticks(5),
+                Trigger::Now,
+                Trigger::Delay(Duration::from_secs(1))
+            ]
+        );
+
+        let mut player = Player::new(&script);
+        assert_eq!(player.poll(4), None);
+        assert_eq!(player.poll(5).as_deref(), Some("1\n 2"));
+        assert_eq!(player.poll(6), None);
+
+        assert!(matches!(
+            "after 5: 1".parse::<Script>(),
+            Err(Error::Syntax(1))
+        ));
+    }
+}
@@ -0,0 +1,191 @@
+use std::path::{Path, PathBuf};
+
+use puccinia::{
+    config::{self, Config},
+    dialect::{Dialect, Extension},
+    directives::Directives,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Could not read `{0}`: {1}")]
+    Read(String, std::io::Error),
+    #[error("Invalid configuration `{0}`: {1}")]
+    Invalid(String, config::Error),
+}
+
+/// Configuration of the user, in `$XDG_CONFIG_HOME/mst/config.toml` or
+/// `~/.config/mst/config.toml`.
+fn user() -> Option<PathBuf> {
+    let home = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(home.join("mst").join("config.toml"))
+}
+
+/// Configuration of the project the current directory is in, the closest [config::PROJECT] file
+/// up from it.
+fn project() -> Option<PathBuf> {
+    let current = std::env::current_dir().ok()?;
+    current
+        .ancestors()
+        .map(|directory| directory.join(config::PROJECT))
+        .find(|path| path.is_file())
+}
+
+fn open(path: &Path) -> Result<Config, Error> {
+    let name = path.display().to_string();
+    let text = std::fs::read_to_string(path).map_err(|err| Error::Read(name.clone(), err))?;
+    let config = Config::parse(&text).map_err(|err| Error::Invalid(name, err))?;
+    Ok(config.rooted(path.parent().unwrap_or(Path::new("."))))
+}
+
+/// Settings of the user merged with those of the project, the latter taking precedence.
+pub(crate) fn load() -> Result<Config, Error> {
+    let user = match user().filter(|path| path.is_file()) {
+        Some(path) => open(&path)?,
+        None => Config::default(),
+    };
+    match project() {
+        Some(path) => {
+            let project = open(&path)?;
+            user.merge(project)
+                .map_err(|err| Error::Invalid(path.display().to_string(), err))
+        }
+        None => Ok(user),
+    }
+}
+
+/// Dialect and extensions programs are run with on top of their directives, as given on the
+/// command line or in the settings.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Semantics {
+    pub dialect: Option<Dialect>,
+    pub extensions: Vec<Extension>,
+}
+
+impl Semantics {
+    pub fn new(dialect: Option<Dialect>, extensions: &[Extension]) -> Self {
+        Self {
+            dialect,
+            extensions: extensions.to_vec(),
+        }
+    }
+
+    /// Those of the settings.
+    pub fn of(config: &Config) -> Self {
+        Self::new(config.dialect, &config.extensions)
+    }
+
+    /// What a program with these `directives` runs with, the command line (`self`) taking
+    /// precedence over the directives and those over the `settings`.
+    pub fn or(&self, settings: &Semantics, directives: &Directives) -> Semantics {
+        let dialect = settings.dialect.filter(|_| directives.dialect.is_none());
+        let extensions = match (self.extensions.is_empty(), directives.extensions.is_empty()) {
+            (false, _) => &self.extensions,
+            (true, true) => &settings.extensions,
+            (true, false) => &Vec::new(),
+        };
+        Self::new(self.dialect.or(dialect), extensions)
+    }
+}
+
+/// Makes the settings of `config` the defaults of the matching options of every subcommand, so
+/// that the command line still takes precedence. The dialect and extensions are left to
+/// [Semantics], programs' own directives coming before them.
+pub(crate) fn apply(mut command: clap::Command, config: &Config) -> clap::Command {
+    let mut defaults = Vec::new();
+    if let Some(ticks) = config.max_ticks {
+        defaults.push(("max_ticks", vec![ticks.to_string()]));
+    }
+    if let Some(theme) = config.theme {
+        defaults.push(("theme", vec![theme.to_string()]));
+    }
+    if let Some(layout) = &config.layout {
+        defaults.push(("layout", vec![layout.clone()]));
+    }
+    if let Some(length) = config.trail_length {
+        defaults.push(("trail_length", vec![length.to_string()]));
+    }
+    let switches = [
+        ("ansi", config.ansi),
+        ("accessible", config.accessible),
+        ("trail", config.trail),
+    ];
+    for (id, set) in switches {
+        if let Some(set) = set {
+            defaults.push((id, vec![set.to_string()]));
+        }
+    }
+
+    command = command.mut_args(
+        |arg| match defaults.iter().find(|(id, _)| arg.get_id() == *id) {
+            Some((_, values)) => arg.default_values(values.iter()),
+            None => arg,
+        },
+    );
+    let names = command
+        .get_subcommands()
+        .map(|subcommand| subcommand.get_name().to_owned())
+        .collect::<Vec<_>>();
+    for name in names {
+        command = command.mut_subcommand(name, |subcommand| apply(subcommand, config));
+    }
+    command
+}
+
+#[cfg(test)]
+mod test {
+    use clap::CommandFactory;
+    use puccinia::{directives, interpreter::Interpreter};
+
+    use super::*;
+    use crate::{headless, Args};
+
+    #[test]
+    fn precedence() {
+        let config = Config::parse("dialect = \"befunge96\"\nextensions = [\"quit\"]").unwrap();
+        let settings = Semantics::of(&config);
+
+        // The settings aren't defaults of the command line, which would hide the program's own
+        let matches = apply(Args::command(), &config).get_matches_from(["puccinia", "run", "a.bf"]);
+        let (_, run) = matches.subcommand().unwrap();
+        assert_eq!(run.get_one::<Dialect>("dialect"), None);
+
+        let output = |program: &str, flags: Semantics| {
+            let (grid, directives) = directives::parse_program(program.as_bytes()).unwrap();
+            let semantics = flags.or(&settings, &directives);
+            let mut interpreter = Interpreter::new(grid);
+            headless::configure(
+                &mut interpreter,
+                &directives,
+                semantics.dialect,
+                &semantics.extensions,
+                None,
+            );
+            headless::run_to_end(&mut interpreter, "", 100);
+            interpreter.take_output()
+        };
+        let none = Semantics::default();
+        assert_eq!(output("#!mst dialect=befunge93\n0!.@", none.clone()), "1 ");
+        assert_eq!(output("0!.@", none.clone()), "-1 ");
+        let befunge93 = Semantics::new(Some(Dialect::Befunge93), &[]);
+        assert_eq!(output("0!.@", befunge93), "1 ");
+
+        let directives = Directives {
+            extensions: vec![Extension::Assert],
+            ..Default::default()
+        };
+        assert_eq!(
+            none.or(&settings, &Directives::default()).extensions,
+            [Extension::Quit]
+        );
+        assert_eq!(none.or(&settings, &directives).extensions, []);
+        let concurrent = Semantics::new(None, &[Extension::Concurrent]);
+        assert_eq!(
+            concurrent.or(&settings, &Directives::default()).extensions,
+            [Extension::Concurrent]
+        );
+    }
+}
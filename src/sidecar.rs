@@ -0,0 +1,122 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    annotation::{Annotation, Label, Region},
+    assembler::SourceMap,
+    config::Layout,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("Malformed sidecar file: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+pub type Result<T> = anyhow::Result<T, Error>;
+
+/// Extension appended to a program's file name to get that of its sidecar.
+pub const EXTENSION: &str = "mst";
+
+/// Editor state kept next to a program in `program.bf.mst`, so that it survives restarts and
+/// can be committed along with the program.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sidecar {
+    /// Cells marked with a letter, to jump back to them
+    pub bookmarks: BTreeMap<char, (usize, usize)>,
+    /// Notes on cells and regions
+    pub annotations: Vec<Annotation>,
+    /// Named regions, such as data tables
+    pub labels: Vec<Label>,
+    /// Cells runs stop at
+    pub breakpoints: BTreeSet<(usize, usize)>,
+    /// Expressions shown along with their values as the program runs
+    pub watches: Vec<String>,
+    /// Lines of funge assembly the program was laid out from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_map: Option<SourceMap>,
+    /// Panels of the TUI shown during runs, as last arranged
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout: Option<Layout>,
+}
+
+/// Location of the sidecar of a program.
+pub fn path(program: impl AsRef<Path>) -> PathBuf {
+    let mut path = program.as_ref().as_os_str().to_owned();
+    path.push(".");
+    path.push(EXTENSION);
+    PathBuf::from(path)
+}
+
+impl Sidecar {
+    /// Regions marked as holding data only.
+    pub fn data(&self) -> Vec<Region> {
+        self.labels
+            .iter()
+            .filter(|label| label.data)
+            .map(|label| label.region)
+            .collect()
+    }
+
+    /// Reads the sidecar of a program, which is empty if there is none yet.
+    pub fn load(program: impl AsRef<Path>) -> Result<Self> {
+        match std::fs::read_to_string(path(program)) {
+            Ok(content) => Ok(serde_json::from_str(&content)?),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes the sidecar of a program, only creating the file once there is something to keep.
+    pub fn save(&self, program: impl AsRef<Path>) -> Result<()> {
+        let path = path(program);
+        if *self == Self::default() && !path.exists() {
+            return Ok(());
+        }
+
+        std::fs::write(path, serde_json::to_string_pretty(self)? + "\n")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let program = std::env::temp_dir().join(format!("puccinia-{}.bf", std::process::id()));
+        assert_eq!(Sidecar::load(&program).unwrap(), Sidecar::default());
+
+        let sidecar = Sidecar {
+            bookmarks: BTreeMap::from([('a', (3, 4)), ('z', (0, 1))]),
+            annotations: vec![Annotation {
+                region: Region::new((0, 0), (2, 1)),
+                text: "read input".to_owned(),
+            }],
+            labels: vec![Label {
+                region: Region::new((0, 3), (79, 24)),
+                name: "font table".to_owned(),
+                folded: true,
+                data: true,
+            }],
+            breakpoints: BTreeSet::from([(2, 0), (5, 1)]),
+            watches: vec!["stack[0] + 1".to_owned()],
+            source_map: None,
+            layout: Some(Layout::default()),
+        };
+        sidecar.save(&program).unwrap();
+        let read = Sidecar::load(&program);
+        std::fs::remove_file(path(&program)).unwrap();
+
+        assert_eq!(read.unwrap(), sidecar);
+    }
+}
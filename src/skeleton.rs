@@ -0,0 +1,43 @@
+use std::path::Path;
+
+use puccinia::templates::Template;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("`{0}` already exists, use `--force` to overwrite it")]
+    Exists(String),
+    #[error("Could not write `{0}`: {1}")]
+    Write(String, std::io::Error),
+}
+
+#[derive(clap::Args)]
+pub(crate) struct Options {
+    /// Program file to create
+    output: String,
+
+    /// Starting point: `input-loop`, `table-lookup` or `walker`
+    #[arg(long, value_name = "NAME", default_value = "input-loop")]
+    template: Template,
+
+    /// Overwrite the program and its sidecar if they exist
+    #[arg(long)]
+    force: bool,
+}
+
+/// Writes a program generated from a template, its placeholders annotated in its sidecar.
+pub(crate) fn run(options: Options) -> anyhow::Result<()> {
+    if !options.force && Path::new(&options.output).exists() {
+        return Err(Error::Exists(options.output).into());
+    }
+
+    let skeleton = options.template.skeleton();
+    std::fs::write(&options.output, &skeleton.program)
+        .map_err(|err| Error::Write(options.output.clone(), err))?;
+    skeleton.sidecar.save(&options.output)?;
+
+    eprintln!(
+        "Created `{}` from the {} template, its placeholders are annotated",
+        options.output, options.template
+    );
+    Ok(())
+}
@@ -0,0 +1,163 @@
+#[cfg(feature = "sound")]
+use std::time::Duration;
+
+use puccinia::renderer::Frame;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[cfg(not(feature = "sound"))]
+    #[error("Sound feedback requires building with the `sound` feature")]
+    Disabled,
+    #[cfg(feature = "sound")]
+    #[error("Could not open the audio device: {0}")]
+    Device(String),
+}
+
+type Result<T> = anyhow::Result<T, Error>;
+
+/// What runs sound like.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum Mode {
+    /// A tone per executed instruction, the run being slowed down to the pace of the tones so
+    /// that loops can be heard as repeating melodies
+    Instructions,
+    /// A tone per output byte, newlines being silences
+    Output,
+}
+
+#[cfg(feature = "sound")]
+const INSTRUCTION: Duration = Duration::from_millis(40);
+
+#[cfg(feature = "sound")]
+const OUTPUT: Duration = Duration::from_millis(80);
+
+/// Major pentatonic scale, in semitones, so that any sequence of tones sounds fine.
+const PENTATONIC: [u8; 5] = [0, 2, 4, 7, 9];
+
+/// Tone of an instruction or output byte, over four octaves from A3.
+#[cfg_attr(not(feature = "sound"), allow(dead_code))]
+fn pitch(byte: u8) -> f32 {
+    let octave = byte / PENTATONIC.len() as u8 % 4;
+    let semitones = 12 * octave + PENTATONIC[byte as usize % PENTATONIC.len()];
+    220.0 * 2f32.powf(f32::from(semitones) / 12.0)
+}
+
+/// Audio device playing runs as tones.
+#[cfg(feature = "sound")]
+pub(crate) struct Sound {
+    mode: Mode,
+    /// Playback stops once the device is dropped
+    _device: rodio::MixerDeviceSink,
+    player: rodio::Player,
+    /// Tick and instruction of the last frame
+    last: Option<(u64, u8)>,
+}
+
+/// Stand-in for the audio device, which can't be opened without the `sound` feature.
+#[cfg(not(feature = "sound"))]
+pub(crate) enum Sound {}
+
+#[cfg(feature = "sound")]
+impl Sound {
+    pub fn open(mode: Mode) -> Result<Self> {
+        let mut device = rodio::DeviceSinkBuilder::open_default_sink()
+            .map_err(|err| Error::Device(err.to_string()))?;
+        device.log_on_drop(false);
+        let player = rodio::Player::connect_new(device.mixer());
+
+        Ok(Self {
+            mode,
+            _device: device,
+            player,
+            last: None,
+        })
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Plays what happened during a frame.
+    pub fn play(&mut self, frame: &Frame<'_>) {
+        match self.mode {
+            Mode::Instructions => {
+                let interpreter = frame.debugger.interpreter();
+                let (x, y) = interpreter.position();
+                let current = (
+                    interpreter.ticks(),
+                    u8::try_from(char::from(interpreter.grid().get(x, y).value)).unwrap_or(b'?'),
+                );
+
+                if let Some((ticks, instruction)) = self.last.replace(current) {
+                    if ticks != current.0 {
+                        self.tone(pitch(instruction), INSTRUCTION);
+                        // Keep at most one tone ahead of the run
+                        while self.player.len() > 1 {
+                            std::thread::sleep(Duration::from_millis(1));
+                        }
+                    }
+                }
+            }
+            Mode::Output => {
+                for byte in frame.output.bytes() {
+                    match byte {
+                        b'\n' => self.tone(0.0, OUTPUT),
+                        byte => self.tone(pitch(byte), OUTPUT),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Waits for queued tones to be played.
+    pub fn finish(&self) {
+        self.player.sleep_until_end();
+    }
+
+    fn tone(&self, frequency: f32, duration: Duration) {
+        use rodio::{source::SineWave, Source};
+
+        let volume = if frequency > 0.0 { 0.2 } else { 0.0 };
+        self.player.append(
+            SineWave::new(frequency)
+                .take_duration(duration)
+                .fade_in(Duration::from_millis(5))
+                .amplify(volume),
+        );
+    }
+}
+
+#[cfg(not(feature = "sound"))]
+impl Sound {
+    pub fn open(_mode: Mode) -> Result<Self> {
+        Err(Error::Disabled)
+    }
+
+    pub fn mode(&self) -> Mode {
+        match *self {}
+    }
+
+    pub fn play(&mut self, _frame: &Frame<'_>) {
+        match *self {}
+    }
+
+    pub fn finish(&self) {
+        match *self {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn pitch() {
+        let a3 = super::pitch(0);
+        assert_eq!(a3, 220.0);
+        assert_eq!(super::pitch(5), 2.0 * a3);
+
+        // Neighbouring instructions are told apart, and stay within the scale's range
+        for byte in 0..u8::MAX {
+            assert_ne!(super::pitch(byte), super::pitch(byte + 1));
+            assert!((a3..16.0 * a3).contains(&super::pitch(byte)));
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::{
+    fmt::Debug,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Source of the time seen by the interpreter, for environments without a system clock such as
+/// WASM, or tests that need the same time on every run.
+pub trait Clock: Debug + Send + Sync {
+    /// Time elapsed since the Unix epoch.
+    fn now(&self) -> Duration;
+
+    fn boxed_clone(&self) -> Box<dyn Clock>;
+}
+
+impl Clone for Box<dyn Clock> {
+    fn clone(&self) -> Self {
+        self.boxed_clone()
+    }
+}
+
+/// Clock of the operating system.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Clock> {
+        Box::new(*self)
+    }
+}
+
+/// Clock always telling the same time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FixedClock(pub Duration);
+
+impl Clock for FixedClock {
+    fn now(&self) -> Duration {
+        self.0
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Clock> {
+        Box::new(*self)
+    }
+}
+
+/// Source of the random numbers behind `?` and random schedules. It is cloned along with the
+/// interpreter, so that runs brought back to a snapshot draw the same numbers again.
+pub trait Entropy: Debug + Send + Sync {
+    fn next_u64(&mut self) -> u64;
+
+    /// Restarts the sequence from `seed`, as asked by the `seed` directive.
+    fn reseed(&mut self, seed: u64);
+
+    fn boxed_clone(&self) -> Box<dyn Entropy>;
+}
+
+impl Clone for Box<dyn Entropy> {
+    fn clone(&self) -> Self {
+        self.boxed_clone()
+    }
+}
+
+/// Xorshift generator, the default source of randomness.
+#[derive(Clone, Copy, Debug)]
+pub struct Xorshift(u64);
+
+impl Xorshift {
+    pub fn new(seed: u64) -> Self {
+        // The generator is stuck on a zero state
+        Self(seed | 1)
+    }
+}
+
+impl Entropy for Xorshift {
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        *self = Self::new(seed);
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Entropy> {
+        Box::new(*self)
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cell::{BinaryOperator, CellValue, Operator, UnaryOperator};
+
+/// Broad category of an executed instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Arithmetic,
+    Logic,
+    Stack,
+    Movement,
+    Branch,
+    Literal,
+    /// Characters pushed in string mode, and the quotes around them
+    String,
+    /// `g` and `p`
+    Grid,
+    Input,
+    Output,
+    Space,
+    Unknown,
+    End,
+}
+
+impl Kind {
+    pub fn of(value: CellValue) -> Self {
+        match value {
+            CellValue::Empty => Kind::Space,
+            CellValue::Op(Operator::Nullary(_)) => Kind::Input,
+            CellValue::Op(Operator::Unary(op)) => match op {
+                UnaryOperator::Negate => Kind::Logic,
+                UnaryOperator::Duplicate | UnaryOperator::Pop => Kind::Stack,
+                UnaryOperator::WriteNumber | UnaryOperator::WriteASCII => Kind::Output,
+            },
+            CellValue::Op(Operator::Binary(op)) => match op {
+                BinaryOperator::Greater => Kind::Logic,
+                BinaryOperator::Swap => Kind::Stack,
+                BinaryOperator::Get => Kind::Grid,
+                _ => Kind::Arithmetic,
+            },
+            CellValue::Op(Operator::Ternary(_)) => Kind::Grid,
+            CellValue::Dir(_) | CellValue::Bridge => Kind::Movement,
+            CellValue::If(_) => Kind::Branch,
+            CellValue::StringMode => Kind::String,
+            CellValue::End => Kind::End,
+            CellValue::Number(_) => Kind::Literal,
+            CellValue::Char(_) => Kind::Unknown,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kind::Arithmetic => "arithmetic",
+            Kind::Logic => "logic",
+            Kind::Stack => "stack",
+            Kind::Movement => "movement",
+            Kind::Branch => "branch",
+            Kind::Literal => "literal",
+            Kind::String => "string",
+            Kind::Grid => "grid",
+            Kind::Input => "input",
+            Kind::Output => "output",
+            Kind::Space => "space",
+            Kind::Unknown => "unknown",
+            Kind::End => "end",
+        }
+    }
+}
+
+/// Instruction mix of a run.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Statistics {
+    pub total: u64,
+    /// Executions per instruction, characters pushed in string mode excluded
+    pub instructions: BTreeMap<char, u64>,
+    pub kinds: BTreeMap<Kind, u64>,
+    /// Instructions executed by each instruction pointer, in concurrent runs
+    #[serde(default)]
+    pub ips: BTreeMap<u32, u64>,
+    /// Most values held by a stack at once
+    #[serde(default)]
+    pub deepest: usize,
+    /// Cells written with `p`
+    #[serde(default)]
+    pub writes: u64,
+}
+
+impl Statistics {
+    /// Counts one executed instruction, `string_mode` being the mode it was executed in.
+    pub fn record(&mut self, value: CellValue, string_mode: bool) {
+        self.total += 1;
+
+        if string_mode && !matches!(value, CellValue::StringMode) {
+            *self.kinds.entry(Kind::String).or_default() += 1;
+            return;
+        }
+
+        *self.instructions.entry(char::from(value)).or_default() += 1;
+        *self.kinds.entry(Kind::of(value)).or_default() += 1;
+    }
+
+    /// Counts one instruction executed by the instruction pointer with this id.
+    pub fn record_turn(&mut self, ip: u32) {
+        *self.ips.entry(ip).or_default() += 1;
+    }
+
+    /// Keeps track of the deepest stack, `depth` being the size of one after an instruction.
+    pub fn record_depth(&mut self, depth: usize) {
+        self.deepest = self.deepest.max(depth);
+    }
+
+    /// Counts one cell written to the grid.
+    pub fn record_write(&mut self) {
+        self.writes += 1;
+    }
+
+    /// Counts empty cells that were jumped over rather than executed one by one.
+    pub fn record_skipped(&mut self, count: u64) {
+        if count > 0 {
+            self.total += count;
+            *self.instructions.entry(' ').or_default() += count;
+            *self.kinds.entry(Kind::Space).or_default() += count;
+        }
+    }
+
+    /// Adds the counts of another run.
+    pub fn merge(&mut self, other: &Statistics) {
+        self.total += other.total;
+        for (instruction, count) in &other.instructions {
+            *self.instructions.entry(*instruction).or_default() += count;
+        }
+        for (kind, count) in &other.kinds {
+            *self.kinds.entry(*kind).or_default() += count;
+        }
+        for (ip, count) in &other.ips {
+            *self.ips.entry(*ip).or_default() += count;
+        }
+        self.deepest = self.deepest.max(other.deepest);
+        self.writes += other.writes;
+    }
+
+    /// Kinds sorted by decreasing count.
+    pub fn kinds_by_count(&self) -> Vec<(Kind, u64)> {
+        let mut kinds = self
+            .kinds
+            .iter()
+            .map(|(kind, count)| (*kind, *count))
+            .collect::<Vec<_>>();
+        kinds.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        kinds
+    }
+
+    /// Instructions sorted by decreasing count.
+    pub fn instructions_by_count(&self) -> Vec<(char, u64)> {
+        let mut instructions = self
+            .instructions
+            .iter()
+            .map(|(instruction, count)| (*instruction, *count))
+            .collect::<Vec<_>>();
+        instructions.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        instructions
+    }
+
+    fn share(&self, count: u64) -> f64 {
+        if self.total == 0 {
+            0.
+        } else {
+            count as f64 * 100. / self.total as f64
+        }
+    }
+}
+
+impl std::fmt::Display for Statistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Executed {} instructions", self.total)?;
+        writeln!(
+            f,
+            "Deepest stack held {} value(s), {} cell(s) were written",
+            self.deepest, self.writes
+        )?;
+
+        for (kind, count) in self.kinds_by_count() {
+            writeln!(
+                f,
+                "  {:<12} {count:>10} {:>5.1}%",
+                kind.name(),
+                self.share(count)
+            )?;
+        }
+
+        writeln!(f, "Most executed:")?;
+        for (instruction, count) in self.instructions_by_count().into_iter().take(10) {
+            writeln!(
+                f,
+                "  `{instruction}`          {count:>10} {:>5.1}%",
+                self.share(count)
+            )?;
+        }
+
+        if self.ips.len() > 1 {
+            let total = self.ips.values().sum::<u64>();
+            writeln!(f, "Instruction pointers:")?;
+            for (ip, count) in &self.ips {
+                let share = *count as f64 * 100. / total as f64;
+                writeln!(f, "  IP {ip:<9} {count:>10} {share:>5.1}%")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn record() {
+        let mut statistics = Statistics::default();
+        for (instruction, string_mode) in [
+            ('"', false),
+            ('+', true),
+            ('"', true),
+            ('+', false),
+            ('1', false),
+            ('1', false),
+        ] {
+            statistics.record(CellValue::from(instruction), string_mode);
+        }
+
+        assert_eq!(statistics.total, 6);
+        assert_eq!(statistics.instructions[&'"'], 2);
+        assert_eq!(statistics.instructions[&'+'], 1);
+        assert_eq!(
+            statistics.kinds_by_count(),
+            vec![(Kind::String, 3), (Kind::Literal, 2), (Kind::Arithmetic, 1)]
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use crate::dialect::Dialect;
+
+/// Program of the conformance suite, along with what it must print.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Case {
+    pub section: &'static str,
+    pub name: &'static str,
+    pub program: &'static str,
+    pub input: &'static str,
+    pub output: &'static str,
+    /// Dialects the expected output is that of, every one if empty
+    pub dialects: &'static [Dialect],
+}
+
+impl Case {
+    pub fn applies(&self, dialect: Dialect) -> bool {
+        self.dialects.is_empty() || self.dialects.contains(&dialect)
+    }
+}
+
+const fn case(
+    section: &'static str,
+    name: &'static str,
+    program: &'static str,
+    output: &'static str,
+) -> Case {
+    Case {
+        section,
+        name,
+        program,
+        input: "",
+        output,
+        dialects: &[],
+    }
+}
+
+/// Conformance suite of the Befunge-93 instruction set, along with the quirks of later dialects.
+pub const CASES: [Case; 30] = [
+    case("arithmetic", "add", "23+.@", "5 "),
+    case("arithmetic", "subtract", "23-.@", "-1 "),
+    case("arithmetic", "multiply", "34*.@", "12 "),
+    case("arithmetic", "divide", "72/.@", "3 "),
+    case("arithmetic", "divide rounds towards zero", "07-2/.@", "-3 "),
+    case("arithmetic", "modulo", "72%.@", "1 "),
+    Case {
+        dialects: &[Dialect::Befunge93, Dialect::Befunge97],
+        ..case("arithmetic", "not of zero", "0!.@", "1 ")
+    },
+    Case {
+        dialects: &[Dialect::Befunge96],
+        ..case("arithmetic", "not of zero", "0!.@", "-1 ")
+    },
+    case("arithmetic", "not of non-zero", "5!.@", "0 "),
+    case("arithmetic", "greater than", "52`25`..@", "0 1 "),
+    case("stack", "empty stack pops zero", ".@", "0 "),
+    case("stack", "duplicate", "5:..@", "5 5 "),
+    case("stack", "duplicate empty stack", ":..@", "0 0 "),
+    case("stack", "swap", "12\\..@", "1 2 "),
+    case("stack", "discard", "12$.@", "1 "),
+    case("flow", "arrows", "v\n>2.@", "2 "),
+    case("flow", "wrap left", "<@.1", "1 "),
+    case("flow", "wrap up", "^\n@\n.", "0 "),
+    case("flow", "bridge", "#@1.@", "1 "),
+    case("flow", "horizontal if on zero", "0>#v_3.@\n   >2.@", "3 "),
+    case(
+        "flow",
+        "horizontal if on non-zero",
+        "1>#v_3.@\n   >2.@",
+        "2 ",
+    ),
+    case("flow", "vertical if on zero", "v\n0\n|\n3\n.\n@", "3 "),
+    case("flow", "vertical if on non-zero", "^\n@\n.\n5\n|\n1", "5 "),
+    case("flow", "end", "@.1", ""),
+    case("strings", "string mode", "\"ab\",,@", "ba"),
+    case("strings", "spaces are kept", "\"a  b\",,,,@", "b  a"),
+    case("grid", "get", "11g,@\nAB", "B"),
+    case("grid", "put", "\"X\"01p01g,@\n.", "X"),
+    Case {
+        input: "x",
+        ..case("input", "read a character", "~,@", "x")
+    },
+    Case {
+        input: "12 -30",
+        ..case("input", "read numbers", "&&+.@", "-18 ")
+    },
+];
+
+/// Counts of the verdicts a Mycology-style test program prints, one per line.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Tally {
+    pub good: usize,
+    pub bad: usize,
+    /// Behaviours the specification leaves up to interpreters
+    pub undefined: usize,
+}
+
+impl Tally {
+    /// Reads the `GOOD`, `BAD` and `UNDEF` lines of an output.
+    pub fn of(output: &str) -> Self {
+        let mut tally = Tally::default();
+        for line in output.lines().map(str::trim_start) {
+            if line.starts_with("GOOD") {
+                tally.good += 1;
+            } else if line.starts_with("BAD") {
+                tally.bad += 1;
+            } else if line.starts_with("UNDEF") {
+                tally.undefined += 1;
+            }
+        }
+        tally
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        grid::Grid,
+        interpreter::{Interpreter, Status},
+    };
+
+    #[test]
+    fn cases() {
+        for dialect in Dialect::ALL {
+            for case in CASES.iter().filter(|case| case.applies(dialect)) {
+                let mut interpreter = Interpreter::new(Grid::from(case.program.to_owned()));
+                interpreter.set_dialect(dialect);
+                interpreter.feed_input(case.input);
+                for _ in 0..1000 {
+                    if interpreter.step().unwrap() != Status::Running {
+                        break;
+                    }
+                }
+                assert_eq!(
+                    interpreter.take_output(),
+                    case.output,
+                    "{}: {} in {dialect}",
+                    case.section,
+                    case.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn tally() {
+        let output = "GOOD: , works\nBAD: 0! != 1\nUNDEF: # across edge\nGOOD: : duplicates\n";
+        assert_eq!(
+            Tally::of(output),
+            Tally {
+                good: 2,
+                bad: 1,
+                undefined: 1
+            }
+        );
+    }
+}
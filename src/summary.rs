@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+use puccinia::debugger::Debugger;
+
+use crate::{expect, headless};
+
+/// Why a run ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Termination {
+    /// The program reached `@`
+    Ended,
+    /// The program asked for input after the end of it
+    EndOfInput,
+    /// Output stopped matching the expected one
+    Diverged,
+    /// The program ended with output other than that of its `output` directive
+    UnexpectedOutput,
+    Failed,
+}
+
+impl Termination {
+    pub fn of(result: &anyhow::Result<()>) -> Self {
+        let Err(err) = result else {
+            return Termination::Ended;
+        };
+        match err.downcast_ref::<headless::Error>() {
+            Some(headless::Error::EndOfInput { .. }) => Termination::EndOfInput,
+            Some(headless::Error::Expect(expect::Error::Diverged { .. })) => Termination::Diverged,
+            Some(
+                headless::Error::UnexpectedOutput(_)
+                | headless::Error::Expect(expect::Error::Short { .. }),
+            ) => Termination::UnexpectedOutput,
+            _ => Termination::Failed,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Termination::Ended => "ended",
+            Termination::EndOfInput => "ran out of input",
+            Termination::Diverged => "diverged from the expected output",
+            Termination::UnexpectedOutput => "ended with unexpected output",
+            Termination::Failed => "failed",
+        }
+    }
+}
+
+/// Figures about a finished run, for scripts that would rather not parse traces.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct Summary {
+    pub ticks: u64,
+    /// Time spent running, in seconds
+    pub wall_time: f64,
+    pub output_bytes: usize,
+    pub max_stack_depth: usize,
+    pub grid_writes: u64,
+    pub termination: Termination,
+    /// Code the program quit with through `q`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Summary {
+    /// Sums up a run that took `elapsed` to write `output_bytes` and end with `result`.
+    pub fn of(
+        debugger: &Debugger,
+        output_bytes: usize,
+        elapsed: Duration,
+        result: &anyhow::Result<()>,
+    ) -> Self {
+        let statistics = debugger.statistics();
+        Self {
+            ticks: debugger.interpreter().ticks(),
+            wall_time: elapsed.as_secs_f64(),
+            output_bytes,
+            max_stack_depth: statistics.deepest,
+            grid_writes: statistics.writes,
+            termination: Termination::of(result),
+            exit_code: debugger.interpreter().exit_code(),
+            error: result.as_ref().err().map(|err| err.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let code = self
+            .exit_code
+            .map(|code| format!(" with exit code {code}"))
+            .unwrap_or_default();
+        writeln!(
+            f,
+            "Program {}{code} after {} ticks in {:.3}s",
+            self.termination.name(),
+            self.ticks,
+            self.wall_time
+        )?;
+        writeln!(f, "  output          {:>10} byte(s)", self.output_bytes)?;
+        writeln!(f, "  deepest stack   {:>10}", self.max_stack_depth)?;
+        writeln!(f, "  grid writes     {:>10}", self.grid_writes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use puccinia::{grid::Grid, interpreter::Interpreter};
+
+    use super::*;
+
+    #[test]
+    fn of() {
+        let interpreter = Interpreter::new(Grid::from("123 00p.@".to_owned()));
+        let mut debugger = Debugger::new(interpreter);
+        while debugger.resume(100).unwrap().is_none() {}
+
+        let summary = Summary::of(&debugger, 2, Duration::from_millis(5), &Ok(()));
+        assert_eq!(summary.ticks, 9);
+        assert_eq!(summary.max_stack_depth, 5);
+        assert_eq!(summary.grid_writes, 1);
+        assert_eq!(summary.termination, Termination::Ended);
+
+        let failed = Err(headless::Error::EndOfInput {
+            tick: 3,
+            position: (2, 0),
+        }
+        .into());
+        let summary = Summary::of(&debugger, 2, Duration::from_millis(5), &failed);
+        assert_eq!(summary.termination, Termination::EndOfInput);
+        assert_eq!(
+            serde_json::to_value(&summary).unwrap()["error"],
+            "Program requested input at tick 3, cell (2, 0), with none left"
+        );
+    }
+}
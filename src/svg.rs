@@ -0,0 +1,106 @@
+use std::fmt::Write;
+
+use crate::{grid::Grid, heatmap::Heatmap};
+
+/// Width and height of a cell, in pixels.
+const CELL: (usize, usize) = (12, 20);
+
+const BACKGROUND: &str = "#1e1e1e";
+const FOREGROUND: &str = "#d4d4d4";
+const HEAT: &str = "#ff7f00";
+const IP: &str = "#ffd700";
+
+/// Draws a grid as an SVG image, executed cells being shaded by how often they ran and the
+/// instruction pointer outlined.
+pub fn render(grid: &Grid, heatmap: Option<&Heatmap>, ip: Option<(usize, usize)>) -> String {
+    let (width, height) = grid.size();
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+         font-family=\"monospace\" font-size=\"16\" text-anchor=\"middle\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"{BACKGROUND}\"/>\n",
+        width * CELL.0,
+        height * CELL.1,
+    );
+
+    if let Some(heatmap) = heatmap {
+        let entries = heatmap.entries();
+        // Logarithmic shading, so that cells ran a few times are still told apart from unused ones
+        let hottest = entries.iter().map(|entry| entry.executions).max();
+        let scale = hottest.map_or(1.0, |hottest| (hottest as f64).ln_1p());
+        for entry in entries {
+            let opacity = 0.15 + 0.7 * (entry.executions as f64).ln_1p() / scale;
+            let _ = writeln!(
+                svg,
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{HEAT}\" \
+                 fill-opacity=\"{opacity:.2}\"/>",
+                entry.x * CELL.0,
+                entry.y * CELL.1,
+                CELL.0,
+                CELL.1,
+            );
+        }
+    }
+
+    if let Some((x, y)) = ip {
+        let _ = writeln!(
+            svg,
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"{IP}\" \
+             stroke-width=\"2\"/>",
+            x * CELL.0 + 1,
+            y * CELL.1 + 1,
+            CELL.0 - 2,
+            CELL.1 - 2,
+        );
+    }
+
+    let _ = writeln!(svg, "<g fill=\"{FOREGROUND}\">");
+    for (y, line) in grid.lines().iter().enumerate() {
+        for (x, c) in line.chars().enumerate().filter(|(_, c)| *c != ' ') {
+            let _ = writeln!(
+                svg,
+                "<text x=\"{}\" y=\"{}\">{}</text>",
+                x * CELL.0 + CELL.0 / 2,
+                y * CELL.1 + CELL.1 * 3 / 4,
+                escape(c),
+            );
+        }
+    }
+    svg.push_str("</g>\n</svg>\n");
+
+    svg
+}
+
+fn escape(c: char) -> String {
+    match c {
+        '&' => "&amp;".to_owned(),
+        '<' => "&lt;".to_owned(),
+        '>' => "&gt;".to_owned(),
+        // Not allowed in XML documents
+        c if c.is_control() => char::REPLACEMENT_CHARACTER.to_string(),
+        c => c.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn render() {
+        let grid = crate::grid!["v<", ">^"];
+        let mut heatmap = Heatmap::default();
+        heatmap.record(0, (0, 0));
+        heatmap.record(1, (0, 1));
+        heatmap.record(4, (0, 0));
+
+        let svg = super::render(&grid, Some(&heatmap), Some((1, 1)));
+        assert!(
+            svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"24\" height=\"40\"")
+        );
+        assert!(svg.contains("<text x=\"18\" y=\"15\">&lt;</text>"));
+        assert!(svg.contains("<text x=\"6\" y=\"35\">&gt;</text>"));
+        assert!(svg.contains("<rect x=\"0\" y=\"0\" width=\"12\" height=\"20\" fill=\"#ff7f00\" fill-opacity=\"0.85\"/>"));
+        assert!(svg.contains("<rect x=\"13\" y=\"21\" width=\"10\" height=\"18\" fill=\"none\""));
+        assert_eq!(svg.matches("<text").count(), 4);
+    }
+}
@@ -0,0 +1,322 @@
+use std::{collections::HashMap, sync::OnceLock};
+
+/// Value up to which the shortest pushes are searched exhaustively.
+const LIMIT: usize = 1024;
+
+/// How the shortest push of a value is built.
+#[derive(Clone, Copy, Debug)]
+enum Recipe {
+    Digit,
+    /// Combining a value with a copy of itself made with `:`
+    Duplicate(usize, char),
+    /// Combining a value with two copies of itself made with `::`
+    Triplicate(usize, char),
+    Binary(usize, usize, char),
+}
+
+/// Shortest Befunge-93 instruction sequence pushing `value` from digits and arithmetic, for
+/// values up to `LIMIT`.
+fn constant(value: usize) -> Option<&'static str> {
+    static CONSTANTS: OnceLock<Vec<String>> = OnceLock::new();
+    CONSTANTS
+        .get_or_init(constants)
+        .get(value)
+        .map(String::as_str)
+}
+
+/// Shortest pushes of every value up to `LIMIT`, found by combining the values of shorter pushes
+/// into ever longer ones, intermediate values staying within `LIMIT` too.
+fn constants() -> Vec<String> {
+    let mut recipes = vec![None; LIMIT + 1];
+    for recipe in &mut recipes[..10] {
+        *recipe = Some(Recipe::Digit);
+    }
+    // Values by length of their shortest push
+    let mut layers = vec![Vec::new(), (0..10).collect::<Vec<_>>()];
+    let mut found = 10;
+
+    while found <= LIMIT {
+        let length = layers.len();
+        let mut layer = Vec::new();
+        let mut offer = |value: usize, recipe: Recipe| {
+            if value <= LIMIT && recipes[value].is_none() {
+                recipes[value] = Some(recipe);
+                layer.push(value);
+            }
+        };
+
+        if length > 2 {
+            for &a in &layers[length - 2] {
+                offer(a * a, Recipe::Duplicate(a, '*'));
+                offer(a + a, Recipe::Duplicate(a, '+'));
+            }
+        }
+        if length > 4 {
+            for &a in &layers[length - 4] {
+                offer(a * a * a, Recipe::Triplicate(a, '*'));
+                offer(a * 3, Recipe::Triplicate(a, '+'));
+            }
+        }
+        for left in 1..length - 1 {
+            for &a in &layers[left] {
+                for &b in &layers[length - 1 - left] {
+                    offer(a + b, Recipe::Binary(a, b, '+'));
+                    offer(a * b, Recipe::Binary(a, b, '*'));
+                    if a > b {
+                        offer(a - b, Recipe::Binary(a, b, '-'));
+                    }
+                    if b > 1 {
+                        offer(a / b, Recipe::Binary(a, b, '/'));
+                    }
+                }
+            }
+        }
+
+        found += layer.len();
+        layers.push(layer);
+    }
+
+    // Shorter pushes come first, so the parts of each one are already written
+    let mut codes = vec![String::new(); LIMIT + 1];
+    for value in layers.into_iter().flatten() {
+        codes[value] = match recipes[value].expect("values of layers have a recipe") {
+            Recipe::Digit => value.to_string(),
+            Recipe::Duplicate(a, operator) => format!("{}:{operator}", codes[a]),
+            Recipe::Triplicate(a, operator) => format!("{}::{operator}{operator}", codes[a]),
+            Recipe::Binary(a, b, operator) => format!("{}{}{operator}", codes[a], codes[b]),
+        };
+    }
+    codes
+}
+
+/// Shortest push of `value` found from digits and arithmetic, searched exhaustively up to 1024
+/// and built from a product and a remainder past that. Given the value on top of the stack, it
+/// may also be duplicated and built upon, the stack then holding both.
+pub fn number(value: i32, top: Option<i32>) -> String {
+    let mut best = signed(i64::from(value), &mut HashMap::new());
+
+    if let Some(top) = top {
+        let (value, top) = (i64::from(value), i64::from(top));
+        let mut offer = |code: String| {
+            if code.len() < best.len() {
+                best = code;
+            }
+        };
+        let memo = &mut HashMap::new();
+
+        if value == top {
+            offer(":".to_owned());
+        }
+        offer(format!(":{}+", signed(value - top, memo)));
+        offer(format!(":{}-", signed(top - value, memo)));
+        if top != 0 && value % top == 0 {
+            offer(format!(":{}*", signed(value / top, memo)));
+        }
+        if value == top * top {
+            offer("::*".to_owned());
+        }
+    }
+
+    best
+}
+
+fn signed(value: i64, memo: &mut HashMap<u64, String>) -> String {
+    match value < 0 {
+        true => format!("0{}-", unsigned(value.unsigned_abs(), memo)),
+        false => unsigned(value as u64, memo),
+    }
+}
+
+fn unsigned(value: u64, memo: &mut HashMap<u64, String>) -> String {
+    if let Some(code) = constant(value as usize).filter(|_| value <= LIMIT as u64) {
+        return code.to_owned();
+    }
+    if let Some(code) = memo.get(&value) {
+        return code.clone();
+    }
+
+    let mut best = None::<String>;
+    let mut offer = |code: String| {
+        if best.as_ref().is_none_or(|best| code.len() < best.len()) {
+            best = Some(code);
+        }
+    };
+
+    let root = value.isqrt();
+    if root * root == value {
+        offer(format!("{}:*", unsigned(root, memo)));
+    }
+    // Factors with pushes of up to 3 cells, rounding the quotient down or up
+    for factor in (2..=LIMIT).filter(|factor| constant(*factor).is_some_and(|code| code.len() <= 3))
+    {
+        let code = constant(factor).expect("factors are constants");
+        let (quotient, remainder) = (value / factor as u64, value % factor as u64);
+        let product = format!("{}{code}*", unsigned(quotient, memo));
+        match remainder {
+            0 => offer(product),
+            remainder => {
+                offer(format!("{product}{}+", unsigned(remainder, memo)));
+                offer(format!(
+                    "{}{code}*{}-",
+                    unsigned(quotient + 1, memo),
+                    unsigned(factor as u64 - remainder, memo)
+                ));
+            }
+        }
+    }
+
+    let best = best.expect("there is always a factor");
+    memo.insert(value, best.clone());
+    best
+}
+
+/// Whether a character can be pushed from string mode in a single-line snippet.
+fn stringable(c: char) -> bool {
+    c != '"' && !c.is_control()
+}
+
+/// Shortest single-line snippet printing `text` when run from left to right, leaving the stack
+/// as it found it. Characters are pushed in string mode where possible and through arithmetic
+/// otherwise, then printed with one `,` each or with a loop for longer texts.
+pub fn print(text: &str) -> String {
+    let reversed = text.chars().rev().collect::<Vec<_>>();
+    let pushes = pushes(&reversed);
+
+    let direct = format!("{pushes}{}", ",".repeat(reversed.len()));
+    // The loop stops at the first zero it prints
+    let looped =
+        (!reversed.is_empty() && !reversed.contains(&'\0')).then(|| format!("0{pushes}>:#,_$"));
+
+    match looped {
+        Some(looped) if looped.len() < direct.len() => looped,
+        _ => direct,
+    }
+}
+
+/// Shortest single-line snippet pushing the characters of `text` in order, the last one ending
+/// on top.
+pub fn text(text: &str) -> String {
+    pushes(&text.chars().collect::<Vec<_>>())
+}
+
+/// Shortest sequence pushing `chars` in order, switching in and out of string mode.
+fn pushes(chars: &[char]) -> String {
+    const OUTSIDE: usize = 0;
+    const INSIDE: usize = 1;
+
+    // Length of the shortest sequence pushing the first `i` characters and ending outside or
+    // inside of string mode, along with the mode the previous character was pushed in. The
+    // closing quote of string mode is counted as soon as it opens.
+    let mut costs = vec![[None::<(usize, usize)>; 2]; chars.len() + 1];
+    costs[0][OUTSIDE] = Some((0, OUTSIDE));
+    for (i, c) in chars.iter().enumerate() {
+        let previous = costs[i];
+        let cost = |state: usize| previous[state].map(|(cost, _)| cost);
+        let cheapest = |options: [Option<(usize, usize)>; 2]| {
+            options.into_iter().flatten().min_by_key(|(cost, _)| *cost)
+        };
+
+        costs[i + 1][OUTSIDE] = constant(*c as usize).and_then(|code| {
+            cheapest([
+                cost(OUTSIDE).map(|cost| (cost + code.len(), OUTSIDE)),
+                cost(INSIDE).map(|cost| (cost + code.len(), INSIDE)),
+            ])
+        });
+        costs[i + 1][INSIDE] = stringable(*c)
+            .then(|| {
+                cheapest([
+                    cost(INSIDE).map(|cost| (cost + 1, INSIDE)),
+                    cost(OUTSIDE).map(|cost| (cost + 3, OUTSIDE)),
+                ])
+            })
+            .flatten();
+    }
+
+    let mut state = [OUTSIDE, INSIDE]
+        .into_iter()
+        .filter_map(|state| costs[chars.len()][state].map(|(cost, _)| (cost, state)))
+        .min()
+        .expect("control characters are all below 256")
+        .1;
+    let mut states = vec![OUTSIDE; chars.len()];
+    for i in (0..chars.len()).rev() {
+        states[i] = state;
+        state = costs[i + 1][state]
+            .expect("only reachable states are walked")
+            .1;
+    }
+
+    let mut code = String::new();
+    let mut quoted = false;
+    for (c, state) in chars.iter().zip(states) {
+        if quoted != (state == INSIDE) {
+            code.push('"');
+            quoted = !quoted;
+        }
+        match quoted {
+            true => code.push(*c),
+            false => code.push_str(constant(*c as usize).expect("pushable characters were picked")),
+        }
+    }
+    if quoted {
+        code.push('"');
+    }
+
+    code
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        grid::Grid,
+        interpreter::{Interpreter, Status},
+    };
+
+    fn run(code: &str) -> (String, usize) {
+        let mut interpreter = Interpreter::new(Grid::from(format!("{code}@")));
+        for _ in 0..10_000 {
+            if interpreter.step().unwrap() != Status::Running {
+                break;
+            }
+        }
+        (interpreter.take_output(), interpreter.stack().len())
+    }
+
+    #[test]
+    fn number() {
+        assert_eq!(super::number(7, None), "7");
+        assert_eq!(super::number(79, None).len(), 5);
+        assert_eq!(super::number(81, None).len(), 3);
+        assert_eq!(super::number(1000, None).len(), 7);
+        assert_eq!(super::number(1000, Some(999)), ":1+");
+        assert_eq!(super::number(123, Some(123)), ":");
+        for (value, top) in [
+            (34, None),
+            (1000, None),
+            (-97, None),
+            (123_456, None),
+            (40, Some(8)),
+        ] {
+            let code = super::number(value, top);
+            let setup = top.map(|top| super::number(top, None)).unwrap_or_default();
+            assert_eq!(
+                run(&format!("{setup}{code}.")).0,
+                format!("{value} "),
+                "{code}"
+            );
+        }
+        let largest = super::number(i32::MAX, None);
+        assert_eq!(run(&format!("{largest}.")).0, format!("{} ", i32::MAX));
+    }
+
+    #[test]
+    fn print() {
+        assert_eq!(super::print("hi"), "\"ih\",,");
+        assert_eq!(super::print(""), "");
+        for text in ["Hello, World!\n", "say \"hi\"", "\0\u{1}", "ünïcode"] {
+            let code = super::print(text);
+            assert_eq!(run(&code), (text.to_owned(), 0), "{code}");
+        }
+        assert!(super::print("Hello, World!").ends_with(">:#,_$"));
+    }
+}
@@ -0,0 +1,191 @@
+use std::str::FromStr;
+
+use crate::{
+    annotation::{Annotation, Label, Region},
+    sidecar::Sidecar,
+};
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Unknown template `{0}`, expected `input-loop`, `table-lookup` or `walker`")]
+    Unknown(String),
+}
+
+/// Starter program with its routing laid out, leaving the actual work to placeholders.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Template {
+    /// Reads a line character by character
+    InputLoop,
+    /// Reads indices and looks them up in a data table
+    TableLookup,
+    /// Visits every cell of a region row by row
+    Walker,
+}
+
+/// Program generated from a template, along with the sidecar marking its placeholders.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Skeleton {
+    pub program: String,
+    pub sidecar: Sidecar,
+}
+
+impl Template {
+    pub const ALL: [Template; 3] = [Template::InputLoop, Template::TableLookup, Template::Walker];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Template::InputLoop => "input-loop",
+            Template::TableLookup => "table-lookup",
+            Template::Walker => "walker",
+        }
+    }
+
+    pub fn skeleton(self) -> Skeleton {
+        let (lines, annotations, labels): (&[&str], _, _) = match self {
+            Template::InputLoop => (
+                &[">~:55+-#v_@", "        >,       v", "^                <"],
+                vec![
+                    note(
+                        (9, 1),
+                        (16, 1),
+                        "Placeholder: handle the character on top of the stack, here echoed with \
+                         `,`",
+                    ),
+                    note((0, 0), (10, 0), "Read a character, ending at a newline"),
+                ],
+                vec![],
+            ),
+            Template::TableLookup => (
+                &[
+                    ">&:!#v_1-4g,v",
+                    "     @      v",
+                    "^           <",
+                    "",
+                    "abcdefghij",
+                ],
+                vec![
+                    note(
+                        (11, 0),
+                        (11, 0),
+                        "Placeholder: use the entry on top of the stack, here printed with `,`",
+                    ),
+                    note(
+                        (0, 0),
+                        (10, 0),
+                        "Read a 1-based index, ending at 0, and fetch that entry of row 4",
+                    ),
+                ],
+                vec![data((0, 4), (9, 4), "table")],
+            ),
+            Template::Walker => (
+                &[
+                    "0>::8%\\8/5+g,1+:8%#v_55+,v",
+                    "             v-*83:<     <",
+                    " ^           _@",
+                    "",
+                    "",
+                    "abcdefgh",
+                    "ijklmnop",
+                    "qrstuvwx",
+                ],
+                vec![
+                    note(
+                        (12, 0),
+                        (12, 0),
+                        "Placeholder: handle the cell on top of the stack, here printed with `,`",
+                    ),
+                    note(
+                        (21, 0),
+                        (24, 0),
+                        "Placeholder: end of a row, here printed as a newline",
+                    ),
+                    note(
+                        (2, 0),
+                        (11, 0),
+                        "Turn the counter into a position, 8 cells wide from row 5, and fetch it",
+                    ),
+                    note((13, 1), (18, 1), "Stop after 24 cells"),
+                ],
+                vec![data((0, 5), (7, 7), "walked region")],
+            ),
+        };
+
+        Skeleton {
+            program: lines.join("\n") + "\n",
+            sidecar: Sidecar {
+                annotations,
+                labels,
+                ..Sidecar::default()
+            },
+        }
+    }
+}
+
+fn note(from: (usize, usize), to: (usize, usize), text: &str) -> Annotation {
+    Annotation {
+        region: Region::new(from, to),
+        text: text.to_owned(),
+    }
+}
+
+fn data(from: (usize, usize), to: (usize, usize), name: &str) -> Label {
+    Label {
+        region: Region::new(from, to),
+        name: name.to_owned(),
+        folded: false,
+        data: true,
+    }
+}
+
+impl FromStr for Template {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Template::ALL
+            .into_iter()
+            .find(|template| template.name() == name)
+            .ok_or_else(|| Error::Unknown(name.to_owned()))
+    }
+}
+
+impl std::fmt::Display for Template {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        grid::Grid,
+        interpreter::{Interpreter, Status},
+    };
+
+    #[test]
+    fn skeletons() {
+        let cases = [
+            (Template::InputLoop, "hi\nthere", "hi"),
+            (Template::TableLookup, "3 1 0", "ca"),
+            (Template::Walker, "", "abcdefgh\nijklmnop\nqrstuvwx\n"),
+        ];
+        for (template, input, output) in cases {
+            let skeleton = template.skeleton();
+            let mut interpreter = Interpreter::new(Grid::from(skeleton.program.clone()));
+            interpreter.feed_input(input);
+            for _ in 0..10_000 {
+                if interpreter.step().unwrap() != Status::Running {
+                    break;
+                }
+            }
+            assert_eq!(interpreter.take_output(), output, "{template}");
+
+            let (width, height) = Grid::from(skeleton.program).size();
+            let regions = skeleton.sidecar.annotations.iter().map(|a| a.region);
+            for region in regions.chain(skeleton.sidecar.labels.iter().map(|l| l.region)) {
+                assert!(region.to.0 < width && region.to.1 < height, "{template}");
+            }
+        }
+        assert_eq!("walker".parse(), Ok(Template::Walker));
+    }
+}
@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+
+/// Default number of samples kept before the resolution gets halved.
+pub const DEFAULT_CAPACITY: usize = 4096;
+
+/// Stack shape at a given tick.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sample {
+    pub tick: u64,
+    pub depth: usize,
+    pub top: Option<i32>,
+}
+
+/// Bounded record of the stack depth over time.
+/// Once full, every other sample is dropped and the sampling stride doubles, so memory stays
+/// constant while the whole run remains covered.
+#[derive(Clone, Debug)]
+pub struct Timeline {
+    capacity: usize,
+    stride: u64,
+    samples: Vec<Sample>,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl Timeline {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(2),
+            stride: 1,
+            samples: Vec::new(),
+        }
+    }
+
+    /// Records the stack as it is at `tick`, forgetting anything recorded after it.
+    pub fn record(&mut self, tick: u64, stack: &[i32]) {
+        // Samples are sorted, and only travelling back leaves some at or after `tick`
+        if self
+            .samples
+            .last()
+            .is_some_and(|sample| sample.tick >= tick)
+        {
+            let index = self.samples.partition_point(|sample| sample.tick < tick);
+            self.samples.truncate(index);
+        }
+
+        if !tick.is_multiple_of(self.stride) {
+            return;
+        }
+
+        if self.samples.len() == self.capacity {
+            self.stride *= 2;
+            self.samples
+                .retain(|sample| sample.tick.is_multiple_of(self.stride));
+
+            if !tick.is_multiple_of(self.stride) {
+                return;
+            }
+        }
+
+        self.samples.push(Sample {
+            tick,
+            depth: stack.len(),
+            top: stack.last().copied(),
+        });
+    }
+
+    pub fn samples(&self) -> &[Sample] {
+        &self.samples
+    }
+
+    /// Reduces the timeline to at most `buckets` samples, see [summarize].
+    pub fn summary(&self, buckets: usize) -> Vec<Sample> {
+        summarize(&self.samples, buckets)
+    }
+}
+
+/// Reduces samples to at most `buckets` ones, keeping the deepest one of each bucket under the
+/// tick at which the bucket starts.
+pub fn summarize(samples: &[Sample], buckets: usize) -> Vec<Sample> {
+    if buckets == 0 || samples.is_empty() {
+        return vec![];
+    }
+
+    let size = samples.len().div_ceil(buckets);
+    samples
+        .chunks(size)
+        .filter_map(|chunk| {
+            let deepest = chunk.iter().max_by_key(|sample| sample.depth)?;
+            Some(Sample {
+                tick: chunk[0].tick,
+                ..*deepest
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn downsampling() {
+        let mut timeline = Timeline::new(4);
+        for tick in 0..8 {
+            timeline.record(tick, &vec![0; tick as usize]);
+        }
+
+        let ticks = timeline
+            .samples()
+            .iter()
+            .map(|sample| sample.tick)
+            .collect::<Vec<_>>();
+        assert_eq!(ticks, vec![0, 2, 4, 6]);
+
+        timeline.record(3, &[]);
+        assert_eq!(timeline.samples().len(), 2);
+    }
+
+    #[test]
+    fn summary() {
+        let mut timeline = Timeline::new(16);
+        for (tick, depth) in [1, 3, 2, 0, 5, 1].iter().enumerate() {
+            timeline.record(tick as u64, &vec![7; *depth]);
+        }
+
+        let summary = timeline.summary(3);
+        assert_eq!(
+            summary
+                .iter()
+                .map(|sample| (sample.tick, sample.depth))
+                .collect::<Vec<_>>(),
+            vec![(0, 3), (2, 2), (4, 5)]
+        );
+    }
+}
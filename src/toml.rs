@@ -0,0 +1,436 @@
+use std::fmt::Write;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error(
+        "Line {0}: expected `[table]` or `key = value`, dotted keys, inline tables and arrays of \
+         tables being unsupported"
+    )]
+    Syntax(usize),
+    #[error(
+        "Line {0}: invalid value `{1}`, expected a single-line string, an integer, a boolean or an \
+         array of them"
+    )]
+    Value(usize, String),
+}
+
+pub type Result<T> = anyhow::Result<T, Error>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Value::Integer(integer) => Some(*integer),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(boolean) => Some(*boolean),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if let Some(inner) = text
+            .strip_prefix('[')
+            .and_then(|text| text.strip_suffix(']'))
+        {
+            return split_array(inner)
+                .into_iter()
+                .filter(|item| !item.trim().is_empty())
+                .map(Value::parse)
+                .collect::<Option<_>>()
+                .map(Value::Array);
+        }
+        if text.starts_with(['"', '\'']) {
+            return string(text).map(Value::String);
+        }
+        match text {
+            "true" => Some(Value::Boolean(true)),
+            "false" => Some(Value::Boolean(false)),
+            _ => text.replace('_', "").parse().ok().map(Value::Integer),
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::String(string) => f.write_str(&quote(string)),
+            Value::Integer(integer) => write!(f, "{integer}"),
+            Value::Boolean(boolean) => write!(f, "{boolean}"),
+            Value::Array(values) => {
+                let values = values.iter().map(Value::to_string).collect::<Vec<_>>();
+                write!(f, "[{}]", values.join(", "))
+            }
+        }
+    }
+}
+
+/// Keys of a table, in the order they were written.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Table {
+    /// Dotted path of the table, empty for the keys written before any header
+    pub path: Vec<String>,
+    pub entries: Vec<(String, Value)>,
+}
+
+impl Table {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value)
+    }
+}
+
+/// File in the subset of TOML project files are written in: tables, and keys holding basic or
+/// literal single-line strings, integers, booleans or arrays of them, possibly spanning several
+/// lines. Tables are kept in the order they were written.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Document {
+    pub tables: Vec<Table>,
+}
+
+impl Document {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut tables = vec![Table::default()];
+
+        let mut lines = text.lines().enumerate();
+        while let Some((index, line)) = lines.next() {
+            let number = index + 1;
+            let mut line = strip_comment(line).trim().to_owned();
+            if line.is_empty() {
+                continue;
+            }
+            // Arrays go on until their closing bracket, whatever the line it is on
+            while !line.starts_with('[') && unclosed(&line) {
+                let Some((_, next)) = lines.next() else {
+                    break;
+                };
+                line.push(' ');
+                line.push_str(strip_comment(next).trim());
+            }
+
+            if let Some(header) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                let path = split_path(header).ok_or(Error::Syntax(number))?;
+                tables.push(Table {
+                    path,
+                    entries: Vec::new(),
+                });
+                continue;
+            }
+
+            let (key, value) =
+                split_once_outside_quotes(&line, '=').ok_or(Error::Syntax(number))?;
+            let key = name(key).ok_or(Error::Syntax(number))?;
+            let value =
+                Value::parse(value).ok_or_else(|| Error::Value(number, value.trim().to_owned()))?;
+            tables
+                .last_mut()
+                .expect("there is always a table")
+                .entries
+                .push((key, value));
+        }
+
+        // Keys outside of any table are only kept when there are some
+        if tables[0].entries.is_empty() {
+            tables.remove(0);
+        }
+
+        Ok(Self { tables })
+    }
+
+    pub fn table(&self, path: &[&str]) -> Option<&Table> {
+        self.tables.iter().find(|table| table.path == path)
+    }
+
+    /// Tables directly under `path`, along with their name.
+    pub fn children<'a>(&'a self, path: &'a [&str]) -> impl Iterator<Item = (&'a str, &'a Table)> {
+        self.tables.iter().filter_map(move |table| {
+            let (name, parent) = table.path.split_last()?;
+            (parent == path).then_some((name.as_str(), table))
+        })
+    }
+}
+
+impl std::fmt::Display for Document {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut text = String::new();
+        for table in &self.tables {
+            if !table.path.is_empty() {
+                if !text.is_empty() {
+                    text.push('\n');
+                }
+                let path = table.path.iter().map(|name| key(name)).collect::<Vec<_>>();
+                let _ = writeln!(text, "[{}]", path.join("."));
+            }
+            for (name, value) in &table.entries {
+                let _ = writeln!(text, "{} = {value}", key(name));
+            }
+        }
+        f.write_str(&text)
+    }
+}
+
+fn key(name: &str) -> String {
+    match bare(name) {
+        true => name.to_owned(),
+        false => quote(name),
+    }
+}
+
+fn bare(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Reads a key or the name of a table, bare or quoted.
+fn name(text: &str) -> Option<String> {
+    let text = text.trim();
+    match text.starts_with(['"', '\'']) {
+        true => string(text),
+        false => bare(text).then(|| text.to_owned()),
+    }
+}
+
+pub(crate) fn quote(string: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in string.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Reads a basic string, quotes included.
+pub(crate) fn unquote(text: &str) -> Option<String> {
+    let inner = text.strip_prefix('"')?.strip_suffix('"')?;
+    let mut string = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => string.push(match chars.next()? {
+                'n' => '\n',
+                't' => '\t',
+                c @ ('"' | '\\') => c,
+                _ => return None,
+            }),
+            '"' => return None,
+            c => string.push(c),
+        }
+    }
+    Some(string)
+}
+
+/// Reads a basic or literal string, quotes included.
+fn string(text: &str) -> Option<String> {
+    match text
+        .strip_prefix('\'')
+        .and_then(|text| text.strip_suffix('\''))
+    {
+        Some(literal) => (!literal.contains('\'')).then(|| literal.to_owned()),
+        None => unquote(text),
+    }
+}
+
+/// Where a character is relative to strings.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Quote {
+    Outside,
+    Basic,
+    /// Right after a backslash in a basic string
+    Escaped,
+    Literal,
+}
+
+/// Characters outside of strings along with their byte offset and how many brackets they are
+/// in, brackets themselves counting as outside of the ones they open or close.
+fn outside_quotes(text: &str) -> impl Iterator<Item = (usize, char, usize)> + '_ {
+    let mut quote = Quote::Outside;
+    let mut depth = 0usize;
+    text.char_indices().filter_map(move |(index, c)| {
+        let outside = quote == Quote::Outside;
+        quote = match (quote, c) {
+            (Quote::Escaped, _) => Quote::Basic,
+            (Quote::Basic, '\\') => Quote::Escaped,
+            (Quote::Basic, '"') | (Quote::Literal, '\'') => Quote::Outside,
+            (Quote::Outside, '"') => Quote::Basic,
+            (Quote::Outside, '\'') => Quote::Literal,
+            (quote, _) => quote,
+        };
+        if !outside || quote != Quote::Outside {
+            return None;
+        }
+        match c {
+            '[' => depth += 1,
+            ']' => depth = depth.saturating_sub(1),
+            _ => return Some((index, c, depth)),
+        }
+        Some((index, c, depth - usize::from(c == '[')))
+    })
+}
+
+/// Whether some bracket outside of strings is left open.
+fn unclosed(text: &str) -> bool {
+    let open = outside_quotes(text).fold(0isize, |open, (_, c, _)| match c {
+        '[' => open + 1,
+        ']' => open - 1,
+        _ => open,
+    });
+    open > 0
+}
+
+/// Cuts a line at a `#` outside of any string.
+fn strip_comment(line: &str) -> &str {
+    match outside_quotes(line).find(|(_, c, _)| *c == '#') {
+        Some((index, _, _)) => &line[..index],
+        None => line,
+    }
+}
+
+/// Splits at the commas outside of strings and nested arrays.
+fn split_array(text: &str) -> Vec<&str> {
+    split_outside_quotes(text, ',')
+}
+
+/// Splits a dotted table path, names being bare or quoted.
+fn split_path(header: &str) -> Option<Vec<String>> {
+    split_outside_quotes(header, '.')
+        .into_iter()
+        .map(name)
+        .collect()
+}
+
+fn split_outside_quotes(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    for (index, _, _) in outside_quotes(text).filter(|(_, c, depth)| *c == separator && *depth == 0)
+    {
+        parts.push(&text[start..index]);
+        start = index + separator.len_utf8();
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+fn split_once_outside_quotes(text: &str, separator: char) -> Option<(&str, &str)> {
+    let (index, _, _) = outside_quotes(text).find(|(_, c, _)| *c == separator)?;
+    Some((&text[..index], &text[index + separator.len_utf8()..]))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let text = "\
+title = \"Golf # course\" # comment
+[solutions.hello]
+path = \"hello.bf\"
+bytes = 1_024
+verified = true
+
+[solutions.\"a.b\"]
+tags = [\"x\", \"y\"]
+";
+        let document = Document::parse(text).unwrap();
+        assert_eq!(
+            document.tables[0].get("title"),
+            Some(&Value::String("Golf # course".to_owned()))
+        );
+        let hello = document.table(&["solutions", "hello"]).unwrap();
+        assert_eq!(hello.get("bytes"), Some(&Value::Integer(1024)));
+        assert_eq!(hello.get("verified").and_then(Value::as_bool), Some(true));
+        assert_eq!(
+            document
+                .children(&["solutions"])
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>(),
+            ["hello", "a.b"]
+        );
+
+        let written = document.to_string();
+        assert!(written.contains("[solutions.\"a.b\"]\ntags = [\"x\", \"y\"]\n"));
+        assert_eq!(Document::parse(&written), Ok(document));
+
+        assert_eq!(Document::parse("[a]\nb"), Err(Error::Syntax(2)));
+        assert_eq!(
+            Document::parse("b = nope"),
+            Err(Error::Value(1, "nope".to_owned()))
+        );
+    }
+
+    #[test]
+    fn arrays() {
+        let text = "\
+paths = [
+    'C:\\golf\\a, b.bf', # windows
+    \"[x]\",
+    [1, 2],
+]
+'quoted key' = 'say \"hi\"'
+";
+        let document = Document::parse(text).unwrap();
+        let table = &document.tables[0];
+        assert_eq!(
+            table.get("paths"),
+            Some(&Value::Array(vec![
+                Value::String("C:\\golf\\a, b.bf".to_owned()),
+                Value::String("[x]".to_owned()),
+                Value::Array(vec![Value::Integer(1), Value::Integer(2)]),
+            ]))
+        );
+        assert_eq!(
+            table.get("quoted key").and_then(Value::as_str),
+            Some("say \"hi\"")
+        );
+
+        assert_eq!(
+            Document::parse("a = 'it's'\n"),
+            Err(Error::Value(1, "'it's'".to_owned()))
+        );
+        assert_eq!(Document::parse("[[a]]"), Err(Error::Syntax(1)));
+        assert_eq!(Document::parse("a.b = 1"), Err(Error::Syntax(1)));
+        assert_eq!(
+            Document::parse("a = [1,\n2"),
+            Err(Error::Value(1, "[1, 2".to_owned()))
+        );
+    }
+}
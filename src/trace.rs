@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+
+use crate::debugger::Debugger;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Malformed step on line {0}: {1}")]
+    Json(usize, serde_json::Error),
+}
+
+pub type Result<T> = anyhow::Result<T, Error>;
+
+/// Executed instruction of a trace, written as a line of JSON by `run --trace`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Step {
+    pub tick: u64,
+    /// Instruction pointer that executed the instruction
+    #[serde(default)]
+    pub ip: u32,
+    pub position: (usize, usize),
+    pub instruction: char,
+    /// Stack of the instruction pointer once the instruction executed, bottom first
+    pub stack: Vec<i32>,
+}
+
+impl Step {
+    /// Latest instruction the debugger executed, if it remembers any.
+    pub fn last(debugger: &Debugger) -> Option<Self> {
+        let entry = debugger.history().back()?;
+        let stack = debugger
+            .interpreter()
+            .ips()
+            .find(|ip| ip.id == entry.ip)
+            .map(|ip| ip.stack.clone())
+            .unwrap_or_default();
+        Some(Self {
+            tick: entry.tick,
+            ip: entry.ip,
+            position: entry.position,
+            instruction: entry.instruction,
+            stack,
+        })
+    }
+}
+
+impl std::fmt::Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (x, y) = self.position;
+        write!(f, "tick {:<8} ({x}, {y}) `{}`", self.tick, self.instruction)?;
+        if self.ip != 0 {
+            write!(f, " IP {}", self.ip)?;
+        }
+        write!(f, ", stack {:?}", self.stack)
+    }
+}
+
+/// Reads a trace, one step per line.
+pub fn parse(text: &str) -> Result<Vec<Step>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| serde_json::from_str(line).map_err(|err| Error::Json(index + 1, err)))
+        .collect()
+}
+
+/// What is compared when aligning two traces.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Alignment {
+    /// Leave out the empty cells the instruction pointers go through, which moving code around
+    /// adds and removes
+    pub ignore_spaces: bool,
+    /// Only compare instructions and stacks, for programs laid out differently
+    pub ignore_positions: bool,
+}
+
+/// First pair of steps two traces differ at, the shorter trace having `None` past its end.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    /// Number of aligned steps the traces agree on before diverging
+    pub index: usize,
+    pub a: Option<Step>,
+    pub b: Option<Step>,
+    /// Last step both traces agree on
+    pub common: Option<Step>,
+}
+
+impl Divergence {
+    /// Depth from the bottom of the first value the stacks differ at, if they do.
+    pub fn stack_depth(&self) -> Option<usize> {
+        let (a, b) = (&self.a.as_ref()?.stack, &self.b.as_ref()?.stack);
+        (a != b).then(|| a.iter().zip(b).take_while(|(a, b)| a == b).count())
+    }
+}
+
+impl Alignment {
+    fn keeps(&self, step: &Step) -> bool {
+        !(self.ignore_spaces && step.instruction == ' ')
+    }
+
+    fn same(&self, a: &Step, b: &Step) -> bool {
+        a.instruction == b.instruction
+            && a.stack == b.stack
+            && a.ip == b.ip
+            && (self.ignore_positions || a.position == b.position)
+    }
+
+    /// Aligns two traces step by step, returning where they first differ.
+    pub fn compare(&self, a: &[Step], b: &[Step]) -> Option<Divergence> {
+        let mut a = a.iter().filter(|step| self.keeps(step));
+        let mut b = b.iter().filter(|step| self.keeps(step));
+        let (mut index, mut common) = (0, None);
+        loop {
+            match (a.next(), b.next()) {
+                (None, None) => return None,
+                (Some(left), Some(right)) if self.same(left, right) => {
+                    index += 1;
+                    common = Some(left);
+                }
+                (left, right) => {
+                    return Some(Divergence {
+                        index,
+                        a: left.cloned(),
+                        b: right.cloned(),
+                        common: common.cloned(),
+                    })
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn step(tick: u64, position: (usize, usize), instruction: char, stack: &[i32]) -> Step {
+        Step {
+            tick,
+            ip: 0,
+            position,
+            instruction,
+            stack: stack.to_vec(),
+        }
+    }
+
+    #[test]
+    fn compare() {
+        let text = "{\"tick\":0,\"position\":[0,0],\"instruction\":\"1\",\"stack\":[1]}\n\n";
+        let a = parse(text).unwrap();
+        assert_eq!(a, vec![step(0, (0, 0), '1', &[1])]);
+        assert!(matches!(parse("{}"), Err(Error::Json(1, _))));
+
+        let a = [
+            step(0, (0, 0), '1', &[1]),
+            step(1, (1, 0), '2', &[1, 2]),
+            step(2, (2, 0), '+', &[3]),
+        ];
+        let b = [
+            step(0, (0, 0), '1', &[1]),
+            step(1, (1, 0), ' ', &[1]),
+            step(2, (2, 0), '2', &[1, 2]),
+            step(3, (3, 0), '-', &[-1]),
+        ];
+
+        let divergence = Alignment::default().compare(&a, &b).unwrap();
+        assert_eq!(divergence.index, 1);
+
+        let alignment = Alignment {
+            ignore_spaces: true,
+            ignore_positions: true,
+        };
+        let divergence = alignment.compare(&a, &b).unwrap();
+        assert_eq!(divergence.index, 2);
+        assert_eq!(divergence.common, Some(a[1].clone()));
+        assert_eq!(divergence.stack_depth(), Some(0));
+        assert_eq!(alignment.compare(&a, &a), None);
+        assert_eq!(alignment.compare(&a, &a[..2]).unwrap().b, None);
+    }
+}
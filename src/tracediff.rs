@@ -0,0 +1,66 @@
+use puccinia::trace::{self, Alignment, Step};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Could not read `{0}`: {1}")]
+    Read(String, std::io::Error),
+    #[error("Invalid trace `{0}`: {1}")]
+    Invalid(String, trace::Error),
+    #[error("Traces diverge after {0} matching step(s)")]
+    Diverged(usize),
+}
+
+type Result<T> = anyhow::Result<T, Error>;
+
+#[derive(clap::Args)]
+pub(crate) struct Options {
+    /// Trace written by `run --trace`
+    a: String,
+
+    /// Trace to compare it with
+    b: String,
+
+    /// Leave out the empty cells instruction pointers go through, which moving code around adds
+    /// and removes
+    #[arg(long)]
+    ignore_spaces: bool,
+
+    /// Only compare instructions and stacks, for programs laid out differently
+    #[arg(long)]
+    ignore_positions: bool,
+}
+
+fn open(path: &str) -> Result<Vec<Step>> {
+    let text = std::fs::read_to_string(path).map_err(|err| Error::Read(path.to_owned(), err))?;
+    trace::parse(&text).map_err(|err| Error::Invalid(path.to_owned(), err))
+}
+
+/// Aligns two traces step by step, reporting the first one they differ at.
+pub(crate) fn run(options: Options) -> anyhow::Result<()> {
+    let (a, b) = (open(&options.a)?, open(&options.b)?);
+    let alignment = Alignment {
+        ignore_spaces: options.ignore_spaces,
+        ignore_positions: options.ignore_positions,
+    };
+
+    let Some(divergence) = alignment.compare(&a, &b) else {
+        eprintln!("Traces match over {} step(s)", a.len());
+        return Ok(());
+    };
+
+    match &divergence.common {
+        Some(step) => println!("Last common step: {step}"),
+        None => println!("The traces differ from their first step"),
+    }
+    for (path, step) in [(&options.a, &divergence.a), (&options.b, &divergence.b)] {
+        match step {
+            Some(step) => println!("{path}: {step}"),
+            None => println!("{path}: ended"),
+        }
+    }
+    if let Some(depth) = divergence.stack_depth() {
+        println!("Stacks differ from depth {depth}, counting from the bottom");
+    }
+
+    Err(Error::Diverged(divergence.index).into())
+}
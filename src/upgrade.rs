@@ -0,0 +1,68 @@
+use puccinia::{
+    directives::{self, Directives},
+    grid::Grid,
+    upgrader,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Could not load `{0}`: {1}")]
+    Load(String, directives::Error),
+    #[error("Could not write `{0}`: {1}")]
+    Write(String, std::io::Error),
+}
+
+#[derive(clap::Args)]
+pub(crate) struct Options {
+    /// Befunge-93 program file location
+    input: String,
+
+    /// Where to write the Funge-98 program, defaults to stdout
+    #[arg(short, long, value_name = "PATH")]
+    output: Option<String>,
+}
+
+/// Rewrites a Befunge-93 program for Funge-98, reporting each edit and each construct whose
+/// semantics change.
+pub(crate) fn run(options: Options) -> anyhow::Result<()> {
+    let (grid, directives) =
+        directives::open(&options.input).map_err(|err| Error::Load(options.input.clone(), err))?;
+
+    let upgrade = upgrader::upgrade(&grid);
+    for edit in &upgrade.edits {
+        let (x, y) = edit.position;
+        eprintln!(
+            "{}:({x}, {y}): edit: `{}` -> `{}`, {}",
+            options.input, edit.from, edit.to, edit.reason
+        );
+    }
+    for diagnostic in &upgrade.diagnostics {
+        let (x, y) = diagnostic.position;
+        eprintln!(
+            "{}:({x}, {y}): {}: {}",
+            options.input,
+            diagnostic.severity.name(),
+            diagnostic.message
+        );
+    }
+    eprintln!(
+        "{} edit(s), {} construct(s) to review",
+        upgrade.edits.len(),
+        upgrade.diagnostics.len()
+    );
+
+    // Dialects are those of Befunge-93, which the program no longer is
+    let directives = Directives {
+        dialect: None,
+        ..directives
+    };
+    let program = directives::to_program(&Grid::from(upgrade.lines.join("\n")), &directives);
+    match &options.output {
+        Some(path) => {
+            std::fs::write(path, program).map_err(|err| Error::Write(path.clone(), err))?
+        }
+        None => print!("{program}"),
+    }
+
+    Ok(())
+}
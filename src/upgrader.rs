@@ -0,0 +1,300 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    analyzer::{self, Diagnostic, Ip, Severity},
+    cell::{BinaryOperator, CellValue, Direction, Operator, TernaryOperator},
+    dialect,
+    golf::{self, Metrics},
+    grid::Grid,
+};
+
+type Position = (usize, usize);
+
+/// Run of cells replaced by a shorter Funge-98 equivalent, padded with spaces which the
+/// instruction pointer crosses at no cost there.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Edit {
+    /// First cell of the run, the others following it on its row
+    pub position: Position,
+    pub from: String,
+    pub to: String,
+    pub reason: String,
+}
+
+/// Befunge-93 program rewritten for Funge-98, along with the constructs behaving differently
+/// there.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Upgrade {
+    pub lines: Vec<String>,
+    pub edits: Vec<Edit>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Rewrites constants of a Befunge-93 program into the shorter pushes of Funge-98, and flags the
+/// instructions whose semantics change.
+///
+/// Only runs the instruction pointer goes through from left to right, entering them from their
+/// first cell, are rewritten. Programs reading or writing their own cells are left as they are,
+/// since those may be the cells being rewritten.
+pub fn upgrade(grid: &Grid) -> Upgrade {
+    let (width, height) = grid.size();
+    let mut lines = grid.lines();
+    if width == 0 || height == 0 {
+        return Upgrade {
+            lines,
+            edits: vec![],
+            diagnostics: vec![],
+        };
+    }
+
+    let mut visited = HashSet::new();
+    let mut visits = HashMap::<Position, HashSet<(Direction, bool)>>::new();
+    let mut predecessors = HashMap::<Position, HashSet<Position>>::new();
+    let mut queue = vec![Ip::START];
+    while let Some(ip) = queue.pop() {
+        if !visited.insert(ip) {
+            continue;
+        }
+        visits
+            .entry(ip.position)
+            .or_default()
+            .insert((ip.direction, ip.string_mode));
+        for next in analyzer::successors(grid, ip) {
+            predecessors
+                .entry(next.position)
+                .or_default()
+                .insert(ip.position);
+            queue.push(next);
+        }
+    }
+
+    let bounds = Metrics::of(grid);
+    let mut diagnostics = Vec::new();
+    let mut self_referencing = Vec::new();
+    for ip in &visited {
+        let (x, y) = ip.position;
+        let value = grid.get(x, y).value;
+        let mut diagnose = |severity, message: String| {
+            diagnostics.push(Diagnostic {
+                position: ip.position,
+                severity,
+                message,
+            })
+        };
+
+        if ip.string_mode {
+            let (previous, next) = (
+                grid.neighbour(ip.position, ip.direction.reversed()),
+                grid.neighbour(ip.position, ip.direction),
+            );
+            let space = |(x, y): Position| matches!(grid.get(x, y).value, CellValue::Empty);
+            if space(ip.position) && space(next) && !space(previous) {
+                diagnose(
+                    Severity::Warning,
+                    "Consecutive spaces in string mode push a single one in Funge-98".to_owned(),
+                );
+            }
+            continue;
+        }
+
+        match value {
+            CellValue::Char(c) => match dialect::funge98(c) {
+                Some(meaning) => diagnose(
+                    Severity::Error,
+                    format!("`{c}` {meaning} in Funge-98, rather than doing nothing"),
+                ),
+                None => diagnose(
+                    Severity::Warning,
+                    format!("Unknown instruction `{c}` reverses the direction in Funge-98"),
+                ),
+            },
+            CellValue::Op(Operator::Nullary(_)) => diagnose(
+                Severity::Info,
+                "Reverses the direction at the end of input in Funge-98".to_owned(),
+            ),
+            CellValue::Op(Operator::Binary(BinaryOperator::Divide | BinaryOperator::Modulo)) => {
+                diagnose(
+                    Severity::Info,
+                    "Pushes 0 when dividing by zero in Funge-98, rather than asking for the \
+                     result"
+                        .to_owned(),
+                )
+            }
+            CellValue::Op(Operator::Binary(BinaryOperator::Get))
+            | CellValue::Op(Operator::Ternary(TernaryOperator::Put)) => {
+                self_referencing.push(ip.position)
+            }
+            _ => (),
+        }
+
+        // Funge-98 wraps around the bounding box of the program, whatever its padding
+        if matches!(value, CellValue::Bridge) {
+            continue;
+        }
+        for next in analyzer::successors(grid, *ip) {
+            let (nx, ny) = next.position;
+            let expected = match next.direction {
+                Direction::Right if nx + 1 < x => Some((0, y)),
+                Direction::Left if nx > x + 1 => Some((bounds.width.saturating_sub(1), y)),
+                Direction::Down if ny + 1 < y => Some((x, 0)),
+                Direction::Up if ny > y + 1 => Some((x, bounds.height.saturating_sub(1))),
+                _ => None,
+            };
+            if expected.is_some_and(|expected| expected != next.position) {
+                diagnose(
+                    Severity::Warning,
+                    format!(
+                        "Wraps to ({nx}, {ny}), but around the {}×{} bounding box of the program \
+                         in Funge-98",
+                        bounds.width, bounds.height
+                    ),
+                );
+            }
+        }
+    }
+
+    let mut edits = Vec::new();
+    match self_referencing.into_iter().min_by_key(|(x, y)| (*y, *x)) {
+        Some(position) => diagnostics.push(Diagnostic {
+            position,
+            severity: Severity::Info,
+            message: "The program reads or writes its own cells, so none were rewritten".to_owned(),
+        }),
+        None => {
+            let straight = |position: Position, string_mode: bool| {
+                visits.get(&position).is_some_and(|visits| {
+                    visits.len() == 1 && visits.contains(&(Direction::Right, string_mode))
+                })
+            };
+            // Entered from the previous cell of the row only
+            let chained = |(x, y): Position| {
+                x > 0
+                    && predecessors
+                        .get(&(x, y))
+                        .is_some_and(|from| from.len() == 1 && from.contains(&(x - 1, y)))
+            };
+
+            for (y, line) in lines.iter().enumerate() {
+                let chars = line.chars().collect::<Vec<_>>();
+                let mut x = 0;
+                while x < chars.len() {
+                    let run = (x..chars.len())
+                        .take_while(|&column| {
+                            (column == x || chained((column, y)))
+                                && straight((column, y), column > x && chars[x] == '"')
+                        })
+                        .count();
+                    match rewrite(&chars[x..x + run]) {
+                        Some((length, to, reason)) => {
+                            edits.push(Edit {
+                                position: (x, y),
+                                from: chars[x..x + length].iter().collect(),
+                                to,
+                                reason,
+                            });
+                            x += length;
+                        }
+                        None => x += 1,
+                    }
+                }
+            }
+        }
+    }
+
+    for edit in &edits {
+        let (x, y) = edit.position;
+        let mut chars = lines[y].chars().collect::<Vec<_>>();
+        let length = edit.from.chars().count();
+        let padding = length - edit.to.chars().count();
+        chars.splice(
+            x..x + length,
+            edit.to.chars().chain(std::iter::repeat_n(' ', padding)),
+        );
+        lines[y] = chars.into_iter().collect::<String>().trim_end().to_owned();
+    }
+
+    diagnostics.sort_by(|a, b| {
+        (a.position.1, a.position.0, &a.message).cmp(&(b.position.1, b.position.0, &b.message))
+    });
+    diagnostics.dedup();
+
+    Upgrade {
+        lines,
+        edits,
+        diagnostics,
+    }
+}
+
+/// Shorter Funge-98 push of the constant computed at the start of a straight run of cells, along
+/// with the length of the part it replaces and what it pushes.
+fn rewrite(run: &[char]) -> Option<(usize, String, String)> {
+    // A single character pushed in string mode, fetched by `'` instead
+    if let ['"', c, '"', ..] = run {
+        return Some((3, format!("'{c}"), format!("pushes `{c}` with `'`")));
+    }
+
+    let mut stack = Vec::new();
+    let mut best = None;
+    for (length, c) in run.iter().enumerate() {
+        if golf::compute(&mut stack, *c).is_none() {
+            break;
+        }
+        let [value] = stack[..] else {
+            continue;
+        };
+        let push = match value {
+            0..=15 => char::from_digit(value as u32, 16).map(|digit| {
+                let base = if value < 10 { "decimal" } else { "hexadecimal" };
+                (
+                    digit.to_string(),
+                    format!("pushes {value} with a {base} digit"),
+                )
+            }),
+            // A space would be lost at the end of a line
+            33..=126 => {
+                let c = char::from(value as u8);
+                Some((format!("'{c}"), format!("pushes {value} as `{c}` with `'`")))
+            }
+            _ => None,
+        };
+        if let Some((to, reason)) = push.filter(|(to, _)| to.chars().count() <= length) {
+            best = Some((length + 1, to, reason));
+        }
+    }
+
+    best
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn upgrade() {
+        let upgrade = super::upgrade(&Grid::from("25*,\"x\",88*1+,k@".to_owned()));
+        assert_eq!(upgrade.lines, vec!["a  ,'x ,'A   ,k@"]);
+        assert_eq!(
+            upgrade
+                .edits
+                .iter()
+                .map(|edit| (edit.position, edit.from.as_str()))
+                .collect::<Vec<_>>(),
+            vec![((0, 0), "25*"), ((4, 0), "\"x\""), ((8, 0), "88*1+")]
+        );
+        assert_eq!(
+            upgrade
+                .diagnostics
+                .iter()
+                .map(|diagnostic| (diagnostic.position, diagnostic.severity))
+                .collect::<Vec<_>>(),
+            vec![((14, 0), Severity::Error)]
+        );
+
+        // Runs going left stay as they are, as do programs reading their own cells
+        let upgrade = super::upgrade(&crate::grid![">25*v", "@,+<"]);
+        assert_eq!(upgrade.lines, vec![">a  v", "@,+<"]);
+        let upgrade = super::upgrade(&Grid::from("01g,55+,@".to_owned()));
+        assert!(upgrade.edits.is_empty());
+        assert_eq!(upgrade.diagnostics[0].position, (2, 0));
+    }
+}
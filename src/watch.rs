@@ -0,0 +1,337 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::interpreter::Interpreter;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Unexpected `{0}` at column {1}")]
+    Unexpected(char, usize),
+    #[error("Unexpected end of expression")]
+    UnexpectedEnd,
+    #[error("Unknown name `{0}`, expected `stack`, `cell`, `ticks`, `x` or `y`")]
+    UnknownName(String),
+    #[error("Number `{0}` is too large")]
+    TooLarge(String),
+}
+
+type Result<T> = anyhow::Result<T, Error>;
+
+/// Expression over the state of an interpreter, such as `stack[0] * 2`, `cell(10, 3) == 'a'`,
+/// `stack.len()` or `ticks % 100`. Comparisons give 1 or 0.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Watch {
+    source: String,
+    expression: Expression,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Expression {
+    Number(i64),
+    Ticks,
+    X,
+    Y,
+    StackLen,
+    /// Value at a depth of the stack, 0 being the top
+    Stack(Box<Expression>),
+    /// Value of a cell, in grid coordinates regardless of the storage offset
+    Cell(Box<Expression>, Box<Expression>),
+    Negate(Box<Expression>),
+    Binary(Operator, Box<Expression>, Box<Expression>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl Watch {
+    /// Value of the expression, `None` when it reads past the stack or the grid, overflows or
+    /// divides by zero.
+    pub fn evaluate(&self, interpreter: &Interpreter) -> Option<i64> {
+        self.expression.evaluate(interpreter)
+    }
+
+    /// Whether both always have the same value, however they are written.
+    pub fn same(&self, other: &Watch) -> bool {
+        self.expression == other.expression
+    }
+
+    /// Expression along with its current value, as shown in watch panels.
+    pub fn describe(&self, interpreter: &Interpreter) -> String {
+        match self.evaluate(interpreter) {
+            Some(value) => format!("{self} = {value}"),
+            None => format!("{self} = ?"),
+        }
+    }
+}
+
+impl Display for Watch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl FromStr for Watch {
+    type Err = Error;
+
+    fn from_str(source: &str) -> Result<Self> {
+        let mut parser = Parser {
+            chars: source.char_indices().peekable(),
+        };
+        let expression = parser.comparison()?;
+        match parser.next() {
+            Some((index, c)) => Err(Error::Unexpected(c, index + 1)),
+            None => Ok(Self {
+                source: source.trim().to_owned(),
+                expression,
+            }),
+        }
+    }
+}
+
+impl Expression {
+    fn evaluate(&self, interpreter: &Interpreter) -> Option<i64> {
+        Some(match self {
+            Expression::Number(value) => *value,
+            Expression::Ticks => i64::try_from(interpreter.ticks()).ok()?,
+            Expression::X => interpreter.position().0 as i64,
+            Expression::Y => interpreter.position().1 as i64,
+            Expression::StackLen => interpreter.stack().len() as i64,
+            Expression::Stack(depth) => {
+                let stack = interpreter.stack();
+                let depth = usize::try_from(depth.evaluate(interpreter)?).ok()?;
+                let index = stack.len().checked_sub(depth + 1)?;
+                stack[index] as i64
+            }
+            Expression::Cell(x, y) => {
+                let x = usize::try_from(x.evaluate(interpreter)?).ok()?;
+                let y = usize::try_from(y.evaluate(interpreter)?).ok()?;
+                char::from(interpreter.grid().try_get(x, y)?.value) as i64
+            }
+            Expression::Negate(inner) => inner.evaluate(interpreter)?.checked_neg()?,
+            Expression::Binary(operator, a, b) => {
+                let (a, b) = (a.evaluate(interpreter)?, b.evaluate(interpreter)?);
+                match operator {
+                    Operator::Add => a.checked_add(b)?,
+                    Operator::Subtract => a.checked_sub(b)?,
+                    Operator::Multiply => a.checked_mul(b)?,
+                    Operator::Divide => a.checked_div(b)?,
+                    Operator::Modulo => a.checked_rem(b)?,
+                    Operator::Equal => (a == b) as i64,
+                    Operator::NotEqual => (a != b) as i64,
+                    Operator::Less => (a < b) as i64,
+                    Operator::LessEqual => (a <= b) as i64,
+                    Operator::Greater => (a > b) as i64,
+                    Operator::GreaterEqual => (a >= b) as i64,
+                }
+            }
+        })
+    }
+}
+
+/// Recursive descent parser, one method per precedence level.
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+}
+
+impl Parser<'_> {
+    /// Next character that isn't whitespace.
+    fn next(&mut self) -> Option<(usize, char)> {
+        self.skip_whitespace();
+        self.chars.next()
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.chars.peek().map(|(_, c)| *c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        match self.next() {
+            Some((_, c)) if c == expected => Ok(()),
+            Some((index, c)) => Err(Error::Unexpected(c, index + 1)),
+            None => Err(Error::UnexpectedEnd),
+        }
+    }
+
+    fn comparison(&mut self) -> Result<Expression> {
+        let left = self.sum()?;
+
+        let operator = match self.peek() {
+            Some('=') => {
+                self.next();
+                self.expect('=')?;
+                Operator::Equal
+            }
+            Some('!') => {
+                self.next();
+                self.expect('=')?;
+                Operator::NotEqual
+            }
+            Some(c @ ('<' | '>')) => {
+                self.next();
+                let equal = self.chars.next_if(|(_, c)| *c == '=').is_some();
+                match (c, equal) {
+                    ('<', false) => Operator::Less,
+                    ('<', true) => Operator::LessEqual,
+                    ('>', false) => Operator::Greater,
+                    _ => Operator::GreaterEqual,
+                }
+            }
+            _ => return Ok(left),
+        };
+
+        let right = self.sum()?;
+        Ok(Expression::Binary(
+            operator,
+            Box::new(left),
+            Box::new(right),
+        ))
+    }
+
+    fn sum(&mut self) -> Result<Expression> {
+        let mut left = self.product()?;
+        loop {
+            let operator = match self.peek() {
+                Some('+') => Operator::Add,
+                Some('-') => Operator::Subtract,
+                _ => return Ok(left),
+            };
+            self.next();
+            left = Expression::Binary(operator, Box::new(left), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Expression> {
+        let mut left = self.unary()?;
+        loop {
+            let operator = match self.peek() {
+                Some('*') => Operator::Multiply,
+                Some('/') => Operator::Divide,
+                Some('%') => Operator::Modulo,
+                _ => return Ok(left),
+            };
+            self.next();
+            left = Expression::Binary(operator, Box::new(left), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expression> {
+        if self.peek() == Some('-') {
+            self.next();
+            return Ok(Expression::Negate(Box::new(self.unary()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expression> {
+        let (index, c) = self.next().ok_or(Error::UnexpectedEnd)?;
+        match c {
+            '(' => {
+                let inner = self.comparison()?;
+                self.expect(')')?;
+                Ok(inner)
+            }
+            // Character literals stand for their code, as cells and strings hold them
+            '\'' => {
+                let (_, value) = self.chars.next().ok_or(Error::UnexpectedEnd)?;
+                self.expect('\'')?;
+                Ok(Expression::Number(value as i64))
+            }
+            '0'..='9' => {
+                let mut digits = String::from(c);
+                while let Some((_, digit)) = self.chars.next_if(|(_, c)| c.is_ascii_digit()) {
+                    digits.push(digit);
+                }
+                digits
+                    .parse()
+                    .map(Expression::Number)
+                    .map_err(|_| Error::TooLarge(digits))
+            }
+            c if c.is_ascii_alphabetic() => {
+                let mut name = String::from(c);
+                while let Some((_, c)) = self
+                    .chars
+                    .next_if(|(_, c)| c.is_ascii_alphanumeric() || *c == '_' || *c == '.')
+                {
+                    name.push(c);
+                }
+                self.named(name)
+            }
+            c => Err(Error::Unexpected(c, index + 1)),
+        }
+    }
+
+    fn named(&mut self, name: String) -> Result<Expression> {
+        Ok(match name.as_str() {
+            "ticks" => Expression::Ticks,
+            "x" => Expression::X,
+            "y" => Expression::Y,
+            "stack.len" => {
+                self.expect('(')?;
+                self.expect(')')?;
+                Expression::StackLen
+            }
+            "stack" => {
+                self.expect('[')?;
+                let depth = self.comparison()?;
+                self.expect(']')?;
+                Expression::Stack(Box::new(depth))
+            }
+            "cell" => {
+                self.expect('(')?;
+                let x = self.comparison()?;
+                self.expect(',')?;
+                let y = self.comparison()?;
+                self.expect(')')?;
+                Expression::Cell(Box::new(x), Box::new(y))
+            }
+            _ => return Err(Error::UnknownName(name)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::grid::Grid;
+
+    #[test]
+    fn evaluate() {
+        let mut interpreter = Interpreter::new(Grid::from("12345@".to_owned()));
+        for _ in 0..3 {
+            interpreter.step().unwrap();
+        }
+
+        let value = |source: &str| source.parse::<Watch>().unwrap().evaluate(&interpreter);
+        assert_eq!(value("stack[0]"), Some(3));
+        assert_eq!(value("stack[ stack.len() - 1 ] * -2"), Some(-2));
+        assert_eq!(value("stack[3]"), None);
+        assert_eq!(value("cell(x, y) == '4'"), Some(1));
+        assert_eq!(value("ticks % 2 + 1 < 3"), Some(1));
+        assert_eq!(value("1 / (ticks - 3)"), None);
+
+        assert_eq!(
+            "stack[0".parse::<Watch>(),
+            Err::<Watch, _>(Error::UnexpectedEnd)
+        );
+        assert_eq!(
+            "heap[0]".parse::<Watch>(),
+            Err::<Watch, _>(Error::UnknownName("heap".to_owned()))
+        );
+    }
+}